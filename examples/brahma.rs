@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::vec;
 
 use structopt::*;
@@ -13,21 +14,24 @@ macro_rules! benchmarks {
     }
 }
 
+type Benchmark = fn(&term::Context, &Options) -> SynthResult<Program>;
+
 fn main() {
     env_logger::init();
 
     let opts = Options::from_args();
 
-    let mut config = z3::Config::new();
-    config.set_bool_param_value("auto_config", false);
-    config.set_model_generation(true);
+    let context = term::Context::new();
 
-    let context = z3::Context::new(&config);
+    if opts.clear_cache {
+        if let Some(path) = &opts.cache {
+            let mut cache = Cache::open(path).expect("failed to open the cache");
+            cache.clear();
+            cache.save().expect("failed to save the cache");
+        }
+    }
 
-    let problems: Vec<(
-        &'static str,
-        fn(&z3::Context, &Options) -> SynthResult<Program>,
-    )> = benchmarks! { 
+    let problems: Vec<(&'static str, Benchmark)> = benchmarks! { 
         test_add,
         test_cast,
         test_inconsistent_target_program,
@@ -115,8 +119,11 @@ fn main() {
             Ok(prog) => {
                 println!("Synthesized:\n\n{}", prog);
             }
+            Err(Error::SynthesisUnsatisfiable(Some(why))) => {
+                println!("Error: SynthesisUnsatisfiable\n\n{}", why);
+            }
             Err(e) => {
-                println!("Error: {:?}\n", e);
+                println!("Error: {}\n", e);
             }
         }
     }
@@ -132,6 +139,49 @@ struct Options {
     #[structopt(short = "m", long = "minimal")]
     minimal: bool,
 
+    /// Use bottom-up enumeration instead of the SMT encoding.
+    #[structopt(short = "e", long = "enumerative")]
+    enumerative: bool,
+
+    /// Reuse and record solved problems and learned invalid wirings in this
+    /// cache file.
+    #[structopt(long = "cache", parse(from_os_str))]
+    cache: Option<PathBuf>,
+
+    /// Write every solver query to this directory as an SMT-LIB2 file.
+    #[structopt(long = "dump-queries", parse(from_os_str))]
+    dump_queries: Option<PathBuf>,
+
+    /// Decide queries with this SMT-LIB2 solver command (e.g. "cvc5
+    /// --incremental") instead of in-process Z3.
+    #[structopt(long = "solver")]
+    solver: Option<String>,
+
+    /// The logic to declare to the `--solver` command, for solvers that need
+    /// one.
+    #[structopt(long = "solver-logic", requires = "solver")]
+    solver_logic: Option<String>,
+
+    /// Re-check each synthesized program against its spec before accepting
+    /// it.
+    #[structopt(long = "verify")]
+    verify: bool,
+
+    /// Encode tensor cells as `int`s, as exact `real`s, which divide without
+    /// rounding, or as `bv8`, `bv16`, `bv32` or `bv64` bit-vectors, which wrap
+    /// around on overflow. Problems with floats need `real`. Defaults to
+    /// `int`.
+    #[structopt(long = "domain", conflicts_with = "enumerative")]
+    domain: Option<Domain>,
+
+    /// Print statistics about each synthesis run.
+    #[structopt(short = "r", long = "report")]
+    report: bool,
+
+    /// Throw away everything in the cache before running.
+    #[structopt(long = "clear-cache", requires = "cache")]
+    clear_cache: bool,
+
     /// Run only the problems that we can solver pretty fast.
     // #[structopt(short = "f", long = "only-fast", conflicts_with = "problems")]
     // only_fast: bool,
@@ -159,19 +209,65 @@ impl Options {
 
 fn synthesize(
     opts: &Options,
-    context: &z3::Context,
+    context: &term::Context,
     spec: &dyn Specification,
     library: &Library
 ) -> SynthResult<Program> {
-    Synthesizer::new(context, library, spec)?
+    if opts.enumerative {
+        return EnumerativeSynthesizer::new(context, library, spec)?
+            .set_timeout(opts.timeout)
+            .synthesize();
+    }
+
+    let mut cache = opts
+        .cache
+        .as_ref()
+        .map(|path| Cache::open(path).expect("failed to open the cache"));
+
+    let backend: Box<dyn Backend> = match &opts.solver {
+        Some(command) => {
+            let mut words = command.split_whitespace();
+            let program = words.next().expect("empty solver command");
+            let mut backend = SmtLib2Backend::new(program, words);
+            backend.set_logic(opts.solver_logic.clone());
+            Box::new(backend)
+        }
+        None => {
+            let mut backend = Z3Backend::new();
+            backend.set_param("auto_config", "false");
+            Box::new(backend)
+        }
+    };
+
+    let mut synthesizer = Synthesizer::new(context, library, spec)?;
+    synthesizer
         .set_timeout(opts.timeout)
+        .set_backend(backend)
         .should_synthesize_minimal_programs(opts.minimal)
-        .synthesize()
+        .should_verify_programs(opts.verify)
+        .set_domain(opts.domain.unwrap_or_default())
+        .set_cache(cache.as_mut())
+        .set_query_dump_dir(opts.dump_queries.clone());
+
+    // 报告要数查询的大小，比较慢，所以只在需要时才要
+    let program = if opts.report {
+        let (program, report) = synthesizer.synthesize_with_report();
+        println!("Report:\n\n{}", report);
+        program
+    } else {
+        synthesizer.synthesize()
+    };
+    drop(synthesizer);
+
+    if let Some(cache) = &cache {
+        cache.save().expect("failed to save the cache");
+    }
+    program
 }
 // test_benchmarks
 
 // test_add
-fn test_add(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn test_add(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -192,7 +288,7 @@ fn test_add(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 }
 
 // test_cast
-fn test_cast(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn test_cast(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -202,15 +298,15 @@ fn test_cast(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
     let in1 = builder.var(input1);
 
-    let _ = builder.tf_cast(in1);
+    let _ = builder.tf_cast(in1, DType::Float);
     let spec = builder.finish();
 
-    return synthesize(opts, context, &spec, &library); 
+    synthesize(opts, context, &spec, &library)
 }
 
 // test_inconsistent_target_program
 // 我们把它手动改成对的不就行了？
-fn test_inconsistent_target_program(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn test_inconsistent_target_program(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -231,7 +327,7 @@ fn test_inconsistent_target_program(context: &z3::Context, opts: &Options) -> Sy
 }
 
 // duplicate_test_add
-fn duplicate_test_add(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn duplicate_test_add(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -254,7 +350,7 @@ fn duplicate_test_add(context: &z3::Context, opts: &Options) -> SynthResult<Prog
 // simple——benchmarks
 
 // simple_broadcasted_add
-fn simple_broadcasted_add(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_broadcasted_add(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -276,7 +372,7 @@ fn simple_broadcasted_add(context: &z3::Context, opts: &Options) -> SynthResult<
 }
 
 // simple_with_input_names
-fn simple_with_input_names(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_with_input_names(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -298,7 +394,7 @@ fn simple_with_input_names(context: &z3::Context, opts: &Options) -> SynthResult
 }
 
 // simple_cast
-fn simple_cast(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_cast(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -308,10 +404,10 @@ fn simple_cast(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
     let in1 = builder.var(input1);
 
-    let _ = builder.tf_cast(in1);
+    let _ = builder.tf_cast(in1, DType::Float);
     let spec = builder.finish();
 
-    return synthesize(opts, context, &spec, &library); 
+    synthesize(opts, context, &spec, &library)
 }
 
 //simple_index 直接数组下标操作 in1[in2] 与项目无关，无法实现
@@ -320,7 +416,7 @@ fn simple_cast(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
 // simple_sparse_add
 // 暂时先不管啥稠密张量稀疏张量，能满足二维数组就行，并且有些还不符合要求呢
-fn simple_sparse_add(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_sparse_add(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -343,7 +439,7 @@ fn simple_sparse_add(context: &z3::Context, opts: &Options) -> SynthResult<Progr
 
 // simple_add_big_tensors
 // 我们的数组是4*10的，所以把超过4（第二个输入要expanddims所以看最多4个）的部分砍掉
-fn simple_add_big_tensors(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_add_big_tensors(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -366,7 +462,7 @@ fn simple_add_big_tensors(context: &z3::Context, opts: &Options) -> SynthResult<
 
 // simple_using_constant
 // 目前先用变量代替常量
-fn simple_using_constant(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_using_constant(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -388,20 +484,18 @@ fn simple_using_constant(context: &z3::Context, opts: &Options) -> SynthResult<P
 
 // simple_using_output_shape
 // 我们的数组是4*10的，所以把5改成了4
-fn simple_using_output_shape(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_using_output_shape(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
-    let mut input1 : Vec<Vec<i64>> = Vec::new();   
-    input1.push(vec![7]);
+    let input1: Vec<Vec<i64>> = vec![vec![7]];
 
-    let mut input2 : Vec<Vec<i64>> = Vec::new();   
-    input2.push(vec![4]);
+    let input2: Vec<Vec<i64>> = vec![vec![4]];
 
-    let mut input3 : Vec<Vec<i64>> = Vec::new();   
-    input3.push(vec![4]);
+    let input3: Vec<Vec<i64>> = vec![vec![4]];
 
-    let in1 = builder.var(input1);
+    // tf.eye得到的是浮点数，所以乘上去的标量也得是浮点数
+    let in1 = builder.var_with_dtype(input1, DType::Float);
     let in2 = builder.var(input2);
     let in3 = builder.var(input3);
 
@@ -415,7 +509,7 @@ fn simple_using_output_shape(context: &z3::Context, opts: &Options) -> SynthResu
 
 // simple_using_output_shape_tuple 
 // 我们的数组是4*10的，所以把2, 3, 4, 5改成了2, 3
-fn simple_using_output_shape_tuple(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_using_output_shape_tuple(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -433,7 +527,7 @@ fn simple_using_output_shape_tuple(context: &z3::Context, opts: &Options) -> Syn
 
 // simple_using_boolean_constant
 // 将稀疏张量手动设置为连续的张量
-fn simple_using_boolean_constant(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_using_boolean_constant(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -453,7 +547,7 @@ fn simple_using_boolean_constant(context: &z3::Context, opts: &Options) -> Synth
 //todo simple_using_constant_kwarg tf.argsort
 
 // simple_using_primitive_input
-fn simple_using_primitive_input(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_using_primitive_input(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -477,7 +571,7 @@ fn simple_using_primitive_input(context: &z3::Context, opts: &Options) -> SynthR
 
 // simple_output_equals_input_single
 // 直接相等，我们采用constant来等价
-fn simple_output_equals_input_single(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_output_equals_input_single(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -495,7 +589,7 @@ fn simple_output_equals_input_single(context: &z3::Context, opts: &Options) -> S
 
 // simple_output_equals_input_multiple
 // 我们自己改成多个输入不就好了？
-fn simple_output_equals_input_multiple(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_output_equals_input_multiple(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -521,7 +615,7 @@ fn simple_output_equals_input_multiple(context: &z3::Context, opts: &Options) ->
 
 // simple_output_equals_constant
 // 我们自己改成多个输入找常量不就好了？
-fn simple_output_equals_constant(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn simple_output_equals_constant(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -553,7 +647,7 @@ fn simple_output_equals_constant(context: &z3::Context, opts: &Options) -> Synth
 
 // google_01
 // 原来的样例超出4*10范围，将[0, 0, 0, 1, 3, 3]改成[0, 0, 0, 1]
-fn google_01(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_01(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -566,15 +660,15 @@ fn google_01(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
     let o1 = builder.tf_bincount(in1);
     let o2 = builder.tf_sequence_mask(o1);
     let o3 = builder.tf_where1(o2);
-    let _ = builder.tf_cast(o3);
+    let _ = builder.tf_cast(o3, DType::Int);
     let spec = builder.finish();
 
-    return synthesize(opts, context, &spec, &library); 
+    synthesize(opts, context, &spec, &library)
 }
 
 // google_02
 // 浮点数手动调整为整数
-fn google_02(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_02(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -607,7 +701,7 @@ fn google_02(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 // google_05 无法实现，维度已经超过二维
 
 // google_06
-fn google_06(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_06(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -630,7 +724,7 @@ fn google_06(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 //todo google_07 出现多次，暂时无法实现
 
 // google_08
-fn google_08(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_08(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -651,16 +745,16 @@ fn google_08(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
     let o1 = builder.tf_expand_dims(in1);
     let o2 = builder.tf_range(in2, in3);
     let o3 = builder.tf_greater(o1, o2);
-    let _ = builder.tf_cast(o3);
+    let _ = builder.tf_cast(o3, DType::Int);
     let spec = builder.finish();
 
-    return synthesize(opts, context, &spec, &library); 
+    synthesize(opts, context, &spec, &library)
 }
 
 //todo google_09 tf.gather tf.argsort
 
 // google_10
-fn google_10(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_10(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -671,7 +765,7 @@ fn google_10(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
     input2.push(vec![1, 1, 0, 1, 0, 1]);
 
     let in1 = builder.var(input1);
-    let in2 = builder.var(input2);
+    let in2 = builder.var_with_dtype(input2, DType::Bool);
 
     let _ = builder.tf_boolean_mask(in1, in2);
     let spec = builder.finish();
@@ -681,7 +775,7 @@ fn google_10(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
 // google_11 
 // 用到了浮点数转换为整数，目前可以将输入手动转为整数
-fn google_11(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_11(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -702,7 +796,7 @@ fn google_11(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
     let in2 = builder.var(input2);
 
     let o1 = builder.tf_greater(in1, in2);
-    let o2 = builder.tf_cast(o1);
+    let o2 = builder.tf_cast(o1, DType::Int);
     let _ = builder.tf_reduce_sum1(o2);
     let spec = builder.finish();
 
@@ -712,7 +806,7 @@ fn google_11(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 // google_12
 // 用到了浮点数转换为整数，目前可以将输入手动转为整数
 // 手动实现logical_and
-fn google_12(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_12(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -724,14 +818,14 @@ fn google_12(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
     let in1 = builder.var(input1);
 
-    let _ = builder.tf_cast(in1);
+    let _ = builder.tf_cast(in1, DType::Int);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
 }
 
 // google_13
-fn google_13(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_13(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -753,7 +847,7 @@ fn google_13(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 }
 
 // google_14
-fn google_14(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_14(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -764,7 +858,7 @@ fn google_14(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
     let in1 = builder.var(input1);
 
-    let _ = builder.tf_roll(in1);
+    let _ = builder.tf_roll(in1, 1, 1);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -775,24 +869,20 @@ fn google_14(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 //todo google_16 tf.gather
 
 // google_17
-// 输入的布尔值手动改为整数
-fn google_17(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+// 输入的布尔值用1 0表示，并标成Bool类型
+fn google_17(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
-    let mut input1 : Vec<Vec<i64>> = Vec::new();
-    input1.push(vec![1, 0, 0, 1, 0]);
+    let input1: Vec<Vec<i64>> = vec![vec![1, 0, 0, 1, 0]];
 
-    let mut input2 : Vec<Vec<i64>> = Vec::new();
-    input2.push(vec![1, 2, 3, 4, 5]);
+    let input2: Vec<Vec<i64>> = vec![vec![1, 2, 3, 4, 5]];
 
-    let mut input3 : Vec<Vec<i64>> = Vec::new();
-    input3.push(vec![-10, -10, -10, -10, -10]);
+    let input3: Vec<Vec<i64>> = vec![vec![-10, -10, -10, -10, -10]];
 
-    let mut input4 : Vec<Vec<i64>> = Vec::new();
-    input4.push(vec![1, 2, 3, 4, 5]);
+    let input4: Vec<Vec<i64>> = vec![vec![1, 2, 3, 4, 5]];
 
-    let in1 = builder.var(input1);
+    let in1 = builder.var_with_dtype(input1, DType::Bool);
     let in2 = builder.var(input2);
     let in3 = builder.var(input3);
     let in4 = builder.var(input4);
@@ -807,7 +897,7 @@ fn google_17(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 // google_18
 // 采用等价的方式进行处理
 // 超出4*10范围，[5, 7, -12, 10, 20], [1, 2, 3, 1, 2]改为[5, 7, -12, 10], [1, 2, 3, 1]
-fn google_18(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn google_18(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -826,8 +916,9 @@ fn google_18(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
     let o1 = builder.tf_expand_dims(in2);
     let o2 = builder.tf_equal(o1, in3);
-    let o3 = builder.tf_cast(o2);
-    let _ = builder.tf_matmul(o3, in1);
+    let o3 = builder.tf_cast(o2, DType::Int);
+    let o4 = builder.tf_expand_dims(in1);
+    let _ = builder.tf_matmul(o3, o4);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -845,7 +936,7 @@ fn google_18(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
 // stackoverflow_01
 // 原本的测试样例是矩阵翻转之后又复制了一遍，现在就不复制了，原本的小数改为整数
-fn stackoverflow_01(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_01(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -856,7 +947,7 @@ fn stackoverflow_01(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
     let in1 = builder.var(input1);
 
-    let o1 = builder.tf_cast(in1);
+    let o1 = builder.tf_cast(in1, DType::Int);
     let _ = builder.tf_transpose(o1);
     let spec = builder.finish();
 
@@ -864,7 +955,7 @@ fn stackoverflow_01(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 }
 
 // stackoverflow_02
-fn stackoverflow_02(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_02(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -890,7 +981,7 @@ fn stackoverflow_02(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_05
 // 原来的第二个输入需要按照列进行遍历，手动改为列遍历后的结果
-fn stackoverflow_05(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_05(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -914,7 +1005,7 @@ fn stackoverflow_05(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_06
 // 我们的数组是4*10的，所以把3, 5, 0, 2, 3, 3, 0改成3, 5, 0, 2
-fn stackoverflow_06(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_06(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -929,7 +1020,7 @@ fn stackoverflow_06(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
     let o1 = builder.tf_expand_dims(in1);
     let o2 = builder.tf_equal(in2, o1);
-    let _ = builder.tf_cast(o2);
+    let _ = builder.tf_cast(o2, DType::Int);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -941,7 +1032,7 @@ fn stackoverflow_06(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 // 原输入超过十个，手动调整为十个以内,
 // [-1, 0, -3, 2, 1, 3, 5, -1, -9, 2, 10]为[-1, 0, -3, 2, 1, 3, 5, -1, -9, 2]
 // [12, 3, 45, 6, 7, 8, 9, 87, 65, 4, 32]为[12, 3, 45, 6, 7, 8, 9, 87, 65, 4]
-fn stackoverflow_08(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_08(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -972,7 +1063,7 @@ fn stackoverflow_08(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_11
 // 我们的数组是4*10的，所以把4, 0, 1, 1, 0, 4, 0, 0, 3, 4, 1改成4, 0, 1, 1, 0, 4, 0, 0, 3, 4
-fn stackoverflow_11(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_11(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -991,7 +1082,7 @@ fn stackoverflow_11(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_13
 // 原本的输入维度高于二维，手动改为二维 [[[1, 0], [5, 4]], [[3, 10], [2, -2]]]改为[[1, 0], [5, 4]]
-fn stackoverflow_13(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_13(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1016,7 +1107,7 @@ fn stackoverflow_13(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 // 超出4*10范围，[[False, False, True], [False, False, False], [True, False, True], [False, True, False], [False, False, False],
 // [True, True, True], [True, True, False]]修改为[[False, False, True], [False, False, False], [True, False, True], [False, True, False]]
 // 用1 0代替true false
-fn stackoverflow_14(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_14(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1026,7 +1117,7 @@ fn stackoverflow_14(context: &z3::Context, opts: &Options) -> SynthResult<Progra
     input1.push(vec![1, 0, 1]);
     input1.push(vec![0, 1, 0]);
 
-    let in1 = builder.var(input1);
+    let in1 = builder.var_with_dtype(input1, DType::Bool);
 
     let _ = builder.tf_reduce_any1(in1);
     let spec = builder.finish();
@@ -1035,7 +1126,7 @@ fn stackoverflow_14(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 }
 
 // stackoverflow_15
-fn stackoverflow_15(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_15(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1054,7 +1145,7 @@ fn stackoverflow_15(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
     let o1 = builder.tf_constant(in2);
     let o2 = builder.tf_equal(in1, o1);
-    let o3 = builder.tf_cast(o2);
+    let o3 = builder.tf_cast(o2, DType::Int);
     let _ = builder.tf_subtract(in3, o3);
     let spec = builder.finish();
 
@@ -1062,7 +1153,7 @@ fn stackoverflow_15(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 }
 
 // stackoverflow_16
-fn stackoverflow_16(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_16(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1086,7 +1177,7 @@ fn stackoverflow_16(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_17
 // tf.stack和tf.concat等价
-fn stackoverflow_17(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_17(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1111,7 +1202,7 @@ fn stackoverflow_17(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_20
 // 原输入超过4行，删除最后一行，将小数修正为整数
-fn stackoverflow_20(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_20(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1127,9 +1218,9 @@ fn stackoverflow_20(context: &z3::Context, opts: &Options) -> SynthResult<Progra
     let in1 = builder.var(input1);
     let in2 = builder.var(input2);
 
-    let o1 = builder.tf_argmax(in1);
+    let o1 = builder.tf_argmax(in1, 1);
     let o2 = builder.tf_one_hot(o1, in2);
-    let _ = builder.tf_cast(o2);
+    let _ = builder.tf_cast(o2, DType::Int);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -1139,7 +1230,7 @@ fn stackoverflow_20(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_22
 // 原本第二个输入是小数，手动改成整数
-fn stackoverflow_22(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_22(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1154,7 +1245,7 @@ fn stackoverflow_22(context: &z3::Context, opts: &Options) -> SynthResult<Progra
     let in1 = builder.var(input1);
     let in2 = builder.var(input2);
 
-    let o1 = builder.tf_cast(in1);
+    let o1 = builder.tf_cast(in1, DType::Int);
     let _ = builder.tf_tensordot(o1, in2);
     let spec = builder.finish();
 
@@ -1165,7 +1256,7 @@ fn stackoverflow_22(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_24
 // 原本输入是小数，手动改成整数
-fn stackoverflow_24(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_24(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1183,10 +1274,10 @@ fn stackoverflow_24(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
     let in1 = builder.var(input1);
     let in2 = builder.var(input2);
-    let in3 = builder.var(input3);
+    let in3 = builder.var_with_dtype(input3, DType::Float);
     let in4 = builder.var(input4);
 
-    let o1 = builder.tf_cast(in2);
+    let o1 = builder.tf_cast(in2, DType::Bool);
     let o2 = builder.tf_divide(in1, in4);
     let _ = builder.tf_where3(o1, o2, in3);
     let spec = builder.finish();
@@ -1198,7 +1289,7 @@ fn stackoverflow_24(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_26 
 // 由于原输入是三维的，手动实现第一次reducesum
-fn stackoverflow_26(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_26(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -1217,7 +1308,7 @@ fn stackoverflow_26(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 }
 
 // stackoverflow_27
-fn stackoverflow_27(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_27(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -1233,10 +1324,10 @@ fn stackoverflow_27(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
     let o1 = builder.tf_one_hot(in1, in2);
     let o2 = builder.tf_reduce_max0(o1);
-    let _ = builder.tf_cast(o2);
+    let _ = builder.tf_cast(o2, DType::Int);
     let spec = builder.finish();
 
-    return synthesize(opts, context, &spec, &library); 
+    synthesize(opts, context, &spec, &library)
 }
 
 // stackoverflow_28 无法实现，维度超过二维
@@ -1248,7 +1339,7 @@ fn stackoverflow_27(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 // stackoverflow_31
 // 手动将稀疏张量变为普通张量
 // 将浮点数变为整数
-fn stackoverflow_31(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_31(context: &term::Context, opts: &Options) -> SynthResult<Program> {
 
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
@@ -1274,7 +1365,7 @@ fn stackoverflow_31(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_32
 // 由于tensordot第二个参数方向是纵轴方向，所以自己手动用expand_dims调整，由于是小数，调整为整数
-fn stackoverflow_32(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_32(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1294,7 +1385,7 @@ fn stackoverflow_32(context: &z3::Context, opts: &Options) -> SynthResult<Progra
     let in3 = builder.var(input3);
 
     let o1 = builder.tf_range(in2, in3);
-    let o2 = builder.tf_cast(o1);
+    let o2 = builder.tf_cast(o1, DType::Int);
     let o3 = builder.tf_expand_dims(o2);
     let _ = builder.tf_tensordot(in1, o3);
     let spec = builder.finish();
@@ -1306,7 +1397,7 @@ fn stackoverflow_32(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_34
 // 原本第一个输入不是二维数组，手动选取二维数组，[[[1, 2], [3, 4]], [[5, 6], [7, 8]], [[10, 20], [30, 40]]]为[[1, 2]], [[5, 6]], [[10, 20]]
-fn stackoverflow_34(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_34(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1328,33 +1419,36 @@ fn stackoverflow_34(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 }
 
 // stackoverflow_35
+// 输入改成4行后，第三个输入也要补成4个元素[1, 4, 8, 2]，否则expand_dims的[3, 1]和[4, 2]无法广播
 // 我们的数组是4*10的，所以把[[[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]], [[10., 20.], [30., 40.], [50., 60.]]], 
 // [[[9.0, 8.0], [7.0, 6.0], [5.0, 4.0]], [[90., 80.], [70., 60.], [50., 40.]]] 改成
 // [1.0, 2.0], [3.0, 4.0], [5.0, 6.0], [10., 20.]和[9.0, 8.0], [7.0, 6.0], [5.0, 4.0], [90., 80.]
-fn stackoverflow_35(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_35(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
-    let mut input1 : Vec<Vec<i64>> = Vec::new();
-    input1.push(vec![1, 2]);
-    input1.push(vec![3, 4]);
-    input1.push(vec![5, 6]);
-    input1.push(vec![10, 20]);
+    let input1: Vec<Vec<i64>> = vec![
+        vec![1, 2],
+        vec![3, 4],
+        vec![5, 6],
+        vec![10, 20],
+    ];
 
-    let mut input2 : Vec<Vec<i64>> = Vec::new();
-    input2.push(vec![9, 8]);
-    input2.push(vec![7, 6]);
-    input2.push(vec![5, 4]);
-    input2.push(vec![90, 80]);
+    let input2: Vec<Vec<i64>> = vec![
+        vec![9, 8],
+        vec![7, 6],
+        vec![5, 4],
+        vec![90, 80],
+    ];
 
-    let mut input3 : Vec<Vec<i64>> = Vec::new();
-    input3.push(vec![1, 4, 8]);
+    let input3: Vec<Vec<i64>> = vec![vec![1, 4, 8, 2]];
 
-    let mut input4 : Vec<Vec<i64>> = Vec::new();
-    input4.push(vec![9, 8]);
-    input4.push(vec![7, 6]);
-    input4.push(vec![5, 4]);
-    input4.push(vec![90, 80]);
+    let input4: Vec<Vec<i64>> = vec![
+        vec![9, 8],
+        vec![7, 6],
+        vec![5, 4],
+        vec![90, 80],
+    ];
 
     let in1 = builder.var(input1);
     let in2 = builder.var(input2);
@@ -1371,7 +1465,7 @@ fn stackoverflow_35(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 }
 
 // stackoverflow_36
-fn stackoverflow_36(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_36(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1395,7 +1489,7 @@ fn stackoverflow_36(context: &z3::Context, opts: &Options) -> SynthResult<Progra
     let o1 = builder.tf_range(in2, in3);
     let o2 = builder.tf_add(in1, o1);
     let o3 = builder.tf_divide(in4, o2);
-    let _ = builder.tf_cast(o3);
+    let _ = builder.tf_cast(o3, DType::Int);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -1405,7 +1499,7 @@ fn stackoverflow_36(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 // 第一个输入不是二维的，手动修改[[[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]], [[1.2, 3.4, 5.6], [7.8, 9.8, 7.6]]]],为[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]，[1.2, 3.4, 5.6], [7.8, 9.8, 7.6]]
 // 输入是小数，手动修改为整数
 // 由于tensordot第二个参数方向是纵轴方向，所以自己手动用expand_dims调整，由于是小数，调整为整数
-fn stackoverflow_37(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_37(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1429,7 +1523,7 @@ fn stackoverflow_37(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 }
 
 // stackoverflow_38
-fn stackoverflow_38(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_38(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1465,7 +1559,7 @@ fn stackoverflow_38(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 // stackoverflow_39
 // 由于类型只有整数，并且不允许多次调用同一个表达式，因此自行优化[[-1.5, 1.0, 0.9, 2.0], [1.1, 0.0, -0.1, -0.9], [-1.0, 0.1, -1.1, 2.5]]为
 // [[-15, 1, 0, 2], [1, 0, 0, 0], [-1, 0, -11, 25]]
-fn stackoverflow_39(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_39(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1482,7 +1576,7 @@ fn stackoverflow_39(context: &z3::Context, opts: &Options) -> SynthResult<Progra
     let in1 = builder.var(input1);
     let in2 = builder.var(input2);
 
-    let o1 = builder.tf_cast(in1);
+    let o1 = builder.tf_cast(in1, DType::Int);
     let o2 = builder.tf_square(o1);
     let _ = builder.tf_multiply(o2, in2);
     let spec = builder.finish();
@@ -1493,7 +1587,7 @@ fn stackoverflow_39(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 //todo stackoverflow_40 tf.sparse.to_dense tf.SparseTensor
 
 // stackoverflow_41
-fn stackoverflow_41(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_41(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1524,7 +1618,7 @@ fn stackoverflow_41(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 }
 
 // stackoverflow_42 
-fn stackoverflow_42(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_42(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1539,7 +1633,7 @@ fn stackoverflow_42(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
     let o1 = builder.tf_reduce_max(in1);
     let o2 = builder.tf_equal(in2, o1);
-    let _ = builder.tf_cast(o2);
+    let _ = builder.tf_cast(o2, DType::Int);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -1552,7 +1646,7 @@ fn stackoverflow_42(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 // stackoverflow_45
 // 原本的输入是三维的，为了适应4*10的数组
 // [[[12, 34], [56, 78], [23, 54], [76, 78], [42, 24]]]改为[[12, 34], [56, 78], [23, 54], [76, 78]]， [1, 0, 1, 0, 1]改为[1, 0, 1, 0]
-fn stackoverflow_45(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_45(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1576,7 +1670,7 @@ fn stackoverflow_45(context: &z3::Context, opts: &Options) -> SynthResult<Progra
     let in3 = builder.var(input3);
 
     let o1 = builder.tf_sequence_mask(in2);
-    let o2 = builder.tf_roll(in1);
+    let o2 = builder.tf_roll(in1, 1, 1);
     let _ = builder.tf_where3(o1, o2, in3);
     let spec = builder.finish();
 
@@ -1585,7 +1679,7 @@ fn stackoverflow_45(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_46
 // 输入超出范围，[3, 4, 1]改成[1, 2]
-fn stackoverflow_46(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_46(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1596,7 +1690,7 @@ fn stackoverflow_46(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
     let o1 = builder.tf_sequence_mask(in1);
     let o2 = builder.tf_where1(o1);
-    let _ = builder.tf_cast(o2);
+    let _ = builder.tf_cast(o2, DType::Int);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -1605,23 +1699,22 @@ fn stackoverflow_46(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 // stackoverflow_47 无法实现，出现重复的表达式和其他运算
 
 // stackoverflow_48
-fn stackoverflow_48(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+// expand_dims之后是[6, 1]，和[1, 8]比较的结果超出了4*10，所以第二个输入只保留前4个元素[38, 53, 89, 38]
+fn stackoverflow_48(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
-    let mut input1 : Vec<Vec<i64>> = Vec::new();
-    input1.push(vec![32, 53, 45, 38, 29, 89, 64, 23]);
+    let input1: Vec<Vec<i64>> = vec![vec![32, 53, 45, 38, 29, 89, 64, 23]];
 
-    let mut input2 : Vec<Vec<i64>> = Vec::new();
-    input2.push(vec![38, 53, 89, 38, 32, 64]);
+    let input2: Vec<Vec<i64>> = vec![vec![38, 53, 89, 38]];
 
     let in1 = builder.var(input1);
     let in2 = builder.var(input2);
 
     let o1 = builder.tf_expand_dims(in2);
     let o2 = builder.tf_equal(in1, o1);
-    let o3 = builder.tf_cast(o2);
-    let _ = builder.tf_argmax(o3);
+    let o3 = builder.tf_cast(o2, DType::Int);
+    let _ = builder.tf_argmax(o3, 1);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -1631,7 +1724,7 @@ fn stackoverflow_48(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
 // stackoverflow_50
 // 原输入有5行，修改为4行，手动调整python的数组格式(5,)为[1, 5]
-fn stackoverflow_50(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn stackoverflow_50(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1650,7 +1743,7 @@ fn stackoverflow_50(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 
     let o1 = builder.tf_fill(in1, in3);
     let o2 = builder.tf_one_hot(o1, in2);
-    let _ = builder.tf_cast(o2);
+    let _ = builder.tf_cast(o2, DType::Int);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -1667,7 +1760,7 @@ fn stackoverflow_50(context: &z3::Context, opts: &Options) -> SynthResult<Progra
 // autopandas4
 // 原输入超出4*10范围，[[1, 2, 3, 4, 5], [9, 8, 7, 6, 5], [3, 0, 2, 5, 8], [8, 8, 6, 3, 2], [2, 0, 7, 7, 3], [9, 0, 3, 2, 7], [1, 3, 8, 9, 4]]改为
 // [[1, 2, 3, 4, 5], [9, 8, 7, 6, 5], [3, 0, 2, 5, 8], [8, 8, 6, 3, 2]]，并手动实现其中的取下标
-fn autopandas4(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn autopandas4(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1683,7 +1776,7 @@ fn autopandas4(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
     let in1 = builder.var(input1);
     let in2 = builder.var(input2);
 
-    let o1 = builder.tf_cast(in2);
+    let o1 = builder.tf_cast(in2, DType::Bool);
     let _ = builder.tf_boolean_mask_(in1, o1);
     let spec = builder.finish();
 
@@ -1698,7 +1791,7 @@ fn autopandas4(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
 // autopandas8
 // 原输入超出4*10范围，[[5, 7], [6, 8], [-1, 9], [-2, 10], [2, 11], [1, 12], [3, -3]]改为[[5, 7], [6, 8], [-1, 9], [-2, 10]]，并手动实现其中的取下标
-fn autopandas8(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn autopandas8(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1730,7 +1823,7 @@ fn autopandas8(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 // autopandas10
 // 手动实现tf.math.logical_not(tf.math.is_nan(in1))，为[0, 1, 1, 0, 1, 1]
 // [float('nan'), 11, 12, float('nan'), 16, 18]改为[-1, 11, 12, -1, 16, 18]
-fn autopandas10(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn autopandas10(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1741,10 +1834,10 @@ fn autopandas10(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
     input2.push(vec![0, 1, 1, 0, 1, 1]);
 
     let in1 = builder.var(input1);
-    let in2 = builder.var(input2);
+    let in2 = builder.var_with_dtype(input2, DType::Bool);
 
     let o1 = builder.tf_boolean_mask(in1, in2);
-    let _ = builder.tf_cast(o1);
+    let _ = builder.tf_cast(o1, DType::Int);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
@@ -1752,16 +1845,17 @@ fn autopandas10(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
 // autopandas11
 // 暂时还没实现expand_dims中axis=0的实现，所以先用个中间结果保持住
-fn autopandas11(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+// 转置后是[5, 3]，超出了4*10，所以输入只保留前4列
+fn autopandas11(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
-    let mut input1 : Vec<Vec<i64>> = Vec::new();
-    input1.push(vec![1, 4, 2, 7, 6]);
-    input1.push(vec![20, 10, 50, 40, 30]);
+    let input1: Vec<Vec<i64>> = vec![
+        vec![1, 4, 2, 7],
+        vec![20, 10, 50, 40],
+    ];
 
-    let mut input2 : Vec<Vec<i64>> = Vec::new();
-    input2.push(vec![0, 1, 2, 3, 4]);
+    let input2: Vec<Vec<i64>> = vec![vec![0, 1, 2, 3]];
 
     let in1 = builder.var(input1);
     let in2 = builder.var(input2);
@@ -1780,7 +1874,7 @@ fn autopandas11(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 // [106, 5, 16, 5], [107, 6, 17, 4], [108, 7, 18, 7], [109, 8, 19, 7], [110, 9, 20, 4]]修改为
 // [[103, 2, 13, 2], [105, 4, 15, 4], [107, 6, 17, 4], [108, 7, 18, 7]]
 // 手动实现[:, 1]切片
-fn autopandas13(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn autopandas13(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1811,7 +1905,7 @@ fn autopandas13(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
 // autopandas14
 // 原数据是float(nan)，自己改成-1
-fn autopandas14(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn autopandas14(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1832,7 +1926,7 @@ fn autopandas14(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
     let in3 = builder.var(input3);
     
     let o1 = builder.tf_fill(in2, in3);
-    let o2 = builder.tf_cast(in1);
+    let o2 = builder.tf_cast(in1, DType::Int);
     let _ = builder.tf_concat1(o2, o1);
     let spec = builder.finish();
 
@@ -1840,27 +1934,23 @@ fn autopandas14(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 }
 
 // autopandas15
-fn autopandas15(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn autopandas15(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
     let mut input1 : Vec<Vec<i64>> = Vec::new();
     input1.push(vec![1, 1, 2, 1, 3, 2]);
 
-    let mut input2 : Vec<Vec<i64>> = Vec::new();
-    input2.push(vec![0]);
-
     let in1 = builder.var(input1);
-    let in2 = builder.var(input2);
     
-    let _ = builder.tf_cumsum(in1, in2);
+    let _ = builder.tf_cumsum(in1, 1, false, false);
     let spec = builder.finish();
 
     synthesize(opts, context, &spec, &library)
 }
 
 // autopandas16
-fn autopandas16(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
+fn autopandas16(context: &term::Context, opts: &Options) -> SynthResult<Program> {
     let library = Library::brahma_std();
     let mut builder = ProgramBuilder::new();
 
@@ -1872,7 +1962,7 @@ fn autopandas16(context: &z3::Context, opts: &Options) -> SynthResult<Program> {
 
     let in1 = builder.var(input1);
     
-    let o1 = builder.tf_cast(in1);
+    let o1 = builder.tf_cast(in1, DType::Float);
     let _ = builder.tf_reduce_mean(o1);
     let spec = builder.finish();
 
@@ -0,0 +1,1258 @@
+//! Solver backends.
+//!
+//! Our encodings are built as solver-independent terms (see `term`), and a
+//! `Backend` decides them: either in-process with Z3 (`Z3Backend`, the
+//! default), which translates them into Z3's own terms, or by printing them
+//! as SMT-LIB2 for any solver that reads it on its standard input
+//! (`SmtLib2Backend`), such as cvc5 or yices. Z3 is only used by `Z3Backend`.
+//!
+//! A backend only needs to answer the questions the synthesizer asks: is the
+//! query satisfiable, what do terms over the query's constants evaluate to in
+//! a model, and which assumptions are in an unsat core. Models are the values
+//! of the query's constants, and we evaluate terms in them ourselves, the same
+//! way whichever solver found them.
+
+use crate::report::SolverStatistics;
+use crate::term::{self, Bool, Dynamic, Int, Op, Sort, Term, Value, Wrap};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use z3::ast::Ast as _;
+
+/// Whether a query is satisfiable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SatResult {
+    Unsat,
+    Unknown,
+    Sat,
+}
+
+/// A satisfying assignment found by a backend.
+pub trait Model<'ctx> {
+    /// The value of `term` in this model, or `None` if it doesn't evaluate
+    /// to an integer that fits in an `i64`.
+    ///
+    /// Constants that don't appear in the query can take any value; like Z3's
+    /// model completion, they are taken to be zero.
+    fn eval_int(&self, term: &Int<'ctx>) -> Option<i64>;
+
+    /// The value of `term` in this model, as a literal of `term`'s sort, or
+    /// `None` if it doesn't evaluate to one. Missing constants are zero, as
+    /// for `eval_int`.
+    fn eval(&self, term: &Dynamic<'ctx>) -> Option<Dynamic<'ctx>>;
+}
+
+/// The answer to a single query.
+pub enum CheckResult<'ctx> {
+    Sat(Box<dyn Model<'ctx> + 'ctx>),
+    /// The query is unsatisfiable, and these are the indices of the
+    /// assumptions in the unsat core (empty if there were no assumptions).
+    Unsat(Vec<usize>),
+    /// The solver gave up, for the given reason.
+    Unknown(String),
+}
+
+impl<'ctx> CheckResult<'ctx> {
+    pub fn sat_result(&self) -> SatResult {
+        match self {
+            CheckResult::Sat(_) => SatResult::Sat,
+            CheckResult::Unsat(_) => SatResult::Unsat,
+            CheckResult::Unknown(_) => SatResult::Unknown,
+        }
+    }
+}
+
+impl fmt::Debug for CheckResult<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckResult::Sat(_) => write!(f, "Sat(..)"),
+            CheckResult::Unsat(core) => f.debug_tuple("Unsat").field(core).finish(),
+            CheckResult::Unknown(reason) => f.debug_tuple("Unknown").field(reason).finish(),
+        }
+    }
+}
+
+/// Something that can decide our synthesis queries.
+pub trait Backend: fmt::Debug {
+    /// Check whether `assertions` are satisfiable, together with every one of
+    /// `assumptions`, giving up after `timeout`.
+    ///
+    /// When the answer is unsat and there are assumptions, the result carries
+    /// an unsat core over them.
+    fn check<'ctx>(
+        &self,
+        assertions: &[Bool<'ctx>],
+        assumptions: &[Bool<'ctx>],
+        timeout: Option<Duration>,
+    ) -> CheckResult<'ctx>;
+
+    /// Like `check`, but also report what the solver said about the query.
+    /// By default, nothing is reported.
+    fn check_with_statistics<'ctx>(
+        &self,
+        assertions: &[Bool<'ctx>],
+        assumptions: &[Bool<'ctx>],
+        timeout: Option<Duration>,
+    ) -> (CheckResult<'ctx>, SolverStatistics) {
+        let result = self.check(assertions, assumptions, timeout);
+        (result, SolverStatistics::default())
+    }
+}
+
+/// Render `assertions` as SMT-LIB2 declarations and asserts.
+pub(crate) fn to_smtlib2(assertions: &[Bool]) -> String {
+    let mut script = String::new();
+    for (name, sort) in term::constants(assertions.iter().map(Wrap::term)) {
+        script.push_str(&format!("(declare-fun {} () {})\n", term::symbol(&name), sort));
+    }
+    for a in assertions {
+        script.push_str(&format!("(assert {})\n", a));
+    }
+    script
+}
+
+/// The values a backend found for the constants of a query. A constant whose
+/// value we couldn't read has no value, rather than a made-up one.
+struct Assignment(HashMap<String, Option<Value>>);
+
+impl Assignment {
+    fn evaluate(&self, term: &Term) -> Option<Value> {
+        term::evaluate(term, |name, sort| match self.0.get(name) {
+            Some(value) => value.clone(),
+            None => Some(sort.zero()),
+        })
+    }
+}
+
+impl<'ctx> Model<'ctx> for Assignment {
+    fn eval_int(&self, term: &Int<'ctx>) -> Option<i64> {
+        match self.evaluate(term.term())? {
+            Value::Int(n) => i64::try_from(n).ok(),
+            _ => None,
+        }
+    }
+
+    fn eval(&self, term: &Dynamic<'ctx>) -> Option<Dynamic<'ctx>> {
+        match self.evaluate(term.term())? {
+            Value::Array(..) => None,
+            value => Some(Dynamic::from_value(value)),
+        }
+    }
+}
+
+/// Solve in-process with Z3.
+#[derive(Clone, Debug, Default)]
+pub struct Z3Backend {
+    params: Vec<(String, String)>,
+}
+
+impl Z3Backend {
+    pub fn new() -> Self {
+        Z3Backend::default()
+    }
+
+    /// Set Z3's global configuration parameter `key` (e.g. `auto_config`) for
+    /// every query.
+    pub fn set_param(&mut self, key: &str, value: &str) -> &mut Self {
+        self.params.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Translates terms into Z3's, sharing what they share.
+struct Lowering<'z> {
+    context: &'z z3::Context,
+    terms: HashMap<usize, z3::ast::Dynamic<'z>>,
+    /// The constants we've translated, with their sorts.
+    constants: Vec<(String, Sort, z3::ast::Dynamic<'z>)>,
+}
+
+impl<'z> Lowering<'z> {
+    fn new(context: &'z z3::Context) -> Self {
+        Lowering {
+            context,
+            terms: HashMap::new(),
+            constants: vec![],
+        }
+    }
+
+    fn sort(&self, sort: &Sort) -> z3::Sort<'z> {
+        match sort {
+            Sort::Bool => z3::Sort::bool(self.context),
+            Sort::Int => z3::Sort::int(self.context),
+            Sort::Real => z3::Sort::real(self.context),
+            Sort::BitVec(width) => z3::Sort::bitvector(self.context, *width),
+            Sort::Array(domain, range) => z3::Sort::array(self.context, &self.sort(domain), &self.sort(range)),
+        }
+    }
+
+    fn lower(&mut self, roots: &[&Term]) -> Vec<z3::ast::Dynamic<'z>> {
+        term::post_order(roots.iter().copied(), |t| {
+            let lowered = self.lower_node(t);
+            self.terms.insert(t.id(), lowered);
+        });
+        roots.iter().map(|root| self.terms[&root.id()].clone()).collect()
+    }
+
+    /// Translate `t`, whose arguments we've already translated.
+    fn lower_node(&mut self, t: &Term) -> z3::ast::Dynamic<'z> {
+        use z3::ast::{Array, Bool, Dynamic, Int, Real, BV};
+
+        let context = self.context;
+        let args: Vec<_> = t.args().iter().map(|arg| &self.terms[&arg.id()]).collect();
+        let bools: Vec<_> = args.iter().filter_map(|a| a.as_bool()).collect();
+        let ints: Vec<_> = args.iter().filter_map(|a| a.as_int()).collect();
+        let reals: Vec<_> = args.iter().filter_map(|a| a.as_real()).collect();
+        let bvs: Vec<_> = args.iter().filter_map(|a| a.as_bv()).collect();
+        let is_int = |sort: &Sort| *sort == Sort::Int;
+        let on_ints = t.args().first().is_some_and(|arg| is_int(arg.sort()));
+        let fold_bvs = |op: fn(&BV<'z>, &BV<'z>) -> BV<'z>| {
+            Dynamic::from(bvs[1..].iter().fold(bvs[0].clone(), |acc, bv| op(&acc, bv)))
+        };
+
+        match t.op() {
+            Op::Const(name) => {
+                let name = name.as_str();
+                let constant: Dynamic = match t.sort() {
+                    Sort::Bool => Bool::new_const(context, name).into(),
+                    Sort::Int => Int::new_const(context, name).into(),
+                    Sort::Real => Real::new_const(context, name).into(),
+                    Sort::BitVec(width) => BV::new_const(context, name, *width).into(),
+                    Sort::Array(domain, range) => {
+                        Array::new_const(context, name, &self.sort(domain), &self.sort(range)).into()
+                    }
+                };
+                self.constants.push((name.to_string(), t.sort().clone(), constant.clone()));
+                constant
+            }
+            Op::Literal(value) => match value {
+                Value::Bool(b) => Bool::from_bool(context, *b).into(),
+                Value::Int(n) => Int::from_str(context, &n.to_string()).unwrap().into(),
+                Value::Real(n, d) => Real::from_real_str(context, &n.to_string(), &d.to_string()).unwrap().into(),
+                Value::BV(bits, width) => match u64::try_from(*bits) {
+                    Ok(bits) => BV::from_u64(context, bits, *width).into(),
+                    Err(_) => BV::from_int(&Int::from_str(context, &bits.to_string()).unwrap(), *width).into(),
+                },
+                Value::Array(..) => unreachable!(),
+            },
+            Op::Not => bools[0].not().into(),
+            Op::And => Bool::and(context, &bools.iter().collect::<Vec<_>>()).into(),
+            Op::Or => Bool::or(context, &bools.iter().collect::<Vec<_>>()).into(),
+            Op::Xor => bools[1..].iter().fold(bools[0].clone(), |acc, b| acc.xor(b)).into(),
+            Op::Implies => bools[0].implies(&bools[1]).into(),
+            Op::Eq => args[0]._eq(args[1]).into(),
+            Op::Distinct => Dynamic::distinct(context, &args).into(),
+            Op::Ite => bools[0].ite(args[1], args[2]),
+            Op::Add if is_int(t.sort()) => Int::add(context, &ints.iter().collect::<Vec<_>>()).into(),
+            Op::Add => Real::add(context, &reals.iter().collect::<Vec<_>>()).into(),
+            Op::Sub if is_int(t.sort()) => Int::sub(context, &ints.iter().collect::<Vec<_>>()).into(),
+            Op::Sub => Real::sub(context, &reals.iter().collect::<Vec<_>>()).into(),
+            Op::Mul if is_int(t.sort()) => Int::mul(context, &ints.iter().collect::<Vec<_>>()).into(),
+            Op::Mul => Real::mul(context, &reals.iter().collect::<Vec<_>>()).into(),
+            Op::Neg if is_int(t.sort()) => ints[0].unary_minus().into(),
+            Op::Neg => reals[0].unary_minus().into(),
+            Op::Div => ints[0].div(&ints[1]).into(),
+            Op::Mod => ints[0].modulo(&ints[1]).into(),
+            Op::RealDiv => reals[0].div(&reals[1]).into(),
+            Op::Lt if on_ints => ints[0].lt(&ints[1]).into(),
+            Op::Lt => reals[0].lt(&reals[1]).into(),
+            Op::Le if on_ints => ints[0].le(&ints[1]).into(),
+            Op::Le => reals[0].le(&reals[1]).into(),
+            Op::Gt if on_ints => ints[0].gt(&ints[1]).into(),
+            Op::Gt => reals[0].gt(&reals[1]).into(),
+            Op::Ge if on_ints => ints[0].ge(&ints[1]).into(),
+            Op::Ge => reals[0].ge(&reals[1]).into(),
+            Op::ToReal => Real::from_int(&ints[0]).into(),
+            Op::ToInt => reals[0].to_int().into(),
+            Op::BvAdd => fold_bvs(BV::bvadd),
+            Op::BvSub => fold_bvs(BV::bvsub),
+            Op::BvMul => fold_bvs(BV::bvmul),
+            Op::BvNeg => bvs[0].bvneg().into(),
+            Op::BvSdiv => bvs[0].bvsdiv(&bvs[1]).into(),
+            Op::BvSrem => bvs[0].bvsrem(&bvs[1]).into(),
+            Op::BvSmod => bvs[0].bvsmod(&bvs[1]).into(),
+            Op::BvUdiv => bvs[0].bvudiv(&bvs[1]).into(),
+            Op::BvUrem => bvs[0].bvurem(&bvs[1]).into(),
+            Op::BvSlt => bvs[0].bvslt(&bvs[1]).into(),
+            Op::BvSle => bvs[0].bvsle(&bvs[1]).into(),
+            Op::BvUlt => bvs[0].bvult(&bvs[1]).into(),
+            Op::BvUle => bvs[0].bvule(&bvs[1]).into(),
+            Op::Bv2Nat => bvs[0].to_int(false).into(),
+            Op::Int2Bv => match t.sort() {
+                Sort::BitVec(width) => BV::from_int(&ints[0], *width).into(),
+                _ => unreachable!(),
+            },
+            Op::Select => args[0].as_array().unwrap().select(args[1]),
+            Op::Store => args[0].as_array().unwrap().store(args[1], args[2]).into(),
+            Op::ConstArray => match t.sort() {
+                Sort::Array(domain, _) => Array::const_array(context, &self.sort(domain), args[0]).into(),
+                _ => unreachable!(),
+            },
+        }
+    }
+}
+
+/// The statistics Z3 keeps for `solver`'s last check.
+///
+/// The `z3` crate doesn't expose them, so we read them with `z3-sys`. That
+/// needs the raw handles that `z3::Context` and `z3::Solver` keep private: a
+/// context is nothing but its handle, and a solver is a reference to its
+/// context plus its handle. If their layout is ever anything else, we report
+/// nothing rather than guess.
+fn z3_statistics(context: &z3::Context, solver: &z3::Solver) -> SolverStatistics {
+    use std::mem::size_of;
+    use z3_sys::*;
+
+    let mut statistics = SolverStatistics::default();
+    let word = size_of::<usize>();
+    if size_of::<z3::Context>() != word || size_of::<z3::Solver>() != 2 * word {
+        return statistics;
+    }
+    let context_address = context as *const z3::Context as usize;
+    // 两个字里不是上下文地址的那个就是求解器的句柄，不依赖字段的顺序
+    let words = unsafe { *(solver as *const z3::Solver as *const [usize; 2]) };
+    let z3_slv = match words {
+        [a, b] if a == context_address && b != context_address => b,
+        [a, b] if b == context_address && a != context_address => a,
+        _ => return statistics,
+    } as Z3_solver;
+    let z3_ctx = unsafe { *(context as *const z3::Context as *const Z3_context) };
+
+    unsafe {
+        let stats = Z3_solver_get_statistics(z3_ctx, z3_slv);
+        Z3_stats_inc_ref(z3_ctx, stats);
+        for i in 0..Z3_stats_size(z3_ctx, stats) {
+            let key = CStr::from_ptr(Z3_stats_get_key(z3_ctx, stats, i)).to_string_lossy();
+            let value = if Z3_stats_is_uint(z3_ctx, stats, i) {
+                Z3_stats_get_uint_value(z3_ctx, stats, i) as f64
+            } else {
+                Z3_stats_get_double_value(z3_ctx, stats, i)
+            };
+            statistics.record(&key, value);
+        }
+        Z3_stats_dec_ref(z3_ctx, stats);
+    }
+    statistics
+}
+
+impl Backend for Z3Backend {
+    fn check<'ctx>(
+        &self,
+        assertions: &[Bool<'ctx>],
+        assumptions: &[Bool<'ctx>],
+        timeout: Option<Duration>,
+    ) -> CheckResult<'ctx> {
+        self.check_with_statistics(assertions, assumptions, timeout).0
+    }
+
+    fn check_with_statistics<'ctx>(
+        &self,
+        assertions: &[Bool<'ctx>],
+        assumptions: &[Bool<'ctx>],
+        timeout: Option<Duration>,
+    ) -> (CheckResult<'ctx>, SolverStatistics) {
+        let mut config = z3::Config::new();
+        config.set_model_generation(true);
+        for (key, value) in &self.params {
+            config.set_param_value(key, value);
+        }
+        let context = z3::Context::new(&config);
+        let mut lowering = Lowering::new(&context);
+        let roots: Vec<_> = assertions.iter().chain(assumptions).map(Wrap::term).collect();
+        let lowered: Vec<_> = lowering.lower(&roots).iter().map(|a| a.as_bool().unwrap()).collect();
+        let (lowered_assertions, lowered_assumptions) = lowered.split_at(assertions.len());
+
+        let solver = z3::Solver::new(&context);
+        let mut params = z3::Params::new(&context);
+        if let Some(timeout) = timeout {
+            params.set_u32("timeout", timeout.as_millis().min(u32::MAX as u128) as u32);
+        }
+        if !assumptions.is_empty() {
+            params.set_bool("core.minimize", true);
+        }
+        solver.set_params(&params);
+
+        for a in lowered_assertions {
+            solver.assert(a);
+        }
+        let result = match solver.check_assumptions(lowered_assumptions) {
+            z3::SatResult::Sat => match solver.get_model() {
+                Some(model) => {
+                    let values = lowering
+                        .constants
+                        .iter()
+                        .map(|(name, sort, constant)| {
+                            let value = model.eval(constant).and_then(|value| {
+                                let value = Reader::new(value.to_string().as_bytes()).next().ok()?;
+                                value.as_value(sort)
+                            });
+                            (name.clone(), value)
+                        })
+                        .collect();
+                    CheckResult::Sat(Box::new(Assignment(values)))
+                }
+                None => CheckResult::Unknown("no model".to_string()),
+            },
+            z3::SatResult::Unsat => {
+                let core = solver
+                    .get_unsat_core()
+                    .iter()
+                    .filter_map(|c| lowered_assumptions.iter().position(|a| a == c))
+                    .collect();
+                CheckResult::Unsat(core)
+            }
+            z3::SatResult::Unknown => CheckResult::Unknown(
+                solver
+                    .get_reason_unknown()
+                    .unwrap_or_else(|| "unknown".to_string()),
+            ),
+        };
+        (result, z3_statistics(&context, &solver))
+    }
+}
+
+/// Solve with an external solver that reads SMT-LIB2 on its standard input
+/// and answers on its standard output, e.g. `cvc5 --incremental` or
+/// `yices-smt2 --incremental`.
+///
+/// A fresh process is started for every query and killed once we have our
+/// answer or run out of time.
+#[derive(Clone, Debug)]
+pub struct SmtLib2Backend {
+    program: String,
+    args: Vec<String>,
+    logic: Option<String>,
+}
+
+impl SmtLib2Backend {
+    /// Run `program` with `args` for each query.
+    pub fn new(program: impl Into<String>, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        SmtLib2Backend {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            logic: None,
+        }
+    }
+
+    /// Declare `logic` (e.g. `QF_AUFNIA`) at the start of every query. Some
+    /// solvers, like yices, insist on this.
+    pub fn set_logic(&mut self, logic: Option<String>) -> &mut Self {
+        self.logic = logic;
+        self
+    }
+
+    fn run<'ctx>(
+        &self,
+        assertions: &[Bool<'ctx>],
+        assumptions: &[Bool<'ctx>],
+        timeout: Option<Duration>,
+    ) -> io::Result<(CheckResult<'ctx>, SolverStatistics)> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut session = Session::new(&mut child)?;
+        let result = session.check(self, assertions, assumptions, timeout);
+        // 超时的时候求解器还在忙，问不出统计信息
+        let result = result.map(|result| match result {
+            CheckResult::Unknown(reason) if reason == "timeout" => {
+                (CheckResult::Unknown(reason), SolverStatistics::default())
+            }
+            result => (result, session.statistics()),
+        });
+        drop(session);
+        let _ = child.kill();
+        let _ = child.wait();
+        result
+    }
+}
+
+impl Backend for SmtLib2Backend {
+    fn check<'ctx>(
+        &self,
+        assertions: &[Bool<'ctx>],
+        assumptions: &[Bool<'ctx>],
+        timeout: Option<Duration>,
+    ) -> CheckResult<'ctx> {
+        self.check_with_statistics(assertions, assumptions, timeout).0
+    }
+
+    fn check_with_statistics<'ctx>(
+        &self,
+        assertions: &[Bool<'ctx>],
+        assumptions: &[Bool<'ctx>],
+        timeout: Option<Duration>,
+    ) -> (CheckResult<'ctx>, SolverStatistics) {
+        match self.run(assertions, assumptions, timeout) {
+            Ok(result) => result,
+            Err(e) => (
+                CheckResult::Unknown(format!("{}: {}", self.program, e)),
+                SolverStatistics::default(),
+            ),
+        }
+    }
+}
+
+/// A conversation with a running solver process.
+struct Session {
+    stdin: Option<ChildStdin>,
+    // 单独的线程负责读取输出，这样等待结果时才能超时
+    responses: mpsc::Receiver<io::Result<Sexp>>,
+}
+
+impl Session {
+    fn new(child: &mut Child) -> io::Result<Session> {
+        let stdin = child.stdin.take();
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("no stdout"))?;
+        let (sender, responses) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = Reader::new(BufReader::new(stdout));
+            loop {
+                let response = reader.next();
+                let done = response.is_err();
+                if sender.send(response).is_err() || done {
+                    break;
+                }
+            }
+        });
+        Ok(Session { stdin, responses })
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| io::Error::other("no stdin"))?;
+        stdin.write_all(command.as_bytes())?;
+        stdin.flush()
+    }
+
+    fn receive(&mut self, timeout: Option<Duration>) -> io::Result<Option<Sexp>> {
+        let response = match timeout {
+            Some(timeout) => match self.responses.recv_timeout(timeout) {
+                Ok(response) => response,
+                Err(mpsc::RecvTimeoutError::Timeout) => return Ok(None),
+                Err(mpsc::RecvTimeoutError::Disconnected) => Err(closed()),
+            },
+            None => self.responses.recv().unwrap_or_else(|_| Err(closed())),
+        }?;
+        if let Sexp::List(items) = &response {
+            if let [Sexp::Atom(error), message] = &items[..] {
+                if error == "error" {
+                    return Err(io::Error::other(message.to_string()));
+                }
+            }
+        }
+        Ok(Some(response))
+    }
+
+    fn check<'ctx>(
+        &mut self,
+        backend: &SmtLib2Backend,
+        assertions: &[Bool<'ctx>],
+        assumptions: &[Bool<'ctx>],
+        timeout: Option<Duration>,
+    ) -> io::Result<CheckResult<'ctx>> {
+        let mut script = String::from("(set-option :print-success false)\n");
+        script.push_str("(set-option :produce-models true)\n");
+        if !assumptions.is_empty() {
+            script.push_str("(set-option :produce-unsat-cores true)\n");
+        }
+        if let Some(logic) = &backend.logic {
+            script.push_str(&format!("(set-logic {})\n", logic));
+        }
+        script.push_str(&to_smtlib2(assertions));
+        if assumptions.is_empty() {
+            script.push_str("(check-sat)\n");
+        } else {
+            let names: Vec<_> = assumptions.iter().map(|a| a.to_string()).collect();
+            script.push_str(&format!("(check-sat-assuming ({}))\n", names.join(" ")));
+        }
+        self.send(&script)?;
+
+        let answer = match self.receive(timeout)? {
+            Some(answer) => answer,
+            None => return Ok(CheckResult::Unknown("timeout".to_string())),
+        };
+        match answer.atom() {
+            Some("sat") => {
+                self.send("(get-model)\n")?;
+                let model = self.receive(None)?.ok_or_else(closed)?;
+                let constants = term::constants(assertions.iter().chain(assumptions).map(Wrap::term));
+                Ok(CheckResult::Sat(Box::new(Assignment(parse_model(&model, &constants)))))
+            }
+            Some("unsat") => {
+                if assumptions.is_empty() {
+                    return Ok(CheckResult::Unsat(vec![]));
+                }
+                self.send("(get-unsat-core)\n")?;
+                let core = self.receive(None)?.ok_or_else(closed)?;
+                let core = match core {
+                    Sexp::List(items) => items
+                        .iter()
+                        .filter_map(|item| {
+                            let name = unquote(item.atom()?);
+                            assumptions.iter().position(|a| unquote(&a.to_string()) == name)
+                        })
+                        .collect(),
+                    Sexp::Atom(_) => vec![],
+                };
+                Ok(CheckResult::Unsat(core))
+            }
+            Some("unknown") => {
+                self.send("(get-info :reason-unknown)\n")?;
+                let reason = match self.receive(Some(Duration::from_secs(1))) {
+                    Ok(Some(Sexp::List(items))) if items.len() == 2 => items[1].to_string(),
+                    _ => "unknown".to_string(),
+                };
+                Ok(CheckResult::Unknown(reason.trim_matches('"').to_string()))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected answer to check-sat: {}", answer),
+            )),
+        }
+    }
+
+    /// Ask for the solver's statistics about the last query. Solvers that
+    /// don't answer within a second, or answer in a form we don't know,
+    /// report nothing.
+    fn statistics(&mut self) -> SolverStatistics {
+        let mut statistics = SolverStatistics::default();
+        if self.send("(get-info :all-statistics)\n").is_err() {
+            return statistics;
+        }
+        if let Ok(Some(response)) = self.receive(Some(Duration::from_secs(1))) {
+            statistics_from_info(&response, &mut statistics);
+        }
+        statistics
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.send("(exit)\n");
+        self.stdin = None;
+    }
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "solver exited")
+}
+
+/// Record the `:keyword value` pairs of a `get-info` response.
+fn statistics_from_info(response: &Sexp, statistics: &mut SolverStatistics) {
+    if let Sexp::List(items) = response {
+        for pair in items.windows(2) {
+            if let [Sexp::Atom(key), Sexp::Atom(value)] = pair {
+                if let (true, Ok(value)) = (key.starts_with(':'), value.parse()) {
+                    statistics.record(key, value);
+                }
+            }
+        }
+    }
+}
+
+/// Strip the `|...|` quoting from an SMT-LIB2 symbol.
+fn unquote(symbol: &str) -> &str {
+    symbol
+        .strip_prefix('|')
+        .and_then(|s| s.strip_suffix('|'))
+        .unwrap_or(symbol)
+}
+
+/// The values of `constants` in a `get-model` response. Constants the solver
+/// leaves out are left out here too, and so are zero.
+fn parse_model(model: &Sexp, constants: &[(String, Sort)]) -> HashMap<String, Option<Value>> {
+    let mut definitions = HashMap::new();
+    if let Sexp::List(items) = model {
+        for definition in items {
+            // (define-fun name () Int value)
+            if let Sexp::List(items) = definition {
+                if let [Sexp::Atom(define), Sexp::Atom(name), Sexp::List(args), _, value] = &items[..] {
+                    if define == "define-fun" && args.is_empty() {
+                        definitions.insert(unquote(name), value);
+                    }
+                }
+            }
+        }
+    }
+    constants
+        .iter()
+        .filter_map(|(name, sort)| Some((name.clone(), definitions.get(name.as_str())?.as_value(sort))))
+        .collect()
+}
+
+/// A solver response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Sexp::Atom(a) => Some(a),
+            Sexp::List(_) => None,
+        }
+    }
+
+    /// Interpret an integer literal: `5`, `(- 5)` or `-5`.
+    fn as_integer(&self) -> Option<i128> {
+        match self {
+            Sexp::Atom(a) => a.parse().ok(),
+            Sexp::List(items) => match &items[..] {
+                [Sexp::Atom(minus), n] if minus == "-" => n.as_integer()?.checked_neg(),
+                _ => None,
+            },
+        }
+    }
+
+    /// Interpret a real literal, like `2`, `2.5`, `(/ 5 2)` or `(- (/ 5.0
+    /// 2.0))`, as a numerator and a positive denominator.
+    fn as_rational(&self) -> Option<(i128, i128)> {
+        let (numerator, denominator) = match self {
+            Sexp::Atom(a) => match a.split_once('.') {
+                Some((whole, fraction)) => {
+                    let scale = 10i128.checked_pow(fraction.len() as u32)?;
+                    let digits = format!("{}{}", whole, fraction);
+                    (Sexp::Atom(digits).as_integer()?, scale)
+                }
+                None => (self.as_integer()?, 1),
+            },
+            Sexp::List(items) => match &items[..] {
+                [Sexp::Atom(minus), x] if minus == "-" => {
+                    let (n, d) = x.as_rational()?;
+                    (n.checked_neg()?, d)
+                }
+                [Sexp::Atom(slash), a, b] if slash == "/" => {
+                    let (an, ad) = a.as_rational()?;
+                    let (bn, bd) = b.as_rational()?;
+                    (an.checked_mul(bd)?, ad.checked_mul(bn)?)
+                }
+                _ => return None,
+            },
+        };
+        if denominator == 0 {
+            return None;
+        }
+        let (mut a, mut b) = (numerator, denominator);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        // `a`是最大公约数，带上分母的符号，约分后分母就是正的
+        let gcd = if (a < 0) == (denominator < 0) { a } else { a.checked_neg()? };
+        Some((numerator / gcd, denominator / gcd))
+    }
+
+    /// Interpret a value of `sort`. Arrays have to be written as `store`s
+    /// into a constant array.
+    fn as_value(&self, sort: &Sort) -> Option<Value> {
+        match sort {
+            Sort::Bool => match self.atom()? {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            Sort::Int => Some(Value::Int(self.as_integer()?)),
+            Sort::Real => {
+                let (n, d) = self.as_rational()?;
+                Value::real(n, d)
+            }
+            Sort::BitVec(width) => Some(Value::bv(self.as_bits()?.into(), *width)),
+            Sort::Array(domain, range) => match self {
+                Sexp::List(items) => match &items[..] {
+                    // ((as const (Array Int Int)) 0)
+                    [Sexp::List(as_const), value] if as_const.first().and_then(Sexp::atom) == Some("as") => {
+                        Some(Value::Array(Box::new(value.as_value(range)?), vec![]))
+                    }
+                    [Sexp::Atom(store), array, index, value] if store == "store" => {
+                        match array.as_value(sort)? {
+                            Value::Array(default, mut stores) => {
+                                stores.push((index.as_value(domain)?, value.as_value(range)?));
+                                Some(Value::Array(default, stores))
+                            }
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                },
+                Sexp::Atom(_) => None,
+            },
+        }
+    }
+
+    /// Interpret a bit-vector literal: `#b0101`, `#x0f` or `(_ bv15 8)`.
+    fn as_bits(&self) -> Option<u64> {
+        match self {
+            Sexp::Atom(a) => {
+                if let Some(bits) = a.strip_prefix("#b") {
+                    u64::from_str_radix(bits, 2).ok()
+                } else {
+                    u64::from_str_radix(a.strip_prefix("#x")?, 16).ok()
+                }
+            }
+            Sexp::List(items) => match &items[..] {
+                [Sexp::Atom(underscore), Sexp::Atom(value), _] if underscore == "_" => {
+                    value.strip_prefix("bv")?.parse().ok()
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Sexp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sexp::Atom(a) => write!(f, "{}", a),
+            Sexp::List(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Reads whole s-expressions off a solver's output.
+struct Reader<R> {
+    input: R,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl<R: BufRead> Reader<R> {
+    fn new(input: R) -> Self {
+        Reader {
+            input,
+            chars: vec![],
+            pos: 0,
+        }
+    }
+
+    fn peek(&mut self) -> io::Result<Option<char>> {
+        while self.pos == self.chars.len() {
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            self.chars = line.chars().collect();
+            self.pos = 0;
+        }
+        Ok(Some(self.chars[self.pos]))
+    }
+
+    fn bump(&mut self) -> io::Result<char> {
+        let c = self.peek()?.ok_or_else(closed)?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn skip_whitespace(&mut self) -> io::Result<()> {
+        while let Some(c) = self.peek()? {
+            if c == ';' {
+                self.pos = self.chars.len();
+            } else if c.is_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> io::Result<Sexp> {
+        self.skip_whitespace()?;
+        match self.bump()? {
+            '(' => {
+                let mut items = vec![];
+                loop {
+                    self.skip_whitespace()?;
+                    if self.peek()? == Some(')') {
+                        self.pos += 1;
+                        return Ok(Sexp::List(items));
+                    }
+                    items.push(self.next()?);
+                }
+            }
+            ')' => Err(io::Error::new(io::ErrorKind::InvalidData, "unbalanced `)`")),
+            c @ ('|' | '"') => {
+                let mut atom = c.to_string();
+                loop {
+                    let d = self.bump()?;
+                    atom.push(d);
+                    if d == c {
+                        // `""` is an escaped quote inside a string literal.
+                        if c == '"' && self.peek()? == Some('"') {
+                            atom.push(self.bump()?);
+                            continue;
+                        }
+                        return Ok(Sexp::Atom(atom));
+                    }
+                }
+            }
+            c => {
+                let mut atom = c.to_string();
+                while let Some(d) = self.peek()? {
+                    if d.is_whitespace() || d == '(' || d == ')' {
+                        break;
+                    }
+                    atom.push(d);
+                    self.pos += 1;
+                }
+                Ok(Sexp::Atom(atom))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::term::{Array, Ast, Context, Real, BV};
+
+    fn read(text: &str) -> Vec<Sexp> {
+        let mut reader = Reader::new(text.as_bytes());
+        let mut sexps = vec![];
+        while let Ok(sexp) = reader.next() {
+            sexps.push(sexp);
+        }
+        sexps
+    }
+
+    #[test]
+    fn reader() {
+        let sexps = read("sat ; a comment\n(a (|b c| \"d \"\" e\") ())\n(error \"x\")");
+        assert_eq!(sexps.len(), 3);
+        assert_eq!(sexps[0], Sexp::Atom("sat".to_string()));
+        assert_eq!(sexps[1].to_string(), "(a (|b c| \"d \"\" e\") ())");
+        assert_eq!(sexps[2].to_string(), "(error \"x\")");
+        assert!(Reader::new(")".as_bytes()).next().is_err());
+        assert!(Reader::new("(a".as_bytes()).next().is_err());
+    }
+
+    #[test]
+    fn literals() {
+        let literal = |text: &str| read(text).pop().unwrap();
+        assert_eq!(literal("-5").as_integer(), Some(-5));
+        assert_eq!(literal("(- 5)").as_integer(), Some(-5));
+        assert_eq!(literal("5.0").as_integer(), None);
+        assert_eq!(literal("2.5").as_rational(), Some((5, 2)));
+        assert_eq!(literal("(- (/ 5.0 2.0))").as_rational(), Some((-5, 2)));
+        assert_eq!(literal("(/ 1 (- 3))").as_rational(), Some((-1, 3)));
+        assert_eq!(literal("(/ 1 0)").as_rational(), None);
+        assert_eq!(literal("#b0101").as_bits(), Some(5));
+        assert_eq!(literal("#xff").as_bits(), Some(255));
+        assert_eq!(literal("(_ bv3 8)").as_bits(), Some(3));
+    }
+
+    #[test]
+    fn model() {
+        let context = Context::new();
+        let response = read(
+            "(\n\
+               (define-fun x () Int 5)\n\
+               (define-fun |y z| () Int (- 3))\n\
+               (define-fun r () Real (/ 5.0 2.0))\n\
+               (define-fun s () Real (- 1.5))\n\
+               (define-fun b () (_ BitVec 8) #xff)\n\
+               (define-fun c () (_ BitVec 8) (_ bv3 8))\n\
+               (define-fun a () (Array Int Int) (store ((as const (Array Int Int)) 1) 2 3))\n\
+               (define-fun f ((a Int)) Int a)\n\
+             )",
+        );
+        let int = |name: &str| Int::new_const(&context, name);
+        let real = |name: &str| Real::new_const(&context, name);
+        let bv = |name: &str| BV::new_const(&context, name, 8);
+        let array = Array::new_const(&context, "a", &Sort::int(&context), &Sort::int(&context));
+        let two = Int::from_i64(&context, 2);
+
+        let terms: Vec<Dynamic> = vec![
+            int("x").into(),
+            int("y z").into(),
+            real("r").into(),
+            real("s").into(),
+            bv("b").into(),
+            bv("c").into(),
+            array.clone().into(),
+            int("f").into(),
+        ];
+        let constants = term::constants(terms.iter().map(Wrap::term));
+        let model = Assignment(parse_model(&response[0], &constants));
+        assert!(!model.0.contains_key("f"));
+
+        assert_eq!(model.eval_int(&Int::add(&context, &[&int("x"), &Int::mul(&context, &[&int("y z"), &two])])), Some(-1));
+        assert_eq!(model.eval_int(&real("r").to_int()), Some(2));
+        assert_eq!(model.eval_int(&Real::mul(&context, &[&real("r"), &Real::from_real(&context, 2, 1)]).to_int()), Some(5));
+        assert_eq!(model.eval_int(&real("s").to_int()), Some(-2));
+        assert_eq!(model.eval_int(&bv("b").to_int(true)), Some(-1));
+        assert_eq!(model.eval_int(&bv("b").bvadd(&bv("c")).to_int(false)), Some(2));
+        assert_eq!(model.eval_int(&array.select(&two).as_int().unwrap()), Some(3));
+        assert_eq!(model.eval_int(&array.select(&int("x")).as_int().unwrap()), Some(1));
+
+        // 模型里没有的常量取0，就像Z3的模型补全
+        assert_eq!(model.eval_int(&Int::add(&context, &[&int("w"), &two])), Some(2));
+
+        // 除以0没有定义，没有值；但没取到的那一支除以0不要紧
+        let zero = Int::from_i64(&context, 0);
+        assert_eq!(model.eval_int(&int("x").div(&int("w"))), None);
+        let guarded = int("w")._eq(&zero).ite(&zero, &int("x").div(&int("w")));
+        assert_eq!(model.eval_int(&guarded), Some(0));
+
+        // 实数和位向量的值原样取出来，不取整
+        let r: Dynamic = real("r").into();
+        let b: Dynamic = bv("b").into();
+        assert_eq!(model.eval(&r).and_then(|r| r.as_real()?.as_real()), Some((5, 2)));
+        assert_eq!(model.eval(&b).and_then(|b| b.as_bv()?.as_u64()), Some(255));
+        assert!(model.eval(&array.into()).is_none());
+    }
+
+    #[test]
+    fn statistics() {
+        let mut statistics = SolverStatistics::default();
+        let info = read("(:conflicts 3 :decisions 10 :sat-conflicts 2 :memory 19.25 :other 1 :rlimit)");
+        statistics_from_info(&info[0], &mut statistics);
+        assert_eq!(statistics.conflicts, Some(5));
+        assert_eq!(statistics.decisions, Some(10));
+        assert_eq!(statistics.memory, Some(19.25));
+
+        // Z3在进程内解的时候也报告统计信息
+        let context = Context::new();
+        let bools: Vec<_> = (0..6).map(|i| Bool::new_const(&context, format!("b{}", i))).collect();
+        let mut assertions = vec![];
+        for (i, a) in bools.iter().enumerate() {
+            for b in &bools[i + 1..] {
+                assertions.push(Bool::or(&context, &[a, b]));
+                assertions.push(Bool::or(&context, &[&a.not(), &b.not()]));
+            }
+        }
+        let (result, statistics) = Z3Backend::new().check_with_statistics(&assertions, &[], None);
+        assert!(matches!(result, CheckResult::Unsat(_)));
+        assert!(statistics.conflicts.is_some(), "{:?}", statistics);
+        assert!(statistics.decisions.is_some(), "{:?}", statistics);
+        assert!(statistics.memory.is_some_and(|memory| memory > 0.0), "{:?}", statistics);
+    }
+
+    #[test]
+    fn z3_model() {
+        let context = Context::new();
+        let r = Real::new_const(&context, "r");
+        let a = Array::new_const(&context, "a", &Sort::int(&context), &Sort::int(&context));
+        let int = |n| Int::from_i64(&context, n);
+        let assertions = [
+            Real::mul(&context, &[&r, &Real::from_real(&context, 2, 1)])._eq(&Real::from_real(&context, 5, 1)),
+            a.select(&int(1)).as_int().unwrap()._eq(&int(5)),
+            a.select(&int(3)).as_int().unwrap()._eq(&int(7)),
+        ];
+        let model = match Z3Backend::new().check(&assertions, &[], None) {
+            CheckResult::Sat(model) => model,
+            result => panic!("{:?}", result),
+        };
+
+        let r: Dynamic = r.into();
+        assert_eq!(model.eval(&r).and_then(|r| r.as_real()?.as_real()), Some((5, 2)));
+        assert_eq!(model.eval_int(&r.as_real().unwrap().to_int()), Some(2));
+        let sum = Int::add(&context, &[&a.select(&int(1)).as_int().unwrap(), &a.select(&int(3)).as_int().unwrap()]);
+        assert_eq!(model.eval_int(&sum), Some(12));
+    }
+
+    #[test]
+    fn z3_unsat_core() {
+        let context = Context::new();
+        let x = Int::new_const(&context, "x");
+        let int = |n| Int::from_i64(&context, n);
+        let assumptions = [
+            Bool::new_const(&context, "small"),
+            Bool::new_const(&context, "positive"),
+            Bool::new_const(&context, "big"),
+        ];
+        let assertions = [
+            assumptions[0].implies(&x.lt(&int(5))),
+            assumptions[1].implies(&x.gt(&int(0))),
+            assumptions[2].implies(&x.gt(&int(10))),
+        ];
+        let result = Z3Backend::new().check(&assertions, &assumptions, None);
+        match result {
+            CheckResult::Unsat(mut core) => {
+                core.sort();
+                assert_eq!(core, vec![0, 2]);
+            }
+            result => panic!("{:?}", result),
+        }
+    }
+
+    /// Builds a term out of two operands.
+    type Build = for<'c> fn(&'c Context, &[Dynamic<'c>]) -> Dynamic<'c>;
+
+    /// Check that `build` folds `a` and `b` to the value Z3 gives it.
+    fn assert_folds_like_z3(build: Build, sort: &Sort, a: Value, b: Value) {
+        let context = Context::new();
+        let literals = [Dynamic::from_value(a), Dynamic::from_value(b)];
+        let folded = build(&context, &literals);
+        assert!(folded.is_literal(), "{}", folded);
+
+        let symbols = [Dynamic::fresh_const(&context, "a", sort), Dynamic::fresh_const(&context, "b", sort)];
+        let built = build(&context, &symbols);
+        let result = Dynamic::fresh_const(&context, "result", &built.get_sort());
+        let assertions = [symbols[0]._eq(&literals[0]), symbols[1]._eq(&literals[1]), result._eq(&built)];
+        let answer = Z3Backend::new().check(&assertions, &[], None);
+        match answer {
+            CheckResult::Sat(model) => {
+                let value = model.eval(&result).unwrap();
+                assert_eq!(folded.to_string(), value.to_string(), "{} with {} and {}", built, literals[0], literals[1]);
+            }
+            answer => panic!("{:?}", answer),
+        }
+    }
+
+    #[test]
+    fn folding_agrees_with_z3() {
+        fn int<'c>(x: &Dynamic<'c>) -> Int<'c> {
+            x.as_int().unwrap()
+        }
+        fn real<'c>(x: &Dynamic<'c>) -> Real<'c> {
+            x.as_real().unwrap()
+        }
+        fn bv<'c>(x: &Dynamic<'c>) -> BV<'c> {
+            x.as_bv().unwrap()
+        }
+
+        // 整数和实数除以0没有定义，只比较除数不是0的情况
+        let int_ops: [Build; 5] = [
+            |c, x| Int::sub(c, &[&int(&x[0]), &int(&x[1])]).into(),
+            |c, x| Int::mul(c, &[&int(&x[0]), &int(&x[1])]).into(),
+            |_, x| int(&x[0]).le(&int(&x[1])).into(),
+            |_, x| int(&x[0]).unary_minus().into(),
+            |_, x| BV::from_int(&int(&x[0]), 3).into(),
+        ];
+        let int_division_ops: [Build; 2] = [
+            |_, x| int(&x[0]).div(&int(&x[1])).into(),
+            |_, x| int(&x[0]).modulo(&int(&x[1])).into(),
+        ];
+        let real_ops: [Build; 4] = [
+            |c, x| Real::add(c, &[&real(&x[0]), &real(&x[1])]).into(),
+            |c, x| Real::mul(c, &[&real(&x[0]), &real(&x[1])]).into(),
+            |_, x| real(&x[0]).lt(&real(&x[1])).into(),
+            |_, x| real(&x[0]).to_int().into(),
+        ];
+        let real_division_ops: [Build; 1] = [|_, x| real(&x[0]).div(&real(&x[1])).into()];
+        // 位向量除以0是有定义的，一起比较
+        let bv_ops: [Build; 12] = [
+            |_, x| bv(&x[0]).bvadd(&bv(&x[1])).into(),
+            |_, x| bv(&x[0]).bvmul(&bv(&x[1])).into(),
+            |_, x| bv(&x[0]).bvneg().into(),
+            |_, x| bv(&x[0]).bvsdiv(&bv(&x[1])).into(),
+            |_, x| bv(&x[0]).bvsrem(&bv(&x[1])).into(),
+            |_, x| bv(&x[0]).bvsmod(&bv(&x[1])).into(),
+            |_, x| bv(&x[0]).bvudiv(&bv(&x[1])).into(),
+            |_, x| bv(&x[0]).bvurem(&bv(&x[1])).into(),
+            |_, x| bv(&x[0]).bvslt(&bv(&x[1])).into(),
+            |_, x| bv(&x[0]).bvule(&bv(&x[1])).into(),
+            |_, x| bv(&x[0]).to_int(true).into(),
+            |_, x| bv(&x[0]).to_int(false).into(),
+        ];
+
+        let numbers = [-7, -2, 0, 3, 5];
+        for &a in &numbers {
+            for &b in &numbers {
+                let ints = || (Value::Int(a), Value::Int(b));
+                let reals = || (Value::real(a, 2).unwrap(), Value::real(b, 3).unwrap());
+                let bvs = || (Value::bv(a, 4), Value::bv(b, 4));
+                for &op in int_ops.iter().chain(if b == 0 { &[][..] } else { &int_division_ops[..] }) {
+                    let (a, b) = ints();
+                    assert_folds_like_z3(op, &Sort::Int, a, b);
+                }
+                for &op in real_ops.iter().chain(if b == 0 { &[][..] } else { &real_division_ops[..] }) {
+                    let (a, b) = reals();
+                    assert_folds_like_z3(op, &Sort::Real, a, b);
+                }
+                for &op in &bv_ops {
+                    let (a, b) = bvs();
+                    assert_folds_like_z3(op, &Sort::BitVec(4), a, b);
+                }
+            }
+        }
+    }
+
+    /// Parse `script` with Z3, and check that what it asserts is equivalent
+    /// to `assertions`.
+    fn assert_z3_reads_as(script: &str, assertions: &[Bool]) {
+        use std::ffi::CString;
+        use z3_sys::*;
+
+        let context = z3::Context::new(&z3::Config::new());
+        let z3_ctx = unsafe { *(&context as *const z3::Context as *const Z3_context) };
+        let text = CString::new(script).unwrap();
+        // 链接的Z3其实返回一组断言，而不是它们的合取
+        let parsed: Vec<_> = unsafe {
+            let parsed = Z3_parse_smtlib2_string(
+                z3_ctx,
+                text.as_ptr(),
+                0,
+                std::ptr::null(),
+                std::ptr::null(),
+                0,
+                std::ptr::null(),
+                std::ptr::null(),
+            ) as Z3_ast_vector;
+            assert_eq!(Z3_get_error_code(z3_ctx), ErrorCode::OK, "{}", script);
+            (0..Z3_ast_vector_size(z3_ctx, parsed))
+                .map(|i| z3::ast::Bool::new(&context, Z3_ast_vector_get(z3_ctx, parsed, i)))
+                .collect()
+        };
+        let mut lowering = Lowering::new(&context);
+        let roots: Vec<_> = assertions.iter().map(Wrap::term).collect();
+        let lowered = lowering.lower(&roots);
+        assert_eq!(parsed.len(), lowered.len());
+        for (parsed, lowered) in parsed.iter().zip(&lowered) {
+            let solver = z3::Solver::new(&context);
+            solver.assert(&parsed._eq(&lowered.as_bool().unwrap()).not());
+            assert_eq!(solver.check(), z3::SatResult::Unsat, "{} vs {}", parsed, lowered);
+        }
+    }
+
+    #[test]
+    fn smtlib2_round_trip() {
+        let context = Context::new();
+        let x = Int::new_const(&context, "x");
+        let r = Real::fresh_const(&context, "r:");
+        let b = BV::new_const(&context, "1b", 8);
+        let a = Array::fresh_const(&context, "a", &Sort::int(&context), &Sort::int(&context));
+        let int = |n| Int::from_i64(&context, n);
+
+        // 共享的子项用let绑定，一层套一层
+        let shared = Int::add(&context, &[&x, &int(-3)]);
+        let twice = Int::mul(&context, &[&shared, &shared]);
+        let filled = Array::const_array(&context, &Sort::int(&context), &int(0)).store(&x, &twice);
+        let assertions = [
+            twice.gt(&shared)._eq(&Bool::new_const(&context, "p")),
+            Int::add(&context, &[&twice, &twice, &filled.select(&shared).as_int().unwrap()]).le(&int(100)),
+            a.store(&x, &int(1))._eq(&a),
+            Real::from_int(&x).div(&r).lt(&Real::from_real(&context, -1, 3)),
+            b.to_int(true)._eq(&x.modulo(&int(7))),
+            BV::from_int(&x, 8).bvsdiv(&b).bvslt(&BV::from_i64(&context, -1, 8)),
+        ];
+        let script = to_smtlib2(&assertions);
+        assert!(script.contains("(let ((?x0 "), "{}", script);
+        assert!(script.contains("|1b|") && script.contains("|r:!0|"), "{}", script);
+        assert_z3_reads_as(&script, &assertions);
+    }
+}
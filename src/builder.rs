@@ -1,4 +1,4 @@
-use crate::{Id, Instruction, Operator, Program };
+use crate::{DType, Id, Instruction, Operator, Program, Reduction};
 
 const _DIMSIZE : [usize ; 2] = [4,10];
 
@@ -12,7 +12,8 @@ impl ProgramBuilder {
         ProgramBuilder {
             program: Program {
                 instructions: vec![],
-                inputs: vec![]
+                inputs: vec![],
+                input_dtypes: vec![],
             },
         }
     }
@@ -26,6 +27,12 @@ impl ProgramBuilder {
     }
 
     pub fn var(&mut self, input : Vec<Vec<i64>>) -> Id {
+        self.var_with_dtype(input, DType::Int)
+    }
+
+    /// Like `var`, but for an input whose elements are of the given dtype
+    /// (e.g. a `Bool` mask, written as 0s and 1s).
+    pub fn var_with_dtype(&mut self, input: Vec<Vec<i64>>, dtype: DType) -> Id {
         /*assert!(
             self.program
                 .instructions
@@ -40,9 +47,9 @@ impl ProgramBuilder {
             operator: Operator::Var,
         });
 
-        //println!("{:?}", input);
         //将输入存入program中
         self.program.inputs.push(input);
+        self.program.input_dtypes.push(dtype);
         result
     }
 
@@ -64,11 +71,20 @@ impl ProgramBuilder {
         result
     }
 
-    pub fn tf_argmax(&mut self, a: Id) -> Id {
+    pub fn tf_argmax(&mut self, a: Id, axis: usize) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfArgmax(a),
+            operator: Operator::TfArgmax(a, axis),
+        });
+        result
+    }
+
+    pub fn tf_argmin(&mut self, a: Id, axis: usize) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfArgmin(a, axis),
         });
         result
     }
@@ -91,11 +107,11 @@ impl ProgramBuilder {
         result
     }
 
-    pub fn tf_cast(&mut self, a: Id) -> Id {
+    pub fn tf_cast(&mut self, a: Id, dtype: DType) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfCast(a),
+            operator: Operator::TfCast(a, dtype),
         });
         result
     }
@@ -172,15 +188,31 @@ impl ProgramBuilder {
         result
     }
 
-    pub fn tf_cumsum(&mut self, a: Id, b: Id) -> Id {
+    pub fn tf_cumulative(&mut self, a: Id, reduction: Reduction, axis: usize, exclusive: bool, reverse: bool) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfCumsum(a, b),
+            operator: Operator::TfCumulative(a, reduction, axis, exclusive, reverse),
         });
         result
     }
 
+    pub fn tf_cumsum(&mut self, a: Id, axis: usize, exclusive: bool, reverse: bool) -> Id {
+        self.tf_cumulative(a, Reduction::Sum, axis, exclusive, reverse)
+    }
+
+    pub fn tf_cumprod(&mut self, a: Id, axis: usize, exclusive: bool, reverse: bool) -> Id {
+        self.tf_cumulative(a, Reduction::Prod, axis, exclusive, reverse)
+    }
+
+    pub fn tf_cummax(&mut self, a: Id, axis: usize, exclusive: bool, reverse: bool) -> Id {
+        self.tf_cumulative(a, Reduction::Max, axis, exclusive, reverse)
+    }
+
+    pub fn tf_cummin(&mut self, a: Id, axis: usize, exclusive: bool, reverse: bool) -> Id {
+        self.tf_cumulative(a, Reduction::Min, axis, exclusive, reverse)
+    }
+
     pub fn tf_multiply(&mut self, a: Id, b: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
@@ -208,147 +240,207 @@ impl ProgramBuilder {
         result
     }
 
-    pub fn tf_reduce_max(&mut self, a: Id) -> Id {
+    pub fn tf_reduce(&mut self, a: Id, reduction: Reduction, axis: Option<usize>, keepdims: bool) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceMax(a),
+            operator: Operator::TfReduce(a, reduction, axis, keepdims),
         });
         result
     }
 
+    pub fn tf_reduce_max(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Max, None, false)
+    }
+
     pub fn tf_reduce_max0(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Max, Some(0), false)
+    }
+
+    pub fn tf_reduce_max1(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Max, Some(1), false)
+    }
+
+    pub fn tf_reduce_sum(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Sum, None, false)
+    }
+
+    pub fn tf_reduce_sum0(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Sum, Some(0), false)
+    }
+
+    pub fn tf_reduce_sum1(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Sum, Some(1), false)
+    }
+
+    pub fn tf_sequence_mask(&mut self, a: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceMax0(a),
+            operator: Operator::TfSequenceMask(a),
         });
         result
     }
 
-    pub fn tf_reduce_max1(&mut self, a: Id) -> Id {
+    pub fn tf_square(&mut self, a: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceMax1(a),
+            operator: Operator::TfSquare(a),
         });
         result
     }
 
-    pub fn tf_reduce_sum(&mut self, a: Id) -> Id {
+    pub fn tf_subtract(&mut self, a: Id, b: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceSum(a),
+            operator: Operator::TfSubtract(a, b),
         });
         result
     }
 
-    pub fn tf_reduce_sum0(&mut self, a: Id) -> Id {
+    pub fn tf_tensordot(&mut self, a: Id, b: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceSum0(a),
+            operator: Operator::TfTensordot(a, b),
         });
         result
     }
 
-    pub fn tf_reduce_sum1(&mut self, a: Id) -> Id {
+    pub fn tf_transpose(&mut self, a: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceSum1(a),
+            operator: Operator::TfTranspose(a),
         });
         result
     }
 
-    pub fn tf_sequence_mask(&mut self, a: Id) -> Id {
+    pub fn tf_where1(&mut self, a: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfSequenceMask(a),
+            operator: Operator::TfWhere1(a),
         });
         result
     }
 
-    pub fn tf_square(&mut self, a: Id) -> Id {
+    pub fn tf_where3(&mut self, a: Id, b: Id, c: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfSquare(a),
+            operator: Operator::TfWhere3(a, b, c),
         });
         result
     }
 
-    pub fn tf_subtract(&mut self, a: Id, b: Id) -> Id {
+
+    pub fn tf_eye(&mut self, a: Id, b: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfSubtract(a, b),
+            operator: Operator::TfEye(a, b),
         });
         result
     }
 
-    pub fn tf_tensordot(&mut self, a: Id, b: Id) -> Id {
+    pub fn tf_fill(&mut self, a: Id, b: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfTensordot(a, b),
+            operator: Operator::TfFill(a, b),
         });
         result
     }
 
-    pub fn tf_transpose(&mut self, a: Id) -> Id {
+    pub fn tf_segment_sum(&mut self, data: Id, segment_ids: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfTranspose(a),
+            operator: Operator::TfSegment(data, segment_ids, Reduction::Sum),
         });
         result
     }
 
-    pub fn tf_where1(&mut self, a: Id) -> Id {
+    pub fn tf_segment_prod(&mut self, data: Id, segment_ids: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfWhere1(a),
+            operator: Operator::TfSegment(data, segment_ids, Reduction::Prod),
         });
         result
     }
 
-    pub fn tf_where3(&mut self, a: Id, b: Id, c: Id) -> Id {
+    pub fn tf_segment_max(&mut self, data: Id, segment_ids: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfWhere3(a, b, c),
+            operator: Operator::TfSegment(data, segment_ids, Reduction::Max),
         });
         result
     }
 
+    pub fn tf_segment_min(&mut self, data: Id, segment_ids: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfSegment(data, segment_ids, Reduction::Min),
+        });
+        result
+    }
 
-    pub fn tf_eye(&mut self, a: Id, b: Id) -> Id {
+    pub fn tf_segment_mean(&mut self, data: Id, segment_ids: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfEye(a, b),
+            operator: Operator::TfSegment(data, segment_ids, Reduction::Mean),
         });
         result
     }
 
-    pub fn tf_fill(&mut self, a: Id, b: Id) -> Id {
+    pub fn tf_unsorted_segment_sum(&mut self, data: Id, segment_ids: Id, num_segments: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfFill(a, b),
+            operator: Operator::TfUnsortedSegment(data, segment_ids, num_segments, Reduction::Sum),
         });
         result
     }
 
-    pub fn tf_segment_max(&mut self, a: Id, b: Id) -> Id {
+    pub fn tf_unsorted_segment_prod(&mut self, data: Id, segment_ids: Id, num_segments: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfSegmentMax(a, b),
+            operator: Operator::TfUnsortedSegment(data, segment_ids, num_segments, Reduction::Prod),
+        });
+        result
+    }
+
+    pub fn tf_unsorted_segment_max(&mut self, data: Id, segment_ids: Id, num_segments: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfUnsortedSegment(data, segment_ids, num_segments, Reduction::Max),
+        });
+        result
+    }
+
+    pub fn tf_unsorted_segment_min(&mut self, data: Id, segment_ids: Id, num_segments: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfUnsortedSegment(data, segment_ids, num_segments, Reduction::Min),
+        });
+        result
+    }
+
+    pub fn tf_unsorted_segment_mean(&mut self, data: Id, segment_ids: Id, num_segments: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfUnsortedSegment(data, segment_ids, num_segments, Reduction::Mean),
         });
         result
     }
@@ -399,55 +491,361 @@ impl ProgramBuilder {
     }
 
     pub fn tf_reduce_any0(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Any, Some(0), false)
+    }
+
+    pub fn tf_reduce_any1(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Any, Some(1), false)
+    }
+
+    pub fn tf_reduce_mean(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Mean, Some(0), false)
+    }
+
+    pub fn tf_reduce_prod(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::Prod, Some(1), false)
+    }
+
+    pub fn tf_roll(&mut self, a: Id, shift: i64, axis: usize) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceAny0(a),
+            operator: Operator::TfRoll(a, shift, axis),
         });
         result
     }
 
-    pub fn tf_reduce_any1(&mut self, a: Id) -> Id {
+    pub fn tf_zeros(&mut self, a: Id) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceAny1(a),
+            operator: Operator::TfZeros(a),
         });
         result
     }
 
-    pub fn tf_reduce_mean(&mut self, a: Id) -> Id {
+    pub fn tf_gather(&mut self, a: Id, b: Id, axis: usize) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceMean(a),
+            operator: Operator::TfGather(a, b, axis),
         });
         result
     }
 
-    pub fn tf_reduce_prod(&mut self, a: Id) -> Id {
+    pub fn tf_gather_nd(&mut self, a: Id, b: Id, batch_dims: usize) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfReduceProd(a),
+            operator: Operator::TfGatherNd(a, b, batch_dims),
         });
         result
     }
 
-    pub fn tf_roll(&mut self, a: Id) -> Id {
+    pub fn tf_take_along_axis(&mut self, a: Id, b: Id, axis: usize) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfRoll(a),
+            operator: Operator::TfTakeAlongAxis(a, b, axis),
         });
         result
     }
 
-    pub fn tf_zeros(&mut self, a: Id) -> Id {
+    pub fn tf_sort(&mut self, a: Id, axis: usize, descending: bool) -> Id {
         let result = self.next_id();
         self.program.instructions.push(Instruction {
             result,
-            operator: Operator::TfZeros(a),
+            operator: Operator::TfSort(a, axis, descending),
+        });
+        result
+    }
+
+    pub fn tf_argsort(&mut self, a: Id, axis: usize, descending: bool) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfArgsort(a, axis, descending),
+        });
+        result
+    }
+
+    pub fn tf_top_k_values(&mut self, a: Id, k: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfTopKValues(a, k),
+        });
+        result
+    }
+
+    pub fn tf_top_k_indices(&mut self, a: Id, k: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfTopKIndices(a, k),
+        });
+        result
+    }
+
+    pub fn tf_reshape(&mut self, a: Id, shape: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfReshape(a, shape),
+        });
+        result
+    }
+
+    pub fn tf_reshape_to(&mut self, a: Id, shape: [i64; 2]) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfReshapeTo(a, shape),
+        });
+        result
+    }
+
+    pub fn tf_squeeze(&mut self, a: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfSqueeze(a),
+        });
+        result
+    }
+
+    pub fn tf_tile(&mut self, a: Id, multiples: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfTile(a, multiples),
+        });
+        result
+    }
+
+    pub fn tf_stack(&mut self, a: Id, b: Id, axis: usize) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfStack(a, b, axis),
+        });
+        result
+    }
+
+    pub fn tf_scatter_nd(&mut self, indices: Id, updates: Id, shape: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfScatterNd(indices, updates, shape),
+        });
+        result
+    }
+
+    pub fn tf_tensor_scatter_nd_update(&mut self, tensor: Id, indices: Id, updates: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfTensorScatterNdUpdate(tensor, indices, updates),
+        });
+        result
+    }
+
+    pub fn tf_tensor_scatter_nd_add(&mut self, tensor: Id, indices: Id, updates: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfTensorScatterNdAdd(tensor, indices, updates),
+        });
+        result
+    }
+
+    pub fn tf_sparse_to_dense(&mut self, indices: Id, values: Id, dense_shape: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfSparseToDense(indices, values, dense_shape),
+        });
+        result
+    }
+
+    pub fn tf_less(&mut self, a: Id, b: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfLess(a, b),
+        });
+        result
+    }
+
+    pub fn tf_less_equal(&mut self, a: Id, b: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfLessEqual(a, b),
+        });
+        result
+    }
+
+    pub fn tf_greater_equal(&mut self, a: Id, b: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfGreaterEqual(a, b),
+        });
+        result
+    }
+
+    pub fn tf_logical_and(&mut self, a: Id, b: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfLogicalAnd(a, b),
+        });
+        result
+    }
+
+    pub fn tf_logical_or(&mut self, a: Id, b: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfLogicalOr(a, b),
+        });
+        result
+    }
+
+    pub fn tf_logical_xor(&mut self, a: Id, b: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfLogicalXor(a, b),
+        });
+        result
+    }
+
+    pub fn tf_logical_not(&mut self, a: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfLogicalNot(a),
+        });
+        result
+    }
+
+    pub fn tf_reduce_all0(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::All, Some(0), false)
+    }
+
+    pub fn tf_reduce_all1(&mut self, a: Id) -> Id {
+        self.tf_reduce(a, Reduction::All, Some(1), false)
+    }
+
+    pub fn tf_abs(&mut self, a: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfAbs(a),
+        });
+        result
+    }
+
+    pub fn tf_negative(&mut self, a: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfNegative(a),
+        });
+        result
+    }
+
+    pub fn tf_sign(&mut self, a: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfSign(a),
+        });
+        result
+    }
+
+    pub fn tf_floordiv(&mut self, a: Id, b: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfFloorDiv(a, b),
+        });
+        result
+    }
+
+    pub fn tf_floormod(&mut self, a: Id, b: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfFloorMod(a, b),
+        });
+        result
+    }
+
+    pub fn tf_pow(&mut self, a: Id, b: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfPow(a, b),
+        });
+        result
+    }
+
+    pub fn tf_clip_by_value(&mut self, t: Id, min: Id, max: Id) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfClipByValue(t, min, max),
+        });
+        result
+    }
+
+    pub fn tf_slice(&mut self, a: Id, begin: [i64; 2], size: [i64; 2]) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfSlice(a, begin, size),
+        });
+        result
+    }
+
+    pub fn tf_strided_slice(&mut self, a: Id, begin: [i64; 2], end: [i64; 2], strides: [i64; 2]) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfStridedSlice(a, begin, end, strides),
+        });
+        result
+    }
+
+    pub fn tf_pad(&mut self, a: Id, paddings: [[i64; 2]; 2]) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfPad(a, paddings),
+        });
+        result
+    }
+
+    pub fn tf_reverse(&mut self, a: Id, axis: usize) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::TfReverse(a, axis),
+        });
+        result
+    }
+
+    /// A hole in a sketch, to be filled in by the synthesizer with a value
+    /// computed from the library. If `shape` is given, whatever fills the hole
+    /// must have exactly that shape.
+    pub fn hole(&mut self, shape: Option<[usize; 2]>) -> Id {
+        let result = self.next_id();
+        self.program.instructions.push(Instruction {
+            result,
+            operator: Operator::Hole(shape, None),
         });
         result
     }
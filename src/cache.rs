@@ -0,0 +1,302 @@
+//! A persistent, on-disk cache of synthesis results.
+//!
+//! Benchmark runs tend to re-solve the same problems over and over. The cache
+//! remembers, for each task, the assignments of a program that solved it and
+//! any assignments that were learned to be invalid along the way, so that a
+//! later run can skip straight to the answer or at least avoid repeating known
+//! dead ends.
+//!
+//! A task is keyed by a hash of everything that determines its result: the
+//! components (including a sketch's instructions), the specification and its
+//! examples, whether we asked for a minimal program, and the domain that cells
+//! are encoded in. Changing any of these gives a new key, so stale entries are
+//! simply never looked up again. Entries can also be dropped explicitly with
+//! `Cache::clear`.
+//!
+//! The hashed description is written out explicitly, one line per fact, after
+//! a `KEY_FORMAT` line: each component's operand and immediate arities and the
+//! operator it makes (as printed in programs, with placeholder operands and
+//! immediates), the locations and hole shapes a sketch fixes, each input's
+//! dtype and values, the output's dtype, the simplified SMT-LIB2 constraint
+//! the spec puts on an output with fixed names, and the settings. It never
+//! relies on `Debug` output. Bump `KEY_FORMAT` whenever it changes.
+//!
+//! Invalid assignments are learned when a synthesized program fails
+//! `Synthesizer::should_verify_programs`'s re-check, and a cached solution is
+//! always re-checked against the spec before it is used.
+//!
+//! The file format is a line-oriented text format private to this module; a
+//! file written by a different version of the format is ignored.
+
+use crate::{Assignments, Vecs};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const HEADER: &str = "synth-loop-free-prog cache v1";
+
+/// The first line of every task description that we hash into a key.
+pub(crate) const KEY_FORMAT: &str = "synth-loop-free-prog task v2";
+
+/// The cache's key for a single synthesis task.
+pub(crate) type Key = u64;
+
+/// Hash a task's description down to its key.
+///
+/// This is 64-bit FNV-1a, which (unlike `std`'s `DefaultHasher`) is
+/// guaranteed to be stable across Rust versions, so keys stay valid between
+/// runs.
+pub(crate) fn key(description: &str) -> Key {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in description.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    solution: Option<Assignments>,
+    invalid_assignments: Vec<Assignments>,
+}
+
+/// An on-disk cache of solved tasks and learned invalid assignments.
+#[derive(Debug)]
+pub struct Cache {
+    path: PathBuf,
+    entries: HashMap<Key, Entry>,
+}
+
+impl Cache {
+    /// Open the cache stored at `path`.
+    ///
+    /// A missing file, or one written with a different version of the format,
+    /// is treated as an empty cache.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Cache> {
+        let path = path.into();
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => parse(&contents).unwrap_or_default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Cache { path, entries })
+    }
+
+    /// Write the cache back to the file it was opened from.
+    pub fn save(&self) -> io::Result<()> {
+        let mut keys: Vec<_> = self.entries.keys().collect();
+        keys.sort();
+
+        let mut contents = String::new();
+        writeln!(contents, "{}", HEADER).unwrap();
+        for key in keys {
+            let entry = &self.entries[key];
+            writeln!(contents, "entry {:016x}", key).unwrap();
+            if let Some(solution) = &entry.solution {
+                writeln!(contents, "solution {}", format_assignments(solution)).unwrap();
+            }
+            for invalid in &entry.invalid_assignments {
+                writeln!(contents, "invalid {}", format_assignments(invalid)).unwrap();
+            }
+        }
+        fs::write(&self.path, contents)
+    }
+
+    /// Forget everything in the cache.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(crate) fn solution(&self, key: Key) -> Option<&Assignments> {
+        self.entries.get(&key)?.solution.as_ref()
+    }
+
+    pub(crate) fn invalid_assignments(&self, key: Key) -> &[Assignments] {
+        self.entries
+            .get(&key)
+            .map_or(&[], |entry| &entry.invalid_assignments[..])
+    }
+
+    pub(crate) fn insert_solution(&mut self, key: Key, solution: Assignments) {
+        self.entries.entry(key).or_default().solution = Some(solution);
+    }
+
+    pub(crate) fn remove_solution(&mut self, key: Key) {
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.solution = None;
+        }
+    }
+
+    pub(crate) fn insert_invalid_assignment(&mut self, key: Key, invalid: Assignments) {
+        let entry = self.entries.entry(key).or_default();
+        if !entry.invalid_assignments.contains(&invalid) {
+            entry.invalid_assignments.push(invalid);
+        }
+    }
+}
+
+// 每组赋值存成一行：`<output> | <results> | <params> | <immediates>`，
+// 每个立即数写成`行,列:值,值,...`（包括填充部分），立即数之间用`;`分隔
+
+fn format_lines(lines: &[u32]) -> String {
+    lines.iter().map(|l| l.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn format_assignments(assignments: &Assignments) -> String {
+    let immediates: Vec<_> = assignments
+        .immediates
+        .iter()
+        .map(|imm| {
+            let cells: Vec<_> = imm.vecs.iter().flatten().map(|c| c.to_string()).collect();
+            format!("{},{}:{}", imm.dims[0], imm.dims[1], cells.join(","))
+        })
+        .collect();
+    format!(
+        "{} | {} | {} | {}",
+        assignments.output,
+        format_lines(&assignments.results),
+        format_lines(&assignments.params),
+        immediates.join(";")
+    )
+}
+
+fn parse_list<T: std::str::FromStr>(s: &str) -> Option<Vec<T>> {
+    if s.is_empty() {
+        return Some(vec![]);
+    }
+    s.split(',').map(|x| x.parse().ok()).collect()
+}
+
+fn parse_immediate(s: &str) -> Option<Vecs<i64>> {
+    let (dims, cells) = s.split_once(':')?;
+    let dims: Vec<i64> = parse_list(dims)?;
+    let cells: Vec<i64> = parse_list(cells)?;
+    if dims.len() != 2 || cells.len() != crate::DIMS[0] * crate::DIMS[1] {
+        return None;
+    }
+
+    let mut result = Vecs::new([dims[0], dims[1]]);
+    for (row, chunk) in result.vecs.iter_mut().zip(cells.chunks(crate::DIMS[1])) {
+        row.extend_from_slice(chunk);
+    }
+    Some(result)
+}
+
+fn parse_assignments(s: &str) -> Option<Assignments> {
+    let fields: Vec<_> = s.split('|').map(str::trim).collect();
+    if fields.len() != 4 {
+        return None;
+    }
+    let immediates = if fields[3].is_empty() {
+        vec![]
+    } else {
+        fields[3].split(';').map(parse_immediate).collect::<Option<_>>()?
+    };
+    Some(Assignments {
+        immediates,
+        params: parse_list(fields[2])?,
+        results: parse_list(fields[1])?,
+        output: fields[0].parse().ok()?,
+    })
+}
+
+fn parse(contents: &str) -> Option<HashMap<Key, Entry>> {
+    let mut lines = contents.lines();
+    if lines.next()? != HEADER {
+        return None;
+    }
+
+    let mut entries = HashMap::new();
+    let mut current = None;
+    for line in lines {
+        let (kind, rest) = line.split_once(' ')?;
+        match kind {
+            "entry" => {
+                let key = Key::from_str_radix(rest, 16).ok()?;
+                entries.insert(key, Entry::default());
+                current = Some(key);
+            }
+            "solution" => {
+                entries.get_mut(&current?)?.solution = Some(parse_assignments(rest)?);
+            }
+            "invalid" => {
+                let invalid = parse_assignments(rest)?;
+                entries.get_mut(&current?)?.invalid_assignments.push(invalid);
+            }
+            _ => return None,
+        }
+    }
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DIMS;
+
+    fn assignments(output: u32, shift: i64) -> Assignments {
+        let mut immediate = Vecs::new([DIMS[0] as i64, DIMS[1] as i64]);
+        for (i, row) in immediate.vecs.iter_mut().enumerate() {
+            row.extend((0..DIMS[1] as i64).map(|j| shift * (i as i64 * 100 + j) - 7));
+        }
+        Assignments {
+            immediates: vec![immediate.clone(), immediate],
+            params: vec![0, 1, 3],
+            results: vec![2, 3],
+            output,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("synth-cache-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn key_is_fnv1a() {
+        assert_eq!(key(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(key("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round-trip");
+        let mut cache = Cache::open(&path).unwrap();
+        cache.insert_solution(1, assignments(3, 1));
+        cache.insert_invalid_assignment(1, assignments(3, -1));
+        cache.insert_invalid_assignment(1, assignments(3, -1));
+        cache.insert_invalid_assignment(0xffff_ffff_ffff_ffff, assignments(2, 2));
+        cache.insert_solution(2, Assignments {
+            immediates: vec![],
+            params: vec![],
+            results: vec![1],
+            output: 1,
+        });
+        cache.save().unwrap();
+
+        let reopened = Cache::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reopened.solution(1), Some(&assignments(3, 1)));
+        assert_eq!(reopened.invalid_assignments(1), &[assignments(3, -1)]);
+        assert_eq!(reopened.solution(0xffff_ffff_ffff_ffff), None);
+        assert_eq!(reopened.invalid_assignments(0xffff_ffff_ffff_ffff), &[assignments(2, 2)]);
+        assert_eq!(reopened.solution(2).unwrap().results, vec![1]);
+        assert!(reopened.solution(2).unwrap().immediates.is_empty());
+        assert_eq!(reopened.solution(3), None);
+    }
+
+    #[test]
+    fn other_formats_are_empty() {
+        let path = temp_path("other-format");
+        fs::write(&path, "synth-loop-free-prog cache v0\nentry 0000000000000001\n").unwrap();
+        let cache = Cache::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cache.solution(1), None);
+
+        assert!(parse(&format!("{}\nentry 1\nsolution 1 | 1 | |\n", HEADER)).is_some());
+        assert!(parse(&format!("{}\nentry 1\nsolution 1 | 1 |\n", HEADER)).is_none());
+        assert!(parse(&format!("{}\nsolution 1 | 1 | |\n", HEADER)).is_none());
+    }
+}
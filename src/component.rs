@@ -1,6 +1,6 @@
-use crate::{Id, Operator, Vecs, DIMS};
-use std::{fmt::Debug, usize};
-use z3::{ast::{Int, Bool, Array, Ast}, Sort};
+use crate::{DType, Domain, Error, Id, Operator, Reduction, Result, Tensor, Vecs, DIMS};
+use crate::term::{self, Array, Ast, Bool, Dynamic, Int, Sort};
+use std::fmt::Debug;
 
 const _DIMSIZE : [usize ; 2] = [4, 10];
 const _SIZE_STORE_INDEX : i64 = -2;
@@ -18,31 +18,71 @@ const _SIZE_Y : i64 = 1;
 //     };
 // }
 
-// fn bit_vec_from_u64(context: &z3::Context, val: u64, bit_width: u32) -> BitVec {
+// fn bit_vec_from_u64(context: &term::Context, val: u64, bit_width: u32) -> BitVec {
 //     BitVec::from_i64(context, val as i64, bit_width)
 // }
 
-// fn zero(context: &z3::Context, bit_width: u32) -> BitVec {
+// fn zero(context: &term::Context, bit_width: u32) -> BitVec {
 //     bit_vec_from_u64(context, 0, bit_width)
 // }
 
-// fn one(context: &z3::Context, bit_width: u32) -> BitVec {
+// fn one(context: &term::Context, bit_width: u32) -> BitVec {
 //     bit_vec_from_u64(context, 1, bit_width)
 // }
-fn int_from_i64(context: &z3::Context, val: i64, _bit_width: u32) -> Int {
-    Int::from_i64(context, val as i64)
+
+// 下标和形状总是Int，单元格的值则用domain的方法来构造
+
+fn zero(context: &term::Context) -> Int<'_> {
+    Int::from_i64(context, 0)
+}
+
+fn one(context: &term::Context) -> Int<'_> {
+    Int::from_i64(context, 1)
 }
 
-fn zero(context: &z3::Context, bit_width: u32) -> Int {
-    int_from_i64(context, 0, bit_width)
+// 逐元素的部件按NumPy的规则广播：大小为1的维度会被拉伸成另一个操作数的大小
+
+/// The shape that `operands` broadcast to. It only makes sense when
+/// `are_broadcast_compatible` holds.
+fn broadcast_dims<'a>(context: &'a term::Context, operands: &[Tensor<'a>]) -> [Int<'a>; 2] {
+    let one = one(context);
+    let dim = |d: usize| {
+        operands[1..].iter().fold(operands[0].dims[d].clone(), |dim, operand| {
+            dim._eq(&one).ite(&operand.dims[d], &dim)
+        })
+    };
+    [dim(0), dim(1)]
 }
 
-fn one(context: &z3::Context, bit_width: u32) -> Int {
-    int_from_i64(context, 1, bit_width)
+/// Do the shapes of `operands` broadcast together? This is the symbolic
+/// counterpart of `are_broadcastable`.
+fn are_broadcast_compatible<'a>(context: &'a term::Context, operands: &[Tensor<'a>]) -> Bool<'a> {
+    let one = one(context);
+    let mut compatible = vec![];
+    for a in operands {
+        for b in operands {
+            for d in 0 .. 2 {
+                compatible.push(Bool::or(context, &[
+                    &a.dims[d]._eq(&b.dims[d]),
+                    &a.dims[d]._eq(&one),
+                    &b.dims[d]._eq(&one),
+                ]));
+            }
+        }
+    }
+    Bool::and(context, &compatible.iter().collect::<Vec<_>>())
 }
 
-fn _min_int(context: &z3::Context, bit_width: u32) -> Int {
-    int_from_i64(context, -2^63, bit_width)
+/// The cell at `[i, j]` of `operand` once it has been broadcast, i.e. reading
+/// row (or column) 0 along a dimension of size 1. Outside the broadcast shape
+/// this is whatever padding the operand has there, so callers mask it with
+/// `is_in_shape`.
+fn broadcast_cell<'a>(context: &'a term::Context, operand: &Tensor<'a>, i: usize, j: usize) -> Dynamic<'a> {
+    let one = one(context);
+    let is_one_row = operand.dims[0]._eq(&one);
+    let is_one_col = operand.dims[1]._eq(&one);
+    let in_row = |i: usize| is_one_col.ite(&operand.vecs[i][0], &operand.vecs[i][j]);
+    is_one_row.ite(&in_row(0), &in_row(i))
 }
 
 pub trait Component: Debug {
@@ -52,7 +92,7 @@ pub trait Component: Debug {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
+        context: &'a term::Context,
         // immediates: &[BitVec<'a>],
         // operands: &[BitVec<'a>],
         // immediates: &[Vec<Int<'a>>],
@@ -62,17 +102,128 @@ pub trait Component: Debug {
         // operands: &[Vecs<Array<'a>>],
         // bit_width: u32,
 
-        immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
     ) -> 
         //BitVec<'a> 
-        Vecs<Int<'a>>;
+        Tensor<'a>;
         
     /// How many immediates does this component require?
     fn immediate_arity(&self) -> usize {
         0
     }
+
+    /// Can this component be applied to operands of the given concrete shapes?
+    ///
+    /// `shapes[i]` is `None` when the `i`th operand's shape isn't known ahead
+    /// of time (e.g. because it is the result of another component). This is
+    /// only used to prune wirings before solving, so it must be conservative
+    /// and only reject shapes that can never work.
+    fn accepts_operand_shapes(&self, _shapes: &[Option<[usize; 2]>]) -> bool {
+        true
+    }
+
+    /// The constraint this component puts on its immediates and operands when
+    /// they are only known symbolically, e.g. that their shapes are compatible
+    /// or that the indices they hold are in range. Operands that don't satisfy
+    /// it are invalid wirings. It should agree with `accepts_operand_shapes`, and by
+    /// default there is none.
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        _operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        Bool::from_bool(context, true)
+    }
+
+    /// The dtype of this component's result when its operands have the given
+    /// dtypes, or `None` if it can't be applied to operands of those dtypes.
+    ///
+    /// By default, every operand must have the same dtype, which the result
+    /// shares.
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_dtype(dtypes)
+    }
+}
+
+// 下面几个函数用来描述各个部件对输入形状的要求，形状未知（None）时一律认为满足
+
+fn is_scalar(shape: &Option<[usize; 2]>) -> bool {
+    shape.is_none_or(|s| s == [1, 1])
+}
+
+fn is_row_vector(shape: &Option<[usize; 2]>, max_len: usize) -> bool {
+    shape.is_none_or(|s| s[0] == 1 && s[1] <= max_len)
+}
+
+fn has_at_most_cols(shape: &Option<[usize; 2]>, max_cols: usize) -> bool {
+    shape.is_none_or(|s| s[1] <= max_cols)
+}
+
+/// Are all of the known shapes compatible under broadcasting, i.e. does every
+/// dimension either agree or have size 1?
+fn are_broadcastable(shapes: &[Option<[usize; 2]>]) -> bool {
+    let known: Vec<_> = shapes.iter().filter_map(|s| *s).collect();
+    known.iter().all(|a| {
+        known.iter().all(|b| (0..2).all(|d| a[d] == b[d] || a[d] == 1 || b[d] == 1))
+    })
+}
+
+fn are_same_shape(shapes: &[Option<[usize; 2]>]) -> bool {
+    let known: Vec<_> = shapes.iter().filter_map(|s| *s).collect();
+    known.windows(2).all(|w| w[0] == w[1])
+}
+
+/// The inner dimensions of a matrix product must agree, and the encoding only
+/// sums over the first `DIMS[0]` columns of the left-hand side.
+fn are_matmul_shapes(shapes: &[Option<[usize; 2]>]) -> bool {
+    if !has_at_most_cols(&shapes[0], DIMS[0]) || !has_at_most_cols(&shapes[1], DIMS[0]) {
+        return false;
+    }
+    match (shapes[0], shapes[1]) {
+        (Some(a), Some(b)) => a[1] == b[0],
+        _ => true,
+    }
+}
+
+/// Does `operand` have the shape `dims`?
+fn has_shape<'a>(context: &'a term::Context, operand: &Tensor<'a>, dims: [usize; 2]) -> Bool<'a> {
+    Bool::and(context, &[
+        &operand.dims[0]._eq(&Int::from_i64(context, dims[0] as i64)),
+        &operand.dims[1]._eq(&Int::from_i64(context, dims[1] as i64)),
+    ])
+}
+
+/// The symbolic counterpart of `are_matmul_shapes`.
+fn are_matmul_operands<'a>(context: &'a term::Context, operands: &[Tensor<'a>]) -> Bool<'a> {
+    let max_cols = Int::from_i64(context, DIMS[0] as i64);
+    Bool::and(context, &[
+        &operands[0].dims[1]._eq(&operands[1].dims[0]),
+        &operands[0].dims[1].le(&max_cols),
+        &operands[1].dims[1].le(&max_cols),
+    ])
+}
+
+// 下面几个函数用来描述各个部件对输入类型的要求
+
+/// The dtype every one of `dtypes` has, if they all agree. Components without
+/// operands produce `Int`s unless they say otherwise.
+fn same_dtype(dtypes: &[DType]) -> Option<DType> {
+    match dtypes.split_first() {
+        Some((first, rest)) => rest.iter().all(|d| d == first).then_some(*first),
+        None => Some(DType::Int),
+    }
+}
+
+fn same_numeric_dtype(dtypes: &[DType]) -> Option<DType> {
+    same_dtype(dtypes).filter(|d| d.is_numeric())
+}
+
+fn are_all(dtypes: &[DType], dtype: DType) -> bool {
+    dtypes.iter().all(|&d| d == dtype)
 }
 
 // #[derive(Debug)]
@@ -89,7 +240,7 @@ pub trait Component: Debug {
 
 //     fn make_expression<'a>(
 //         &self,
-//         context: &'a z3::Context,
+//         context: &'a term::Context,
 //         // _immediates: &[Vec<Int<'a>>],
 //         // _operands: &[Vec<Int<'a>>],
 //         _immediates: &[Vecs<Int<'a>>],
@@ -149,19 +300,42 @@ impl Component for TfAdd {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        _bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                result.vecs[i].push(Int::add(&context, &[&operands[0].vecs[i][j], &operands[1].vecs[i][j]]));
+                let lhs = broadcast_cell(context, &operands[0], i, j);
+                let rhs = broadcast_cell(context, &operands[1], i, j);
+                let value = domain.add(context, &[&lhs, &rhs]);
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
@@ -169,53 +343,73 @@ pub fn tf_add() -> Box<dyn Component> {
     Box::new(TfAdd) as _
 }
 
+/// `tf.math.argmax(input, axis)` and `tf.math.argmin(input, axis)`. Ties go to
+/// the first index, and the result is 1-D along either axis.
 #[derive(Debug)]
-struct TfArgmax;
+struct TfArg {
+    max: bool,
+    axis: usize,
+}
 
-impl Component for TfArgmax {
+impl Component for TfArg {
     fn operand_arity(&self) -> usize {
         1
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfArgmax(operands[0])
+        if self.max {
+            Operator::TfArgmax(operands[0], self.axis)
+        } else {
+            Operator::TfArgmin(operands[0], self.axis)
+        }
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        // 注意，这里只有axis=1的实现方式，这么做的原因是因为它不给axis=0的测试样例所以没有写。。。
-        // 目前遇到的问题是，如果采用之前array的方法，即计算两遍然后根据axis判断返回结果，那么会导致ite的时候类型不匹配
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new([one(context, bit_width), operands[0].dims[0].clone()]);
-        for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
-        }
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let operand = &operands[0];
+        let cell0 = domain.constant(context, 0);
+        let reduction = if self.max { Reduction::Max } else { Reduction::Min };
+        let dims = [one(context), operand.dims[1 - self.axis].clone()];
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
-            let mut max = Int::from_i64(context, -9223372036854775808);
-            let mut index = const0.clone();
             for j in 0 .. DIMS[1] {
-                max = operands[0].vecs[i][j].gt(&max).ite(&operands[0].vecs[i][j], &max);
-            }
-            for j in (0 .. DIMS[1]).rev() {
-                let col = Int::from_i64(context, j as i64);
-                index = operands[0].vecs[i][j]._eq(&max).ite(&col, &index);
+                if i > 0 || j >= DIMS[1 - self.axis] {
+                    result.vecs[i].push(cell0.clone());
+                    continue;
+                }
+                let cells = (0 .. DIMS[self.axis]).map(|k| if self.axis == 0 { (k, j) } else { (j, k) });
+                let items = line_items(context, operand, cells);
+                let best = reduce(context, domain, reduction, &items);
+                // 倒着找，这样相等时留下的是第一个
+                let mut index = zero(context);
+                for (k, (is_member, value)) in items.iter().enumerate().rev() {
+                    let is_best = Bool::and(context, &[is_member, &value._eq(&best)]);
+                    index = is_best.ite(&Int::from_i64(context, k as i64), &index);
+                }
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&domain.cell_of(&index), &cell0));
             }
-            result.vecs[0][i] = index;
         }
+        result
+    }
 
-        return result;
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        dtypes[0].is_numeric().then_some(DType::Int)
     }
 }
 
-pub fn tf_argmax() -> Box<dyn Component> {
-    Box::new(TfArgmax) as _
+pub fn tf_argmax(axis: usize) -> Box<dyn Component> {
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfArg { max: true, axis }) as _
+}
+
+pub fn tf_argmin(axis: usize) -> Box<dyn Component> {
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfArg { max: false, axis }) as _
 }
 
 #[derive(Debug)]
@@ -232,33 +426,62 @@ impl Component for TfBooleanMask {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
-        let domain_sort = Sort::int(&context);
-        let range_sort = Sort::int(&context);
-        let mut array = Array::fresh_const(context, "boolean_mask_array:", &domain_sort, &range_sort);
-        let mut result_index = zero(context, bit_width);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        // 和TF一样，选中的元素按行优先的顺序排成一维，每个元素的位置就是它前面选中的元素个数
+        let (tensor, mask) = (&operands[0], &operands[1]);
+        let const1 = one(context);
+        let cell0 = domain.constant(context, 0);
+        let mut count = zero(context);
+        let mut kept = vec![];
         for i in 0 .. DIMS[0] {
-            let mut index = zero(context, bit_width);
-            for j in 0 .. DIMS[1] {
-                let cur_index = operands[1].vecs[i][j]._eq(&const0).ite(&Int::from_i64(context, -1), &index);
-                index = operands[1].vecs[i][j]._eq(&const0).ite(&index, &Int::add(context, &[&index, &const1]));
-                array = array.store(&cur_index, &operands[0].vecs[i][j]);
-            }
-            result_index = result_index.lt(&index).ite(&index, &result_index);
             for j in 0 .. DIMS[1] {
-                result.vecs[i].push(array.select(&Int::from_i64(context, j as i64)).as_int().unwrap_or(const0.clone()));
+                let is_kept = Bool::and(context, &[
+                    &is_in_shape(context, &tensor.dims, i, j),
+                    &mask.vecs[i][j]._eq(&cell0).not(),
+                ]);
+                let next = Int::add(context, &[&count, &const1]);
+                kept.push((is_kept.clone(), count.clone(), &tensor.vecs[i][j]));
+                count = is_kept.ite(&next, &count);
             }
         }
-        result.dims[1] = result_index;
+        let dims = [const1, count];
+        let mut result = Vecs::new(dims.clone());
+        for j in 0 .. DIMS[1] {
+            let position = Int::from_i64(context, j as i64);
+            let value = kept.iter().fold(cell0.clone(), |value, (is_kept, k, x)| {
+                Bool::and(context, &[is_kept, &k._eq(&position)]).ite(x, &value)
+            });
+            result.vecs[0].push(is_in_shape(context, &dims, 0, j).ite(&value, &cell0));
+        }
+        for i in 1 .. DIMS[0] {
+            result.vecs[i] = vec![cell0.clone(); DIMS[1]];
+        }
+        result
+    }
 
-        return result;
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_same_shape(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        Bool::and(context, &[
+            &operands[0].dims[0]._eq(&operands[1].dims[0]),
+            &operands[0].dims[1]._eq(&operands[1].dims[1]),
+        ])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[1] == DType::Bool).then_some(dtypes[0])
     }
 }
 
@@ -280,51 +503,75 @@ impl Component for TfBooleanMask_ {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let const1 = one(context);
+        let cell0 = domain.constant(context, 0);
         let mut result = Vecs::new(operands[0].dims.clone());
-        let domain_sort = Sort::int(&context);
-        let range_sort = Sort::int(&context);
-        let domain_sort_ = Sort::int(&context);
-        let range_sort_ = Sort::int(&context);
+        let domain_sort = Sort::int(context);
+        let range_sort = domain.sort(context);
+        let domain_sort_ = Sort::int(context);
+        let range_sort_ = domain.sort(context);
         let array_sort = Sort::array(context, &domain_sort_, &range_sort_);
-        let first_dim_sort = Sort::int(&context);
+        let first_dim_sort = Sort::int(context);
         let mut array = Array::fresh_const(context, "boolean_mask_array_second:", &domain_sort, &range_sort);
         let mut array_ = Array::fresh_const(context, "boolean_mask_array:", &first_dim_sort, &array_sort);
         for i in 0 .. DIMS[0] {
             for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+                result.vecs[i].push(cell0.clone());
             }
         }
-        let mut index_ = zero(context, bit_width);
-        let mut result_index = zero(context, bit_width);
+        let mut index_ = zero(context);
         for i in 0 .. DIMS[0] {
-            let mut index = zero(context, bit_width);
+            let mut index = zero(context);
             for j in 0 .. DIMS[1] {
-                let cur_index = operands[1].vecs[0][i]._eq(&const0).ite(&Int::from_i64(context, -1), &index);
-                index = operands[1].vecs[0][i]._eq(&const0).ite(&index, &Int::add(context, &[&index, &const1]));
+                let cur_index = operands[1].vecs[0][i]._eq(&cell0).ite(&Int::from_i64(context, -1), &index);
+                index = operands[1].vecs[0][i]._eq(&cell0).ite(&index, &Int::add(context, &[&index, &const1]));
                 array = array.store(&cur_index, &operands[0].vecs[i][j]);
             }
-            result_index = result_index.lt(&index).ite(&index, &result_index);
-            let cur_index_ = operands[1].vecs[0][i]._eq(&const0).ite(&Int::from_i64(context, -1), &index_);
-            index_ = operands[1].vecs[0][i]._eq(&const0).ite(&index_, &Int::add(context, &[&index_, &const1]));
+            let cur_index_ = operands[1].vecs[0][i]._eq(&cell0).ite(&Int::from_i64(context, -1), &index_);
+            index_ = operands[1].vecs[0][i]._eq(&cell0).ite(&index_, &Int::add(context, &[&index_, &const1]));
             array_ = array_.store(&cur_index_, &array);
         }
         for i in 0 .. DIMS[0] {
             let row = array_.select(&Int::from_i64(context, i as i64)).as_array().unwrap();
             for j in 0 .. DIMS[1] {
-                result.vecs[i][j] = row.select(&Int::from_i64(context, j as i64)).as_int().unwrap_or(const0.clone());
+                result.vecs[i][j] = row.select(&Int::from_i64(context, j as i64));
             }
         }
+        // 选中的行保持原来的列数
         result.dims[0] = index_;
-        result.dims[1] = result_index;
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        // 第二个参数是一个长度等于第一个参数行数的行向量
+        is_row_vector(&shapes[1], DIMS[0])
+            && match (shapes[0], shapes[1]) {
+                (Some(a), Some(b)) => a[0] == b[1],
+                _ => true,
+            }
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        Bool::and(context, &[
+            &operands[1].dims[0]._eq(&one(context)),
+            &operands[1].dims[1]._eq(&operands[0].dims[0]),
+        ])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[1] == DType::Bool).then_some(dtypes[0])
     }
 }
 
@@ -332,8 +579,11 @@ pub fn tf_boolean_mask_() -> Box<dyn Component> {
     Box::new(TfBooleanMask_) as _
 }
 
+/// Convert a tensor to another dtype. Casting to `Bool` maps every non-zero
+/// element to 1, and casting to `Int` rounds toward zero (which only matters in
+/// `Domain::Real`); casting to `Float` keeps the values as they are.
 #[derive(Debug)]
-struct TfCast;
+struct TfCast(DType);
 
 impl Component for TfCast {
     fn operand_arity(&self) -> usize {
@@ -341,29 +591,40 @@ impl Component for TfCast {
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfCast(operands[0])
+        Operator::TfCast(operands[0], self.0)
     }
 
     fn make_expression<'a>(
         &self,
-        _context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        _bit_width: u32,
-    ) -> Vecs<Int<'a>> {
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
         let mut result = Vecs::new(operands[0].dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                result.vecs[i].push(operands[0].vecs[i][j].clone());
+                let value = &operands[0].vecs[i][j];
+                result.vecs[i].push(match self.0 {
+                    DType::Bool => value._eq(&cell0).ite(&cell0, &cell1),
+                    DType::Int => domain.trunc(context, value),
+                    DType::Float => value.clone(),
+                });
             }
         }
 
-        return result;
+        result
+    }
+
+    fn result_dtype(&self, _dtypes: &[DType]) -> Option<DType> {
+        Some(self.0)
     }
 }
 
-pub fn tf_cast() -> Box<dyn Component> {
-    Box::new(TfCast) as _
+pub fn tf_cast(dtype: DType) -> Box<dyn Component> {
+    Box::new(TfCast(dtype)) as _
 }
 
 #[derive(Debug)]
@@ -380,18 +641,18 @@ impl Component for TfConcat0 {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
         let mut result = Vecs::new([Int::add(context, &[&operands[0].dims[0], &operands[1].dims[0]]), operands[0].dims[1].clone()]);
-        let domain_sort = Sort::int(&context);
-        let range_sort = Sort::int(&context);
+        let domain_sort = Sort::int(context);
+        let range_sort = domain.sort(context);
         for i in 0 .. DIMS[0] {
             for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+                result.vecs[i].push(cell0.clone());
             }
         }
         for j in 0 .. DIMS[1] {
@@ -405,12 +666,19 @@ impl Component for TfConcat0 {
                 array = array.store(&row_index, &operands[1].vecs[i][j]);
             }
             for i in 0 .. DIMS[0] {
-                let row_index = Int::from_i64(context, j as i64);
-                result.vecs[i][j] = array.select(&row_index).as_int().unwrap_or(const0.clone());
+                let row_index = Int::from_i64(context, i as i64);
+                result.vecs[i][j] = array.select(&row_index);
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        match (shapes[0], shapes[1]) {
+            (Some(a), Some(b)) => a[1] == b[1] && a[0] + b[0] <= DIMS[0],
+            _ => true,
+        }
     }
 }
 
@@ -432,15 +700,14 @@ impl Component for TfConcat1 {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
         let mut result = Vecs::new([operands[0].dims[0].clone(), Int::add(context, &[&operands[0].dims[1], &operands[1].dims[1]])]);
-        let domain_sort = Sort::int(&context);
-        let range_sort = Sort::int(&context);
+        let domain_sort = Sort::int(context);
+        let range_sort = domain.sort(context);
         for i in 0 .. DIMS[0] {
             let mut array = Array::fresh_const(context, "concat_1_array:", &domain_sort, &range_sort);
             for j in 0 .. DIMS[1] {
@@ -453,11 +720,18 @@ impl Component for TfConcat1 {
             }
             for j in 0 .. DIMS[1] {
                 let col_index = Int::from_i64(context, j as i64);
-                result.vecs[i].push(array.select(&col_index).as_int().unwrap_or(const0.clone()));
+                result.vecs[i].push(array.select(&col_index));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        match (shapes[0], shapes[1]) {
+            (Some(a), Some(b)) => a[0] == b[0] && a[1] + b[1] <= DIMS[1],
+            _ => true,
+        }
     }
 }
 
@@ -479,11 +753,11 @@ impl Component for TfConstant {
 
     fn make_expression<'a>(
         &self,
-        _context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        _bit_width: u32,
-    ) -> Vecs<Int<'a>> {
+        _context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Tensor<'a> {
         let mut result = Vecs::new(operands[0].dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
@@ -513,28 +787,52 @@ impl Component for TfDivide {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        // 整数域里是整除，实数域里才是真正的除法。
+        // TF里除以0得到inf或nan，这里放不下，约定结果就是被除数
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
+                let lhs = broadcast_cell(context, &operands[0], i, j);
+                let rhs = broadcast_cell(context, &operands[1], i, j);
                 let row = Int::from_i64(context, i as i64);
                 let col = Int::from_i64(context, j as i64);
-                let is_in_row = row.lt(&operands[0].dims[0]);
-                let is_in_col = col.lt(&operands[0].dims[1]);
-                let fenmu = operands[1].vecs[i][j]._eq(&const0).ite(&const1, &operands[1].vecs[i][j]);
+                let is_in_row = row.lt(&dims[0]);
+                let is_in_col = col.lt(&dims[1]);
+                let fenmu = rhs._eq(&cell0).ite(&cell1, &rhs);
                 let value = Bool::and(context, &[&is_in_row, &is_in_col])
-                    .ite(&Int::div(&operands[0].vecs[i][j], &fenmu), &const0);
+                    .ite(&domain.div(&lhs, &fenmu), &cell0);
                 result.vecs[i].push(value);
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        // 和TF的truediv一样，两个整数相除得到浮点数
+        same_numeric_dtype(dtypes).map(|_| DType::Float)
     }
 }
 
@@ -556,26 +854,47 @@ impl Component for TfEqual {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
+                let lhs = broadcast_cell(context, &operands[0], i, j);
+                let rhs = broadcast_cell(context, &operands[1], i, j);
                 let row = Int::from_i64(context, i as i64);
                 let col = Int::from_i64(context, j as i64);
-                let is_in_row = row.lt(&operands[0].dims[0]);
-                let is_in_col = col.lt(&operands[0].dims[1]);
-                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col, &operands[0].vecs[i][j]._eq(&operands[1].vecs[i][j])])
-                    .ite(&const1, &const0));
+                let is_in_row = row.lt(&dims[0]);
+                let is_in_col = col.lt(&dims[1]);
+                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col, &lhs._eq(&rhs)])
+                    .ite(&cell1, &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_dtype(dtypes).map(|_| DType::Bool)
     }
 }
 
@@ -597,28 +916,38 @@ impl Component for TfExpandDims {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
         // 样例中能用的（有些数组维度超过了二维）基本上都是axis = 1的情况，目前只考虑这个
-        let const0 = zero(context, bit_width);
-        let const4 = Int::from_i64(context, DIMS[0] as i64);
-        let result_row = operands[0].dims[1].lt(&const4).ite(&operands[0].dims[1], &const4);
-        let mut result = Vecs::new([result_row, one(context, bit_width)]);
+        let cell0 = domain.constant(context, 0);
+        let mut result = Vecs::new([operands[0].dims[1].clone(), one(context)]);
         for i in 0 .. DIMS[0] {
             for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+                result.vecs[i].push(cell0.clone());
             }
         }
-        for i in 0 .. DIMS[0] {
-            for j in 0 .. DIMS[0] {
-                result.vecs[j][0] = operands[0].vecs[i][j].clone();
-            }
+        for j in 0 .. DIMS[0] {
+            result.vecs[j][0] = operands[0].vecs[0][j].clone();
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        is_row_vector(&shapes[0], DIMS[0])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        operands[0].dims[0]._eq(&one(context))
     }
 }
 
@@ -640,26 +969,47 @@ impl Component for TfGreater {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
+                let lhs = broadcast_cell(context, &operands[0], i, j);
+                let rhs = broadcast_cell(context, &operands[1], i, j);
                 let row = Int::from_i64(context, i as i64);
                 let col = Int::from_i64(context, j as i64);
-                let is_in_row = row.lt(&operands[0].dims[0]);
-                let is_in_col = col.lt(&operands[0].dims[1]);
-                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col, &operands[0].vecs[i][j].gt(&operands[1].vecs[i][j])])
-                    .ite(&const1, &const0));
+                let is_in_row = row.lt(&dims[0]);
+                let is_in_col = col.lt(&dims[1]);
+                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col, &domain.gt(&lhs, &rhs)])
+                    .ite(&cell1, &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes).map(|_| DType::Bool)
     }
 }
 
@@ -681,34 +1031,60 @@ impl Component for TfBincount {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        // 所有的样例里面只有一个输入的情况，目前就考虑这个
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        // 和TF一样把所有元素放在一起数，结果的长度是最大值加一
+        let operand = &operands[0];
+        let const1 = one(context);
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        let mut len = zero(context);
+        let mut counts = vec![cell0.clone(); DIMS[1]];
         for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+            for j in 0 .. DIMS[1] {
+                let is_in = is_in_shape(context, &operand.dims, i, j);
+                let value = domain.to_index(&operand.vecs[i][j]);
+                let next = Int::add(context, &[&value, &const1]);
+                len = Bool::and(context, &[&is_in, &next.gt(&len)]).ite(&next, &len);
+                for (k, count) in counts.iter_mut().enumerate() {
+                    let is_bin = Bool::and(context, &[&is_in, &value._eq(&Int::from_i64(context, k as i64))]);
+                    *count = is_bin.ite(&domain.add(context, &[count, &cell1]), count);
+                }
             }
         }
-        let mut result_index = zero(context, bit_width);
+        let mut result = Vecs::new([const1, len]);
+        result.vecs[0] = counts;
+        for i in 1 .. DIMS[0] {
+            result.vecs[i] = vec![cell0.clone(); DIMS[1]];
+        }
+        result
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        // 负数没有对应的桶
+        let operand = &operands[0];
+        let const0 = zero(context);
+        let mut constraints = vec![];
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                result_index = result_index.gt(&operands[0].vecs[i][j]).ite(&result_index, &operands[0].vecs[i][j]);
-                for k in 0 .. DIMS[1] {
-                    let index = Int::from_i64(context, k as i64);
-                    let new_value = Int::add(context, &[&result.vecs[i][k], &const1]);
-                    result.vecs[i][k] = index._eq(&operands[0].vecs[i][j]).ite(&new_value, &result.vecs[i][k]);
-                }
+                let is_counted = const0.le(&domain.to_index(&operand.vecs[i][j]));
+                constraints.push(is_in_shape(context, &operand.dims, i, j).implies(&is_counted));
             }
         }
-        result.dims[1] = result_index;
+        Bool::and(context, &constraints.iter().collect::<Vec<_>>())
+    }
 
-        return result;
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Int).then_some(DType::Int)
     }
 }
 
@@ -716,50 +1092,70 @@ pub fn tf_bincount() -> Box<dyn Component> {
     Box::new(TfBincount) as _
 }
 
+/// `tf.math.cumsum(x, axis, exclusive, reverse)`, `tf.math.cumprod`, and the
+/// running max and min. An exclusive scan starts from the reduction's
+/// identity, and a reversed one runs from the end of the axis.
 #[derive(Debug)]
-struct TfCumsum;
+struct TfCumulative {
+    reduction: Reduction,
+    axis: usize,
+    exclusive: bool,
+    reverse: bool,
+}
 
-impl Component for TfCumsum {
+impl Component for TfCumulative {
     fn operand_arity(&self) -> usize {
-        2
+        1
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfCumsum(operands[0], operands[1])
+        Operator::TfCumulative(operands[0], self.reduction, self.axis, self.exclusive, self.reverse)
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        // 所有样例只有输入数组和exclusive两个输入，只考虑这俩
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
-        let is_exclusive = operands[1].vecs[0][0]._eq(&const0);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        // 每个位置把扫过的那一段归约一下
+        let operand = &operands[0];
+        let cell0 = domain.constant(context, 0);
+        let identity = identity(context, domain, self.reduction);
+        let mut result = Vecs::new(operand.dims.clone());
         for i in 0 .. DIMS[0] {
-            result.vecs[i].push(is_exclusive.ite(&operands[0].vecs[i][0], &const0));
-            for j in 1 .. (DIMS[1] + 1) {
-                let col_index = Int::from_i64(context, j as i64);
-                let is_in_col = col_index.lt(&operands[0].dims[1]);
-                let mut temp = j;
-                if j == DIMS[1] {
-                    temp = DIMS[1] - 1;
-                }
-                let is_exclusive_value = is_exclusive.ite(&operands[0].vecs[i][temp], &operands[0].vecs[i][j - 1]);
-                let value = Int::add(context, &[&result.vecs[i][j - 1], &is_exclusive_value]);
-                result.vecs[i].push(is_in_col.ite(&value, &const0));
+            for j in 0 .. DIMS[1] {
+                let k = if self.axis == 0 { i } else { j };
+                let positions = match (self.reverse, self.exclusive) {
+                    (false, false) => 0 .. k + 1,
+                    (false, true) => 0 .. k,
+                    (true, false) => k .. DIMS[self.axis],
+                    (true, true) => k + 1 .. DIMS[self.axis],
+                };
+                let cells = positions.map(|m| if self.axis == 0 { (m, j) } else { (i, m) });
+                let items = line_items(context, operand, cells);
+                let is_empty = Bool::or(context, &items.iter().map(|(is_member, _)| is_member).collect::<Vec<_>>()).not();
+                let value = is_empty.ite(&identity, &reduce(context, domain, self.reduction, &items));
+                result.vecs[i].push(is_in_shape(context, &operand.dims, i, j).ite(&value, &cell0));
             }
         }
+        result
+    }
 
-        return result;
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        dtypes[0].is_numeric().then_some(dtypes[0])
     }
 }
 
-pub fn tf_cumsum() -> Box<dyn Component> {
-    Box::new(TfCumsum) as _
+pub fn tf_cumulative(reduction: Reduction, axis: usize, exclusive: bool, reverse: bool) -> Box<dyn Component> {
+    assert!(
+        matches!(reduction, Reduction::Sum | Reduction::Prod | Reduction::Max | Reduction::Min),
+        "there is no cumulative {:?}",
+        reduction
+    );
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfCumulative { reduction, axis, exclusive, reverse }) as _
 }
 
 #[derive(Debug)]
@@ -776,19 +1172,42 @@ impl Component for TfMultiply {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        _bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                result.vecs[i].push(Int::mul(&context, &[&operands[0].vecs[i][j], &operands[1].vecs[i][j]]));
+                let lhs = broadcast_cell(context, &operands[0], i, j);
+                let rhs = broadcast_cell(context, &operands[1], i, j);
+                let value = domain.mul(context, &[&lhs, &rhs]);
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
@@ -810,30 +1229,50 @@ impl Component for TfOneHot {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let const4 = Int::from_i64(context, DIMS[0] as i64);
-        let result_row = operands[0].dims[1].lt(&const4).ite(&operands[0].dims[1], &const4);
-        let depth = operands[1].vecs[0][0].clone();
-        let mut result = Vecs::new([result_row, depth.clone()]);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        // 第i行对应第i个下标，越界的下标那一行全是0
+        let depth = domain.to_index(&operands[1].vecs[0][0]);
+        let dims = [operands[0].dims[1].clone(), depth];
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                let row_index = Int::from_i64(context, i as i64);
                 let col_index = Int::from_i64(context, j as i64);
-                let is_in_row = row_index.lt(&operands[0].dims[0]);
-                let is_in_col = Bool::and(context, &[&col_index.lt(&operands[0].dims[1]), &col_index.lt(&depth)]);
-                let is_equal = operands[0].vecs[0][i]._eq(&col_index);
-                let value = Bool::and(context, &[&is_in_row, &is_in_col, &is_equal]).ite(&const1, &const0);
+                let is_equal = operands[0].vecs[0][i]._eq(&domain.cell_of(&col_index));
+                let value = Bool::and(context, &[&is_in_shape(context, &dims, i, j), &is_equal]).ite(&cell1, &cell0);
                 result.vecs[i].push(value);
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        is_row_vector(&shapes[0], DIMS[0]) && is_scalar(&shapes[1])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        let const1 = one(context);
+        Bool::and(context, &[
+            &operands[0].dims[0]._eq(&const1),
+            &operands[1].dims[0]._eq(&const1),
+            &operands[1].dims[1]._eq(&const1),
+        ])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Int).then_some(DType::Float)
     }
 }
 
@@ -855,31 +1294,50 @@ impl Component for TfRange {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
         // 所有样例只有两个参数，起始和结束，第三个参数delta按照1处理
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let start = operands[0].vecs[0][0].clone();
-        let limit = operands[1].vecs[0][0].clone();
+        let const0 = zero(context);
+        let const1 = one(context);
+        let cell0 = domain.constant(context, 0);
+        let start = domain.to_index(&operands[0].vecs[0][0]);
+        let limit = domain.to_index(&operands[1].vecs[0][0]);
         let len = Int::sub(context, &[&limit, &start]);
         let col = len.gt(&const0).ite(&len, &const0);
         let mut result = Vecs::new([const1.clone(), col]);
         let mut value = start.clone();
         for i in 0 .. DIMS[0] {
             for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+                result.vecs[i].push(cell0.clone());
             }
         }
         for j in 0 .. DIMS[1] {
-            result.vecs[0][j] = value.lt(&limit).ite(&value, &const0);
+            result.vecs[0][j] = value.lt(&limit).ite(&domain.cell_of(&value), &cell0);
             value = Int::add(context, &[&value, &const1]);
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        is_scalar(&shapes[0]) && is_scalar(&shapes[1])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        Bool::and(context, &[&has_shape(context, &operands[0], [1, 1]), &has_shape(context, &operands[1], [1, 1])])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Int).then_some(DType::Int)
     }
 }
 
@@ -887,375 +1345,344 @@ pub fn tf_range() -> Box<dyn Component> {
     Box::new(TfRange) as _
 }
 
+/// Pair the cells of `operand` at `cells` with whether they are inside its
+/// shape.
+fn line_items<'a, 'b>(
+    context: &'a term::Context,
+    operand: &'b Tensor<'a>,
+    cells: impl Iterator<Item = (usize, usize)>,
+) -> Vec<(Bool<'a>, &'b Dynamic<'a>)> {
+    cells.map(|(row, col)| (is_in_shape(context, &operand.dims, row, col), &operand.vecs[row][col])).collect()
+}
+
+/// Reduce the values in `items` whose flag is set. `Mean` and `Variance`
+/// divide by how many there are, so at least one of them has to be; TF's mean
+/// of nothing is NaN, which no domain has. `Any`, `All` and `Count` only look
+/// at whether the values are nonzero.
+fn reduce<'a>(
+    context: &'a term::Context,
+    domain: Domain,
+    reduction: Reduction,
+    items: &[(Bool<'a>, &Dynamic<'a>)],
+) -> Dynamic<'a> {
+    let const0 = zero(context);
+    let const1 = one(context);
+    let cell0 = domain.constant(context, 0);
+    let cell1 = domain.constant(context, 1);
+    let mut count = const0.clone();
+    // 没有值可以归约时就是单位元，比如空的最大值是最小的那个值
+    let mut acc = identity(context, domain, reduction);
+    for (is_member, value) in items {
+        let is_first = count._eq(&const0);
+        let is_nonzero = value._eq(&cell0).not();
+        let combined = match reduction {
+            Reduction::Sum | Reduction::Mean | Reduction::Variance => domain.add(context, &[&acc, value]),
+            Reduction::Prod => domain.mul(context, &[&acc, value]),
+            Reduction::Max => Bool::or(context, &[&is_first, &domain.gt(value, &acc)]).ite(*value, &acc),
+            Reduction::Min => Bool::or(context, &[&is_first, &domain.lt(value, &acc)]).ite(*value, &acc),
+            Reduction::Any => is_nonzero.ite(&cell1, &acc),
+            Reduction::All => is_nonzero.ite(&acc, &cell0),
+            Reduction::Count => is_nonzero.ite(&domain.add(context, &[&acc, &cell1]), &acc),
+        };
+        acc = is_member.ite(&combined, &acc);
+        count = is_member.ite(&Int::add(context, &[&count, &const1]), &count);
+    }
+    match reduction {
+        Reduction::Mean => domain.div(&acc, &domain.cell_of(&count)),
+        Reduction::Variance => {
+            // 方差要再过一遍，累加和均值之差的平方
+            let count = domain.cell_of(&count);
+            let mean = domain.div(&acc, &count);
+            let squares = items.iter().fold(cell0, |sum, (is_member, value)| {
+                let deviation = domain.sub(context, &[value, &mean]);
+                let square = domain.mul(context, &[&deviation, &deviation]);
+                is_member.ite(&domain.add(context, &[&sum, &square]), &sum)
+            });
+            domain.div(&squares, &count)
+        }
+        _ => acc,
+    }
+}
+
+/// What reducing no values at all comes out as: the smallest value for a max,
+/// the largest for a min, 1 for a product or an `All` and 0 otherwise.
+fn identity<'a>(context: &'a term::Context, domain: Domain, reduction: Reduction) -> Dynamic<'a> {
+    match reduction {
+        Reduction::Prod | Reduction::All => domain.constant(context, 1),
+        Reduction::Max => domain.min_value(context),
+        Reduction::Min => domain.max_value(context),
+        _ => domain.constant(context, 0),
+    }
+}
+
+/// `tf.reduce_*(input_tensor, axis, keepdims)` and `tf.math.count_nonzero`,
+/// reducing over everything when there is no axis. Without `keepdims`,
+/// reducing along axis 1 leaves one value per row, as a 1-D tensor.
 #[derive(Debug)]
-struct TfReduceMax;
+struct TfReduce {
+    reduction: Reduction,
+    axis: Option<usize>,
+    keepdims: bool,
+}
 
-impl Component for TfReduceMax {
-    fn operand_arity(&self) -> usize {
-        1
+impl TfReduce {
+    /// Does this reduction divide by the number of values, so that an empty
+    /// operand has no result?
+    fn needs_values(&self) -> bool {
+        matches!(self.reduction, Reduction::Mean | Reduction::Variance)
     }
 
-    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceMax(operands[0])
+    fn dims<'a>(&self, context: &'a term::Context, operand: &Tensor<'a>) -> [Int<'a>; 2] {
+        let const1 = one(context);
+        match (self.axis, self.keepdims) {
+            (None, _) => [const1.clone(), const1],
+            (Some(0), _) => [const1, operand.dims[1].clone()],
+            (_, false) => [const1, operand.dims[0].clone()],
+            (_, true) => [operand.dims[0].clone(), const1],
+        }
     }
 
-    fn make_expression<'a>(
-        &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new([const1.clone(), const1.clone()]);
-        let mut max = Int::from_i64(context, -9223372036854775808);
-        for i in 0 .. DIMS[0] {
-            for j in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-                let row_index = Int::from_i64(context, i as i64);
-                let col_index = Int::from_i64(context, j as i64);
-                let is_in_row = row_index.lt(&operands[0].dims[0]);
-                let is_in_col = col_index.lt(&operands[0].dims[1]);
-                let value = operands[0].vecs[i][j].gt(&max).ite(&operands[0].vecs[i][j], &max);
-                max = Bool::and(context, &[&is_in_row, &is_in_col]).ite(&value, &max);
+    /// The cells that reduce into cell `(i, j)` of the result, if it can be in
+    /// the result at all.
+    fn line(&self, i: usize, j: usize) -> Option<Vec<(usize, usize)>> {
+        match (self.axis, self.keepdims) {
+            (None, _) if i == 0 && j == 0 => {
+                Some((0 .. DIMS[0]).flat_map(|row| (0 .. DIMS[1]).map(move |col| (row, col))).collect())
             }
+            (Some(0), _) if i == 0 => Some((0 .. DIMS[0]).map(|row| (row, j)).collect()),
+            (Some(1), false) if i == 0 && j < DIMS[0] => Some((0 .. DIMS[1]).map(|col| (j, col)).collect()),
+            (Some(1), true) if j == 0 => Some((0 .. DIMS[1]).map(|col| (i, col)).collect()),
+            _ => None,
         }
-        result.vecs[0][0] = max;
-
-        return result;
     }
 }
 
-pub fn tf_reduce_max() -> Box<dyn Component> {
-    Box::new(TfReduceMax) as _
-}
-
-#[derive(Debug)]
-struct TfReduceMax0;
-
-impl Component for TfReduceMax0 {
+impl Component for TfReduce {
     fn operand_arity(&self) -> usize {
         1
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceMax0(operands[0])
+        Operator::TfReduce(operands[0], self.reduction, self.axis, self.keepdims)
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new([const1.clone(), operands[0].dims[1].clone()]);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let operand = &operands[0];
+        let cell0 = domain.constant(context, 0);
+        let dims = self.dims(context, operand);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+            for j in 0 .. DIMS[1] {
+                let value = match self.line(i, j) {
+                    Some(cells) => {
+                        let items = line_items(context, operand, cells.into_iter());
+                        let value = reduce(context, domain, self.reduction, &items);
+                        is_in_shape(context, &dims, i, j).ite(&value, &cell0)
+                    }
+                    None => cell0.clone(),
+                };
+                result.vecs[i].push(value);
             }
         }
-        for j in 0 .. DIMS[1] {
-            let mut max = Int::from_i64(context, -9223372036854775808);
-            for i in 0 .. DIMS[0] {
-                let row_index = Int::from_i64(context, i as i64);
-                let col_index = Int::from_i64(context, j as i64);
-                let is_in_row = row_index.lt(&operands[0].dims[0]);
-                let is_in_col = col_index.lt(&operands[0].dims[1]);
-                let value = operands[0].vecs[i][j].gt(&max).ite(&operands[0].vecs[i][j], &max);
-                max = Bool::and(context, &[&is_in_row, &is_in_col]).ite(&value, &max);
-            }
-            result.vecs[0][j] = max;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        !self.needs_values() || shapes[0].is_none_or(|shape| shape[0] > 0 && shape[1] > 0)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        if !self.needs_values() {
+            return Bool::from_bool(context, true);
         }
+        let const0 = zero(context);
+        Bool::and(context, &[&operands[0].dims[0].gt(&const0), &operands[0].dims[1].gt(&const0)])
+    }
 
-        return result;
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        match self.reduction {
+            Reduction::Any | Reduction::All => are_all(dtypes, DType::Bool).then_some(DType::Bool),
+            Reduction::Count => Some(DType::Int),
+            Reduction::Variance => (dtypes[0] == DType::Float).then_some(DType::Float),
+            _ => same_numeric_dtype(dtypes),
+        }
     }
 }
 
-pub fn tf_reduce_max0() -> Box<dyn Component> {
-    Box::new(TfReduceMax0) as _
+pub fn tf_reduce(reduction: Reduction, axis: Option<usize>, keepdims: bool) -> Result<Box<dyn Component>> {
+    if let Some(axis) = axis.filter(|&axis| axis >= 2) {
+        return Err(Error::InvalidComponent(format!(
+            "tf_reduce: axis {} is out of range, tensors only have axes 0 and 1",
+            axis
+        )));
+    }
+    Ok(Box::new(TfReduce { reduction, axis, keepdims }) as _)
 }
 
 #[derive(Debug)]
-struct TfReduceMax1;
+struct TfSequenceMask;
 
-impl Component for TfReduceMax1 {
+impl Component for TfSequenceMask {
     fn operand_arity(&self) -> usize {
         1
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceMax1(operands[0])
+        Operator::TfSequenceMask(operands[0])
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new([const1.clone(), operands[0].dims[0].clone()]);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        let mut maxlen = zero(context);
         for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
+            let length = domain.to_index(&operands[0].vecs[0][i]);
+            let is_longer = Bool::and(context, &[&is_in_shape(context, &operands[0].dims, 0, i), &length.gt(&maxlen)]);
+            maxlen = is_longer.ite(&length, &maxlen);
         }
+        let mut result = Vecs::new([operands[0].dims[1].clone(), maxlen]);
         for i in 0 .. DIMS[0] {
-            let mut max = Int::from_i64(context, -9223372036854775808);
             for j in 0 .. DIMS[1] {
-                let row_index = Int::from_i64(context, i as i64);
-                let col_index = Int::from_i64(context, j as i64);
-                let is_in_row = row_index.lt(&operands[0].dims[0]);
-                let is_in_col = col_index.lt(&operands[0].dims[1]);
-                let value = operands[0].vecs[i][j].gt(&max).ite(&operands[0].vecs[i][j], &max);
-                max = Bool::and(context, &[&is_in_row, &is_in_col]).ite(&value, &max);
+                let row = Int::from_i64(context, i as i64);
+                let col = Int::from_i64(context, j as i64);
+                let is_in_row = row.lt(&operands[0].dims[1]);
+                let is_in_col = col.lt(&domain.to_index(&operands[0].vecs[0][i]));
+                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col]).ite(&cell1, &cell0));
             }
-            result.vecs[0][i] = max;
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        is_row_vector(&shapes[0], DIMS[0])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        operands[0].dims[0]._eq(&one(context))
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Int).then_some(DType::Bool)
     }
 }
 
-pub fn tf_reduce_max1() -> Box<dyn Component> {
-    Box::new(TfReduceMax1) as _
+pub fn tf_sequence_mask() -> Box<dyn Component> {
+    Box::new(TfSequenceMask) as _
 }
 
 #[derive(Debug)]
-struct TfReduceSum;
+struct TfSquare;
 
-impl Component for TfReduceSum {
+impl Component for TfSquare {
     fn operand_arity(&self) -> usize {
         1
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceSum(operands[0])
+        Operator::TfSquare(operands[0])
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new([one(context, bit_width), one(context, bit_width)]);
-        let mut sum = zero(context, bit_width);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let mut result = Vecs::new(operands[0].dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-                sum = Int::add(context, &[&sum, &operands[0].vecs[i][j]]);
+                result.vecs[i].push(domain.mul(context, &[&operands[0].vecs[i][j], &operands[0].vecs[i][j]]));
             }
         }
-        result.vecs[0][0] = sum;
 
-        return result;
+        result
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
-pub fn tf_reduce_sum() -> Box<dyn Component> {
-    Box::new(TfReduceSum) as _
+pub fn tf_square() -> Box<dyn Component> {
+    Box::new(TfSquare) as _
 }
 
 #[derive(Debug)]
-struct TfReduceSum0;
+struct TfSubtract;
 
-impl Component for TfReduceSum0 {
+impl Component for TfSubtract {
     fn operand_arity(&self) -> usize {
-        1
-    }
-
-    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceSum0(operands[0])
-    }
-
-    fn make_expression<'a>(
-        &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new([one(context, bit_width), operands[0].dims[1].clone()]);
-        for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
-        }
-        for j in 0 .. DIMS[1] {
-            let mut sum = zero(context, bit_width);
-            for i in 0 .. DIMS[0] {
-                sum = Int::add(context, &[&sum, &operands[0].vecs[i][j]]);
-            }
-            result.vecs[0][j] = sum;
-        }
-
-        return result;
-    }
-}
-
-pub fn tf_reduce_sum0() -> Box<dyn Component> {
-    Box::new(TfReduceSum0) as _
-}
-
-#[derive(Debug)]
-struct TfReduceSum1;
-
-impl Component for TfReduceSum1 {
-    fn operand_arity(&self) -> usize {
-        1
-    }
-
-    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceSum1(operands[0])
-    }
-
-    fn make_expression<'a>(
-        &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new([one(context, bit_width), operands[0].dims[0].clone()]);
-        for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
-        }
-        for i in 0 .. DIMS[0] {
-            let mut sum = zero(context, bit_width);
-            for j in 0 .. DIMS[1] {
-                sum = Int::add(context, &[&sum, &operands[0].vecs[i][j]]);
-            }
-            result.vecs[0][i] = sum;
-        }
-
-        return result;
-    }
-}
-
-pub fn tf_reduce_sum1() -> Box<dyn Component> {
-    Box::new(TfReduceSum1) as _
-}
-
-#[derive(Debug)]
-struct TfSequenceMask;
-
-impl Component for TfSequenceMask {
-    fn operand_arity(&self) -> usize {
-        1
+        2
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfSequenceMask(operands[0])
+        Operator::TfSubtract(operands[0], operands[1])
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut maxlen = zero(context, bit_width);
-        for i in 0 .. DIMS[0] {
-            maxlen = maxlen.gt(&operands[0].vecs[0][i]).ite(&maxlen, &operands[0].vecs[0][i]);
-        }
-        let const4 = Int::from_i64(context, DIMS[0] as i64);
-        let result_row = operands[0].dims[1].lt(&const4).ite(&operands[0].dims[1], &const4);
-        let mut result = Vecs::new([result_row, maxlen]);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                let row = Int::from_i64(context, i as i64);
-                let col = Int::from_i64(context, j as i64);
-                let is_in_row = row.lt(&operands[0].dims[1]);
-                let is_in_col = col.lt(&operands[0].vecs[0][i]);
-                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col]).ite(&const1, &const0));
+                let lhs = broadcast_cell(context, &operands[0], i, j);
+                let rhs = broadcast_cell(context, &operands[1], i, j);
+                let value = domain.sub(context, &[&lhs, &rhs]);
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
             }
         }
 
-        return result;
-    }
-}
-
-pub fn tf_sequence_mask() -> Box<dyn Component> {
-    Box::new(TfSequenceMask) as _
-}
-
-#[derive(Debug)]
-struct TfSquare;
-
-impl Component for TfSquare {
-    fn operand_arity(&self) -> usize {
-        1
+        result
     }
 
-    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfSquare(operands[0])
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
     }
 
-    fn make_expression<'a>(
+    fn operand_constraint<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        _bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let mut result = Vecs::new(operands[0].dims.clone());
-        for i in 0 .. DIMS[0] {
-            for j in 0 .. DIMS[1] {
-                result.vecs[i].push(Int::mul(&context, &[&operands[0].vecs[i][j], &operands[0].vecs[i][j]]));
-            }
-        }
-
-        return result;
-    }
-}
-
-pub fn tf_square() -> Box<dyn Component> {
-    Box::new(TfSquare) as _
-}
-
-#[derive(Debug)]
-struct TfSubtract;
-
-impl Component for TfSubtract {
-    fn operand_arity(&self) -> usize {
-        2
-    }
-
-    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfSubtract(operands[0], operands[1])
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
     }
 
-    fn make_expression<'a>(
-        &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        _bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let mut result = Vecs::new(operands[0].dims.clone());
-        for i in 0 .. DIMS[0] {
-            for j in 0 .. DIMS[1] {
-                result.vecs[i].push(Int::sub(&context, &[&operands[0].vecs[i][j], &operands[1].vecs[i][j]]));
-            }
-        }
-
-        return result;
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
@@ -1277,31 +1704,47 @@ impl Component for TfTensordot {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
         // 第三个参数axes所有的测试样例里面都是1，其他形式的可以自己转换
-        let const0 = zero(context, bit_width);
-        let const4 = Int::from_i64(context, DIMS[0] as i64);
-        let result = operands[1].dims[1].lt(&const4).ite(&operands[1].dims[1], &const4);
-        let mut result = Vecs::new([operands[0].dims[0].clone(), result]);
+        let cell0 = domain.constant(context, 0);
+        let mut result = Vecs::new([operands[0].dims[0].clone(), operands[1].dims[1].clone()]);
         for i in 0 .. DIMS[0] {
             for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+                result.vecs[i].push(cell0.clone());
             }
         }
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[0] {
                 for k in 0 .. DIMS[0] {
-                    let temp = Int::mul(context, &[&operands[0].vecs[i][k], &operands[1].vecs[k][j]]);
-                    result.vecs[i][j] = Int::add(context, &[&result.vecs[i][j], &temp]);
+                    let temp = domain.mul(context, &[&operands[0].vecs[i][k], &operands[1].vecs[k][j]]);
+                    result.vecs[i][j] = domain.add(context, &[&result.vecs[i][j], &temp]);
                 }
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_matmul_shapes(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_matmul_operands(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
@@ -1323,18 +1766,16 @@ impl Component for TfTranspose {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const4 = Int::from_i64(context, DIMS[0] as i64);
-        let result = operands[0].dims[1].lt(&const4).ite(&operands[0].dims[1], &const4);
-        let mut result = Vecs::new([result, operands[0].dims[0].clone()]);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let mut result = Vecs::new([operands[0].dims[1].clone(), operands[0].dims[0].clone()]);
         for i in 0 .. DIMS[0] {
             for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+                result.vecs[i].push(cell0.clone());
             }
         }
         for i in 0 .. DIMS[0] {
@@ -1343,7 +1784,11 @@ impl Component for TfTranspose {
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        has_at_most_cols(&shapes[0], DIMS[0])
     }
 }
 
@@ -1365,38 +1810,39 @@ impl Component for TfWhere1 {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
         // 所有测试中均为二维下标，不考虑一维的情况
-        let const0 = zero(context, bit_width);
-        let const1 = zero(context, bit_width);
+        let const0 = zero(context);
+        let const1 = one(context);
+        let cell0 = domain.constant(context, 0);
         let const_minus_1 = Int::from_i64(context, -1);
         let mut result = Vecs::new(operands[0].dims.clone());
-        let domain_sort = Sort::int(&context);
-        let range_sort = Sort::int(&context);
-        let domain_sort_ = Sort::int(&context);
-        let range_sort_ = Sort::int(&context);
+        let domain_sort = Sort::int(context);
+        let range_sort = domain.sort(context);
+        let domain_sort_ = Sort::int(context);
+        let range_sort_ = domain.sort(context);
         let array_sort = Sort::array(context, &domain_sort_, &range_sort_);
-        let first_dim_sort = Sort::int(&context);
+        let first_dim_sort = Sort::int(context);
         let mut array_total = Array::fresh_const(context, "where_1_array:", &first_dim_sort, &array_sort);
-        let mut index = zero(context, bit_width);
+        let mut index = zero(context);
         for i in 0 .. DIMS[0] {
             for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+                result.vecs[i].push(cell0.clone());
             }
         }
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                let domain_sort = Sort::int(&context);
-                let range_sort = Sort::int(&context);
+                let domain_sort = Sort::int(context);
+                let range_sort = domain.sort(context);
                 let mut array = Array::fresh_const(context, "where_1_array_second:", &domain_sort, &range_sort);
-                let row_index = operands[0].vecs[i][j]._eq(&const0).ite(&const_minus_1, &Int::from_i64(context, i as i64));
-                let col_index = operands[0].vecs[i][j]._eq(&const0).ite(&const_minus_1, &Int::from_i64(context, j as i64));
-                array = array.store(&const0, &row_index);
-                array = array.store(&const1, &col_index);
+                let row_index = operands[0].vecs[i][j]._eq(&cell0).ite(&const_minus_1, &Int::from_i64(context, i as i64));
+                let col_index = operands[0].vecs[i][j]._eq(&cell0).ite(&const_minus_1, &Int::from_i64(context, j as i64));
+                array = array.store(&const0, &domain.cell_of(&row_index));
+                array = array.store(&const1, &domain.cell_of(&col_index));
                 let index_ = Bool::and(context, &[&row_index._eq(&const_minus_1), &col_index._eq(&const_minus_1)])
                     .ite(&const_minus_1, &index);
                 index = Bool::and(context, &[&row_index._eq(&const_minus_1), &col_index._eq(&const_minus_1)])
@@ -1408,14 +1854,17 @@ impl Component for TfWhere1 {
             for j in 0 .. 2 {
                 result.vecs[i][j] = array_total.select(&Int::from_i64(context, i as i64)).as_array()
                     .unwrap_or(Array::fresh_const(context, "where_1_array_temp:", &domain_sort, &range_sort))
-                    .select(&Int::from_i64(context, j as i64)).as_int().unwrap_or(const0.clone());
+                    .select(&Int::from_i64(context, j as i64));
             }
         }
-        let const4 = Int::from_i64(context, DIMS[0] as i64);
-        result.dims[0] = index.lt(&const4).ite(&index, &const4);
+        result.dims[0] = index;
         result.dims[1] = Int::from_i64(context, 2);
 
-        return result;
+        result
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Bool).then_some(DType::Int)
     }
 }
 
@@ -1437,20 +1886,47 @@ impl Component for TfWhere3 {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                result.vecs[i].push(operands[0].vecs[i][j]._eq(&const0).ite(&operands[2].vecs[i][j], &operands[1].vecs[i][j]));
+                let cond = broadcast_cell(context, &operands[0], i, j);
+                let x = broadcast_cell(context, &operands[1], i, j);
+                let y = broadcast_cell(context, &operands[2], i, j);
+                let value = cond._eq(&cell0).ite(&y, &x);
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        if dtypes[0] == DType::Bool {
+            same_dtype(&dtypes[1..])
+        } else {
+            None
+        }
     }
 }
 
@@ -1474,15 +1950,15 @@ impl Component for TfEye {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let total_row = operands[0].vecs[0][0].clone();
-        let total_col = operands[1].vecs[0][0].clone();
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        let total_row = domain.to_index(&operands[0].vecs[0][0]);
+        let total_col = domain.to_index(&operands[1].vecs[0][0]);
         let mut result = Vecs::new([total_row.clone(), total_col.clone()]);
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
@@ -1491,11 +1967,29 @@ impl Component for TfEye {
                 let is_in_row = row.lt(&total_row);
                 let is_in_col = col.lt(&total_col);
                 let row_equal_col = row._eq(&col);
-                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col, &row_equal_col]).ite(&const1, &const0));
+                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col, &row_equal_col]).ite(&cell1, &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        is_scalar(&shapes[0]) && is_scalar(&shapes[1])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        Bool::and(context, &[&has_shape(context, &operands[0], [1, 1]), &has_shape(context, &operands[1], [1, 1])])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Int).then_some(DType::Float)
     }
 }
 
@@ -1517,14 +2011,14 @@ impl Component for TfFill {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let total_row = operands[0].vecs[0][0].clone();
-        let total_col = operands[0].vecs[0][1].clone();
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let total_row = domain.to_index(&operands[0].vecs[0][0]);
+        let total_col = domain.to_index(&operands[0].vecs[0][1]);
         let fill_value = operands[1].vecs[0][0].clone();
         let mut result = Vecs::new([total_row.clone(), total_col.clone()]);
         for i in 0 .. DIMS[0] {
@@ -1533,11 +2027,30 @@ impl Component for TfFill {
                 let col = Int::from_i64(context, j as i64);
                 let is_in_row = row.lt(&total_row);
                 let is_in_col = col.lt(&total_col);
-                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col]).ite(&fill_value, &const0));
+                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col]).ite(&fill_value, &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        // 第一个参数是形状 [rows, cols]，第二个参数是填充的值
+        shapes[0].is_none_or(|s| s == [1, 2]) && is_scalar(&shapes[1])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        Bool::and(context, &[&has_shape(context, &operands[0], [1, 2]), &has_shape(context, &operands[1], [1, 1])])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[0] == DType::Int).then_some(dtypes[1])
     }
 }
 
@@ -1545,55 +2058,237 @@ pub fn tf_fill() -> Box<dyn Component> {
     Box::new(TfFill) as _
 }
 
+/// Reduce the values in one segment. `items` are the segment ids and values
+/// to go through, of which the first `len` are real; a segment without any
+/// values is `empty`.
+fn reduce_segment<'a, 'b>(
+    context: &'a term::Context,
+    domain: Domain,
+    reduction: Reduction,
+    segment: usize,
+    items: impl Iterator<Item = (&'b Dynamic<'a>, &'b Dynamic<'a>)>,
+    len: &Int<'a>,
+    empty: &Dynamic<'a>,
+) -> Dynamic<'a>
+where
+    'a: 'b,
+{
+    let segment = Int::from_i64(context, segment as i64);
+    let items: Vec<_> = items
+        .enumerate()
+        .map(|(k, (id, value))| {
+            let is_member = Bool::and(context, &[
+                &Int::from_i64(context, k as i64).lt(len),
+                &domain.to_index(id)._eq(&segment),
+            ]);
+            (is_member, value)
+        })
+        .collect();
+    let is_empty = Bool::or(context, &items.iter().map(|(is_member, _)| is_member).collect::<Vec<_>>()).not();
+    is_empty.ite(empty, &reduce(context, domain, reduction, &items))
+}
+
+/// `tf.math.segment_*(data, segment_ids)` and the unsorted variants, with
+/// `num_segments` segments. A 1-D `data` is a row vector, and gets segmented
+/// by columns; anything else gets segmented by rows.
+fn segment_reduce<'a>(
+    context: &'a term::Context,
+    domain: Domain,
+    reduction: Reduction,
+    operands: &[Tensor<'a>],
+    num_segments: &Int<'a>,
+    empty: &Dynamic<'a>,
+) -> Tensor<'a> {
+    let (data, ids) = (&operands[0], &operands[1]);
+    let const1 = one(context);
+    let cell0 = domain.constant(context, 0);
+    let is_vector = data.dims[0]._eq(&const1);
+    let dims = [
+        is_vector.ite(&const1, num_segments),
+        is_vector.ite(num_segments, &data.dims[1]),
+    ];
+    let mut result = Vecs::new(dims.clone());
+    for i in 0 .. DIMS[0] {
+        for j in 0 .. DIMS[1] {
+            let by_cols = if i == 0 {
+                let items = ids.vecs[0].iter().zip(&data.vecs[0]);
+                reduce_segment(context, domain, reduction, j, items, &data.dims[1], empty)
+            } else {
+                cell0.clone()
+            };
+            let items = ids.vecs[0].iter().zip(data.vecs.iter().map(|row| &row[j]));
+            let by_rows = reduce_segment(context, domain, reduction, i, items, &data.dims[0], empty);
+            let value = is_vector.ite(&by_cols, &by_rows);
+            result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
+        }
+    }
+    result
+}
+
+/// Is `ids` a row vector with one segment id per row of `data` (or per
+/// column, if `data` is 1-D)?
+fn are_segment_ids<'a>(context: &'a term::Context, data: &Tensor<'a>, ids: &Tensor<'a>) -> Bool<'a> {
+    let const1 = one(context);
+    Bool::and(context, &[
+        &ids.dims[0]._eq(&const1),
+        &ids.dims[1]._eq(&data.dims[0]._eq(&const1).ite(&data.dims[1], &data.dims[0])),
+    ])
+}
+
+/// How many segments fit in the result, given `data`'s shape.
+fn max_segments<'a>(context: &'a term::Context, data: &Tensor<'a>) -> Int<'a> {
+    data.dims[0]._eq(&one(context)).ite(
+        &Int::from_i64(context, DIMS[1] as i64),
+        &Int::from_i64(context, DIMS[0] as i64),
+    )
+}
+
+fn are_segment_shapes(shapes: &[Option<[usize; 2]>]) -> bool {
+    match (shapes[0], shapes[1]) {
+        (Some([rows, cols]), Some([ids_rows, len])) => ids_rows == 1 && len == if rows == 1 { cols } else { rows },
+        (_, ids) => is_row_vector(&ids, DIMS[1]),
+    }
+}
+
+/// TensorFlow only has segment sums, products, maxima, minima and means.
+fn is_segment_reduction(reduction: Reduction) -> bool {
+    matches!(reduction, Reduction::Sum | Reduction::Prod | Reduction::Max | Reduction::Min | Reduction::Mean)
+}
+
+/// `tf.math.segment_*(data, segment_ids)`, where the ids are sorted. Empty
+/// segments are 0, or 1 for a product.
 #[derive(Debug)]
-struct TfSegmentMax;
+struct TfSegment(Reduction);
+
+impl TfSegment {
+    fn num_segments<'a>(&self, context: &'a term::Context, operands: &[Tensor<'a>], domain: Domain) -> Int<'a> {
+        // id是排好序的，最后一个最大
+        let ids = &operands[1];
+        let const1 = one(context);
+        let last = select_in_row(context, ids, 0, &Int::sub(context, &[&ids.dims[1], &const1]));
+        Int::add(context, &[&domain.to_index(&last), &const1])
+    }
+}
 
-impl Component for TfSegmentMax {
+impl Component for TfSegment {
     fn operand_arity(&self) -> usize {
         2
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfSegmentMax(operands[0], operands[1])
+        Operator::TfSegment(operands[0], operands[1], self.0)
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
-        let domain_sort = Sort::int(&context);
-        let range_sort = Sort::int(&context);
-        let mut col = zero(context, bit_width);
-        for i in 0 .. DIMS[0] {
-            let mut array = Array::fresh_const(context, "segment_max_array:", &domain_sort, &range_sort);
-            let max = Int::from_i64(context, -9223372036854775808);
-            let flag = max.clone();
-            for j in 0 .. DIMS[1] {
-                let select_value = array.select(&operands[0].vecs[i][j]).as_int().unwrap_or(max.clone());
-                col = select_value._eq(&flag).ite(&col, &Int::add(context, &[&col, &const1]));
-                let final_value = select_value.gt(&operands[1].vecs[i][j]).ite(&select_value, &operands[1].vecs[i][j]);
-                array = array.store(&operands[0].vecs[i][j], &final_value);
-            }
-            for j in 0 .. DIMS[1] {
-                let index = Int::from_i64(context, j as i64);
-                let value = array.select(&index).as_int().unwrap_or(const0.clone());
-                result.vecs[i].push(value);
-            }
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let empty = domain.constant(context, if self.0 == Reduction::Prod { 1 } else { 0 });
+        let num_segments = self.num_segments(context, operands, domain);
+        segment_reduce(context, domain, self.0, operands, &num_segments, &empty)
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_segment_shapes(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let (data, ids) = (&operands[0], &operands[1]);
+        let mut constraints = vec![
+            are_segment_ids(context, data, ids),
+            domain.to_index(&ids.vecs[0][0]).ge(&zero(context)),
+            self.num_segments(context, operands, domain).le(&max_segments(context, data)),
+        ];
+        for k in 1 .. DIMS[1] {
+            let is_sorted = domain.to_index(&ids.vecs[0][k - 1]).le(&domain.to_index(&ids.vecs[0][k]));
+            constraints.push(Int::from_i64(context, k as i64).lt(&ids.dims[1]).implies(&is_sorted));
         }
-        result.dims[1] = col;
+        Bool::and(context, &constraints.iter().collect::<Vec<_>>())
+    }
 
-        return result;
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[0].is_numeric() && dtypes[1] == DType::Int).then_some(dtypes[0])
+    }
+}
+
+pub fn tf_segment(reduction: Reduction) -> Box<dyn Component> {
+    assert!(is_segment_reduction(reduction), "there is no tf.math.segment_* for {:?}", reduction);
+    Box::new(TfSegment(reduction)) as _
+}
+
+/// `tf.math.unsorted_segment_*(data, segment_ids, num_segments)`. Empty
+/// segments hold the reduction's identity: the smallest value for a max, the
+/// largest for a min, and 0 for a mean.
+#[derive(Debug)]
+struct TfUnsortedSegment(Reduction);
+
+impl Component for TfUnsortedSegment {
+    fn operand_arity(&self) -> usize {
+        3
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfUnsortedSegment(operands[0], operands[1], operands[2], self.0)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let empty = identity(context, domain, self.0);
+        let num_segments = domain.to_index(&operands[2].vecs[0][0]);
+        segment_reduce(context, domain, self.0, operands, &num_segments, &empty)
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_segment_shapes(shapes) && is_scalar(&shapes[2])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        // 负的id会被丢掉，但不能超出num_segments
+        let (data, ids) = (&operands[0], &operands[1]);
+        let const1 = one(context);
+        let num_segments = domain.to_index(&operands[2].vecs[0][0]);
+        let mut constraints = vec![
+            are_segment_ids(context, data, ids),
+            operands[2].dims[0]._eq(&const1),
+            operands[2].dims[1]._eq(&const1),
+            num_segments.ge(&const1),
+            num_segments.le(&max_segments(context, data)),
+        ];
+        for k in 0 .. DIMS[1] {
+            let is_in_segments = domain.to_index(&ids.vecs[0][k]).lt(&num_segments);
+            constraints.push(Int::from_i64(context, k as i64).lt(&ids.dims[1]).implies(&is_in_segments));
+        }
+        Bool::and(context, &constraints.iter().collect::<Vec<_>>())
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[0].is_numeric() && dtypes[1] == DType::Int && dtypes[2] == DType::Int).then_some(dtypes[0])
     }
 }
 
-pub fn tf_segment_max() -> Box<dyn Component> {
-    Box::new(TfSegmentMax) as _
+pub fn tf_unsorted_segment(reduction: Reduction) -> Box<dyn Component> {
+    assert!(is_segment_reduction(reduction), "there is no tf.math.unsorted_segment_* for {:?}", reduction);
+    Box::new(TfUnsortedSegment(reduction)) as _
 }
 
 #[derive(Debug)]
@@ -1610,31 +2305,47 @@ impl Component for TfMatmul {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
         // 所有的测试样例里面只有两个参数的形式
-        let const0 = zero(context, bit_width);
-        let const4 = Int::from_i64(context, DIMS[0] as i64);
-        let result = operands[1].dims[1].lt(&const4).ite(&operands[1].dims[1], &const4);
-        let mut result = Vecs::new([operands[0].dims[0].clone(), result]);
+        let cell0 = domain.constant(context, 0);
+        let mut result = Vecs::new([operands[0].dims[0].clone(), operands[1].dims[1].clone()]);
         for i in 0 .. DIMS[0] {
             for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
+                result.vecs[i].push(cell0.clone());
             }
         }
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[0] {
                 for k in 0 .. DIMS[0] {
-                    let temp = Int::mul(context, &[&operands[0].vecs[i][k], &operands[1].vecs[k][j]]);
-                    result.vecs[i][j] = Int::add(context, &[&result.vecs[i][j], &temp]);
+                    let temp = domain.mul(context, &[&operands[0].vecs[i][k], &operands[1].vecs[k][j]]);
+                    result.vecs[i][j] = domain.add(context, &[&result.vecs[i][j], &temp]);
                 }
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_matmul_shapes(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_matmul_operands(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
@@ -1656,26 +2367,47 @@ impl Component for TfMaximum {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
+                let lhs = broadcast_cell(context, &operands[0], i, j);
+                let rhs = broadcast_cell(context, &operands[1], i, j);
                 let row = Int::from_i64(context, i as i64);
                 let col = Int::from_i64(context, j as i64);
-                let is_in_row = row.lt(&operands[0].dims[0]);
-                let is_in_col = col.lt(&operands[0].dims[1]);
+                let is_in_row = row.lt(&dims[0]);
+                let is_in_col = col.lt(&dims[1]);
                 result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col])
-                    .ite(&operands[0].vecs[i][j].gt(&operands[1].vecs[i][j])
-                    .ite(&operands[0].vecs[i][j], &operands[1].vecs[i][j]), &const0));
+                    .ite(&domain.gt(&lhs, &rhs)
+                    .ite(&lhs, &rhs), &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
@@ -1697,26 +2429,47 @@ impl Component for TfMinimum {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
+                let lhs = broadcast_cell(context, &operands[0], i, j);
+                let rhs = broadcast_cell(context, &operands[1], i, j);
                 let row = Int::from_i64(context, i as i64);
                 let col = Int::from_i64(context, j as i64);
-                let is_in_row = row.lt(&operands[0].dims[0]);
-                let is_in_col = col.lt(&operands[0].dims[1]);
+                let is_in_row = row.lt(&dims[0]);
+                let is_in_col = col.lt(&dims[1]);
                 result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col])
-                    .ite(&operands[0].vecs[i][j].lt(&operands[1].vecs[i][j])
-                    .ite(&operands[0].vecs[i][j], &operands[1].vecs[i][j]), &const0));
+                    .ite(&domain.lt(&lhs, &rhs)
+                    .ite(&lhs, &rhs), &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
@@ -1738,27 +2491,48 @@ impl Component for TfNotEqual {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        let dims = broadcast_dims(context, operands);
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
+                let lhs = broadcast_cell(context, &operands[0], i, j);
+                let rhs = broadcast_cell(context, &operands[1], i, j);
                 let row = Int::from_i64(context, i as i64);
                 let col = Int::from_i64(context, j as i64);
-                let is_in_row = row.lt(&operands[0].dims[0]);
-                let is_in_col = col.lt(&operands[0].dims[1]);
+                let is_in_row = row.lt(&dims[0]);
+                let is_in_col = col.lt(&dims[1]);
                 result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col])
-                    .ite(&operands[0].vecs[i][j]._eq(&operands[1].vecs[i][j])
-                    .ite(&const0, &const1), &const0));
+                    .ite(&lhs._eq(&rhs)
+                    .ite(&cell0, &cell1), &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_dtype(dtypes).map(|_| DType::Bool)
     }
 }
 
@@ -1780,15 +2554,15 @@ impl Component for TfOnes {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let total_row = operands[0].vecs[0][0].clone();
-        let total_col = operands[0].vecs[0][1].clone();
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        let total_row = domain.to_index(&operands[0].vecs[0][0]);
+        let total_col = domain.to_index(&operands[0].vecs[0][1]);
         let mut result = Vecs::new([total_row.clone(), total_col.clone()]);
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
@@ -1796,11 +2570,29 @@ impl Component for TfOnes {
                 let col = Int::from_i64(context, j as i64);
                 let is_in_row = row.lt(&total_row);
                 let is_in_col = col.lt(&total_col);
-                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col]).ite(&const1, &const0));
+                result.vecs[i].push(Bool::and(context, &[&is_in_row, &is_in_col]).ite(&cell1, &cell0));
             }
         }
 
-        return result;
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        shapes[0].is_none_or(|s| s == [1, 2])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        has_shape(context, &operands[0], [1, 2])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Int).then_some(DType::Float)
     }
 }
 
@@ -1808,268 +2600,2252 @@ pub fn tf_ones() -> Box<dyn Component> {
     Box::new(TfOnes) as _
 }
 
+/// `tf.roll(input, shift, axis)`, with a `shift` the solver picks from
+/// `1 .. dims[axis]` unless it is fixed. Other shifts roll the same way as one
+/// of these.
 #[derive(Debug)]
-struct TfReduceAny0;
+struct TfRoll {
+    shift: Option<i64>,
+    axis: usize,
+}
 
-impl Component for TfReduceAny0 {
-    fn operand_arity(&self) -> usize {
-        1
+impl TfRoll {
+    fn shift<'a>(&self, context: &'a term::Context, immediates: &[Tensor<'a>], domain: Domain) -> Int<'a> {
+        match self.shift {
+            Some(shift) => Int::from_i64(context, shift),
+            None => domain.to_index(&immediates[0].vecs[0][0]),
+        }
+    }
+}
+
+impl Component for TfRoll {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn immediate_arity(&self) -> usize {
+        if self.shift.is_some() {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn make_operator(&self, immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        let shift = self.shift.unwrap_or_else(|| immediates[0].vecs[0][0]);
+        Operator::TfRoll(operands[0], shift, self.axis)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        // 第k个位置来自k - shift，小于0的话再绕回末尾
+        let operand = &operands[0];
+        let cell0 = domain.constant(context, 0);
+        let dim = &operand.dims[self.axis];
+        let shift = self.shift(context, immediates, domain);
+        let mut result = Vecs::new(operand.dims.clone());
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                let k = if self.axis == 0 { i } else { j } as i64;
+                let source = match self.shift {
+                    // 固定的shift可以是任意整数，对每种可能的长度直接算出绕回后的位置
+                    Some(shift) => (1 ..= DIMS[self.axis] as i64).fold(zero(context), |source, n| {
+                        let wrapped = Int::from_i64(context, (k - shift).rem_euclid(n));
+                        dim._eq(&Int::from_i64(context, n)).ite(&wrapped, &source)
+                    }),
+                    None => {
+                        let source = Int::sub(context, &[&Int::from_i64(context, k), &shift]);
+                        source.lt(&zero(context)).ite(&Int::add(context, &[&source, dim]), &source)
+                    }
+                };
+                let value = select_along(context, operand, self.axis, i, j, &source);
+                result.vecs[i].push(is_in_shape(context, &operand.dims, i, j).ite(&value, &cell0));
+            }
+        }
+        result
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        if self.shift.is_some() {
+            return Bool::from_bool(context, true);
+        }
+        let shift = self.shift(context, immediates, domain);
+        Bool::and(context, &[&one(context).le(&shift), &shift.lt(&operands[0].dims[self.axis])])
+    }
+}
+
+pub fn tf_roll(axis: usize) -> Box<dyn Component> {
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfRoll { shift: None, axis }) as _
+}
+
+#[derive(Debug)]
+struct TfZeros;
+
+impl Component for TfZeros {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfZeros(operands[0])
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        // 和`tf.ones`一样，参数是形状 [rows, cols]
+        let cell0 = domain.constant(context, 0);
+        let total_row = domain.to_index(&operands[0].vecs[0][0]);
+        let total_col = domain.to_index(&operands[0].vecs[0][1]);
+        let mut result = Vecs::new([total_row, total_col]);
+        for i in 0 .. DIMS[0] {
+            for _ in 0 .. DIMS[1] {
+                result.vecs[i].push(cell0.clone());
+            }
+        }
+
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        shapes[0].is_none_or(|s| s == [1, 2])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        has_shape(context, &operands[0], [1, 2])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Int).then_some(DType::Float)
+    }
+}
+
+pub fn tf_zeros() -> Box<dyn Component> {
+    Box::new(TfZeros) as _
+}
+
+// 下面几个函数按符号下标读取单元格，下标越界时读到的值没有意义
+
+/// `operand[row][j]` for a symbolic `row`.
+fn select_in_col<'a>(context: &'a term::Context, operand: &Tensor<'a>, row: &Int<'a>, j: usize) -> Dynamic<'a> {
+    (1 .. DIMS[0]).fold(operand.vecs[0][j].clone(), |cell, i| {
+        row._eq(&Int::from_i64(context, i as i64)).ite(&operand.vecs[i][j], &cell)
+    })
+}
+
+/// `operand[i][col]` for a symbolic `col`.
+fn select_in_row<'a>(context: &'a term::Context, operand: &Tensor<'a>, i: usize, col: &Int<'a>) -> Dynamic<'a> {
+    (1 .. DIMS[1]).fold(operand.vecs[i][0].clone(), |cell, j| {
+        col._eq(&Int::from_i64(context, j as i64)).ite(&operand.vecs[i][j], &cell)
+    })
+}
+
+/// `operand[row][col]` for a symbolic `row` and `col`.
+fn select_cell<'a>(context: &'a term::Context, operand: &Tensor<'a>, row: &Int<'a>, col: &Int<'a>) -> Dynamic<'a> {
+    (1 .. DIMS[1]).fold(select_in_col(context, operand, row, 0), |cell, j| {
+        col._eq(&Int::from_i64(context, j as i64)).ite(&select_in_col(context, operand, row, j), &cell)
+    })
+}
+
+/// Is `index` in range for a dimension of size `dim`?
+fn is_in_range<'a>(context: &'a term::Context, index: &Int<'a>, dim: &Int<'a>) -> Bool<'a> {
+    Bool::and(context, &[&zero(context).le(index), &index.lt(dim)])
+}
+
+/// Is `[i, j]` inside the shape `dims`?
+fn is_in_shape<'a>(context: &'a term::Context, dims: &[Int<'a>; 2], i: usize, j: usize) -> Bool<'a> {
+    let row = Int::from_i64(context, i as i64);
+    let col = Int::from_i64(context, j as i64);
+    Bool::and(context, &[&row.lt(&dims[0]), &col.lt(&dims[1])])
+}
+
+/// `tf.gather(params, indices, axis)`, where `indices` is a row vector.
+#[derive(Debug)]
+struct TfGather(usize);
+
+impl Component for TfGather {
+    fn operand_arity(&self) -> usize {
+        2
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfGather(operands[0], operands[1], self.0)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let (params, indices) = (&operands[0], &operands[1]);
+        let cell0 = domain.constant(context, 0);
+        let mut dims = params.dims.clone();
+        dims[self.0] = indices.dims[1].clone();
+        let mut result = Vecs::new(dims.clone());
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                let value = if self.0 == 0 {
+                    select_in_col(context, params, &domain.to_index(&indices.vecs[0][i]), j)
+                } else {
+                    select_in_row(context, params, i, &domain.to_index(&indices.vecs[0][j]))
+                };
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
+            }
+        }
+
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        is_row_vector(&shapes[1], DIMS[self.0])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let (params, indices) = (&operands[0], &operands[1]);
+        let max_len = Int::from_i64(context, DIMS[self.0] as i64);
+        let mut constraints = vec![indices.dims[0]._eq(&one(context)), indices.dims[1].le(&max_len)];
+        for j in 0 .. DIMS[self.0] {
+            let col = Int::from_i64(context, j as i64);
+            let index = domain.to_index(&indices.vecs[0][j]);
+            constraints.push(col.lt(&indices.dims[1]).implies(&is_in_range(context, &index, &params.dims[self.0])));
+        }
+        Bool::and(context, &constraints.iter().collect::<Vec<_>>())
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[1] == DType::Int).then_some(dtypes[0])
+    }
+}
+
+pub fn tf_gather(axis: usize) -> Box<dyn Component> {
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfGather(axis)) as _
+}
+
+/// `tf.gather_nd(params, indices, batch_dims)`. With `batch_dims = 0`, each
+/// row of `indices` is either a row index or a `[row, col]` coordinate of
+/// `params`. With `batch_dims = 1`, `indices` is a column holding one column
+/// index for each row of `params`.
+#[derive(Debug)]
+struct TfGatherNd(usize);
+
+impl Component for TfGatherNd {
+    fn operand_arity(&self) -> usize {
+        2
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfGatherNd(operands[0], operands[1], self.0)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let (params, indices) = (&operands[0], &operands[1]);
+        let const1 = one(context);
+        let cell0 = domain.constant(context, 0);
+        // 结果是一行：batch_dims = 1时每行取一个，否则按坐标取
+        let dims = if self.0 == 0 {
+            let is_rows = indices.dims[1]._eq(&const1);
+            [is_rows.ite(&indices.dims[0], &const1), is_rows.ite(&params.dims[1], &indices.dims[0])]
+        } else {
+            [const1.clone(), indices.dims[0].clone()]
+        };
+        let mut result = Vecs::new(dims.clone());
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                let element = if i == 0 && j < DIMS[0] {
+                    let row = domain.to_index(&indices.vecs[j][0]);
+                    if self.0 == 0 {
+                        select_cell(context, params, &row, &domain.to_index(&indices.vecs[j][1]))
+                    } else {
+                        select_in_row(context, params, j, &row)
+                    }
+                } else {
+                    cell0.clone()
+                };
+                let value = if self.0 == 0 {
+                    let slice = select_in_col(context, params, &domain.to_index(&indices.vecs[i][0]), j);
+                    indices.dims[1]._eq(&const1).ite(&slice, &element)
+                } else {
+                    element
+                };
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
+            }
+        }
+
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        if self.0 == 0 {
+            shapes[1].is_none_or(|s| s[1] == 1 || s[1] == 2)
+        } else {
+            shapes[1].is_none_or(|s| s[1] == 1)
+                && match (shapes[0], shapes[1]) {
+                    (Some(a), Some(b)) => a[0] == b[0],
+                    _ => true,
+                }
+        }
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let (params, indices) = (&operands[0], &operands[1]);
+        let const1 = one(context);
+        let const2 = Int::from_i64(context, 2);
+        let mut constraints = vec![];
+        if self.0 == 0 {
+            constraints.push(Bool::or(context, &[&indices.dims[1]._eq(&const1), &indices.dims[1]._eq(&const2)]));
+        } else {
+            constraints.push(indices.dims[1]._eq(&const1));
+            constraints.push(indices.dims[0]._eq(&params.dims[0]));
+        }
+        for i in 0 .. DIMS[0] {
+            let row = Int::from_i64(context, i as i64);
+            let first = domain.to_index(&indices.vecs[i][0]);
+            let in_range = if self.0 == 0 {
+                let second = domain.to_index(&indices.vecs[i][1]);
+                Bool::and(context, &[
+                    &is_in_range(context, &first, &params.dims[0]),
+                    &indices.dims[1]._eq(&const2).implies(&is_in_range(context, &second, &params.dims[1])),
+                ])
+            } else {
+                is_in_range(context, &first, &params.dims[1])
+            };
+            constraints.push(row.lt(&indices.dims[0]).implies(&in_range));
+        }
+        Bool::and(context, &constraints.iter().collect::<Vec<_>>())
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[1] == DType::Int).then_some(dtypes[0])
+    }
+}
+
+pub fn tf_gather_nd(batch_dims: usize) -> Box<dyn Component> {
+    assert!(batch_dims < 2, "tensors only have axes 0 and 1");
+    Box::new(TfGatherNd(batch_dims)) as _
+}
+
+/// `tf.experimental.numpy.take_along_axis(arr, indices, axis)`, where
+/// `indices` has the same size as `arr` along the other axis.
+#[derive(Debug)]
+struct TfTakeAlongAxis(usize);
+
+impl Component for TfTakeAlongAxis {
+    fn operand_arity(&self) -> usize {
+        2
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfTakeAlongAxis(operands[0], operands[1], self.0)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let (arr, indices) = (&operands[0], &operands[1]);
+        let cell0 = domain.constant(context, 0);
+        let mut result = Vecs::new(indices.dims.clone());
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                let index = domain.to_index(&indices.vecs[i][j]);
+                let value = if self.0 == 0 {
+                    select_in_col(context, arr, &index, j)
+                } else {
+                    select_in_row(context, arr, i, &index)
+                };
+                result.vecs[i].push(is_in_shape(context, &indices.dims, i, j).ite(&value, &cell0));
+            }
+        }
+
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        match (shapes[0], shapes[1]) {
+            (Some(a), Some(b)) => a[1 - self.0] == b[1 - self.0],
+            _ => true,
+        }
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let (arr, indices) = (&operands[0], &operands[1]);
+        let mut constraints = vec![indices.dims[1 - self.0]._eq(&arr.dims[1 - self.0])];
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                let index = domain.to_index(&indices.vecs[i][j]);
+                constraints.push(
+                    is_in_shape(context, &indices.dims, i, j).implies(&is_in_range(context, &index, &arr.dims[self.0])),
+                );
+            }
+        }
+        Bool::and(context, &constraints.iter().collect::<Vec<_>>())
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[1] == DType::Int).then_some(dtypes[0])
+    }
+}
+
+pub fn tf_take_along_axis(axis: usize) -> Box<dyn Component> {
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfTakeAlongAxis(axis)) as _
+}
+
+// 排序用每个元素排好后的位置（名次）来编码，名次构成一个置换：比它小的元素个数，
+// 加上它前面和它相等的元素个数。所以相等的元素保持原来的顺序，和TF的stable排序一致
+
+/// Stably sort the first `len` cells of `line`, returning the sorted values
+/// and the position in `line` that each of them came from. Past `len`, both
+/// hold zeros.
+///
+/// This unrolls all O(n²) pairwise comparisons instead of introducing
+/// permutation variables, on purpose. `make_expression` has to return the
+/// sorted values as terms over its operands: a component can't assert
+/// anything about fresh constants, and `Program::output`, validation and
+/// `verify` all evaluate `make_expression` on its own, where unconstrained
+/// permutation variables would take any value. Lines are at most `DIMS[1]`
+/// long, so that is at most 90 comparisons per line.
+fn sort_line<'a>(
+    context: &'a term::Context,
+    domain: Domain,
+    line: &[&Dynamic<'a>],
+    len: &Int<'a>,
+    descending: bool,
+) -> (Vec<Dynamic<'a>>, Vec<Dynamic<'a>>) {
+    let const0 = zero(context);
+    let const1 = one(context);
+    let cell0 = domain.constant(context, 0);
+    let position = |k: usize| Int::from_i64(context, k as i64);
+    let is_in_line: Vec<_> = (0 .. line.len()).map(|k| position(k).lt(len)).collect();
+    // Does `line[m]` come before `line[k]` once they're sorted?
+    let precedes = |m: usize, k: usize| {
+        let (a, b) = if descending { (line[k], line[m]) } else { (line[m], line[k]) };
+        if m < k {
+            domain.lt(b, a).not()
+        } else {
+            domain.lt(a, b)
+        }
+    };
+    let ranks: Vec<_> = (0 .. line.len())
+        .map(|k| {
+            let preceding: Vec<_> = (0 .. line.len())
+                .filter(|&m| m != k)
+                .map(|m| Bool::and(context, &[&is_in_line[m], &precedes(m, k)]).ite(&const1, &const0))
+                .collect();
+            Int::add(context, &preceding.iter().collect::<Vec<_>>())
+        })
+        .collect();
+
+    let mut values = vec![];
+    let mut indices = vec![];
+    for p in 0 .. line.len() {
+        let mut value = cell0.clone();
+        let mut index = cell0.clone();
+        for k in 0 .. line.len() {
+            let is_here = Bool::and(context, &[&is_in_line[k], &ranks[k]._eq(&position(p))]);
+            value = is_here.ite(line[k], &value);
+            index = is_here.ite(&domain.cell_of(&position(k)), &index);
+        }
+        values.push(value);
+        indices.push(index);
+    }
+    (values, indices)
+}
+
+/// Sort `operand` along `axis`, returning the sorted values and their indices
+/// along that axis.
+fn sort_along<'a>(
+    context: &'a term::Context,
+    domain: Domain,
+    operand: &Tensor<'a>,
+    axis: usize,
+    descending: bool,
+) -> (Tensor<'a>, Tensor<'a>) {
+    let cell0 = domain.constant(context, 0);
+    let mut values = Vecs::new(operand.dims.clone());
+    let mut indices = Vecs::new(operand.dims.clone());
+    for i in 0 .. DIMS[0] {
+        for _ in 0 .. DIMS[1] {
+            values.vecs[i].push(cell0.clone());
+            indices.vecs[i].push(cell0.clone());
+        }
+    }
+    let mut place = |i: usize, j: usize, value: &Dynamic<'a>, index: &Dynamic<'a>| {
+        let is_in_shape = is_in_shape(context, &operand.dims, i, j);
+        values.vecs[i][j] = is_in_shape.ite(value, &cell0);
+        indices.vecs[i][j] = is_in_shape.ite(index, &cell0);
+    };
+    if axis == 0 {
+        for j in 0 .. DIMS[1] {
+            let line: Vec<_> = (0 .. DIMS[0]).map(|i| &operand.vecs[i][j]).collect();
+            let (sorted, positions) = sort_line(context, domain, &line, &operand.dims[0], descending);
+            for i in 0 .. DIMS[0] {
+                place(i, j, &sorted[i], &positions[i]);
+            }
+        }
+    } else {
+        for i in 0 .. DIMS[0] {
+            let line: Vec<_> = operand.vecs[i].iter().collect();
+            let (sorted, positions) = sort_line(context, domain, &line, &operand.dims[1], descending);
+            for j in 0 .. DIMS[1] {
+                place(i, j, &sorted[j], &positions[j]);
+            }
+        }
+    }
+    (values, indices)
+}
+
+/// `tf.sort(values, axis, direction)`.
+#[derive(Debug)]
+struct TfSort(usize, bool);
+
+impl Component for TfSort {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfSort(operands[0], self.0, self.1)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        sort_along(context, domain, &operands[0], self.0, self.1).0
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
+    }
+}
+
+pub fn tf_sort(axis: usize, descending: bool) -> Box<dyn Component> {
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfSort(axis, descending)) as _
+}
+
+/// `tf.argsort(values, axis, direction, stable=True)`.
+#[derive(Debug)]
+struct TfArgsort(usize, bool);
+
+impl Component for TfArgsort {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfArgsort(operands[0], self.0, self.1)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        sort_along(context, domain, &operands[0], self.0, self.1).1
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        dtypes[0].is_numeric().then_some(DType::Int)
+    }
+}
+
+pub fn tf_argsort(axis: usize, descending: bool) -> Box<dyn Component> {
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfArgsort(axis, descending)) as _
+}
+
+/// `tf.math.top_k(input, k)`, which is either its `values` or its `indices`.
+/// Ties go to the element that comes first, like in TF.
+#[derive(Debug)]
+struct TfTopK {
+    indices: bool,
+}
+
+impl Component for TfTopK {
+    fn operand_arity(&self) -> usize {
+        2
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        if self.indices {
+            Operator::TfTopKIndices(operands[0], operands[1])
+        } else {
+            Operator::TfTopKValues(operands[0], operands[1])
+        }
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let k = domain.to_index(&operands[1].vecs[0][0]);
+        let (values, indices) = sort_along(context, domain, &operands[0], 1, true);
+        let sorted = if self.indices { indices } else { values };
+        let dims = [operands[0].dims[0].clone(), k];
+        let mut result = Vecs::new(dims.clone());
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&sorted.vecs[i][j], &cell0));
+            }
+        }
+
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        is_scalar(&shapes[1])
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let const1 = one(context);
+        let k = domain.to_index(&operands[1].vecs[0][0]);
+        Bool::and(context, &[
+            &operands[1].dims[0]._eq(&const1),
+            &operands[1].dims[1]._eq(&const1),
+            &zero(context).le(&k),
+            &k.le(&operands[0].dims[1]),
+        ])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        if !dtypes[0].is_numeric() || dtypes[1] != DType::Int {
+            None
+        } else if self.indices {
+            Some(DType::Int)
+        } else {
+            Some(dtypes[0])
+        }
+    }
+}
+
+pub fn tf_top_k_values() -> Box<dyn Component> {
+    Box::new(TfTopK { indices: false }) as _
+}
+
+pub fn tf_top_k_indices() -> Box<dyn Component> {
+    Box::new(TfTopK { indices: true }) as _
+}
+
+// 形状之间的乘除法和取模：为了不引入非线性的运算，把其中一个量可能的取值都列举出来
+
+/// `a * b`, where `1 <= a <= max_a`.
+fn times<'a>(context: &'a term::Context, a: &Int<'a>, b: &Int<'a>, max_a: usize) -> Int<'a> {
+    (1 ..= max_a).fold(zero(context), |product, k| {
+        let k = Int::from_i64(context, k as i64);
+        a._eq(&k).ite(&Int::mul(context, &[b, &k]), &product)
+    })
+}
+
+/// `a / b`, where `1 <= b <= max_b`.
+fn quotient<'a>(context: &'a term::Context, a: &Int<'a>, b: &Int<'a>, max_b: usize) -> Int<'a> {
+    (1 ..= max_b).fold(zero(context), |quotient, k| {
+        let k = Int::from_i64(context, k as i64);
+        b._eq(&k).ite(&a.div(&k), &quotient)
+    })
+}
+
+/// `i % n`, where `1 <= n <= max_n`.
+fn modulo<'a>(context: &'a term::Context, i: usize, n: &Int<'a>, max_n: usize) -> Int<'a> {
+    (1 ..= max_n).fold(zero(context), |remainder, k| {
+        n._eq(&Int::from_i64(context, k as i64)).ite(&Int::from_i64(context, (i % k) as i64), &remainder)
+    })
+}
+
+/// Is `1 <= dim <= max`?
+fn is_dim_at_most<'a>(context: &'a term::Context, dim: &Int<'a>, max: usize) -> Bool<'a> {
+    Bool::and(context, &[&one(context).le(dim), &dim.le(&Int::from_i64(context, max as i64))])
+}
+
+/// The cells of `operand`, in row-major order, laid out in the shape `dims`,
+/// which must have as many cells.
+fn reshape<'a>(context: &'a term::Context, domain: Domain, operand: &Tensor<'a>, dims: [Int<'a>; 2]) -> Tensor<'a> {
+    let cell0 = domain.constant(context, 0);
+    let mut result = Vecs::new(dims.clone());
+    for i in 0 .. DIMS[0] {
+        for j in 0 .. DIMS[1] {
+            let mut value = cell0.clone();
+            for new_cols in j + 1 ..= DIMS[1] {
+                let flat = i * new_cols + j;
+                for cols in 1 ..= DIMS[1] {
+                    if flat / cols >= DIMS[0] {
+                        continue;
+                    }
+                    let is_layout = Bool::and(context, &[
+                        &dims[1]._eq(&Int::from_i64(context, new_cols as i64)),
+                        &operand.dims[1]._eq(&Int::from_i64(context, cols as i64)),
+                    ]);
+                    value = is_layout.ite(&operand.vecs[flat / cols][flat % cols], &value);
+                }
+            }
+            result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
+        }
+    }
+    result
+}
+
+/// The number of cells in `operand`.
+fn size<'a>(context: &'a term::Context, operand: &Tensor<'a>) -> Int<'a> {
+    times(context, &operand.dims[0], &operand.dims[1], DIMS[0])
+}
+
+/// Does reshaping `operand` to `dims` keep its number of cells, and fit?
+fn is_reshape<'a>(context: &'a term::Context, operand: &Tensor<'a>, dims: &[Int<'a>; 2]) -> Bool<'a> {
+    Bool::and(context, &[
+        &is_dim_at_most(context, &dims[0], DIMS[0]),
+        &is_dim_at_most(context, &dims[1], DIMS[1]),
+        &times(context, &dims[0], &dims[1], DIMS[0])._eq(&size(context, operand)),
+    ])
+}
+
+/// `tf.reshape(tensor, shape)`, where `shape` is a row vector of one or two
+/// dims, one of which may be `-1`.
+#[derive(Debug)]
+struct TfReshape;
+
+impl TfReshape {
+    fn dims<'a>(&self, context: &'a term::Context, operands: &[Tensor<'a>], domain: Domain) -> [Int<'a>; 2] {
+        let (tensor, shape) = (&operands[0], &operands[1]);
+        let const1 = one(context);
+        let minus1 = Int::from_i64(context, -1);
+        let total = size(context, tensor);
+        let first = domain.to_index(&shape.vecs[0][0]);
+        let second = domain.to_index(&shape.vecs[0][1]);
+        // 一维的形状[n]就是[1, n]
+        let is_vector = shape.dims[1]._eq(&const1);
+        let rows = is_vector.ite(
+            &const1,
+            &first._eq(&minus1).ite(&quotient(context, &total, &second, DIMS[1]), &first),
+        );
+        let cols = is_vector.ite(
+            &first._eq(&minus1).ite(&total, &first),
+            &second._eq(&minus1).ite(&quotient(context, &total, &first, DIMS[0]), &second),
+        );
+        [rows, cols]
+    }
+}
+
+impl Component for TfReshape {
+    fn operand_arity(&self) -> usize {
+        2
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfReshape(operands[0], operands[1])
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        reshape(context, domain, &operands[0], self.dims(context, operands, domain))
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        is_row_vector(&shapes[1], 2)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let shape = &operands[1];
+        Bool::and(context, &[
+            &shape.dims[0]._eq(&one(context)),
+            &is_dim_at_most(context, &shape.dims[1], 2),
+            &is_reshape(context, &operands[0], &self.dims(context, operands, domain)),
+        ])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[1] == DType::Int).then_some(dtypes[0])
+    }
+}
+
+pub fn tf_reshape() -> Box<dyn Component> {
+    Box::new(TfReshape) as _
+}
+
+/// `tf.reshape(tensor, shape)` with a constant `shape`. Until it is
+/// synthesized, the shape is an immediate.
+#[derive(Debug)]
+struct TfReshapeTo(Option<[i64; 2]>);
+
+impl TfReshapeTo {
+    fn dims<'a>(&self, context: &'a term::Context, immediates: &[Tensor<'a>], domain: Domain) -> [Int<'a>; 2] {
+        match self.0 {
+            Some(shape) => [Int::from_i64(context, shape[0]), Int::from_i64(context, shape[1])],
+            None => [domain.to_index(&immediates[0].vecs[0][0]), domain.to_index(&immediates[0].vecs[0][1])],
+        }
+    }
+}
+
+impl Component for TfReshapeTo {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn immediate_arity(&self) -> usize {
+        if self.0.is_some() {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn make_operator(&self, immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        let shape = self.0.unwrap_or_else(|| [immediates[0].vecs[0][0], immediates[0].vecs[0][1]]);
+        Operator::TfReshapeTo(operands[0], shape)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        reshape(context, domain, &operands[0], self.dims(context, immediates, domain))
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        is_reshape(context, &operands[0], &self.dims(context, immediates, domain))
+    }
+}
+
+pub fn tf_reshape_to() -> Box<dyn Component> {
+    Box::new(TfReshapeTo(None)) as _
+}
+
+/// `tf.squeeze(input)`. A column becomes a row vector, since that is how we
+/// store 1-D tensors, and everything else keeps its shape.
+#[derive(Debug)]
+struct TfSqueeze;
+
+impl Component for TfSqueeze {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfSqueeze(operands[0])
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let operand = &operands[0];
+        let const1 = one(context);
+        let cell0 = domain.constant(context, 0);
+        let is_column = operand.dims[1]._eq(&const1);
+        let dims = [
+            is_column.ite(&const1, &operand.dims[0]),
+            is_column.ite(&operand.dims[0], &operand.dims[1]),
+        ];
+        let mut result = Vecs::new(dims.clone());
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                let squeezed = if i == 0 && j < DIMS[0] { &operand.vecs[j][0] } else { &cell0 };
+                let value = is_column.ite(squeezed, &operand.vecs[i][j]);
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
+            }
+        }
+
+        result
+    }
+}
+
+pub fn tf_squeeze() -> Box<dyn Component> {
+    Box::new(TfSqueeze) as _
+}
+
+/// `tf.tile(input, multiples)`, where `multiples` is a row vector with one
+/// multiple per dim of `input`. A 1-D `input` takes just one.
+#[derive(Debug)]
+struct TfTile;
+
+impl TfTile {
+    fn multiples<'a>(&self, context: &'a term::Context, operands: &[Tensor<'a>], domain: Domain) -> [Int<'a>; 2] {
+        let multiples = &operands[1];
+        let const1 = one(context);
+        let first = domain.to_index(&multiples.vecs[0][0]);
+        let second = domain.to_index(&multiples.vecs[0][1]);
+        let is_vector = multiples.dims[1]._eq(&const1);
+        [is_vector.ite(&const1, &first), is_vector.ite(&first, &second)]
+    }
+}
+
+impl Component for TfTile {
+    fn operand_arity(&self) -> usize {
+        2
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfTile(operands[0], operands[1])
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let operand = &operands[0];
+        let cell0 = domain.constant(context, 0);
+        let multiples = self.multiples(context, operands, domain);
+        let dims = [
+            times(context, &multiples[0], &operand.dims[0], DIMS[0]),
+            times(context, &multiples[1], &operand.dims[1], DIMS[1]),
+        ];
+        let mut result = Vecs::new(dims.clone());
+        for i in 0 .. DIMS[0] {
+            let row = modulo(context, i, &operand.dims[0], DIMS[0]);
+            for j in 0 .. DIMS[1] {
+                let col = modulo(context, j, &operand.dims[1], DIMS[1]);
+                let value = select_cell(context, operand, &row, &col);
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
+            }
+        }
+
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        is_row_vector(&shapes[1], 2)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let (operand, multiples) = (&operands[0], &operands[1]);
+        let const1 = one(context);
+        let [rows, cols] = self.multiples(context, operands, domain);
+        Bool::and(context, &[
+            &multiples.dims[0]._eq(&const1),
+            &is_dim_at_most(context, &multiples.dims[1], 2),
+            &multiples.dims[1]._eq(&const1).implies(&operand.dims[0]._eq(&const1)),
+            &is_dim_at_most(context, &rows, DIMS[0]),
+            &is_dim_at_most(context, &cols, DIMS[1]),
+            &is_dim_at_most(context, &times(context, &rows, &operand.dims[0], DIMS[0]), DIMS[0]),
+            &is_dim_at_most(context, &times(context, &cols, &operand.dims[1], DIMS[1]), DIMS[1]),
+        ])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[1] == DType::Int).then_some(dtypes[0])
+    }
+}
+
+pub fn tf_tile() -> Box<dyn Component> {
+    Box::new(TfTile) as _
+}
+
+/// `tf.stack([a, b], axis)` for 1-D `a` and `b`.
+#[derive(Debug)]
+struct TfStack(usize);
+
+impl Component for TfStack {
+    fn operand_arity(&self) -> usize {
+        2
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfStack(operands[0], operands[1], self.0)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let two = Int::from_i64(context, 2);
+        let len = operands[0].dims[1].clone();
+        let dims = if self.0 == 0 { [two, len] } else { [len, two] };
+        let mut result = Vecs::new(dims.clone());
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                let value = match (self.0, i, j) {
+                    (0, 0 ..= 1, _) => &operands[i].vecs[0][j],
+                    (1, _, 0 ..= 1) => &operands[j].vecs[0][i],
+                    _ => &cell0,
+                };
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(value, &cell0));
+            }
+        }
+
+        result
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        let max_len = DIMS[1 - self.0];
+        is_row_vector(&shapes[0], max_len) && is_row_vector(&shapes[1], max_len) && are_same_shape(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        let (a, b) = (&operands[0], &operands[1]);
+        Bool::and(context, &[
+            &a.dims[0]._eq(&one(context)),
+            &b.dims[0]._eq(&a.dims[0]),
+            &b.dims[1]._eq(&a.dims[1]),
+            &is_dim_at_most(context, &a.dims[1], DIMS[1 - self.0]),
+        ])
+    }
+}
+
+pub fn tf_stack(axis: usize) -> Box<dyn Component> {
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfStack(axis)) as _
+}
+
+// scatter类的部件把张量放进一个以 行 * DIMS[1] + 列 为下标的数组里，再依次写入更新
+
+/// The key of a cell in a scatter's array.
+fn scatter_key<'a>(context: &'a term::Context, row: &Int<'a>, col: &Int<'a>) -> Int<'a> {
+    let width = Int::from_i64(context, DIMS[1] as i64);
+    Int::add(context, &[&Int::mul(context, &[row, &width]), col])
+}
+
+/// Write `updates` into `tensor` (or zeros) at `indices`, and read the result
+/// back in the shape `dims`. The indices are laid out like `TfGatherNd`'s,
+/// except that a result with one row is 1-D, so a lone index is a column.
+/// Later updates win, unless they `accumulate`.
+fn scatter<'a>(
+    context: &'a term::Context,
+    domain: Domain,
+    tensor: Option<&Tensor<'a>>,
+    dims: [Int<'a>; 2],
+    indices: &Tensor<'a>,
+    updates: &Tensor<'a>,
+    accumulate: bool,
+) -> Tensor<'a> {
+    let const0 = zero(context);
+    let cell0 = domain.constant(context, 0);
+    let nowhere = Int::from_i64(context, -1);
+    let mut array = Array::const_array(context, &Sort::int(context), &cell0);
+    if let Some(tensor) = tensor {
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                let key = scatter_key(context, &Int::from_i64(context, i as i64), &Int::from_i64(context, j as i64));
+                array = array.store(&key, &tensor.vecs[i][j]);
+            }
+        }
+    }
+    let write = |array: Array<'a>, is_valid: &Bool<'a>, key: &Int<'a>, value: &Dynamic<'a>| {
+        let key = is_valid.ite(key, &nowhere);
+        let value = if accumulate { domain.add(context, &[&array.select(&key), value]) } else { value.clone() };
+        array.store(&key, &value)
+    };
+    let (is_column, is_row) = scatter_modes(context, &dims, indices);
+    for k in 0 .. DIMS[0] {
+        let is_update = Int::from_i64(context, k as i64).lt(&indices.dims[0]);
+        let first = domain.to_index(&indices.vecs[k][0]);
+        let second = domain.to_index(&indices.vecs[k][1]);
+        let row = is_column.ite(&const0, &first);
+        let col = is_column.ite(&first, &second);
+        let is_valid = Bool::and(context, &[&is_update, &is_row.not()]);
+        array = write(array, &is_valid, &scatter_key(context, &row, &col), &updates.vecs[0][k]);
+        let is_valid = Bool::and(context, &[&is_update, &is_row]);
+        for j in 0 .. DIMS[1] {
+            let key = scatter_key(context, &first, &Int::from_i64(context, j as i64));
+            array = write(array, &is_valid, &key, &updates.vecs[k][j]);
+        }
+    }
+    let mut result = Vecs::new(dims.clone());
+    for i in 0 .. DIMS[0] {
+        for j in 0 .. DIMS[1] {
+            let key = scatter_key(context, &Int::from_i64(context, i as i64), &Int::from_i64(context, j as i64));
+            result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&array.select(&key), &cell0));
+        }
+    }
+    result
+}
+
+/// Does a scatter into the shape `dims` write a column of a 1-D result, or
+/// a whole row, for each index? Otherwise it writes the cell at a coordinate.
+fn scatter_modes<'a>(context: &'a term::Context, dims: &[Int<'a>; 2], indices: &Tensor<'a>) -> (Bool<'a>, Bool<'a>) {
+    let const1 = one(context);
+    let is_index = indices.dims[1]._eq(&const1);
+    let is_vector = dims[0]._eq(&const1);
+    (
+        Bool::and(context, &[&is_index, &is_vector]),
+        Bool::and(context, &[&is_index, &is_vector.not()]),
+    )
+}
+
+/// Do `indices` and `updates` fit a scatter into the shape `dims`?
+fn is_scatter<'a>(
+    context: &'a term::Context,
+    domain: Domain,
+    dims: &[Int<'a>; 2],
+    indices: &Tensor<'a>,
+    updates: &Tensor<'a>,
+) -> Bool<'a> {
+    let const1 = one(context);
+    let const2 = Int::from_i64(context, 2);
+    let (is_column, is_row) = scatter_modes(context, dims, indices);
+    let mut constraints = vec![
+        Bool::or(context, &[&indices.dims[1]._eq(&const1), &indices.dims[1]._eq(&const2)]),
+        updates.dims[0]._eq(&is_row.ite(&indices.dims[0], &const1)),
+        updates.dims[1]._eq(&is_row.ite(&dims[1], &indices.dims[0])),
+    ];
+    for k in 0 .. DIMS[0] {
+        let first = domain.to_index(&indices.vecs[k][0]);
+        let second = domain.to_index(&indices.vecs[k][1]);
+        let in_range = is_column.ite(
+            &is_in_range(context, &first, &dims[1]),
+            &Bool::and(context, &[
+                &is_in_range(context, &first, &dims[0]),
+                &indices.dims[1]._eq(&const2).implies(&is_in_range(context, &second, &dims[1])),
+            ]),
+        );
+        constraints.push(Int::from_i64(context, k as i64).lt(&indices.dims[0]).implies(&in_range));
+    }
+    Bool::and(context, &constraints.iter().collect::<Vec<_>>())
+}
+
+/// The dims given by a `shape` operand, a row vector of one or two dims.
+fn shape_dims<'a>(context: &'a term::Context, domain: Domain, shape: &Tensor<'a>) -> [Int<'a>; 2] {
+    let const1 = one(context);
+    let first = domain.to_index(&shape.vecs[0][0]);
+    let second = domain.to_index(&shape.vecs[0][1]);
+    let is_vector = shape.dims[1]._eq(&const1);
+    [is_vector.ite(&const1, &first), is_vector.ite(&first, &second)]
+}
+
+/// Is `shape` a row vector of one or two dims that fit?
+fn is_shape<'a>(context: &'a term::Context, domain: Domain, shape: &Tensor<'a>) -> Bool<'a> {
+    let dims = shape_dims(context, domain, shape);
+    Bool::and(context, &[
+        &shape.dims[0]._eq(&one(context)),
+        &is_dim_at_most(context, &shape.dims[1], 2),
+        &is_dim_at_most(context, &dims[0], DIMS[0]),
+        &is_dim_at_most(context, &dims[1], DIMS[1]),
+    ])
+}
+
+/// `tf.scatter_nd(indices, updates, shape)`. Updates at the same index are
+/// summed.
+#[derive(Debug)]
+struct TfScatterNd;
+
+impl Component for TfScatterNd {
+    fn operand_arity(&self) -> usize {
+        3
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfScatterNd(operands[0], operands[1], operands[2])
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let (indices, updates, shape) = (&operands[0], &operands[1], &operands[2]);
+        scatter(context, domain, None, shape_dims(context, domain, shape), indices, updates, true)
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        has_at_most_cols(&shapes[0], 2) && is_row_vector(&shapes[2], 2)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let (indices, updates, shape) = (&operands[0], &operands[1], &operands[2]);
+        let dims = shape_dims(context, domain, shape);
+        Bool::and(context, &[&is_shape(context, domain, shape), &is_scatter(context, domain, &dims, indices, updates)])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[0] == DType::Int && dtypes[1].is_numeric() && dtypes[2] == DType::Int).then_some(dtypes[1])
+    }
+}
+
+pub fn tf_scatter_nd() -> Box<dyn Component> {
+    Box::new(TfScatterNd) as _
+}
+
+/// `tf.tensor_scatter_nd_update(tensor, indices, updates)`, or
+/// `tf.tensor_scatter_nd_add` if it should `add`.
+#[derive(Debug)]
+struct TfTensorScatterNd {
+    add: bool,
+}
+
+impl Component for TfTensorScatterNd {
+    fn operand_arity(&self) -> usize {
+        3
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        if self.add {
+            Operator::TfTensorScatterNdAdd(operands[0], operands[1], operands[2])
+        } else {
+            Operator::TfTensorScatterNdUpdate(operands[0], operands[1], operands[2])
+        }
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let (tensor, indices, updates) = (&operands[0], &operands[1], &operands[2]);
+        scatter(context, domain, Some(tensor), tensor.dims.clone(), indices, updates, self.add)
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        has_at_most_cols(&shapes[1], 2)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let (tensor, indices, updates) = (&operands[0], &operands[1], &operands[2]);
+        is_scatter(context, domain, &tensor.dims, indices, updates)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        let is_numeric = !self.add || dtypes[0].is_numeric();
+        (is_numeric && dtypes[1] == DType::Int && dtypes[2] == dtypes[0]).then_some(dtypes[0])
+    }
+}
+
+pub fn tf_tensor_scatter_nd_update() -> Box<dyn Component> {
+    Box::new(TfTensorScatterNd { add: false }) as _
+}
+
+pub fn tf_tensor_scatter_nd_add() -> Box<dyn Component> {
+    Box::new(TfTensorScatterNd { add: true }) as _
+}
+
+/// `tf.sparse.to_dense(sp_input)` of the sparse tensor with the given
+/// `indices`, `values` and `dense_shape`. The indices should be distinct, but
+/// if they aren't, later values win.
+#[derive(Debug)]
+struct TfSparseToDense;
+
+impl Component for TfSparseToDense {
+    fn operand_arity(&self) -> usize {
+        3
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfSparseToDense(operands[0], operands[1], operands[2])
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let (indices, values, dense_shape) = (&operands[0], &operands[1], &operands[2]);
+        scatter(context, domain, None, shape_dims(context, domain, dense_shape), indices, values, false)
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        has_at_most_cols(&shapes[0], 2) && is_row_vector(&shapes[1], DIMS[0]) && is_row_vector(&shapes[2], 2)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        // 稀疏张量的下标都是完整的坐标，不能写入一整行
+        let (indices, values, dense_shape) = (&operands[0], &operands[1], &operands[2]);
+        let dims = shape_dims(context, domain, dense_shape);
+        Bool::and(context, &[
+            &is_shape(context, domain, dense_shape),
+            &scatter_modes(context, &dims, indices).1.not(),
+            &is_scatter(context, domain, &dims, indices, values),
+        ])
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        (dtypes[0] == DType::Int && dtypes[2] == DType::Int).then_some(dtypes[1])
+    }
+}
+
+pub fn tf_sparse_to_dense() -> Box<dyn Component> {
+    Box::new(TfSparseToDense) as _
+}
+
+/// A broadcast elementwise predicate, as a tensor of 0s and 1s.
+fn elementwise_bool<'a>(
+    context: &'a term::Context,
+    domain: Domain,
+    operands: &[Tensor<'a>],
+    predicate: impl Fn(&Dynamic<'a>, &Dynamic<'a>) -> Bool<'a>,
+) -> Tensor<'a> {
+    let cell0 = domain.constant(context, 0);
+    let cell1 = domain.constant(context, 1);
+    let dims = broadcast_dims(context, operands);
+    let mut result = Vecs::new(dims.clone());
+    for i in 0 .. DIMS[0] {
+        for j in 0 .. DIMS[1] {
+            let lhs = broadcast_cell(context, &operands[0], i, j);
+            let rhs = broadcast_cell(context, &operands[1], i, j);
+            let is_true = Bool::and(context, &[&is_in_shape(context, &dims, i, j), &predicate(&lhs, &rhs)]);
+            result.vecs[i].push(is_true.ite(&cell1, &cell0));
+        }
+    }
+    result
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    Less,
+    LessEqual,
+    GreaterEqual,
+}
+
+/// `tf.math.less`, `tf.math.less_equal` and `tf.math.greater_equal`.
+#[derive(Debug)]
+struct TfCompare(Comparison);
+
+impl Component for TfCompare {
+    fn operand_arity(&self) -> usize {
+        2
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        match self.0 {
+            Comparison::Less => Operator::TfLess(operands[0], operands[1]),
+            Comparison::LessEqual => Operator::TfLessEqual(operands[0], operands[1]),
+            Comparison::GreaterEqual => Operator::TfGreaterEqual(operands[0], operands[1]),
+        }
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        elementwise_bool(context, domain, operands, |lhs, rhs| match self.0 {
+            Comparison::Less => domain.lt(lhs, rhs),
+            Comparison::LessEqual => domain.gt(lhs, rhs).not(),
+            Comparison::GreaterEqual => domain.lt(lhs, rhs).not(),
+        })
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes).map(|_| DType::Bool)
+    }
+}
+
+pub fn tf_less() -> Box<dyn Component> {
+    Box::new(TfCompare(Comparison::Less)) as _
+}
+
+pub fn tf_less_equal() -> Box<dyn Component> {
+    Box::new(TfCompare(Comparison::LessEqual)) as _
+}
+
+pub fn tf_greater_equal() -> Box<dyn Component> {
+    Box::new(TfCompare(Comparison::GreaterEqual)) as _
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Connective {
+    And,
+    Or,
+    Xor,
+}
+
+/// `tf.math.logical_and`, `tf.math.logical_or` and `tf.math.logical_xor`.
+#[derive(Debug)]
+struct TfLogical(Connective);
+
+impl Component for TfLogical {
+    fn operand_arity(&self) -> usize {
+        2
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        match self.0 {
+            Connective::And => Operator::TfLogicalAnd(operands[0], operands[1]),
+            Connective::Or => Operator::TfLogicalOr(operands[0], operands[1]),
+            Connective::Xor => Operator::TfLogicalXor(operands[0], operands[1]),
+        }
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        elementwise_bool(context, domain, operands, |lhs, rhs| {
+            let lhs = lhs._eq(&cell0).not();
+            let rhs = rhs._eq(&cell0).not();
+            match self.0 {
+                Connective::And => Bool::and(context, &[&lhs, &rhs]),
+                Connective::Or => Bool::or(context, &[&lhs, &rhs]),
+                Connective::Xor => lhs.xor(&rhs),
+            }
+        })
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Bool<'a> {
+        are_broadcast_compatible(context, operands)
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Bool).then_some(DType::Bool)
+    }
+}
+
+pub fn tf_logical_and() -> Box<dyn Component> {
+    Box::new(TfLogical(Connective::And)) as _
+}
+
+pub fn tf_logical_or() -> Box<dyn Component> {
+    Box::new(TfLogical(Connective::Or)) as _
+}
+
+pub fn tf_logical_xor() -> Box<dyn Component> {
+    Box::new(TfLogical(Connective::Xor)) as _
+}
+
+#[derive(Debug)]
+struct TfLogicalNot;
+
+impl Component for TfLogicalNot {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfLogicalNot(operands[0])
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        let cell1 = domain.constant(context, 1);
+        let dims = operands[0].dims.clone();
+        let mut result = Vecs::new(dims.clone());
+        for i in 0 .. DIMS[0] {
+            for j in 0 .. DIMS[1] {
+                let is_true = Bool::and(context, &[&is_in_shape(context, &dims, i, j), &operands[0].vecs[i][j]._eq(&cell0)]);
+                result.vecs[i].push(is_true.ite(&cell1, &cell0));
+            }
+        }
+
+        result
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        are_all(dtypes, DType::Bool).then_some(DType::Bool)
+    }
+}
+
+pub fn tf_logical_not() -> Box<dyn Component> {
+    Box::new(TfLogicalNot) as _
+}
+
+/// Apply `f` to the broadcast cells of `operands`.
+fn elementwise<'a>(
+    context: &'a term::Context,
+    domain: Domain,
+    operands: &[Tensor<'a>],
+    f: impl Fn(&[Dynamic<'a>]) -> Dynamic<'a>,
+) -> Tensor<'a> {
+    let cell0 = domain.constant(context, 0);
+    let dims = broadcast_dims(context, operands);
+    let mut result = Vecs::new(dims.clone());
+    for i in 0 .. DIMS[0] {
+        for j in 0 .. DIMS[1] {
+            let cells: Vec<_> = operands.iter().map(|operand| broadcast_cell(context, operand, i, j)).collect();
+            result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&f(&cells), &cell0));
+        }
+    }
+    result
+}
+
+/// Does `predicate` hold for the broadcast cells of `operands` that are in
+/// the result?
+fn holds_elementwise<'a>(
+    context: &'a term::Context,
+    operands: &[Tensor<'a>],
+    predicate: impl Fn(&[Dynamic<'a>]) -> Bool<'a>,
+) -> Bool<'a> {
+    let dims = broadcast_dims(context, operands);
+    let mut constraints = vec![are_broadcast_compatible(context, operands)];
+    for i in 0 .. DIMS[0] {
+        for j in 0 .. DIMS[1] {
+            let cells: Vec<_> = operands.iter().map(|operand| broadcast_cell(context, operand, i, j)).collect();
+            constraints.push(is_in_shape(context, &dims, i, j).implies(&predicate(&cells)));
+        }
+    }
+    Bool::and(context, &constraints.iter().collect::<Vec<_>>())
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Unary {
+    Abs,
+    Negative,
+    Sign,
+}
+
+/// `tf.math.abs`, `tf.math.negative` and `tf.math.sign`.
+#[derive(Debug)]
+struct TfUnary(Unary);
+
+impl Component for TfUnary {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        match self.0 {
+            Unary::Abs => Operator::TfAbs(operands[0]),
+            Unary::Negative => Operator::TfNegative(operands[0]),
+            Unary::Sign => Operator::TfSign(operands[0]),
+        }
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let cell0 = domain.constant(context, 0);
+        elementwise(context, domain, operands, |cells| {
+            let x = &cells[0];
+            let negative = domain.sub(context, &[&cell0, x]);
+            match self.0 {
+                Unary::Abs => domain.lt(x, &cell0).ite(&negative, x),
+                Unary::Negative => negative,
+                Unary::Sign => domain.lt(x, &cell0).ite(
+                    &domain.constant(context, -1),
+                    &domain.gt(x, &cell0).ite(&domain.constant(context, 1), &cell0),
+                ),
+            }
+        })
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
+    }
+}
+
+pub fn tf_abs() -> Box<dyn Component> {
+    Box::new(TfUnary(Unary::Abs)) as _
+}
+
+pub fn tf_negative() -> Box<dyn Component> {
+    Box::new(TfUnary(Unary::Negative)) as _
+}
+
+pub fn tf_sign() -> Box<dyn Component> {
+    Box::new(TfUnary(Unary::Sign)) as _
+}
+
+/// `tf.math.floordiv` and `tf.math.floormod`, which round toward negative
+/// infinity, so a remainder has the sign of the divisor. Dividing by zero is
+/// an error for integers in TF, so here it is invalid for every dtype.
+#[derive(Debug)]
+struct TfFloor {
+    modulo: bool,
+}
+
+impl Component for TfFloor {
+    fn operand_arity(&self) -> usize {
+        2
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceAny0(operands[0])
+        if self.modulo {
+            Operator::TfFloorMod(operands[0], operands[1])
+        } else {
+            Operator::TfFloorDiv(operands[0], operands[1])
+        }
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        elementwise(context, domain, operands, |cells| {
+            if self.modulo {
+                domain.floor_mod(context, &cells[0], &cells[1])
+            } else {
+                domain.floor_div(context, &cells[0], &cells[1])
+            }
+        })
     }
 
-    fn make_expression<'a>(
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new([const1.clone(), operands[0].dims[1].clone()]);
-        for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
-        }
-        for j in 0 .. DIMS[1] {
-            let mut sum = Bool::from_bool(context, false);
-            for i in 0 .. DIMS[0] {
-                let is_zero = operands[0].vecs[i][j]._eq(&const0).ite(&Bool::from_bool(context, false), &Bool::from_bool(context, true));
-                sum = Bool::or(context, &[&sum, &is_zero]);
-            }
-            result.vecs[0][j] = sum.ite(&const1, &const0);
-        }
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let cell0 = domain.constant(context, 0);
+        holds_elementwise(context, operands, |cells| cells[1]._eq(&cell0).not())
+    }
 
-        return result;
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
-pub fn tf_reduce_any0() -> Box<dyn Component> {
-    Box::new(TfReduceAny0) as _
+pub fn tf_floordiv() -> Box<dyn Component> {
+    Box::new(TfFloor { modulo: false }) as _
+}
+
+pub fn tf_floormod() -> Box<dyn Component> {
+    Box::new(TfFloor { modulo: true }) as _
 }
 
+/// The largest exponent `TfPow` can raise to. Powers are unrolled into
+/// multiplications, so this keeps the encoding small.
+pub(crate) const MAX_EXPONENT: i64 = 4;
+
+/// `tf.math.pow(x, y)`. TF rejects negative integer exponents, and we only
+/// handle whole ones up to `MAX_EXPONENT`; `0 ** 0` is 1.
 #[derive(Debug)]
-struct TfReduceAny1;
+struct TfPow;
 
-impl Component for TfReduceAny1 {
+impl Component for TfPow {
     fn operand_arity(&self) -> usize {
-        1
+        2
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceAny1(operands[0])
+        Operator::TfPow(operands[0], operands[1])
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new([const1.clone(), operands[0].dims[0].clone()]);
-        for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
-        }
-        for i in 0 .. DIMS[0] {
-            let mut sum = Bool::from_bool(context, false);
-            for j in 0 .. DIMS[1] {
-                let is_zero = operands[0].vecs[i][j]._eq(&const0).ite(&Bool::from_bool(context, false), &Bool::from_bool(context, true));
-                sum = Bool::or(context, &[&sum, &is_zero]);
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        elementwise(context, domain, operands, |cells| {
+            let (x, y) = (&cells[0], &cells[1]);
+            let mut power = domain.constant(context, 1);
+            let mut result = power.clone();
+            for k in 1 ..= MAX_EXPONENT {
+                power = domain.mul(context, &[&power, x]);
+                result = y._eq(&domain.constant(context, k)).ite(&power, &result);
             }
-            result.vecs[0][i] = sum.ite(&const1, &const0);
-        }
+            result
+        })
+    }
 
-        return result;
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        holds_elementwise(context, operands, |cells| {
+            let y = &cells[1];
+            let exponents: Vec<_> = (0 ..= MAX_EXPONENT).map(|k| y._eq(&domain.constant(context, k))).collect();
+            Bool::or(context, &exponents.iter().collect::<Vec<_>>())
+        })
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
     }
 }
 
-pub fn tf_reduce_any1() -> Box<dyn Component> {
-    Box::new(TfReduceAny1) as _
+pub fn tf_pow() -> Box<dyn Component> {
+    Box::new(TfPow) as _
 }
 
+/// `tf.clip_by_value(t, clip_value_min, clip_value_max)`, which needs
+/// `clip_value_min <= clip_value_max`.
 #[derive(Debug)]
-struct TfReduceMean;
+struct TfClipByValue;
 
-impl Component for TfReduceMean {
+impl Component for TfClipByValue {
     fn operand_arity(&self) -> usize {
-        1
+        3
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceMean(operands[0])
+        Operator::TfClipByValue(operands[0], operands[1], operands[2])
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        // 测试样例中只有axis = 0的情况
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new([const1.clone(), operands[0].dims[1].clone()]);
-        for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
-        }
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        elementwise(context, domain, operands, |cells| {
+            let (t, min, max) = (&cells[0], &cells[1], &cells[2]);
+            domain.lt(t, min).ite(min, &domain.gt(t, max).ite(max, t))
+        })
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        are_broadcastable(shapes)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        holds_elementwise(context, operands, |cells| domain.gt(&cells[1], &cells[2]).not())
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        same_numeric_dtype(dtypes)
+    }
+}
+
+pub fn tf_clip_by_value() -> Box<dyn Component> {
+    Box::new(TfClipByValue) as _
+}
+
+/// `operand[index][j]` along axis 0, or `operand[i][index]` along axis 1.
+fn select_along<'a>(
+    context: &'a term::Context,
+    operand: &Tensor<'a>,
+    axis: usize,
+    i: usize,
+    j: usize,
+    index: &Int<'a>,
+) -> Dynamic<'a> {
+    if axis == 0 {
+        select_in_col(context, operand, index, j)
+    } else {
+        select_in_row(context, operand, i, index)
+    }
+}
+
+// 切片和填充的参数是成对的，每对对应一个轴。合成时它们是立即数的前几行，
+// 第n行的前两个单元格就是第n对参数
+
+/// A component's pairs of attributes: `fixed`, or else the ones the solver
+/// picks in its immediate.
+fn attribute_pairs<'a, const N: usize>(
+    context: &'a term::Context,
+    immediates: &[Tensor<'a>],
+    domain: Domain,
+    fixed: Option<[[i64; 2]; N]>,
+) -> [[Int<'a>; 2]; N] {
+    match fixed {
+        Some(pairs) => pairs.map(|pair| pair.map(|value| Int::from_i64(context, value))),
+        None => std::array::from_fn(|n| {
+            [domain.to_index(&immediates[0].vecs[n][0]), domain.to_index(&immediates[0].vecs[n][1])]
+        }),
+    }
+}
+
+/// The pairs of attributes a model picked, as laid out by `attribute_pairs`.
+fn picked_pairs<const N: usize>(immediates: &[Vecs<i64>]) -> [[i64; 2]; N] {
+    std::array::from_fn(|n| [immediates[0].vecs[n][0], immediates[0].vecs[n][1]])
+}
+
+/// Every `stride`-th cell of `operand` from `begin` on, in the shape `dims`.
+fn slice<'a>(
+    context: &'a term::Context,
+    domain: Domain,
+    operand: &Tensor<'a>,
+    begin: &[Int<'a>; 2],
+    strides: &[Int<'a>; 2],
+    dims: [Int<'a>; 2],
+) -> Tensor<'a> {
+    let cell0 = domain.constant(context, 0);
+    let mut result = Vecs::new(dims.clone());
+    for i in 0 .. DIMS[0] {
         for j in 0 .. DIMS[1] {
-            let mut sum = zero(context, bit_width);
-            for i in 0 .. DIMS[0] {
-                sum = Int::add(context, &[&sum, &operands[0].vecs[i][j]]);
-            }
-            result.vecs[0][j] = Int::div(&sum, &operands[0].dims[0]);
+            let row = Int::add(context, &[&begin[0], &Int::mul(context, &[&strides[0], &Int::from_i64(context, i as i64)])]);
+            let col = Int::add(context, &[&begin[1], &Int::mul(context, &[&strides[1], &Int::from_i64(context, j as i64)])]);
+            let value = select_cell(context, operand, &row, &col);
+            result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
+        }
+    }
+    result
+}
+
+/// `tf.slice(input_, begin, size)`, where the solver picks `begin` and `size`
+/// unless they are fixed.
+#[derive(Debug)]
+struct TfSlice(Option<[[i64; 2]; 2]>);
+
+impl Component for TfSlice {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn immediate_arity(&self) -> usize {
+        if self.0.is_some() {
+            0
+        } else {
+            1
         }
+    }
 
-        return result;
+    fn make_operator(&self, immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        let [begin, size] = self.0.unwrap_or_else(|| picked_pairs(immediates));
+        Operator::TfSlice(operands[0], begin, size)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let [begin, size] = attribute_pairs(context, immediates, domain, self.0);
+        slice(context, domain, &operands[0], &begin, &[one(context), one(context)], size)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let [begin, size] = attribute_pairs(context, immediates, domain, self.0);
+        let const0 = zero(context);
+        let const1 = one(context);
+        let constraints: Vec<_> = (0 .. 2)
+            .flat_map(|k| {
+                [
+                    const0.le(&begin[k]),
+                    const1.le(&size[k]),
+                    Int::add(context, &[&begin[k], &size[k]]).le(&operands[0].dims[k]),
+                ]
+            })
+            .collect();
+        Bool::and(context, &constraints.iter().collect::<Vec<_>>())
     }
 }
 
-pub fn tf_reduce_mean() -> Box<dyn Component> {
-    Box::new(TfReduceMean) as _
+pub fn tf_slice() -> Box<dyn Component> {
+    Box::new(TfSlice(None)) as _
 }
 
+/// `input[begin[0]:end[0]:strides[0], begin[1]:end[1]:strides[1]]`, i.e.
+/// `tf.strided_slice`. The solver picks `begin`, `end` and `strides` unless
+/// they are fixed, and keeps them inside the shape. Indices never count from
+/// the back, so with a negative stride an `end` of -1 runs through index 0,
+/// like `input[begin::stride]` does.
 #[derive(Debug)]
-struct TfReduceProd;
+struct TfStridedSlice(Option<[[i64; 2]; 3]>);
+
+impl TfStridedSlice {
+    /// How far the slice goes along one axis, and how far each step takes it,
+    /// both counted in the direction of `stride`.
+    fn span_and_step<'a>(
+        &self,
+        context: &'a term::Context,
+        begin: &Int<'a>,
+        end: &Int<'a>,
+        stride: &Int<'a>,
+    ) -> [Int<'a>; 2] {
+        let is_forward = stride.gt(&zero(context));
+        [
+            is_forward.ite(&Int::sub(context, &[end, begin]), &Int::sub(context, &[begin, end])),
+            is_forward.ite(stride, &Int::sub(context, &[&zero(context), stride])),
+        ]
+    }
+}
 
-impl Component for TfReduceProd {
+impl Component for TfStridedSlice {
     fn operand_arity(&self) -> usize {
         1
     }
 
-    fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfReduceProd(operands[0])
+    fn immediate_arity(&self) -> usize {
+        if self.0.is_some() {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn make_operator(&self, immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        let [begin, end, strides] = self.0.unwrap_or_else(|| picked_pairs(immediates));
+        Operator::TfStridedSlice(operands[0], begin, end, strides)
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        // 测试样例中只有axis = 1的情况
-        let const0 = zero(context, bit_width);
-        let const1 = one(context, bit_width);
-        let mut result = Vecs::new([const1.clone(), operands[0].dims[0].clone()]);
-        for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        // 结果的长度是 ceil((end - begin) / stride)，步长为负时两边都取反
+        let [begin, end, strides] = attribute_pairs(context, immediates, domain, self.0);
+        let dims = [0, 1].map(|k| {
+            let [span, step] = self.span_and_step(context, &begin[k], &end[k], &strides[k]);
+            let len = Int::sub(context, &[&Int::add(context, &[&span, &step]), &one(context)]);
+            quotient(context, &len, &step, DIMS[k])
+        });
+        slice(context, domain, &operands[0], &begin, &strides, dims)
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let [begin, end, strides] = attribute_pairs(context, immediates, domain, self.0);
+        let const0 = zero(context);
+        let minus1 = Int::from_i64(context, -1);
+        let constraints: Vec<_> = (0 .. 2)
+            .flat_map(|k| {
+                let dim = &operands[0].dims[k];
+                let [_, step] = self.span_and_step(context, &begin[k], &end[k], &strides[k]);
+                let is_forward = strides[k].gt(&const0);
+                [
+                    // 往前切时 0 <= begin < end <= dim，往回切时 -1 <= end < begin < dim
+                    is_forward.implies(&Bool::and(context, &[
+                        &const0.le(&begin[k]),
+                        &begin[k].lt(&end[k]),
+                        &end[k].le(dim),
+                    ])),
+                    is_forward.not().implies(&Bool::and(context, &[
+                        &minus1.le(&end[k]),
+                        &end[k].lt(&begin[k]),
+                        &begin[k].lt(dim),
+                    ])),
+                    is_dim_at_most(context, &step, DIMS[k]),
+                    step.le(dim),
+                ]
+            })
+            .collect();
+        Bool::and(context, &constraints.iter().collect::<Vec<_>>())
+    }
+}
+
+pub fn tf_strided_slice() -> Box<dyn Component> {
+    Box::new(TfStridedSlice(None)) as _
+}
+
+/// `tf.pad(tensor, paddings)` with zeros, where the solver picks `paddings`
+/// unless they are fixed. Row `k` of `paddings` is how much goes before and
+/// after axis `k`.
+#[derive(Debug)]
+struct TfPad(Option<[[i64; 2]; 2]>);
+
+impl Component for TfPad {
+    fn operand_arity(&self) -> usize {
+        1
+    }
+
+    fn immediate_arity(&self) -> usize {
+        if self.0.is_some() {
+            0
+        } else {
+            1
         }
+    }
+
+    fn make_operator(&self, immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
+        Operator::TfPad(operands[0], self.0.unwrap_or_else(|| picked_pairs(immediates)))
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let operand = &operands[0];
+        let cell0 = domain.constant(context, 0);
+        let paddings = attribute_pairs(context, immediates, domain, self.0);
+        let dims = [0, 1].map(|k| Int::add(context, &[&paddings[k][0], &operand.dims[k], &paddings[k][1]]));
+        let mut result = Vecs::new(dims.clone());
         for i in 0 .. DIMS[0] {
-            let mut mul = one(context, bit_width);
             for j in 0 .. DIMS[1] {
-                let col_index = Int::from_i64(context, j as i64);
-                mul = col_index.lt(&operands[0].dims[1]).ite(&Int::mul(context, &[&mul, &operands[0].vecs[i][j]]), &mul);
+                let row = Int::sub(context, &[&Int::from_i64(context, i as i64), &paddings[0][0]]);
+                let col = Int::sub(context, &[&Int::from_i64(context, j as i64), &paddings[1][0]]);
+                let is_inside = Bool::and(context, &[
+                    &is_in_range(context, &row, &operand.dims[0]),
+                    &is_in_range(context, &col, &operand.dims[1]),
+                ]);
+                let value = is_inside.ite(&select_cell(context, operand, &row, &col), &cell0);
+                result.vecs[i].push(is_in_shape(context, &dims, i, j).ite(&value, &cell0));
             }
-            result.vecs[0][i] = mul;
         }
+        result
+    }
 
-        return result;
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        let paddings = attribute_pairs(context, immediates, domain, self.0);
+        let const0 = zero(context);
+        let constraints: Vec<_> = (0 .. 2)
+            .flat_map(|k| {
+                let dim = Int::add(context, &[&paddings[k][0], &operands[0].dims[k], &paddings[k][1]]);
+                [
+                    const0.le(&paddings[k][0]),
+                    const0.le(&paddings[k][1]),
+                    dim.le(&Int::from_i64(context, DIMS[k] as i64)),
+                ]
+            })
+            .collect();
+        Bool::and(context, &constraints.iter().collect::<Vec<_>>())
     }
 }
 
-pub fn tf_reduce_prod() -> Box<dyn Component> {
-    Box::new(TfReduceProd) as _
+pub fn tf_pad() -> Box<dyn Component> {
+    Box::new(TfPad(None)) as _
 }
 
+/// `tf.reverse(tensor, axis=[axis])`.
 #[derive(Debug)]
-struct TfRoll;
+struct TfReverse(usize);
 
-impl Component for TfRoll {
+impl Component for TfReverse {
     fn operand_arity(&self) -> usize {
         1
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfRoll(operands[0])
+        Operator::TfReverse(operands[0], self.0)
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        // 测试样例里面第二个参数和第三个参数都是一样的（都是1），目前只考虑一个参数的情况
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
-        for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
-        }
+        context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
+        let operand = &operands[0];
+        let cell0 = domain.constant(context, 0);
+        let last = Int::sub(context, &[&operand.dims[self.0], &one(context)]);
+        let mut result = Vecs::new(operand.dims.clone());
         for i in 0 .. DIMS[0] {
             for j in 0 .. DIMS[1] {
-                let row = Int::from_i64(context, i as i64);
-                let col = Int::from_i64(context, j as i64);
-                let is_in_row = row.lt(&operands[0].dims[0]);
-                let is_in_col = col.lt(&operands[0].dims[1]);
-                result.vecs[i][(j + 1) % DIMS[1]] = Bool::and(context, &[&is_in_row, &is_in_col]).ite(&operands[0].vecs[i][j], &const0);
-            }
-            for j in 0 .. DIMS[1] {
-                let col = Int::from_i64(context, j as i64);
-                result.vecs[i][0] = col._eq(&operands[0].dims[1]).ite(&operands[0].vecs[i][j], &result.vecs[i][0]);
-                result.vecs[i][j] = col.lt(&operands[0].dims[1]).ite(&result.vecs[i][j], &const0);
+                let k = Int::from_i64(context, if self.0 == 0 { i } else { j } as i64);
+                let value = select_along(context, operand, self.0, i, j, &Int::sub(context, &[&last, &k]));
+                result.vecs[i].push(is_in_shape(context, &operand.dims, i, j).ite(&value, &cell0));
             }
         }
-
-        return result;
+        result
     }
 }
 
-pub fn tf_roll() -> Box<dyn Component> {
-    Box::new(TfRoll) as _
+pub fn tf_reverse(axis: usize) -> Box<dyn Component> {
+    assert!(axis < 2, "tensors only have axes 0 and 1");
+    Box::new(TfReverse(axis)) as _
 }
 
+/// A sketch's hole, viewed as a component: it passes along whichever value the
+/// synthesizer wires into it, as long as that value has the hole's shape.
 #[derive(Debug)]
-struct TfZeros;
+struct Hole(Option<[usize; 2]>);
 
-impl Component for TfZeros {
+impl Component for Hole {
     fn operand_arity(&self) -> usize {
         1
     }
 
     fn make_operator(&self, _immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
-        Operator::TfZeros(operands[0])
+        Operator::Hole(self.0, Some(operands[0]))
     }
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        _immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
-        let const0 = zero(context, bit_width);
-        let mut result = Vecs::new(operands[0].dims.clone());
-        for i in 0 .. DIMS[0] {
-            for _ in 0 .. DIMS[1] {
-                result.vecs[i].push(const0.clone());
-            }
+        _context: &'a term::Context,
+        _immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Tensor<'a> {
+        operands[0].clone()
+    }
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        match (self.0, shapes[0]) {
+            (Some(expected), Some(actual)) => expected == actual,
+            _ => true,
         }
-
-        return result;
     }
 }
 
-pub fn tf_zeros() -> Box<dyn Component> {
-    Box::new(TfZeros) as _
-}
-
 macro_rules! with_operator_component {
     ( $me:expr , |$c:ident| $body:expr ) => {
         match $me {
@@ -2082,8 +4858,12 @@ macro_rules! with_operator_component {
                 let $c = TfAdd;
                 $body
             }
-            Operator::TfArgmax(_) => {
-                let $c = TfArgmax;
+            Operator::TfArgmax(_, axis) => {
+                let $c = TfArg { max: true, axis: *axis };
+                $body
+            }
+            Operator::TfArgmin(_, axis) => {
+                let $c = TfArg { max: false, axis: *axis };
                 $body
             }
             Operator::TfBooleanMask(_, _) => {
@@ -2094,8 +4874,144 @@ macro_rules! with_operator_component {
                 let $c = TfBooleanMask_;
                 $body
             }
-            Operator::TfCast(_) => {
-                let $c = TfCast;
+            Operator::TfCast(_, dtype) => {
+                let $c = TfCast(*dtype);
+                $body
+            }
+            Operator::TfGather(_, _, axis) => {
+                let $c = TfGather(*axis);
+                $body
+            }
+            Operator::TfGatherNd(_, _, batch_dims) => {
+                let $c = TfGatherNd(*batch_dims);
+                $body
+            }
+            Operator::TfTakeAlongAxis(_, _, axis) => {
+                let $c = TfTakeAlongAxis(*axis);
+                $body
+            }
+            Operator::TfSort(_, axis, descending) => {
+                let $c = TfSort(*axis, *descending);
+                $body
+            }
+            Operator::TfArgsort(_, axis, descending) => {
+                let $c = TfArgsort(*axis, *descending);
+                $body
+            }
+            Operator::TfTopKValues(_, _) => {
+                let $c = TfTopK { indices: false };
+                $body
+            }
+            Operator::TfTopKIndices(_, _) => {
+                let $c = TfTopK { indices: true };
+                $body
+            }
+            Operator::TfReshape(_, _) => {
+                let $c = TfReshape;
+                $body
+            }
+            Operator::TfReshapeTo(_, shape) => {
+                let $c = TfReshapeTo(Some(*shape));
+                $body
+            }
+            Operator::TfSqueeze(_) => {
+                let $c = TfSqueeze;
+                $body
+            }
+            Operator::TfTile(_, _) => {
+                let $c = TfTile;
+                $body
+            }
+            Operator::TfStack(_, _, axis) => {
+                let $c = TfStack(*axis);
+                $body
+            }
+            Operator::TfScatterNd(_, _, _) => {
+                let $c = TfScatterNd;
+                $body
+            }
+            Operator::TfTensorScatterNdUpdate(_, _, _) => {
+                let $c = TfTensorScatterNd { add: false };
+                $body
+            }
+            Operator::TfTensorScatterNdAdd(_, _, _) => {
+                let $c = TfTensorScatterNd { add: true };
+                $body
+            }
+            Operator::TfSparseToDense(_, _, _) => {
+                let $c = TfSparseToDense;
+                $body
+            }
+            Operator::TfLess(_, _) => {
+                let $c = TfCompare(Comparison::Less);
+                $body
+            }
+            Operator::TfLessEqual(_, _) => {
+                let $c = TfCompare(Comparison::LessEqual);
+                $body
+            }
+            Operator::TfGreaterEqual(_, _) => {
+                let $c = TfCompare(Comparison::GreaterEqual);
+                $body
+            }
+            Operator::TfLogicalAnd(_, _) => {
+                let $c = TfLogical(Connective::And);
+                $body
+            }
+            Operator::TfLogicalOr(_, _) => {
+                let $c = TfLogical(Connective::Or);
+                $body
+            }
+            Operator::TfLogicalXor(_, _) => {
+                let $c = TfLogical(Connective::Xor);
+                $body
+            }
+            Operator::TfLogicalNot(_) => {
+                let $c = TfLogicalNot;
+                $body
+            }
+            Operator::TfAbs(_) => {
+                let $c = TfUnary(Unary::Abs);
+                $body
+            }
+            Operator::TfNegative(_) => {
+                let $c = TfUnary(Unary::Negative);
+                $body
+            }
+            Operator::TfSign(_) => {
+                let $c = TfUnary(Unary::Sign);
+                $body
+            }
+            Operator::TfFloorDiv(_, _) => {
+                let $c = TfFloor { modulo: false };
+                $body
+            }
+            Operator::TfFloorMod(_, _) => {
+                let $c = TfFloor { modulo: true };
+                $body
+            }
+            Operator::TfPow(_, _) => {
+                let $c = TfPow;
+                $body
+            }
+            Operator::TfClipByValue(_, _, _) => {
+                let $c = TfClipByValue;
+                $body
+            }
+            Operator::TfSlice(_, begin, size) => {
+                let $c = TfSlice(Some([*begin, *size]));
+                $body
+            }
+            Operator::TfStridedSlice(_, begin, end, strides) => {
+                let $c = TfStridedSlice(Some([*begin, *end, *strides]));
+                $body
+            }
+            Operator::TfPad(_, paddings) => {
+                let $c = TfPad(Some(*paddings));
+                $body
+            }
+            Operator::TfReverse(_, axis) => {
+                let $c = TfReverse(*axis);
                 $body
             }
             Operator::TfConcat0(_, _) => {
@@ -2126,8 +5042,13 @@ macro_rules! with_operator_component {
                 let $c = TfBincount;
                 $body
             }
-            Operator::TfCumsum(_, _) => {
-                let $c = TfCumsum;
+            Operator::TfCumulative(_, reduction, axis, exclusive, reverse) => {
+                let $c = TfCumulative {
+                    reduction: *reduction,
+                    axis: *axis,
+                    exclusive: *exclusive,
+                    reverse: *reverse,
+                };
                 $body
             }
             Operator::TfGreater(_, _) => {
@@ -2146,28 +5067,12 @@ macro_rules! with_operator_component {
                 let $c = TfRange;
                 $body
             }
-            Operator::TfReduceMax(_) => {
-                let $c = TfReduceMax;
-                $body
-            }
-            Operator::TfReduceMax0(_) => {
-                let $c = TfReduceMax0;
-                $body
-            }
-            Operator::TfReduceMax1(_) => {
-                let $c = TfReduceMax1;
-                $body
-            }
-            Operator::TfReduceSum(_) => {
-                let $c = TfReduceSum;
-                $body
-            }
-            Operator::TfReduceSum0(_) => {
-                let $c = TfReduceSum0;
-                $body
-            }
-            Operator::TfReduceSum1(_) => {
-                let $c = TfReduceSum1;
+            Operator::TfReduce(_, reduction, axis, keepdims) => {
+                let $c = TfReduce {
+                    reduction: *reduction,
+                    axis: *axis,
+                    keepdims: *keepdims,
+                };
                 $body
             }
             Operator::TfSequenceMask(_) => {
@@ -2207,8 +5112,12 @@ macro_rules! with_operator_component {
                 let $c = TfFill;
                 $body
             }
-            Operator::TfSegmentMax(_, _) => {
-                let $c = TfSegmentMax;
+            Operator::TfSegment(_, _, reduction) => {
+                let $c = TfSegment(*reduction);
+                $body
+            }
+            Operator::TfUnsortedSegment(_, _, _, reduction) => {
+                let $c = TfUnsortedSegment(*reduction);
                 $body
             }
             Operator::TfMatmul(_, _) => {
@@ -2231,37 +5140,29 @@ macro_rules! with_operator_component {
                 let $c = TfOnes;
                 $body
             }
-            Operator::TfReduceAny0(_) => {
-                let $c = TfReduceAny0;
-                $body
-            }
-            Operator::TfReduceAny1(_) => {
-                let $c = TfReduceAny1;
-                $body
-            }
-            Operator::TfReduceMean(_) => {
-                let $c = TfReduceMean;
-                $body
-            }
-            Operator::TfReduceProd(_) => {
-                let $c = TfReduceProd;
-                $body
-            }
-            Operator::TfRoll(_) => {
-                let $c = TfRoll;
+            Operator::TfRoll(_, shift, axis) => {
+                let $c = TfRoll {
+                    shift: Some(*shift),
+                    axis: *axis,
+                };
                 $body
             }
             Operator::TfZeros(_) => {
                 let $c = TfZeros;
                 $body
             }
+            Operator::Hole(shape, _) => {
+                let $c = Hole(*shape);
+                $body
+            }
         }
     };
 }
 
 impl Component for Operator {
+    // 对于草图中还没填的洞，`Operator::arity`是0，但作为部件它需要一个操作数
     fn operand_arity(&self) -> usize {
-        Operator::arity(self)
+        with_operator_component!(self, |c| c.operand_arity())
     }
 
     fn make_operator(&self, immediates: &Vec<Vecs<i64>>, operands: &[Id]) -> Operator {
@@ -2270,17 +5171,125 @@ impl Component for Operator {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        immediates: &[Vecs<Int<'a>>],
-        operands: &[Vecs<Int<'a>>],
-        bit_width: u32,
-    ) -> Vecs<Int<'a>> {
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Tensor<'a> {
         with_operator_component!(self, |c| {
-            c.make_expression(context, immediates, operands, bit_width)
+            c.make_expression(context, immediates, operands, domain)
         })
     }
 
     fn immediate_arity(&self) -> usize {
         with_operator_component!(self, |c| c.immediate_arity())
     }
-}
\ No newline at end of file
+
+    fn accepts_operand_shapes(&self, shapes: &[Option<[usize; 2]>]) -> bool {
+        with_operator_component!(self, |c| c.accepts_operand_shapes(shapes))
+    }
+
+    fn operand_constraint<'a>(
+        &self,
+        context: &'a term::Context,
+        immediates: &[Tensor<'a>],
+        operands: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Bool<'a> {
+        with_operator_component!(self, |c| c.operand_constraint(context, immediates, operands, domain))
+    }
+
+    fn result_dtype(&self, dtypes: &[DType]) -> Option<DType> {
+        with_operator_component!(self, |c| c.result_dtype(dtypes))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{domain::cell_as_i64, input_vecs};
+
+    fn value<'a>(context: &'a term::Context, rows: &[&[i64]]) -> Tensor<'a> {
+        let rows: Vec<Vec<i64>> = rows.iter().map(|row| row.to_vec()).collect();
+        input_vecs(context, Domain::Int, &rows)
+    }
+
+    #[test]
+    fn broadcast_dims_takes_the_dimension_that_isnt_one() {
+        let context = term::Context::new();
+        let shape = |operands: &[Tensor]| {
+            let [rows, cols] = broadcast_dims(&context, operands);
+            [rows.simplify().as_i64().unwrap(), cols.simplify().as_i64().unwrap()]
+        };
+        let row = value(&context, &[&[1, 2, 3]]);
+        let col = value(&context, &[&[1], &[2], &[3], &[4]]);
+        let one = value(&context, &[&[1]]);
+        let matrix = value(&context, &[&[1, 2, 3], &[4, 5, 6]]);
+
+        assert_eq!(shape(&[row.clone(), col.clone()]), [4, 3]);
+        assert_eq!(shape(&[col.clone(), row.clone()]), [4, 3]);
+        assert_eq!(shape(&[one.clone(), matrix.clone()]), [2, 3]);
+        assert_eq!(shape(&[matrix.clone(), one.clone()]), [2, 3]);
+        assert_eq!(shape(&[one.clone(), row.clone(), matrix.clone()]), [2, 3]);
+    }
+
+    #[test]
+    fn sorting_keeps_ties_in_order() {
+        let context = term::Context::new();
+        let domain = Domain::Int;
+        let cells: Vec<_> = [3, 1, 3, 1, 2].iter().map(|&x| domain.constant(&context, x)).collect();
+        let line: Vec<_> = cells.iter().collect();
+        let sort = |len: i64, descending: bool| {
+            let (values, indices) = sort_line(&context, domain, &line, &Int::from_i64(&context, len), descending);
+            let simplify = |cells: Vec<Dynamic>| -> Vec<_> {
+                cells.iter().map(|cell| cell_as_i64(&cell.simplify()).unwrap()).collect()
+            };
+            (simplify(values), simplify(indices))
+        };
+
+        assert_eq!(sort(5, false), (vec![1, 1, 2, 3, 3], vec![1, 3, 4, 0, 2]));
+        assert_eq!(sort(5, true), (vec![3, 3, 2, 1, 1], vec![0, 2, 4, 1, 3]));
+        // 只排前三个，后面补零
+        assert_eq!(sort(3, false), (vec![1, 3, 3, 0, 0], vec![1, 0, 2, 0, 0]));
+    }
+
+    #[test]
+    fn reductions_check_their_arguments() {
+        assert!(matches!(tf_reduce(Reduction::Sum, Some(2), false), Err(Error::InvalidComponent(_))));
+        assert!(tf_reduce(Reduction::Sum, Some(1), true).is_ok());
+
+        // 空张量没有均值和方差
+        let context = term::Context::new();
+        let empty = crate::concrete_vecs(&context, Domain::Int, &Vecs { dims: [2, 0], vecs: vec![vec![0; DIMS[1]]; DIMS[0]] });
+        for (reduction, accepts) in [(Reduction::Sum, true), (Reduction::Mean, false), (Reduction::Variance, false)] {
+            let component = tf_reduce(reduction, Some(0), false).unwrap();
+            assert_eq!(component.accepts_operand_shapes(&[Some([2, 0])]), accepts);
+            let constraint = component.operand_constraint(&context, &[], std::slice::from_ref(&empty), Domain::Int);
+            assert_eq!(constraint.simplify().as_bool(), Some(accepts));
+        }
+    }
+
+    #[test]
+    fn broadcasting_pads_with_zeros() {
+        let context = term::Context::new();
+        let row = value(&context, &[&[1, 2, 3]]);
+        let col = value(&context, &[&[10], &[20]]);
+        let cond = value(&context, &[&[1, 0, 1]]);
+        for (component, operands) in [
+            (tf_add(), vec![row.clone(), col.clone()]),
+            (tf_subtract(), vec![row.clone(), col.clone()]),
+            (tf_multiply(), vec![row.clone(), col.clone()]),
+            (tf_where3(), vec![cond, row, col]),
+        ] {
+            let result = component.make_expression(&context, &[], &operands, Domain::Int);
+            for i in 0 .. DIMS[0] {
+                for j in 0 .. DIMS[1] {
+                    let cell = cell_as_i64(&result.vecs[i][j].simplify());
+                    assert!(cell.is_some(), "{:?} [{}, {}]", component, i, j);
+                    if i >= 2 || j >= 3 {
+                        assert_eq!(cell, Some(0), "{:?} [{}, {}]", component, i, j);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1671 @@
+//! Running programs on concrete values, without going through the SMT
+//! encodings.
+//!
+//! Re-checking a synthesized program with the same encodings that synthesized
+//! it can't catch a bug in those encodings: the program and the spec would
+//! agree on the wrong answer. So every operator also has a plain interpreter
+//! here, written straight from TF's documentation, and `check_program` trusts
+//! it over the encodings.
+//!
+//! Cells are exact `Rational`s, so that dividing in `Domain::Real` comes out
+//! exactly as it does in the encodings. In the other domains every cell is an
+//! integer, wrapped around to the width of a `Domain::BitVec`.
+
+use crate::{
+    component::MAX_EXPONENT, DType, Domain, Error, Id, Instruction, Operator, Program, Reduction, Result, Tensor, Vecs,
+    DIMS,
+};
+use crate::term::{self, Int, Real};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+
+/// An exact cell value: a fraction in lowest terms, with a positive
+/// denominator. Only `Domain::Real` cells are ever anything but integers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numer: i64,
+    denom: i64,
+}
+
+impl Rational {
+    /// `numer / denom` in lowest terms, if both of those fit in an `i64`.
+    fn new(numer: i128, denom: i128) -> Option<Rational> {
+        let gcd = gcd(numer.unsigned_abs(), denom.unsigned_abs()) as i128;
+        let sign = if denom < 0 { -1 } else { 1 };
+        Some(Rational {
+            numer: i64::try_from(sign * numer / gcd).ok()?,
+            denom: i64::try_from(sign * denom / gcd).ok()?,
+        })
+    }
+
+    pub fn numer(self) -> i64 {
+        self.numer
+    }
+
+    pub fn denom(self) -> i64 {
+        self.denom
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    /// The largest integer that is at most this, which is how a cell is used
+    /// as an index or a dimension.
+    fn floor(self) -> i64 {
+        self.numer.div_euclid(self.denom)
+    }
+
+    fn is_zero(self) -> bool {
+        self.numer == 0
+    }
+
+    fn parts(self) -> (i128, i128) {
+        (self.numer as i128, self.denom as i128)
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(n: i64) -> Rational {
+        Rational { numer: n, denom: 1 }
+    }
+}
+
+impl From<bool> for Rational {
+    fn from(b: bool) -> Rational {
+        Rational::from(b as i64)
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        let (a, b) = (self.parts(), other.parts());
+        (a.0 * b.1).cmp(&(b.0 * a.1))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.denom == 1 {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// 下面是各个域里单元格的算术，和domain.rs里的编码一一对应。
+// 返回None表示结果放不进i64
+
+/// `numer / denom` as a cell of `domain`, wrapped around for `BitVec`s.
+fn cell(domain: Domain, numer: i128, denom: i128) -> Option<Rational> {
+    match domain {
+        Domain::BitVec(width) => {
+            let shift = 128 - width.bits();
+            Some(Rational::from(((numer << shift) >> shift) as i64))
+        }
+        _ => Rational::new(numer, denom),
+    }
+}
+
+fn add(domain: Domain, a: Rational, b: Rational) -> Option<Rational> {
+    let ((an, ad), (bn, bd)) = (a.parts(), b.parts());
+    cell(domain, an * bd + bn * ad, ad * bd)
+}
+
+fn sub(domain: Domain, a: Rational, b: Rational) -> Option<Rational> {
+    let ((an, ad), (bn, bd)) = (a.parts(), b.parts());
+    cell(domain, an * bd - bn * ad, ad * bd)
+}
+
+fn mul(domain: Domain, a: Rational, b: Rational) -> Option<Rational> {
+    let ((an, ad), (bn, bd)) = (a.parts(), b.parts());
+    cell(domain, an * bn, ad * bd)
+}
+
+/// `Domain::div`, for a nonzero `b`: Euclidean for `Int`, exact for `Real`
+/// and rounding toward zero for `BitVec`.
+fn div(domain: Domain, a: Rational, b: Rational) -> Option<Rational> {
+    let ((an, ad), (bn, bd)) = (a.parts(), b.parts());
+    match domain {
+        Domain::Int => cell(domain, an.div_euclid(bn), 1),
+        Domain::Real => cell(domain, an * bd, ad * bn),
+        Domain::BitVec(_) => cell(domain, an / bn, 1),
+    }
+}
+
+/// `a / b` rounded toward negative infinity, for a nonzero `b`.
+fn floor_div(domain: Domain, a: Rational, b: Rational) -> Option<Rational> {
+    let ((an, ad), (bn, bd)) = (a.parts(), b.parts());
+    let (x, y) = (an * bd, ad * bn);
+    let q = x.div_euclid(y);
+    // 欧几里得除法的余数总是非负，除数为负且除不尽时要减一
+    cell(domain, if y < 0 && x.rem_euclid(y) != 0 { q - 1 } else { q }, 1)
+}
+
+/// What `floor_div` leaves over, which has the sign of `b`.
+fn floor_mod(domain: Domain, a: Rational, b: Rational) -> Option<Rational> {
+    let q = floor_div(Domain::Real, a, b)?;
+    let ((an, ad), (bn, bd)) = (a.parts(), b.parts());
+    let numer = (an * bd).checked_sub(bn.checked_mul(ad)?.checked_mul(q.numer as i128)?)?;
+    cell(domain, numer, ad * bd)
+}
+
+/// Round toward zero, the way casting to an integer does.
+fn trunc(a: Rational) -> Rational {
+    Rational::from(a.numer / a.denom)
+}
+
+/// The smallest value a cell can hold, like `Domain::min_value`.
+fn min_value(domain: Domain) -> Rational {
+    match domain {
+        Domain::BitVec(width) => Rational::from(i64::MIN >> (64 - width.bits())),
+        _ => Rational::from(i64::MIN),
+    }
+}
+
+/// The largest value a cell can hold, like `Domain::max_value`.
+fn max_value(domain: Domain) -> Rational {
+    match domain {
+        Domain::BitVec(width) => Rational::from(i64::MAX >> (64 - width.bits())),
+        _ => Rational::from(i64::MAX),
+    }
+}
+
+/// What reducing no values at all gives.
+fn identity(domain: Domain, reduction: Reduction) -> Rational {
+    match reduction {
+        Reduction::Prod | Reduction::All => Rational::from(1),
+        Reduction::Max => min_value(domain),
+        Reduction::Min => max_value(domain),
+        _ => Rational::from(0),
+    }
+}
+
+/// `values` reduced with `reduction`. A mean or variance of no values has to
+/// be ruled out by the caller.
+fn reduce(domain: Domain, reduction: Reduction, values: &[Rational]) -> Option<Rational> {
+    let sum = || values.iter().try_fold(Rational::from(0), |sum, &x| add(domain, sum, x));
+    let count = || cell(domain, values.len() as i128, 1);
+    let nonzero = values.iter().filter(|x| !x.is_zero()).count();
+    match reduction {
+        Reduction::Sum => sum(),
+        Reduction::Prod => values.iter().try_fold(Rational::from(1), |product, &x| mul(domain, product, x)),
+        Reduction::Max => Some(values.iter().copied().max().unwrap_or_else(|| identity(domain, reduction))),
+        Reduction::Min => Some(values.iter().copied().min().unwrap_or_else(|| identity(domain, reduction))),
+        Reduction::Mean => div(domain, sum()?, count()?),
+        Reduction::Any => Some(Rational::from(nonzero > 0)),
+        Reduction::All => Some(Rational::from(nonzero == values.len())),
+        Reduction::Count => cell(domain, nonzero as i128, 1),
+        Reduction::Variance => {
+            // 和编码一样，先求均值，再求偏差平方的均值
+            let mean = div(domain, sum()?, count()?)?;
+            let squares = values.iter().try_fold(Rational::from(0), |sum, &x| {
+                let deviation = sub(domain, x, mean)?;
+                add(domain, sum, mul(domain, deviation, deviation)?)
+            })?;
+            div(domain, squares, count()?)
+        }
+    }
+}
+
+/// A concrete value as constant `Vecs` of `domain`.
+pub(crate) fn tensor<'a>(context: &'a term::Context, domain: Domain, value: &Vecs<Rational, i64>) -> Tensor<'a> {
+    let mut result = Vecs::new([Int::from_i64(context, value.dims[0]), Int::from_i64(context, value.dims[1])]);
+    for (row, cells) in result.vecs.iter_mut().zip(&value.vecs) {
+        for &cell in cells {
+            row.push(match domain {
+                Domain::Real => Real::from_real_str(context, &cell.numer.to_string(), &cell.denom.to_string())
+                    .unwrap()
+                    .into(),
+                _ => domain.constant(context, cell.numer),
+            });
+        }
+    }
+    result
+}
+
+/// The positions of `line`'s cells in stably sorted order, so that equal
+/// cells keep the order they had, as `tf.argsort(stable=True)` does.
+fn sorted_positions(line: &[Rational], descending: bool) -> Vec<usize> {
+    let mut positions: Vec<_> = (0..line.len()).collect();
+    if descending {
+        positions.sort_by(|&m, &k| line[k].cmp(&line[m]));
+    } else {
+        positions.sort_by_key(|&k| line[k]);
+    }
+    positions
+}
+
+/// A concrete tensor, without padding.
+#[derive(Clone, Debug, PartialEq)]
+struct Value {
+    dims: [usize; 2],
+    cells: Vec<Vec<Rational>>,
+}
+
+impl Value {
+    /// The value of shape `dims` with the given cells, or `None` if one of
+    /// them doesn't fit.
+    fn new(dims: [usize; 2], mut cell_at: impl FnMut(usize, usize) -> Option<Rational>) -> Option<Value> {
+        let cells = (0..dims[0])
+            .map(|i| (0..dims[1]).map(|j| cell_at(i, j)).collect())
+            .collect::<Option<_>>()?;
+        Some(Value { dims, cells })
+    }
+
+    /// The cell at `[i, j]` once this has been broadcast.
+    fn broadcast_cell(&self, i: usize, j: usize) -> Rational {
+        let i = if self.dims[0] == 1 { 0 } else { i };
+        let j = if self.dims[1] == 1 { 0 } else { j };
+        self.cells[i][j]
+    }
+
+    /// The cells in row-major order.
+    fn flat(&self) -> impl Iterator<Item = Rational> + '_ {
+        self.cells.iter().flatten().copied()
+    }
+
+    fn is_scalar(&self) -> bool {
+        self.dims == [1, 1]
+    }
+
+    fn padded(&self) -> Vecs<Rational, i64> {
+        let mut result = Vecs::new([self.dims[0] as i64, self.dims[1] as i64]);
+        for i in 0..DIMS[0] {
+            for j in 0..DIMS[1] {
+                let cell = self.cells.get(i).and_then(|row| row.get(j));
+                result.vecs[i].push(cell.copied().unwrap_or_else(|| Rational::from(0)));
+            }
+        }
+        result
+    }
+}
+
+/// `program`'s output on `inputs`, padded out to `DIMS`.
+///
+/// This is an error if an instruction is applied to operands that TF would
+/// reject, if the program still has an unfilled hole, if it computes a `Float`
+/// outside of `Domain::Real`, which is the only domain that divides without
+/// rounding, or if a cell of `Domain::Int` or `Domain::Real` doesn't fit in 64
+/// bits.
+pub(crate) fn evaluate(
+    program: &Program,
+    inputs: &[&Vec<Vec<i64>>],
+    domain: Domain,
+) -> Result<Vecs<Rational, i64>> {
+    if domain != Domain::Real {
+        let dtypes = program.dtypes();
+        let mut floats = program.instructions.iter().zip(dtypes).filter(|(_, dtype)| *dtype == Some(DType::Float));
+        if let Some((inst, _)) = floats.next() {
+            return Err(Error::InvalidProgram(format!(
+                "{}: its result is a float, which needs the real domain, not {}",
+                inst, domain
+            )));
+        }
+    }
+
+    let mut values: Vec<Value> = vec![];
+    for inst in &program.instructions {
+        let value = match inst.operator {
+            Operator::Var => {
+                let input = inputs.get(values.len()).ok_or_else(|| {
+                    Error::InvalidProgram(format!("{}: there are only {} inputs", inst, inputs.len()))
+                })?;
+                Value {
+                    dims: [input.len(), input[0].len()],
+                    cells: input
+                        .iter()
+                        .map(|row| row.iter().map(|&x| cell(domain, x as i128, 1).unwrap()).collect())
+                        .collect(),
+                }
+            }
+            _ => {
+                let mut operands = vec![];
+                inst.operator.operands(|Id(x)| operands.push(&values[x as usize]));
+                apply(inst, &operands, domain)?
+            }
+        };
+        values.push(value);
+    }
+    values
+        .last()
+        .map(Value::padded)
+        .ok_or_else(|| Error::InvalidProgram("the program has no instructions".to_string()))
+}
+
+fn apply(inst: &Instruction, operands: &[&Value], domain: Domain) -> Result<Value> {
+    let invalid = |why: String| -> Result<Value> { Err(Error::InvalidProgram(format!("{}: {}", inst, why))) };
+    let bool_cell = |b: bool| Some(Rational::from(b));
+    let int_cell = |n: usize| Some(Rational::from(n as i64));
+    let [a, b] = [0, 1].map(|k| operands.get(k).copied());
+
+    // 逐元素的运算：先广播，再一个一个单元格算
+    let elementwise = |f: &dyn Fn(&[Rational]) -> Option<Rational>| -> Result<Option<Value>> {
+        let mut dims = operands[0].dims;
+        for operand in operands {
+            for (dim, &operand_dim) in dims.iter_mut().zip(&operand.dims) {
+                if *dim == 1 {
+                    *dim = operand_dim;
+                } else if operand_dim != 1 && operand_dim != *dim {
+                    let shapes: Vec<_> = operands.iter().map(|operand| operand.dims).collect();
+                    return invalid(format!("operands of shapes {:?} don't broadcast", shapes)).map(Some);
+                }
+            }
+        }
+        Ok(Value::new(dims, |i, j| {
+            let cells: Vec<_> = operands.iter().map(|operand| operand.broadcast_cell(i, j)).collect();
+            f(&cells)
+        }))
+    };
+
+    // 标量参数，比如`tf.range`的端点和`tf.one_hot`的深度
+    let scalar = |value: &Value, what: &str| -> Result<i64> {
+        if !value.is_scalar() {
+            return Err(Error::InvalidProgram(format!(
+                "{}: its {} has shape {:?}, but must be a scalar",
+                inst, what, value.dims
+            )));
+        }
+        Ok(value.cells[0][0].floor())
+    };
+    let dim = |n: i64, what: &str| -> Result<usize> {
+        usize::try_from(n).map_err(|_| Error::InvalidProgram(format!("{}: its {} is negative", inst, what)))
+    };
+    // `tf.ones`、`tf.zeros`和`tf.fill`的形状参数是`[rows, cols]`
+    let shape = |value: &Value| -> Result<[usize; 2]> {
+        if value.dims != [1, 2] {
+            return Err(Error::InvalidProgram(format!(
+                "{}: its shape operand has shape {:?}, but must be [1, 2]",
+                inst, value.dims
+            )));
+        }
+        Ok([dim(value.cells[0][0].floor(), "shape")?, dim(value.cells[0][1].floor(), "shape")?])
+    };
+    // 形状由操作数的值决定的结果，要先确认放得下再构造
+    let sized = |dims: [usize; 2]| -> Result<[usize; 2]> {
+        if dims[0] > DIMS[0] || dims[1] > DIMS[1] {
+            return Err(Error::InvalidProgram(format!(
+                "{}: its result has shape {:?}, but values can be at most {:?}",
+                inst, dims, DIMS
+            )));
+        }
+        Ok(dims)
+    };
+    let row_vector = |value: &Value, what: &str| -> Result<Vec<Rational>> {
+        if value.dims[0] != 1 {
+            return Err(Error::InvalidProgram(format!(
+                "{}: its {} has shape {:?}, but must be a row vector",
+                inst, what, value.dims
+            )));
+        }
+        Ok(value.cells[0].clone())
+    };
+    // scatter类的形状参数是一维或二维的形状
+    let shape_operand = |value: &Value| -> Result<[usize; 2]> {
+        let shape: Vec<_> = row_vector(value, "shape")?.iter().map(|x| x.floor()).collect();
+        let dims = match shape[..] {
+            [cols] => [1, cols],
+            [rows, cols] => [rows, cols],
+            _ => return Err(Error::InvalidProgram(format!("{}: its shape has {} dims", inst, shape.len()))),
+        };
+        if dims.iter().any(|&d| d < 1) {
+            return Err(Error::InvalidProgram(format!("{}: its shape {:?} has an empty dim", inst, shape)));
+        }
+        sized(dims.map(|d| d as usize))
+    };
+    // 下标越界时TF会报错，不会像编码里那样取到填充的0
+    let index = |x: Rational, len: usize| -> Result<usize> {
+        match usize::try_from(x.floor()) {
+            Ok(k) if k < len => Ok(k),
+            _ => Err(Error::InvalidProgram(format!(
+                "{}: it has an index of {}, but the axis has {} entries",
+                inst, x, len
+            ))),
+        }
+    };
+
+    // 依次把updates写进tensor（或者全0）的下标处，accumulate时相同下标的更新相加，
+    // 否则后写的覆盖先写的。下标的排布和编码里的`scatter`一样
+    let scatter = |tensor: Option<&Value>,
+                   dims: [usize; 2],
+                   indices: &Value,
+                   updates: &Value,
+                   accumulate: bool,
+                   allows_rows: bool|
+     -> Result<Option<Value>> {
+        let mut cells = match tensor {
+            Some(tensor) => tensor.cells.iter().map(|row| row.iter().copied().map(Some).collect()).collect(),
+            None => vec![vec![Some(Rational::from(0)); dims[1]]; dims[0]],
+        };
+        let m = indices.dims[0];
+        let is_row = indices.dims[1] == 1 && dims[0] != 1;
+        let expected = match indices.dims[1] {
+            1 if is_row && allows_rows => [m, dims[1]],
+            1 if !is_row => [1, m],
+            2 => [1, m],
+            _ => {
+                let why = format!("its indices have shape {:?}, which doesn't fit {:?}", indices.dims, dims);
+                return invalid(why).map(Some);
+            }
+        };
+        if updates.dims != expected {
+            let why = format!("its updates have shape {:?}, but must have {:?}", updates.dims, expected);
+            return invalid(why).map(Some);
+        }
+        let mut write = |i: usize, j: usize, update: Rational| {
+            cells[i][j] = if accumulate { cells[i][j].and_then(|x| add(domain, x, update)) } else { Some(update) };
+        };
+        for (k, index_row) in indices.cells.iter().enumerate() {
+            match indices.dims[1] {
+                1 if is_row => {
+                    let i = index(index_row[0], dims[0])?;
+                    for j in 0..dims[1] {
+                        write(i, j, updates.cells[k][j]);
+                    }
+                }
+                1 => write(0, index(index_row[0], dims[1])?, updates.cells[0][k]),
+                _ => write(index(index_row[0], dims[0])?, index(index_row[1], dims[1])?, updates.cells[0][k]),
+            }
+        }
+        Ok(Value::new(dims, |i, j| cells[i][j]))
+    };
+
+    let value = match inst.operator {
+        Operator::TfAdd(..) => elementwise(&|x| add(domain, x[0], x[1]))?,
+        Operator::TfSubtract(..) => elementwise(&|x| sub(domain, x[0], x[1]))?,
+        Operator::TfMultiply(..) => elementwise(&|x| mul(domain, x[0], x[1]))?,
+        Operator::TfSquare(..) => elementwise(&|x| mul(domain, x[0], x[0]))?,
+        Operator::TfMaximum(..) => elementwise(&|x| Some(x[0].max(x[1])))?,
+        Operator::TfMinimum(..) => elementwise(&|x| Some(x[0].min(x[1])))?,
+        Operator::TfAbs(..) => {
+            elementwise(&|x| if x[0].numer < 0 { sub(domain, Rational::from(0), x[0]) } else { Some(x[0]) })?
+        }
+        Operator::TfNegative(..) => elementwise(&|x| sub(domain, Rational::from(0), x[0]))?,
+        Operator::TfSign(..) => elementwise(&|x| Some(Rational::from(x[0].numer.signum())))?,
+        Operator::TfDivide(..) => {
+            // TF会得到inf或nan，这里和编码一样约定除数为0时结果是被除数
+            elementwise(&|x| if x[1].is_zero() { Some(x[0]) } else { div(domain, x[0], x[1]) })?
+        }
+        Operator::TfFloorDiv(..) | Operator::TfFloorMod(..) => {
+            if b.unwrap().flat().any(Rational::is_zero) {
+                return invalid("its divisor has a zero".to_string());
+            }
+            let modulo = matches!(inst.operator, Operator::TfFloorMod(..));
+            elementwise(&|x| if modulo { floor_mod(domain, x[0], x[1]) } else { floor_div(domain, x[0], x[1]) })?
+        }
+        Operator::TfPow(..) => {
+            let exponents = b.unwrap();
+            if let Some(y) = exponents.flat().find(|y| y.denom != 1 || !(0..=MAX_EXPONENT).contains(&y.numer)) {
+                return invalid(format!("it has an exponent of {}, but only 0 to {} are handled", y, MAX_EXPONENT));
+            }
+            // 0的0次方是1
+            elementwise(&|x| (0..x[1].numer).try_fold(Rational::from(1), |power, _| mul(domain, power, x[0])))?
+        }
+        Operator::TfClipByValue(..) => {
+            let is_ordered = elementwise(&|x| bool_cell(x[1] <= x[2]))?.unwrap();
+            if is_ordered.flat().any(Rational::is_zero) {
+                return invalid("its clip_value_min is greater than its clip_value_max".to_string());
+            }
+            elementwise(&|x| Some(x[0].max(x[1]).min(x[2])))?
+        }
+        Operator::TfEqual(..) => elementwise(&|x| bool_cell(x[0] == x[1]))?,
+        Operator::TfNotEqual(..) => elementwise(&|x| bool_cell(x[0] != x[1]))?,
+        Operator::TfGreater(..) => elementwise(&|x| bool_cell(x[0] > x[1]))?,
+        Operator::TfGreaterEqual(..) => elementwise(&|x| bool_cell(x[0] >= x[1]))?,
+        Operator::TfLess(..) => elementwise(&|x| bool_cell(x[0] < x[1]))?,
+        Operator::TfLessEqual(..) => elementwise(&|x| bool_cell(x[0] <= x[1]))?,
+        Operator::TfLogicalAnd(..) => elementwise(&|x| bool_cell(!x[0].is_zero() && !x[1].is_zero()))?,
+        Operator::TfLogicalOr(..) => elementwise(&|x| bool_cell(!x[0].is_zero() || !x[1].is_zero()))?,
+        Operator::TfLogicalXor(..) => elementwise(&|x| bool_cell(x[0].is_zero() != x[1].is_zero()))?,
+        Operator::TfLogicalNot(..) => elementwise(&|x| bool_cell(x[0].is_zero()))?,
+        Operator::TfWhere3(..) => elementwise(&|x| Some(if x[0].is_zero() { x[2] } else { x[1] }))?,
+        Operator::TfCast(_, dtype) => match dtype {
+            DType::Bool => elementwise(&|x| bool_cell(!x[0].is_zero()))?,
+            DType::Int => elementwise(&|x| Some(trunc(x[0])))?,
+            DType::Float => Some(a.unwrap().clone()),
+        },
+        Operator::TfConstant(..) | Operator::Hole(_, Some(_)) => Some(a.unwrap().clone()),
+        Operator::TfArgmax(_, axis) | Operator::TfArgmin(_, axis) => {
+            let a = a.unwrap();
+            if a.dims[axis] == 0 {
+                return invalid(format!("its operand has shape {:?}, so there is nothing to pick from", a.dims));
+            }
+            let is_max = matches!(inst.operator, Operator::TfArgmax(..));
+            Value::new([1, a.dims[1 - axis]], |_, j| {
+                let line = (0..a.dims[axis]).map(|k| if axis == 0 { a.cells[k][j] } else { a.cells[j][k] });
+                // 相等时取第一个
+                let mut best = 0;
+                for (k, x) in line.enumerate() {
+                    let best_x = if axis == 0 { a.cells[best][j] } else { a.cells[j][best] };
+                    if (is_max && x > best_x) || (!is_max && x < best_x) {
+                        best = k;
+                    }
+                }
+                int_cell(best)
+            })
+        }
+        Operator::TfBooleanMask(..) => {
+            let (a, b) = (a.unwrap(), b.unwrap());
+            if a.dims != b.dims {
+                return invalid(format!("its mask has shape {:?}, but its tensor has {:?}", b.dims, a.dims));
+            }
+            let kept: Vec<_> = a.flat().zip(b.flat()).filter(|(_, m)| !m.is_zero()).map(|(x, _)| x).collect();
+            Value::new([1, kept.len()], |_, j| Some(kept[j]))
+        }
+        Operator::TfBooleanMask_(..) => {
+            let (a, mask) = (a.unwrap(), row_vector(b.unwrap(), "mask")?);
+            if mask.len() != a.dims[0] {
+                return invalid(format!("its mask has {} cells, but its tensor has {} rows", mask.len(), a.dims[0]));
+            }
+            let rows: Vec<_> = (0..a.dims[0]).filter(|&i| !mask[i].is_zero()).collect();
+            Value::new([rows.len(), a.dims[1]], |i, j| Some(a.cells[rows[i]][j]))
+        }
+        Operator::TfBincount(..) => {
+            let a = a.unwrap();
+            let mut counts: Vec<usize> = vec![];
+            for x in a.flat() {
+                let x = dim(x.floor(), "value")?;
+                if x >= DIMS[1] {
+                    return invalid(format!("it has a value of {}, so there would be too many bins", x));
+                }
+                if x >= counts.len() {
+                    counts.resize(x + 1, 0);
+                }
+                counts[x] += 1;
+            }
+            Value::new([1, counts.len()], |_, j| int_cell(counts[j]))
+        }
+        Operator::TfOneHot(..) => {
+            let (indices, depth) = (row_vector(a.unwrap(), "indices")?, scalar(b.unwrap(), "depth")?);
+            let depth = dim(depth, "depth")?;
+            // 越界的下标那一行全是0
+            Value::new(sized([indices.len(), depth])?, |i, j| bool_cell(indices[i] == Rational::from(j as i64)))
+        }
+        Operator::TfRange(..) => {
+            let (start, limit) = (scalar(a.unwrap(), "start")?, scalar(b.unwrap(), "limit")?);
+            let len = usize::try_from(limit.saturating_sub(start)).unwrap_or(0);
+            Value::new(sized([1, len])?, |_, j| Some(Rational::from(start + j as i64)))
+        }
+        Operator::TfSequenceMask(..) => {
+            let lengths: Vec<_> = row_vector(a.unwrap(), "lengths")?.iter().map(|x| x.floor()).collect();
+            let maxlen = lengths.iter().copied().max().unwrap_or(0).max(0) as usize;
+            Value::new(sized([lengths.len(), maxlen])?, |i, j| bool_cell((j as i64) < lengths[i]))
+        }
+        Operator::TfTensordot(..) | Operator::TfMatmul(..) => {
+            let (a, b) = (a.unwrap(), b.unwrap());
+            if a.dims[1] != b.dims[0] {
+                return invalid(format!("operands of shapes {:?} and {:?} can't be multiplied", a.dims, b.dims));
+            }
+            Value::new([a.dims[0], b.dims[1]], |i, j| {
+                (0..a.dims[1]).try_fold(Rational::from(0), |sum, k| {
+                    add(domain, sum, mul(domain, a.cells[i][k], b.cells[k][j])?)
+                })
+            })
+        }
+        Operator::TfTranspose(..) => {
+            let a = a.unwrap();
+            Value::new([a.dims[1], a.dims[0]], |i, j| Some(a.cells[j][i]))
+        }
+        Operator::TfExpandDims(..) => {
+            let a = row_vector(a.unwrap(), "operand")?;
+            Value::new([a.len(), 1], |i, _| Some(a[i]))
+        }
+        Operator::TfConcat0(..) | Operator::TfConcat1(..) => {
+            let axis = if matches!(inst.operator, Operator::TfConcat0(..)) { 0 } else { 1 };
+            let (a, b) = (a.unwrap(), b.unwrap());
+            if a.dims[1 - axis] != b.dims[1 - axis] {
+                return invalid(format!("operands of shapes {:?} and {:?} can't be concatenated", a.dims, b.dims));
+            }
+            let mut dims = a.dims;
+            dims[axis] += b.dims[axis];
+            Value::new(dims, |i, j| {
+                Some(if [i, j][axis] < a.dims[axis] {
+                    a.cells[i][j]
+                } else if axis == 0 {
+                    b.cells[i - a.dims[0]][j]
+                } else {
+                    b.cells[i][j - a.dims[1]]
+                })
+            })
+        }
+        Operator::TfWhere1(..) => {
+            let a = a.unwrap();
+            let mut coordinates = vec![];
+            for i in 0..a.dims[0] {
+                for j in 0..a.dims[1] {
+                    if !a.cells[i][j].is_zero() {
+                        coordinates.push([i, j]);
+                    }
+                }
+            }
+            Value::new([coordinates.len(), 2], |i, j| int_cell(coordinates[i][j]))
+        }
+        Operator::TfEye(..) => {
+            let rows = dim(scalar(a.unwrap(), "number of rows")?, "number of rows")?;
+            let cols = dim(scalar(b.unwrap(), "number of columns")?, "number of columns")?;
+            Value::new(sized([rows, cols])?, |i, j| bool_cell(i == j))
+        }
+        Operator::TfOnes(..) | Operator::TfZeros(..) => {
+            let [rows, cols] = shape(a.unwrap())?;
+            let is_ones = matches!(inst.operator, Operator::TfOnes(..));
+            Value::new(sized([rows, cols])?, |_, _| bool_cell(is_ones))
+        }
+        Operator::TfFill(..) => {
+            let [rows, cols] = shape(a.unwrap())?;
+            let b = b.unwrap();
+            if !b.is_scalar() {
+                return invalid(format!("its value has shape {:?}, but must be a scalar", b.dims));
+            }
+            Value::new(sized([rows, cols])?, |_, _| Some(b.cells[0][0]))
+        }
+        Operator::TfRoll(_, shift, axis) => {
+            let a = a.unwrap();
+            let n = a.dims[axis] as i64;
+            Value::new(a.dims, |i, j| {
+                let source = |k: usize| (k as i64 - shift).rem_euclid(n) as usize;
+                Some(if axis == 0 { a.cells[source(i)][j] } else { a.cells[i][source(j)] })
+            })
+        }
+        Operator::TfReverse(_, axis) => {
+            let a = a.unwrap();
+            Value::new(a.dims, |i, j| {
+                Some(if axis == 0 { a.cells[a.dims[0] - 1 - i][j] } else { a.cells[i][a.dims[1] - 1 - j] })
+            })
+        }
+        Operator::TfGather(_, _, axis) => {
+            let params = a.unwrap();
+            let indices = row_vector(b.unwrap(), "indices")?;
+            let indices: Vec<_> = indices.into_iter().map(|x| index(x, params.dims[axis])).collect::<Result<_>>()?;
+            let mut dims = params.dims;
+            dims[axis] = indices.len();
+            Value::new(sized(dims)?, |i, j| {
+                Some(if axis == 0 { params.cells[indices[i]][j] } else { params.cells[i][indices[j]] })
+            })
+        }
+        Operator::TfGatherNd(_, _, batch_dims) => {
+            let (params, indices) = (a.unwrap(), b.unwrap());
+            let rows = indices.cells.iter().map(|row| index(row[0], params.dims[0]));
+            match (batch_dims, indices.dims[1]) {
+                // 每个下标取出一整行
+                (0, 1) => {
+                    let rows: Vec<_> = rows.collect::<Result<_>>()?;
+                    Value::new(sized([rows.len(), params.dims[1]])?, |i, j| Some(params.cells[rows[i]][j]))
+                }
+                // 每个下标是一个坐标，取出一个元素
+                (0, 2) => {
+                    let cells: Vec<_> = indices
+                        .cells
+                        .iter()
+                        .map(|row| Ok(params.cells[index(row[0], params.dims[0])?][index(row[1], params.dims[1])?]))
+                        .collect::<Result<_>>()?;
+                    Value::new(sized([1, cells.len()])?, |_, j| Some(cells[j]))
+                }
+                (1, 1) if indices.dims[0] == params.dims[0] => {
+                    let cols: Vec<_> =
+                        indices.cells.iter().map(|row| index(row[0], params.dims[1])).collect::<Result<_>>()?;
+                    Value::new(sized([1, cols.len()])?, |_, j| Some(params.cells[j][cols[j]]))
+                }
+                _ => {
+                    return invalid(format!(
+                        "its indices have shape {:?}, which doesn't fit params of shape {:?}",
+                        indices.dims, params.dims
+                    ))
+                }
+            }
+        }
+        Operator::TfTakeAlongAxis(_, _, axis) => {
+            let (arr, indices) = (a.unwrap(), b.unwrap());
+            if indices.dims[1 - axis] != arr.dims[1 - axis] {
+                return invalid(format!(
+                    "its indices have shape {:?}, which doesn't line up with {:?}",
+                    indices.dims, arr.dims
+                ));
+            }
+            let taken: Vec<Vec<usize>> = indices
+                .cells
+                .iter()
+                .map(|row| row.iter().map(|&x| index(x, arr.dims[axis])).collect())
+                .collect::<Result<_>>()?;
+            Value::new(indices.dims, |i, j| {
+                Some(if axis == 0 { arr.cells[taken[i][j]][j] } else { arr.cells[i][taken[i][j]] })
+            })
+        }
+        Operator::TfSort(_, axis, descending) | Operator::TfArgsort(_, axis, descending) => {
+            let a = a.unwrap();
+            let is_sort = matches!(inst.operator, Operator::TfSort(..));
+            let lines: Vec<_> = (0..a.dims[1 - axis])
+                .map(|l| {
+                    let line: Vec<_> =
+                        (0..a.dims[axis]).map(|k| if axis == 0 { a.cells[k][l] } else { a.cells[l][k] }).collect();
+                    let positions = sorted_positions(&line, descending);
+                    if is_sort {
+                        positions.iter().map(|&k| line[k]).collect()
+                    } else {
+                        positions.iter().map(|&k| Rational::from(k as i64)).collect()
+                    }
+                })
+                .collect::<Vec<Vec<_>>>();
+            Value::new(a.dims, |i, j| Some(if axis == 0 { lines[j][i] } else { lines[i][j] }))
+        }
+        Operator::TfTopKValues(..) | Operator::TfTopKIndices(..) => {
+            let a = a.unwrap();
+            let k = dim(scalar(b.unwrap(), "k")?, "k")?;
+            if k > a.dims[1] {
+                return invalid(format!("its k is {}, but its input only has {} columns", k, a.dims[1]));
+            }
+            let is_values = matches!(inst.operator, Operator::TfTopKValues(..));
+            let positions: Vec<_> = a.cells.iter().map(|row| sorted_positions(row, true)).collect();
+            Value::new([a.dims[0], k], |i, j| {
+                let k = positions[i][j];
+                Some(if is_values { a.cells[i][k] } else { Rational::from(k as i64) })
+            })
+        }
+        Operator::TfReshape(..) | Operator::TfReshapeTo(..) => {
+            let a = a.unwrap();
+            let size = a.dims[0] * a.dims[1];
+            let shape: Vec<i64> = match inst.operator {
+                Operator::TfReshapeTo(_, shape) => shape.to_vec(),
+                _ => {
+                    let shape = row_vector(b.unwrap(), "shape")?;
+                    if shape.is_empty() || shape.len() > 2 {
+                        return invalid(format!("its shape has {} dims, but tensors have one or two", shape.len()));
+                    }
+                    shape.iter().map(|x| x.floor()).collect()
+                }
+            };
+            // 一维的形状[n]就是[1, n]；-1的那一维由元素个数推出来
+            let mut dims = if shape.len() == 1 { [1, shape[0]] } else { [shape[0], shape[1]] };
+            if shape.len() == 2 && shape[0] == -1 && shape[1] > 0 {
+                dims[0] = size as i64 / shape[1];
+            } else if shape.contains(&-1) && dims[0] > 0 {
+                dims[1] = size as i64 / dims[0];
+            }
+            if dims[0] < 1 || dims[1] < 1 || dims[0] * dims[1] != size as i64 {
+                return invalid(format!("its {} cells can't be reshaped to {:?}", size, shape));
+            }
+            let dims = sized([dims[0] as usize, dims[1] as usize])?;
+            let flat: Vec<_> = a.flat().collect();
+            Value::new(dims, |i, j| Some(flat[i * dims[1] + j]))
+        }
+        Operator::TfSqueeze(..) => {
+            let a = a.unwrap();
+            if a.dims[1] == 1 {
+                Value::new([1, a.dims[0]], |_, j| Some(a.cells[j][0]))
+            } else {
+                Some(a.clone())
+            }
+        }
+        Operator::TfTile(..) => {
+            let (a, multiples) = (a.unwrap(), row_vector(b.unwrap(), "multiples")?);
+            let multiples = match multiples[..] {
+                [m] if a.dims[0] == 1 => [1, m.floor()],
+                [m0, m1] => [m0.floor(), m1.floor()],
+                _ => {
+                    return invalid(format!(
+                        "its multiples have {} cells, which doesn't fit its input of shape {:?}",
+                        multiples.len(),
+                        a.dims
+                    ))
+                }
+            };
+            if multiples.iter().any(|&m| m < 1) {
+                return invalid(format!("its multiples are {:?}, but must be positive", multiples));
+            }
+            let dims = [0, 1].map(|k| a.dims[k].saturating_mul(multiples[k] as usize));
+            Value::new(sized(dims)?, |i, j| Some(a.cells[i % a.dims[0]][j % a.dims[1]]))
+        }
+        Operator::TfStack(_, _, axis) => {
+            let lines = [row_vector(a.unwrap(), "first operand")?, row_vector(b.unwrap(), "second operand")?];
+            if lines[0].len() != lines[1].len() {
+                return invalid(format!("its operands have {} and {} cells", lines[0].len(), lines[1].len()));
+            }
+            let n = lines[0].len();
+            let dims = if axis == 0 { [2, n] } else { [n, 2] };
+            Value::new(dims, |i, j| Some(if axis == 0 { lines[i][j] } else { lines[j][i] }))
+        }
+        Operator::TfReduce(_, reduction, axis, keepdims) => {
+            let a = a.unwrap();
+            if matches!(reduction, Reduction::Mean | Reduction::Variance) && a.dims.contains(&0) {
+                return invalid(format!("its operand has shape {:?}, so there is nothing to average", a.dims));
+            }
+            let column = |j: usize| -> Vec<_> { (0..a.dims[0]).map(|i| a.cells[i][j]).collect() };
+            match (axis, keepdims) {
+                (None, _) => Value::new([1, 1], |_, _| reduce(domain, reduction, &a.flat().collect::<Vec<_>>())),
+                (Some(0), _) => Value::new([1, a.dims[1]], |_, j| reduce(domain, reduction, &column(j))),
+                // 不保留维度时每行的结果排成一维
+                (_, false) => Value::new([1, a.dims[0]], |_, j| reduce(domain, reduction, &a.cells[j])),
+                (_, true) => Value::new([a.dims[0], 1], |i, _| reduce(domain, reduction, &a.cells[i])),
+            }
+        }
+        Operator::TfCumulative(_, reduction, axis, exclusive, reverse) => {
+            let a = a.unwrap();
+            Value::new(a.dims, |i, j| {
+                // 每个位置把扫过的那一段归约一下，一个都没扫到就是单位元
+                let k = [i, j][axis];
+                let positions = match (reverse, exclusive) {
+                    (false, false) => 0..k + 1,
+                    (false, true) => 0..k,
+                    (true, false) => k..a.dims[axis],
+                    (true, true) => k + 1..a.dims[axis],
+                };
+                let values: Vec<_> = positions.map(|m| if axis == 0 { a.cells[m][j] } else { a.cells[i][m] }).collect();
+                reduce(domain, reduction, &values)
+            })
+        }
+        Operator::TfSegment(_, _, reduction) | Operator::TfUnsortedSegment(_, _, _, reduction) => {
+            let data = a.unwrap();
+            let ids: Vec<_> = row_vector(b.unwrap(), "segment ids")?.iter().map(|x| x.floor()).collect();
+            // 一维的data按列分段，否则按行分段
+            let by_cols = data.dims[0] == 1;
+            if ids.len() != data.dims[by_cols as usize] {
+                return invalid(format!("it has {} segment ids for data of shape {:?}", ids.len(), data.dims));
+            }
+            let (num_segments, empty) = match operands.get(2) {
+                Some(num_segments) => {
+                    let num_segments = scalar(num_segments, "number of segments")?;
+                    if num_segments < 1 || ids.iter().any(|&id| id >= num_segments) {
+                        return invalid(format!("its segment ids {:?} don't fit {} segments", ids, num_segments));
+                    }
+                    (num_segments as usize, identity(domain, reduction))
+                }
+                None => {
+                    if ids.first().is_some_and(|&id| id < 0) || ids.windows(2).any(|w| w[0] > w[1]) {
+                        return invalid(format!("its segment ids {:?} aren't sorted and non-negative", ids));
+                    }
+                    let empty = Rational::from(if reduction == Reduction::Prod { 1 } else { 0 });
+                    (ids.last().map_or(0, |&id| id as usize + 1), empty)
+                }
+            };
+            let dims = if by_cols { [1, num_segments] } else { [num_segments, data.dims[1]] };
+            Value::new(sized(dims)?, |i, j| {
+                let segment = if by_cols { j } else { i } as i64;
+                let values: Vec<_> = (0..ids.len())
+                    .filter(|&k| ids[k] == segment)
+                    .map(|k| if by_cols { data.cells[0][k] } else { data.cells[k][j] })
+                    .collect();
+                if values.is_empty() {
+                    Some(empty)
+                } else {
+                    reduce(domain, reduction, &values)
+                }
+            })
+        }
+        Operator::TfScatterNd(..) => {
+            let (indices, updates, shape) = (a.unwrap(), b.unwrap(), operands[2]);
+            scatter(None, shape_operand(shape)?, indices, updates, true, true)?
+        }
+        Operator::TfTensorScatterNdUpdate(..) | Operator::TfTensorScatterNdAdd(..) => {
+            let (tensor, indices, updates) = (a.unwrap(), b.unwrap(), operands[2]);
+            let add = matches!(inst.operator, Operator::TfTensorScatterNdAdd(..));
+            scatter(Some(tensor), tensor.dims, indices, updates, add, true)?
+        }
+        Operator::TfSparseToDense(..) => {
+            // 稀疏张量的下标都是完整的坐标，不能写入一整行
+            let (indices, values, dense_shape) = (a.unwrap(), b.unwrap(), operands[2]);
+            scatter(None, shape_operand(dense_shape)?, indices, values, false, false)?
+        }
+        Operator::TfSlice(_, begin, size) => {
+            let a = a.unwrap();
+            if (0..2).any(|k| begin[k] < 0 || size[k] < 1 || begin[k] + size[k] > a.dims[k] as i64) {
+                return invalid(format!("it doesn't fit in its operand of shape {:?}", a.dims));
+            }
+            let [begin, size] = [begin, size].map(|pair| pair.map(|x| x as usize));
+            Value::new(size, |i, j| Some(a.cells[begin[0] + i][begin[1] + j]))
+        }
+        Operator::TfStridedSlice(_, begin, end, strides) => {
+            let a = a.unwrap();
+            let mut positions = [vec![], vec![]];
+            for k in 0..2 {
+                let dim = a.dims[k] as i64;
+                // 步长为负时从begin往回切，end为-1就一直切到下标0
+                let is_valid = match strides[k] {
+                    s if s > 0 => 0 <= begin[k] && begin[k] < end[k] && end[k] <= dim,
+                    s if s < 0 => -1 <= end[k] && end[k] < begin[k] && begin[k] < dim,
+                    _ => false,
+                };
+                if !is_valid || strides[k].abs() > dim.min(DIMS[k] as i64) {
+                    return invalid(format!("it doesn't fit in its operand of shape {:?}", a.dims));
+                }
+                let mut p = begin[k];
+                while (strides[k] > 0 && p < end[k]) || (strides[k] < 0 && p > end[k]) {
+                    positions[k].push(p as usize);
+                    p += strides[k];
+                }
+            }
+            let [rows, cols] = positions;
+            Value::new([rows.len(), cols.len()], |i, j| Some(a.cells[rows[i]][cols[j]]))
+        }
+        Operator::TfPad(_, paddings) => {
+            let a = a.unwrap();
+            if paddings.iter().flatten().any(|&p| p < 0) {
+                return invalid(format!("its paddings {:?} are negative", paddings));
+            }
+            let [before, after] = [0, 1].map(|n| paddings.map(|pair| pair[n] as usize));
+            let dims = [0, 1].map(|k| before[k].saturating_add(a.dims[k]).saturating_add(after[k]));
+            Value::new(sized(dims)?, |i, j| {
+                let cell = i.checked_sub(before[0]).zip(j.checked_sub(before[1]));
+                Some(cell.and_then(|(i, j)| a.cells.get(i)?.get(j).copied()).unwrap_or_else(|| Rational::from(0)))
+            })
+        }
+        Operator::Hole(_, None) => return invalid("its hole hasn't been filled".to_string()),
+        Operator::Var => unreachable!("inputs aren't applied"),
+    };
+
+    match value {
+        None => Err(Error::Unknown(format!("{}: a cell of its result doesn't fit in 64 bits", inst))),
+        Some(value) if value.dims[0] > DIMS[0] || value.dims[1] > DIMS[1] => invalid(format!(
+            "its result has shape {:?}, but values can be at most {:?}",
+            value.dims, DIMS
+        )),
+        Some(value) => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input_vecs, simplify_vecs, BitWidth, ProgramBuilder, Specification};
+
+    fn rows(value: &Vecs<Rational, i64>) -> Vec<Vec<Rational>> {
+        let [rows, cols] = value.dims;
+        value.vecs[..rows as usize].iter().map(|row| row[..cols as usize].to_vec()).collect()
+    }
+
+    fn ints(value: Vec<Vec<i64>>) -> Vec<Vec<Rational>> {
+        value.into_iter().map(|row| row.into_iter().map(Rational::from).collect()).collect()
+    }
+
+    fn run(program: &Program, domain: Domain) -> Vec<Vec<Rational>> {
+        rows(&evaluate(program, &program.inputs(), domain).unwrap())
+    }
+
+    // 用ProgramBuilder搭一个只有一条指令的程序
+    fn single(inputs: &[Vec<Vec<i64>>], op: impl FnOnce(&mut ProgramBuilder, &[Id]) -> Id) -> Program {
+        let mut builder = ProgramBuilder::new();
+        let ids: Vec<_> = inputs.iter().map(|input| builder.var(input.clone())).collect();
+        op(&mut builder, &ids);
+        builder.finish()
+    }
+
+    fn encoded(program: &Program, domain: Domain) -> Vec<Vec<Rational>> {
+        let context = term::Context::new();
+        let inputs: Vec<_> = program.inputs().iter().map(|input| input_vecs(&context, domain, input)).collect();
+        let value = simplify_vecs(&program.output(&context, &inputs, domain).unwrap()).unwrap();
+        let [rows, cols] = value.dims;
+        ints(value.vecs[..rows as usize].iter().map(|row| row[..cols as usize].to_vec()).collect())
+    }
+
+    fn is_rejected(program: &Program) -> bool {
+        matches!(evaluate(program, &program.inputs(), Domain::Int), Err(Error::InvalidProgram(_)))
+    }
+
+    #[test]
+    fn agrees_with_the_encodings() {
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![7, -7, 3], vec![-1, 0, 5]]);
+        let b = builder.var(vec![vec![2, -2, 2]]);
+        let c = builder.tf_concat0(a, b);
+        let d = builder.tf_floordiv(c, b);
+        let e = builder.tf_less(d, b);
+        let f = builder.tf_where3(e, d, c);
+        builder.tf_reverse(f, 0);
+        let program = builder.finish();
+
+        for domain in [Domain::Int, Domain::Real, Domain::BitVec(BitWidth::W8)] {
+            assert_eq!(run(&program, domain), encoded(&program, domain), "in {}", domain);
+        }
+        assert_eq!(run(&program, Domain::Int), ints(vec![vec![1, -2, 1], vec![-1, 0, 5], vec![7, -7, 1]]));
+    }
+
+    #[test]
+    fn agrees_with_the_encodings_on_shapes() {
+        let programs = [
+            single(&[vec![vec![1, 2, 3]]], |b, x| b.tf_expand_dims(x[0])),
+            single(&[vec![vec![0, 1, 0], vec![0, 0, 1]]], |b, x| b.tf_where1(x[0])),
+            single(&[vec![vec![2, 0, 5]], vec![vec![3]]], |b, x| b.tf_one_hot(x[0], x[1])),
+            single(&[vec![vec![1, 3, 0]]], |b, x| b.tf_sequence_mask(x[0])),
+            single(&[vec![vec![1, 2, 3], vec![4, 5, 6]]], |b, x| b.tf_transpose(x[0])),
+            single(&[vec![vec![1, 2], vec![3, 4]], vec![vec![5], vec![6]]], |b, x| b.tf_matmul(x[0], x[1])),
+            single(&[vec![vec![1, 2], vec![3, 4]], vec![vec![1, 0], vec![0, 1]]], |b, x| {
+                b.tf_boolean_mask(x[0], x[1])
+            }),
+            single(&[vec![vec![1, 2], vec![3, 4], vec![5, 6]], vec![vec![0, 1, 1]]], |b, x| {
+                b.tf_boolean_mask_(x[0], x[1])
+            }),
+            single(&[vec![vec![4, 0, 1, 1, 0], vec![4, 0, 0, 3, 4]]], |b, x| b.tf_bincount(x[0])),
+            single(&[vec![vec![2, 3]]], |b, x| b.tf_zeros(x[0])),
+        ];
+        for program in &programs {
+            for domain in [Domain::Int, Domain::Real, Domain::BitVec(BitWidth::W8)] {
+                // 浮点数只能在实数域里算
+                if domain != Domain::Real && program.dtypes().contains(&Some(DType::Float)) {
+                    continue;
+                }
+                assert_eq!(run(program, domain), encoded(program, domain), "{} in {}", program, domain);
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_follow_tf() {
+        let cases = [
+            (single(&[vec![vec![1, 5, 5], vec![7, 0, 2]]], |b, x| b.tf_argmax(x[0], 0)), vec![vec![1, 0, 0]]),
+            (single(&[vec![vec![1, 5, 5], vec![7, 0, 2]]], |b, x| b.tf_argmax(x[0], 1)), vec![vec![1, 0]]),
+            (single(&[vec![vec![1, 5, 5], vec![7, 0, 2]]], |b, x| b.tf_argmin(x[0], 1)), vec![vec![0, 1]]),
+            (
+                single(&[vec![vec![1, 2], vec![3, 4]], vec![vec![1, 0], vec![0, 1]]], |b, x| {
+                    b.tf_boolean_mask(x[0], x[1])
+                }),
+                vec![vec![1, 4]],
+            ),
+            (
+                single(&[vec![vec![1, 2], vec![3, 4], vec![5, 6]], vec![vec![0, 1, 1]]], |b, x| {
+                    b.tf_boolean_mask_(x[0], x[1])
+                }),
+                vec![vec![3, 4], vec![5, 6]],
+            ),
+            (
+                single(&[vec![vec![4, 0, 1, 1, 0, 4, 0, 0, 3, 4]]], |b, x| b.tf_bincount(x[0])),
+                vec![vec![4, 2, 0, 1, 3]],
+            ),
+            (
+                single(&[vec![vec![2, 0, 5]], vec![vec![3]]], |b, x| b.tf_one_hot(x[0], x[1])),
+                vec![vec![0, 0, 1], vec![1, 0, 0], vec![0, 0, 0]],
+            ),
+            (single(&[vec![vec![2]], vec![vec![5]]], |b, x| b.tf_range(x[0], x[1])), vec![vec![2, 3, 4]]),
+            (
+                single(&[vec![vec![1, 3, 0]]], |b, x| b.tf_sequence_mask(x[0])),
+                vec![vec![1, 0, 0], vec![1, 1, 1], vec![0, 0, 0]],
+            ),
+            (
+                single(&[vec![vec![1, 2], vec![3, 4]], vec![vec![5], vec![6]]], |b, x| b.tf_matmul(x[0], x[1])),
+                vec![vec![17], vec![39]],
+            ),
+            (single(&[vec![vec![1, 2, 3]]], |b, x| b.tf_expand_dims(x[0])), vec![vec![1], vec![2], vec![3]]),
+            (single(&[vec![vec![0, 1, 0], vec![0, 0, 1]]], |b, x| b.tf_where1(x[0])), vec![vec![0, 1], vec![1, 2]]),
+            (single(&[vec![vec![2]], vec![vec![3]]], |b, x| b.tf_eye(x[0], x[1])), vec![vec![1, 0, 0], vec![0, 1, 0]]),
+            (single(&[vec![vec![1, 2]]], |b, x| b.tf_ones(x[0])), vec![vec![1, 1]]),
+            (single(&[vec![vec![2, 1]]], |b, x| b.tf_zeros(x[0])), vec![vec![0], vec![0]]),
+            (single(&[vec![vec![1, 2]], vec![vec![7]]], |b, x| b.tf_fill(x[0], x[1])), vec![vec![7, 7]]),
+            (single(&[vec![vec![1, 2, 3]]], |b, x| b.tf_roll(x[0], 1, 1)), vec![vec![3, 1, 2]]),
+            (single(&[vec![vec![1, 2, 3]]], |b, x| b.tf_roll(x[0], -4, 1)), vec![vec![2, 3, 1]]),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Real), ints(expected.clone()), "{}", program);
+        }
+    }
+
+    #[test]
+    fn real_division_is_exact() {
+        let program = single(&[vec![vec![1, -3, 4]], vec![vec![2, 4, 0]]], |b, x| b.tf_divide(x[0], x[1]));
+        let half = Rational::new(1, 2).unwrap();
+        let quarter = Rational::new(-3, 4).unwrap();
+        assert_eq!(run(&program, Domain::Real), vec![vec![half, quarter, Rational::from(4)]]);
+        assert_eq!(quarter.to_string(), "-3/4");
+    }
+
+    #[test]
+    fn dividing_by_zero_gives_the_dividend() {
+        let program = single(&[vec![vec![7, -7, 0]], vec![vec![2, 0, 0]]], |b, x| b.tf_divide(x[0], x[1]));
+        assert_eq!(program.dtypes().pop().flatten(), Some(DType::Float));
+        let half = Rational::new(7, 2).unwrap();
+        assert_eq!(run(&program, Domain::Real), vec![vec![half, Rational::from(-7), Rational::from(0)]]);
+
+        // 其他域做除法会取整，算不出浮点数
+        assert!(is_rejected(&program));
+    }
+
+    #[test]
+    fn casts_change_the_dtype() {
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![3, -3, 0, 5]]);
+        let b = builder.var(vec![vec![2, 2, 1, 5]]);
+        let c = builder.tf_divide(a, b);
+        let d = builder.tf_cast(c, DType::Int);
+        let e = builder.tf_cast(c, DType::Bool);
+        let f = builder.tf_greater(a, b);
+        let g = builder.tf_cast(f, DType::Int);
+        let h = builder.tf_cast(e, DType::Int);
+        let i = builder.tf_concat0(d, h);
+        let program = builder.finish();
+        let dtypes = program.dtypes();
+        assert_eq!(
+            [c, d, e, f, g, i].map(|Id(x)| dtypes[x as usize]),
+            [DType::Float, DType::Int, DType::Bool, DType::Bool, DType::Int, DType::Int].map(Some),
+        );
+
+        // 转成整数时向0取整
+        assert_eq!(run(&program, Domain::Real), ints(vec![vec![1, -1, 0, 1], vec![1, 1, 0, 1]]));
+        let greater = single(&[vec![vec![1, 5, 3]], vec![vec![2, 2, 2]]], |b, x| {
+            let greater = b.tf_greater(x[0], x[1]);
+            b.tf_cast(greater, DType::Int)
+        });
+        assert_eq!(run(&greater, Domain::Int), ints(vec![vec![0, 1, 1]]));
+    }
+
+    #[test]
+    fn gathers_repeat_indices_and_reject_out_of_range_ones() {
+        let params = vec![vec![10, 20, 30], vec![40, 50, 60]];
+        let cases = [
+            (
+                single(&[params.clone(), vec![vec![1, 1, 0]]], |b, x| b.tf_gather(x[0], x[1], 0)),
+                vec![vec![40, 50, 60], vec![40, 50, 60], vec![10, 20, 30]],
+            ),
+            (
+                single(&[params.clone(), vec![vec![2, 0, 2, 2]]], |b, x| b.tf_gather(x[0], x[1], 1)),
+                vec![vec![30, 10, 30, 30], vec![60, 40, 60, 60]],
+            ),
+            (
+                single(&[params.clone(), vec![vec![1], vec![1]]], |b, x| b.tf_gather_nd(x[0], x[1], 0)),
+                vec![vec![40, 50, 60], vec![40, 50, 60]],
+            ),
+            (
+                single(&[params.clone(), vec![vec![1, 2], vec![0, 0], vec![1, 2]]], |b, x| {
+                    b.tf_gather_nd(x[0], x[1], 0)
+                }),
+                vec![vec![60, 10, 60]],
+            ),
+            (
+                single(&[params.clone(), vec![vec![2], vec![0]]], |b, x| b.tf_gather_nd(x[0], x[1], 1)),
+                vec![vec![30, 40]],
+            ),
+            (
+                single(&[params.clone(), vec![vec![2, 2, 0], vec![1, 0, 0]]], |b, x| {
+                    b.tf_take_along_axis(x[0], x[1], 1)
+                }),
+                vec![vec![30, 30, 10], vec![50, 40, 40]],
+            ),
+            (
+                single(&[params.clone(), vec![vec![1, 0, 1]]], |b, x| b.tf_take_along_axis(x[0], x[1], 0)),
+                vec![vec![40, 20, 60]],
+            ),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+
+        let rejected = [
+            single(&[params.clone(), vec![vec![0, 2]]], |b, x| b.tf_gather(x[0], x[1], 0)),
+            single(&[params.clone(), vec![vec![-1]]], |b, x| b.tf_gather(x[0], x[1], 1)),
+            single(&[params.clone(), vec![vec![1, 3]]], |b, x| b.tf_gather_nd(x[0], x[1], 0)),
+            single(&[params.clone(), vec![vec![0]]], |b, x| b.tf_gather_nd(x[0], x[1], 1)),
+            single(&[params.clone(), vec![vec![0, 3, 0]]], |b, x| b.tf_take_along_axis(x[0], x[1], 1)),
+        ];
+        for program in &rejected {
+            assert!(is_rejected(program), "{}", program);
+        }
+    }
+
+    #[test]
+    fn sorts_keep_ties_in_order() {
+        let a = || vec![vec![2, 7, 2, 5], vec![1, 1, 0, 1]];
+        let b = || vec![vec![3, 1], vec![3, 0], vec![1, 1]];
+        let k = |k| vec![vec![k]];
+        let cases = [
+            (single(&[a()], |b, x| b.tf_argsort(x[0], 1, true)), vec![vec![1, 3, 0, 2], vec![0, 1, 3, 2]]),
+            (single(&[a()], |b, x| b.tf_argsort(x[0], 1, false)), vec![vec![0, 2, 3, 1], vec![2, 0, 1, 3]]),
+            (single(&[a()], |b, x| b.tf_sort(x[0], 0, false)), vec![vec![1, 1, 0, 1], vec![2, 7, 2, 5]]),
+            (single(&[b()], |b, x| b.tf_argsort(x[0], 0, false)), vec![vec![2, 1], vec![0, 0], vec![1, 2]]),
+            (single(&[b()], |b, x| b.tf_sort(x[0], 0, true)), vec![vec![3, 1], vec![3, 1], vec![1, 0]]),
+            (single(&[a(), k(3)], |b, x| b.tf_top_k_values(x[0], x[1])), vec![vec![7, 5, 2], vec![1, 1, 1]]),
+            (single(&[a(), k(3)], |b, x| b.tf_top_k_indices(x[0], x[1])), vec![vec![1, 3, 0], vec![0, 1, 3]]),
+            (single(&[a(), k(0)], |b, x| b.tf_top_k_indices(x[0], x[1])), vec![vec![], vec![]]),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+
+        assert!(is_rejected(&single(&[a(), k(5)], |b, x| b.tf_top_k_values(x[0], x[1]))));
+        assert!(is_rejected(&single(&[a(), k(-1)], |b, x| b.tf_top_k_values(x[0], x[1]))));
+    }
+
+    #[test]
+    fn reshapes_tiles_and_stacks() {
+        let row = || vec![vec![1, 2, 3, 4, 5, 6]];
+        let matrix = || vec![vec![1, 2], vec![3, 4]];
+        let cases = [
+            (
+                single(&[row(), vec![vec![3, -1]]], |b, x| b.tf_reshape(x[0], x[1])),
+                vec![vec![1, 2], vec![3, 4], vec![5, 6]],
+            ),
+            (single(&[row(), vec![vec![-1, 3]]], |b, x| b.tf_reshape(x[0], x[1])), vec![vec![1, 2, 3], vec![4, 5, 6]]),
+            (single(&[matrix(), vec![vec![-1]]], |b, x| b.tf_reshape(x[0], x[1])), vec![vec![1, 2, 3, 4]]),
+            (single(&[row()], |b, x| b.tf_reshape_to(x[0], [3, 2])), vec![vec![1, 2], vec![3, 4], vec![5, 6]]),
+            (single(&[vec![vec![1], vec![2], vec![3]]], |b, x| b.tf_squeeze(x[0])), vec![vec![1, 2, 3]]),
+            (single(&[matrix()], |b, x| b.tf_squeeze(x[0])), matrix()),
+            (single(&[vec![vec![1, 2]], vec![vec![2]]], |b, x| b.tf_tile(x[0], x[1])), vec![vec![1, 2, 1, 2]]),
+            (
+                single(&[matrix(), vec![vec![2, 1]]], |b, x| b.tf_tile(x[0], x[1])),
+                vec![vec![1, 2], vec![3, 4], vec![1, 2], vec![3, 4]],
+            ),
+            (
+                single(&[vec![vec![5, 6]], vec![vec![3, 4]]], |b, x| b.tf_stack(x[0], x[1], 0)),
+                vec![vec![5, 6], vec![3, 4]],
+            ),
+            (
+                single(&[vec![vec![5, 6]], vec![vec![3, 4]]], |b, x| b.tf_stack(x[0], x[1], 1)),
+                vec![vec![5, 3], vec![6, 4]],
+            ),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+
+        let rejected = [
+            single(&[row(), vec![vec![4, -1]]], |b, x| b.tf_reshape(x[0], x[1])),
+            single(&[row(), vec![vec![-1, -1]]], |b, x| b.tf_reshape(x[0], x[1])),
+            single(&[row(), vec![vec![0, 6]]], |b, x| b.tf_reshape(x[0], x[1])),
+            single(&[matrix(), vec![vec![2]]], |b, x| b.tf_tile(x[0], x[1])),
+            single(&[matrix(), vec![vec![0, 1]]], |b, x| b.tf_tile(x[0], x[1])),
+            single(&[vec![vec![1, 2, 3]], vec![vec![1, 4]]], |b, x| b.tf_tile(x[0], x[1])),
+            single(&[vec![vec![5, 6]], vec![vec![3]]], |b, x| b.tf_stack(x[0], x[1], 0)),
+        ];
+        for program in &rejected {
+            assert!(is_rejected(program), "{}", program);
+        }
+    }
+
+    #[test]
+    fn segments_drop_negative_ids_and_fill_empty_segments() {
+        let data = || vec![vec![1, 2], vec![3, 4], vec![5, 6]];
+        let ids = || vec![vec![0, 0, 2]];
+        let row = || vec![vec![5, 1, 7, 2]];
+        let unsorted = || vec![vec![2, 0, 2, -1]];
+        let num_segments = || vec![vec![4]];
+        let cases = [
+            (
+                single(&[data(), ids()], |b, x| b.tf_segment_sum(x[0], x[1])),
+                vec![vec![4, 6], vec![0, 0], vec![5, 6]],
+            ),
+            (
+                single(&[data(), ids()], |b, x| b.tf_segment_max(x[0], x[1])),
+                vec![vec![3, 4], vec![0, 0], vec![5, 6]],
+            ),
+            (
+                single(&[data(), ids()], |b, x| b.tf_segment_prod(x[0], x[1])),
+                vec![vec![3, 8], vec![1, 1], vec![5, 6]],
+            ),
+            (
+                single(&[data(), ids()], |b, x| b.tf_segment_mean(x[0], x[1])),
+                vec![vec![2, 3], vec![0, 0], vec![5, 6]],
+            ),
+            (
+                single(&[vec![vec![1, 2, 3, 4]], vec![vec![0, 1, 1, 1]]], |b, x| b.tf_segment_sum(x[0], x[1])),
+                vec![vec![1, 9]],
+            ),
+            (
+                single(&[row(), unsorted(), num_segments()], |b, x| b.tf_unsorted_segment_sum(x[0], x[1], x[2])),
+                vec![vec![1, 0, 12, 0]],
+            ),
+            (
+                single(&[row(), unsorted(), num_segments()], |b, x| b.tf_unsorted_segment_max(x[0], x[1], x[2])),
+                vec![vec![1, i64::MIN, 7, i64::MIN]],
+            ),
+            (
+                single(&[row(), unsorted(), num_segments()], |b, x| b.tf_unsorted_segment_min(x[0], x[1], x[2])),
+                vec![vec![1, i64::MAX, 5, i64::MAX]],
+            ),
+            (
+                single(&[data(), vec![vec![1, -1, 1]], vec![vec![2]]], |b, x| {
+                    b.tf_unsorted_segment_prod(x[0], x[1], x[2])
+                }),
+                vec![vec![1, 1], vec![5, 12]],
+            ),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+
+        let max = single(&[row(), unsorted(), num_segments()], |b, x| b.tf_unsorted_segment_max(x[0], x[1], x[2]));
+        assert_eq!(run(&max, Domain::BitVec(BitWidth::W8)), ints(vec![vec![1, -128, 7, -128]]));
+        let mean = single(&[vec![vec![1, 2, 3, 4]], vec![vec![0, 0, 1, 1]], vec![vec![2]]], |b, x| {
+            b.tf_unsorted_segment_mean(x[0], x[1], x[2])
+        });
+        let halves = [3, 7].map(|n| Rational::new(n, 2).unwrap()).to_vec();
+        assert_eq!(run(&mean, Domain::Real), vec![halves]);
+
+        let rejected = [
+            single(&[data(), vec![vec![1, 0, 2]]], |b, x| b.tf_segment_sum(x[0], x[1])),
+            single(&[data(), vec![vec![-1, 0, 0]]], |b, x| b.tf_segment_sum(x[0], x[1])),
+            single(&[data(), vec![vec![0, 0]]], |b, x| b.tf_segment_sum(x[0], x[1])),
+            single(&[data(), vec![vec![0, 4, 0]], vec![vec![4]]], |b, x| b.tf_unsorted_segment_sum(x[0], x[1], x[2])),
+            single(&[data(), vec![vec![-1, -1, -1]], vec![vec![0]]], |b, x| {
+                b.tf_unsorted_segment_sum(x[0], x[1], x[2])
+            }),
+        ];
+        for program in &rejected {
+            assert!(is_rejected(program), "{}", program);
+        }
+    }
+
+    #[test]
+    fn scatters_add_or_overwrite_repeated_indices() {
+        let tensor = || vec![vec![1, 2], vec![3, 4]];
+        let twice = || vec![vec![0, 1], vec![0, 1]];
+        let cases = [
+            (
+                single(&[vec![vec![1], vec![3], vec![1]], vec![vec![5, 6, 7]], vec![vec![4]]], |b, x| {
+                    b.tf_scatter_nd(x[0], x[1], x[2])
+                }),
+                vec![vec![0, 12, 0, 6]],
+            ),
+            (
+                single(&[vec![vec![1], vec![1]], vec![vec![1, 2], vec![3, 4]], vec![vec![3, 2]]], |b, x| {
+                    b.tf_scatter_nd(x[0], x[1], x[2])
+                }),
+                vec![vec![0, 0], vec![4, 6], vec![0, 0]],
+            ),
+            (
+                single(
+                    &[vec![vec![0, 1], vec![2, 0], vec![0, 1]], vec![vec![1, 2, 3]], vec![vec![3, 2]]],
+                    |b, x| b.tf_scatter_nd(x[0], x[1], x[2]),
+                ),
+                vec![vec![0, 4], vec![0, 0], vec![2, 0]],
+            ),
+            (
+                single(&[tensor(), twice(), vec![vec![10, 20]]], |b, x| {
+                    b.tf_tensor_scatter_nd_update(x[0], x[1], x[2])
+                }),
+                vec![vec![1, 20], vec![3, 4]],
+            ),
+            (
+                single(&[tensor(), twice(), vec![vec![10, 20]]], |b, x| b.tf_tensor_scatter_nd_add(x[0], x[1], x[2])),
+                vec![vec![1, 32], vec![3, 4]],
+            ),
+            (
+                single(&[tensor(), vec![vec![1], vec![1]], vec![vec![7, 8], vec![9, 9]]], |b, x| {
+                    b.tf_tensor_scatter_nd_update(x[0], x[1], x[2])
+                }),
+                vec![vec![1, 2], vec![9, 9]],
+            ),
+            (
+                single(&[vec![vec![1, 2, 3]], vec![vec![2], vec![0]], vec![vec![5, 6]]], |b, x| {
+                    b.tf_tensor_scatter_nd_update(x[0], x[1], x[2])
+                }),
+                vec![vec![6, 2, 5]],
+            ),
+            (
+                single(&[vec![vec![0, 1], vec![1, 2], vec![0, 1]], vec![vec![4, 5, 6]], vec![vec![2, 3]]], |b, x| {
+                    b.tf_sparse_to_dense(x[0], x[1], x[2])
+                }),
+                vec![vec![0, 6, 0], vec![0, 0, 5]],
+            ),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+
+        let rejected = [
+            single(&[vec![vec![4]], vec![vec![1]], vec![vec![4]]], |b, x| b.tf_scatter_nd(x[0], x[1], x[2])),
+            single(&[vec![vec![-1]], vec![vec![1]], vec![vec![4]]], |b, x| b.tf_scatter_nd(x[0], x[1], x[2])),
+            single(&[vec![vec![0]], vec![vec![1]], vec![vec![0, 3]]], |b, x| b.tf_scatter_nd(x[0], x[1], x[2])),
+            single(&[tensor(), twice(), vec![vec![10]]], |b, x| b.tf_tensor_scatter_nd_add(x[0], x[1], x[2])),
+            single(&[tensor(), vec![vec![0, 2]], vec![vec![10]]], |b, x| b.tf_tensor_scatter_nd_add(x[0], x[1], x[2])),
+            single(&[vec![vec![1]], vec![vec![4, 4, 4]], vec![vec![2, 3]]], |b, x| {
+                b.tf_sparse_to_dense(x[0], x[1], x[2])
+            }),
+        ];
+        for program in &rejected {
+            assert!(is_rejected(program), "{}", program);
+        }
+    }
+
+    #[test]
+    fn comparisons_and_logic_give_booleans() {
+        let a = || vec![vec![1, 3, 6], vec![3, -3, 0]];
+        let b = || vec![vec![3]];
+        let p = || vec![vec![0, 0, 1, 1]];
+        let q = || vec![vec![0, 1, 0, 1]];
+        let cases = [
+            (single(&[a(), b()], |b, x| b.tf_less(x[0], x[1])), vec![vec![1, 0, 0], vec![0, 1, 1]]),
+            (single(&[a(), b()], |b, x| b.tf_less_equal(x[0], x[1])), vec![vec![1, 1, 0], vec![1, 1, 1]]),
+            (single(&[a(), b()], |b, x| b.tf_greater(x[0], x[1])), vec![vec![0, 0, 1], vec![0, 0, 0]]),
+            (single(&[a(), b()], |b, x| b.tf_greater_equal(x[0], x[1])), vec![vec![0, 1, 1], vec![1, 0, 0]]),
+            (single(&[a(), b()], |b, x| b.tf_equal(x[0], x[1])), vec![vec![0, 1, 0], vec![1, 0, 0]]),
+            (single(&[a(), b()], |b, x| b.tf_not_equal(x[0], x[1])), vec![vec![1, 0, 1], vec![0, 1, 1]]),
+            (single(&[p(), q()], |b, x| b.tf_logical_and(x[0], x[1])), vec![vec![0, 0, 0, 1]]),
+            (single(&[p(), q()], |b, x| b.tf_logical_or(x[0], x[1])), vec![vec![0, 1, 1, 1]]),
+            (single(&[p(), q()], |b, x| b.tf_logical_xor(x[0], x[1])), vec![vec![0, 1, 1, 0]]),
+            (single(&[p()], |b, x| b.tf_logical_not(x[0])), vec![vec![1, 1, 0, 0]]),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+
+        // 比较的结果是布尔值，可以直接当作逻辑运算的操作数
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, 4, 6, 9]]);
+        let b = builder.var(vec![vec![3]]);
+        let c = builder.var(vec![vec![7]]);
+        let d = builder.tf_greater_equal(a, b);
+        let e = builder.tf_less(a, c);
+        builder.tf_logical_and(d, e);
+        let program = builder.finish();
+        assert_eq!(program.dtypes().pop().flatten(), Some(DType::Bool));
+        assert_eq!(run(&program, Domain::Int), ints(vec![vec![0, 1, 1, 0]]));
+    }
+
+    #[test]
+    fn elementwise_math_rounds_down_and_checks_its_operands() {
+        let a = || vec![vec![-7, -3, 5, 8]];
+        let cases = [
+            (single(&[a(), vec![vec![3]]], |b, x| b.tf_floordiv(x[0], x[1])), vec![vec![-3, -1, 1, 2]]),
+            (single(&[a(), vec![vec![3]]], |b, x| b.tf_floormod(x[0], x[1])), vec![vec![2, 0, 2, 2]]),
+            (single(&[a(), vec![vec![-3]]], |b, x| b.tf_floordiv(x[0], x[1])), vec![vec![2, 1, -2, -3]]),
+            (single(&[a(), vec![vec![-3]]], |b, x| b.tf_floormod(x[0], x[1])), vec![vec![-1, 0, -1, -1]]),
+            (single(&[vec![vec![-7, 0, 5]]], |b, x| b.tf_abs(x[0])), vec![vec![7, 0, 5]]),
+            (single(&[vec![vec![-7, 0, 5]]], |b, x| b.tf_negative(x[0])), vec![vec![7, 0, -5]]),
+            (single(&[vec![vec![-7, 0, 5]]], |b, x| b.tf_sign(x[0])), vec![vec![-1, 0, 1]]),
+            (single(&[vec![vec![2, -3, 0]], vec![vec![0, 3, 0]]], |b, x| b.tf_pow(x[0], x[1])), vec![vec![1, -27, 1]]),
+            (single(&[vec![vec![2, -3, 0]], vec![vec![4]]], |b, x| b.tf_pow(x[0], x[1])), vec![vec![16, 81, 0]]),
+            (
+                single(&[vec![vec![-5, 2, 9]], vec![vec![0]], vec![vec![4]]], |b, x| {
+                    b.tf_clip_by_value(x[0], x[1], x[2])
+                }),
+                vec![vec![0, 2, 4]],
+            ),
+            (
+                single(&[vec![vec![-5, 2, 9]], vec![vec![3]], vec![vec![3]]], |b, x| {
+                    b.tf_clip_by_value(x[0], x[1], x[2])
+                }),
+                vec![vec![3, 3, 3]],
+            ),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+
+        let square = single(&[vec![vec![100, 3]], vec![vec![2]]], |b, x| b.tf_pow(x[0], x[1]));
+        let domain = Domain::BitVec(BitWidth::W8);
+        assert_eq!(run(&square, domain), ints(vec![vec![16, 9]]));
+        assert_eq!(run(&square, domain), encoded(&square, domain));
+
+        let rejected = [
+            single(&[a(), vec![vec![5]]], |b, x| b.tf_pow(x[0], x[1])),
+            single(&[a(), vec![vec![-1]]], |b, x| b.tf_pow(x[0], x[1])),
+            single(&[a(), vec![vec![4]], vec![vec![3, 3, 3, 2]]], |b, x| b.tf_clip_by_value(x[0], x[1], x[2])),
+            single(&[a(), vec![vec![3, 0, 3, 3]]], |b, x| b.tf_floormod(x[0], x[1])),
+        ];
+        for program in &rejected {
+            assert!(is_rejected(program), "{}", program);
+        }
+    }
+
+    #[test]
+    fn reductions_follow_their_axis_and_keepdims() {
+        let a = || vec![vec![2, 4, 1], vec![6, 8, -1]];
+        let mask = || vec![vec![1, 0, 0], vec![1, 1, 1]];
+        let reduce = |input: Vec<Vec<i64>>, reduction, axis, keepdims| {
+            single(&[input], move |b, x| b.tf_reduce(x[0], reduction, axis, keepdims))
+        };
+        let cases = [
+            (reduce(a(), Reduction::Sum, None, false), vec![vec![20]]),
+            (reduce(a(), Reduction::Sum, Some(0), false), vec![vec![8, 12, 0]]),
+            (reduce(a(), Reduction::Sum, Some(0), true), vec![vec![8, 12, 0]]),
+            (reduce(a(), Reduction::Sum, Some(1), false), vec![vec![7, 13]]),
+            (reduce(a(), Reduction::Sum, Some(1), true), vec![vec![7], vec![13]]),
+            (reduce(a(), Reduction::Prod, Some(1), false), vec![vec![8, -48]]),
+            (reduce(a(), Reduction::Max, Some(0), false), vec![vec![6, 8, 1]]),
+            (reduce(a(), Reduction::Min, Some(1), true), vec![vec![1], vec![-1]]),
+            (reduce(a(), Reduction::Mean, None, false), vec![vec![3]]),
+            (reduce(a(), Reduction::Count, Some(1), false), vec![vec![3, 3]]),
+            (reduce(vec![vec![0, 2, 0]], Reduction::Count, None, false), vec![vec![1]]),
+            (reduce(mask(), Reduction::Any, Some(1), false), vec![vec![1, 1]]),
+            (reduce(mask(), Reduction::All, Some(1), false), vec![vec![0, 1]]),
+            (reduce(mask(), Reduction::All, Some(0), false), vec![vec![1, 0, 0]]),
+            (single(&[vec![vec![3, 1, 1], vec![0, 5, 0]]], |b, x| b.tf_argmin(x[0], 1)), vec![vec![1, 0]]),
+            (single(&[vec![vec![3, 1, 1], vec![0, 5, 0]]], |b, x| b.tf_argmax(x[0], 0)), vec![vec![0, 1, 0]]),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+
+        let mean = reduce(a(), Reduction::Mean, None, false);
+        assert_eq!(run(&mean, Domain::Real), vec![vec![Rational::new(10, 3).unwrap()]]);
+        let variance = reduce(vec![vec![1, 2, 3, 4]], Reduction::Variance, None, false);
+        assert_eq!(run(&variance, Domain::Real), vec![vec![Rational::new(5, 4).unwrap()]]);
+
+        // 空的张量归约成单位元
+        let empty = |reduction, axis| {
+            let mut builder = ProgramBuilder::new();
+            let a = builder.var(vec![vec![1, 2]]);
+            let b = builder.var(vec![vec![0, 0]]);
+            let c = builder.tf_boolean_mask(a, b);
+            builder.tf_reduce(c, reduction, axis, true);
+            builder.finish()
+        };
+        let cases = [
+            (empty(Reduction::Max, None), vec![vec![i64::MIN]]),
+            (empty(Reduction::Min, Some(1)), vec![vec![i64::MAX]]),
+            (empty(Reduction::Sum, None), vec![vec![0]]),
+            (empty(Reduction::Prod, Some(1)), vec![vec![1]]),
+            (empty(Reduction::All, None), vec![vec![1]]),
+            (empty(Reduction::Max, Some(0)), vec![vec![]]),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+        assert!(is_rejected(&empty(Reduction::Mean, None)));
+        assert!(is_rejected(&empty(Reduction::Variance, Some(1))));
+    }
+
+    #[test]
+    fn cumulative_ops_scan_exclusively_and_in_reverse() {
+        let row = || vec![vec![1, 2, 3]];
+        let cases = [
+            (single(&[row()], |b, x| b.tf_cumsum(x[0], 1, false, false)), vec![vec![1, 3, 6]]),
+            (single(&[row()], |b, x| b.tf_cumsum(x[0], 1, true, false)), vec![vec![0, 1, 3]]),
+            (single(&[row()], |b, x| b.tf_cumsum(x[0], 1, false, true)), vec![vec![6, 5, 3]]),
+            (single(&[row()], |b, x| b.tf_cumsum(x[0], 1, true, true)), vec![vec![5, 3, 0]]),
+            (
+                single(&[vec![vec![1, 2], vec![3, 4]]], |b, x| b.tf_cumsum(x[0], 0, false, false)),
+                vec![vec![1, 2], vec![4, 6]],
+            ),
+            (single(&[row()], |b, x| b.tf_cumprod(x[0], 1, true, false)), vec![vec![1, 1, 2]]),
+            (single(&[vec![vec![2, 1, 3]]], |b, x| b.tf_cummax(x[0], 1, false, false)), vec![vec![2, 2, 3]]),
+            (single(&[vec![vec![2, 1, 3]]], |b, x| b.tf_cummin(x[0], 1, true, true)), vec![vec![1, 3, i64::MAX]]),
+            (
+                single(&[vec![vec![2], vec![1]]], |b, x| b.tf_cummax(x[0], 0, true, false)),
+                vec![vec![i64::MIN], vec![2]],
+            ),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+    }
+
+    #[test]
+    fn slices_pads_rolls_and_reversals() {
+        let a = || vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let cases = [
+            (single(&[a()], |b, x| b.tf_slice(x[0], [0, 1], [2, 2])), vec![vec![2, 3], vec![5, 6]]),
+            (single(&[a()], |b, x| b.tf_strided_slice(x[0], [0, 0], [2, 3], [1, 2])), vec![vec![1, 3], vec![4, 6]]),
+            (
+                single(&[a()], |b, x| b.tf_strided_slice(x[0], [1, 2], [-1, -1], [-1, -1])),
+                vec![vec![6, 5, 4], vec![3, 2, 1]],
+            ),
+            (single(&[a()], |b, x| b.tf_strided_slice(x[0], [0, 2], [2, 0], [1, -2])), vec![vec![3], vec![6]]),
+            (single(&[a()], |b, x| b.tf_strided_slice(x[0], [1, 0], [0, 3], [-1, 1])), vec![vec![4, 5, 6]]),
+            (
+                single(&[vec![vec![1, 2, 3, 4, 5]]], |b, x| b.tf_strided_slice(x[0], [0, 4], [1, -1], [1, -2])),
+                vec![vec![5, 3, 1]],
+            ),
+            (
+                single(&[a()], |b, x| b.tf_pad(x[0], [[1, 0], [0, 2]])),
+                vec![vec![0, 0, 0, 0, 0], vec![1, 2, 3, 0, 0], vec![4, 5, 6, 0, 0]],
+            ),
+            (single(&[a()], |b, x| b.tf_roll(x[0], 1, 0)), vec![vec![4, 5, 6], vec![1, 2, 3]]),
+            (single(&[a()], |b, x| b.tf_roll(x[0], -1, 1)), vec![vec![2, 3, 1], vec![5, 6, 4]]),
+            (single(&[a()], |b, x| b.tf_reverse(x[0], 1)), vec![vec![3, 2, 1], vec![6, 5, 4]]),
+        ];
+        for (program, expected) in &cases {
+            assert_eq!(run(program, Domain::Int), ints(expected.clone()), "{}", program);
+            assert_eq!(run(program, Domain::Int), encoded(program, Domain::Int), "{}", program);
+        }
+
+        let rejected = [
+            single(&[a()], |b, x| b.tf_slice(x[0], [0, 2], [1, 2])),
+            single(&[a()], |b, x| b.tf_slice(x[0], [0, 0], [0, 2])),
+            single(&[a()], |b, x| b.tf_strided_slice(x[0], [0, 0], [2, 3], [1, 0])),
+            single(&[a()], |b, x| b.tf_strided_slice(x[0], [2, 0], [-1, 3], [-1, 1])),
+            single(&[a()], |b, x| b.tf_strided_slice(x[0], [1, 0], [-2, 3], [-1, 1])),
+            single(&[a()], |b, x| b.tf_strided_slice(x[0], [0, 0], [2, 3], [3, 1])),
+            single(&[a()], |b, x| b.tf_pad(x[0], [[0, -1], [0, 0]])),
+            single(&[a()], |b, x| b.tf_pad(x[0], [[0, 0], [0, 8]])),
+        ];
+        for program in &rejected {
+            assert!(is_rejected(program), "{}", program);
+        }
+    }
+
+    #[test]
+    fn bit_vectors_wrap_around() {
+        let program = single(&[vec![vec![100, -128]]], |b, x| b.tf_add(x[0], x[0]));
+        assert_eq!(run(&program, Domain::BitVec(BitWidth::W8)), ints(vec![vec![-56, 0]]));
+    }
+
+    #[test]
+    fn rejects_invalid_programs() {
+        let program = single(&[vec![vec![1, 2]], vec![vec![0, 1]]], |b, x| b.tf_floordiv(x[0], x[1]));
+        assert!(matches!(evaluate(&program, &program.inputs(), Domain::Int), Err(Error::InvalidProgram(_))));
+
+        let program = single(&[vec![vec![1, 2], vec![3, 4]], vec![vec![1, 2, 3]]], |b, x| b.tf_matmul(x[0], x[1]));
+        assert!(matches!(evaluate(&program, &program.inputs(), Domain::Int), Err(Error::InvalidProgram(_))));
+
+        let program = single(&[vec![vec![1; 3]; 3]], |b, x| b.tf_sequence_mask(x[0]));
+        assert!(matches!(evaluate(&program, &program.inputs(), Domain::Int), Err(Error::InvalidProgram(_))));
+
+        let mut builder = ProgramBuilder::new();
+        builder.hole(Some([1, 2]));
+        let program = builder.finish();
+        assert!(matches!(evaluate(&program, &program.inputs(), Domain::Int), Err(Error::InvalidProgram(_))));
+
+        let program = ProgramBuilder::new().finish();
+        assert!(matches!(evaluate(&program, &[], Domain::Int), Err(Error::InvalidProgram(_))));
+    }
+}
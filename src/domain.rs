@@ -0,0 +1,344 @@
+//! The sorts that tensor cells are encoded with.
+//!
+//! Shapes and indices are always `Int`s, but the cells of a tensor can be
+//! encoded in different sorts. `Int` cells are unbounded integers, where
+//! division rounds like SMT-LIB's `div`. `Real` cells are exact rationals, so
+//! that components like `TfDivide` and mean reductions divide for real, at the
+//! cost of a harder theory for the solver. `BitVec` cells are two's complement
+//! integers of a fixed width that wrap around on overflow and divide by
+//! truncating, which is how TF's `int32` and `int64` tensors behave.
+//!
+//! Components don't need to know which one they are working with: they build
+//! their cells through the methods here, which dispatch on the domain. The
+//! cells themselves are `Dynamic`s of the domain's sort.
+
+use crate::term::{self, Ast, Bool, Dynamic, Int, Real, Sort, SortKind, BV};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Domain {
+    #[default]
+    Int,
+    Real,
+    /// Bit-vectors of the given width.
+    BitVec(BitWidth),
+}
+
+/// The widths of `Domain::BitVec` cells, which are those of TF's integer
+/// dtypes. Anything wider wouldn't fit the `i64`s that cells are read back as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BitWidth {
+    W8,
+    W16,
+    W32,
+    W64,
+}
+
+impl BitWidth {
+    pub fn bits(self) -> u32 {
+        match self {
+            BitWidth::W8 => 8,
+            BitWidth::W16 => 16,
+            BitWidth::W32 => 32,
+            BitWidth::W64 => 64,
+        }
+    }
+}
+
+impl Domain {
+    /// The sort of a cell.
+    pub(crate) fn sort(self, context: &term::Context) -> Sort {
+        match self {
+            Domain::Int => Sort::int(context),
+            Domain::Real => Sort::real(context),
+            Domain::BitVec(width) => Sort::bitvector(context, width.bits()),
+        }
+    }
+
+    pub(crate) fn fresh_const<'a>(self, context: &'a term::Context, prefix: &str) -> Dynamic<'a> {
+        match self {
+            Domain::Int => Int::fresh_const(context, prefix).into(),
+            Domain::Real => Real::fresh_const(context, prefix).into(),
+            Domain::BitVec(width) => BV::fresh_const(context, prefix, width.bits()).into(),
+        }
+    }
+
+    /// A cell constant named exactly `name`.
+    pub(crate) fn new_const<'a>(self, context: &'a term::Context, name: String) -> Dynamic<'a> {
+        match self {
+            Domain::Int => Int::new_const(context, name).into(),
+            Domain::Real => Real::new_const(context, name).into(),
+            Domain::BitVec(width) => BV::new_const(context, name, width.bits()).into(),
+        }
+    }
+
+    /// The cell holding `value`, wrapped around if it doesn't fit.
+    pub(crate) fn constant(self, context: &term::Context, value: i64) -> Dynamic<'_> {
+        match self {
+            Domain::BitVec(width) => BV::from_i64(context, value, width.bits()).into(),
+            _ => self.cell_of(&Int::from_i64(context, value)),
+        }
+    }
+
+    /// The cell holding the integer `index`.
+    pub(crate) fn cell_of<'a>(self, index: &Int<'a>) -> Dynamic<'a> {
+        match self {
+            Domain::Int => index.clone().into(),
+            Domain::Real => Real::from_int(index).into(),
+            Domain::BitVec(width) => BV::from_int(index, width.bits()).into(),
+        }
+    }
+
+    /// Use a cell as an index or a dimension, rounding it down if it isn't
+    /// an integer.
+    pub(crate) fn to_index<'a>(self, cell: &Dynamic<'a>) -> Int<'a> {
+        match self {
+            Domain::Int => cell.as_int().unwrap(),
+            Domain::Real => cell.as_real().unwrap().to_int(),
+            Domain::BitVec(_) => cell.as_bv().unwrap().to_int(true),
+        }
+    }
+
+    /// Round a cell toward zero, the way casting to an integer does.
+    pub(crate) fn trunc<'a>(self, context: &'a term::Context, cell: &Dynamic<'a>) -> Dynamic<'a> {
+        match self {
+            Domain::Int | Domain::BitVec(_) => cell.clone(),
+            Domain::Real => {
+                let zero = self.constant(context, 0);
+                let down = self.cell_of(&self.to_index(cell));
+                let negated = self.sub(context, &[&zero, cell]);
+                let up = self.sub(context, &[&zero, &self.cell_of(&self.to_index(&negated))]);
+                self.lt(cell, &zero).ite(&up, &down)
+            }
+        }
+    }
+
+    pub(crate) fn add<'a>(self, context: &'a term::Context, values: &[&Dynamic<'a>]) -> Dynamic<'a> {
+        match self {
+            Domain::Int => Int::add(context, &refs(&ints(values))).into(),
+            Domain::Real => Real::add(context, &refs(&reals(values))).into(),
+            Domain::BitVec(_) => fold_bvs(values, BV::bvadd),
+        }
+    }
+
+    pub(crate) fn sub<'a>(self, context: &'a term::Context, values: &[&Dynamic<'a>]) -> Dynamic<'a> {
+        match self {
+            Domain::Int => Int::sub(context, &refs(&ints(values))).into(),
+            Domain::Real => Real::sub(context, &refs(&reals(values))).into(),
+            Domain::BitVec(_) => fold_bvs(values, BV::bvsub),
+        }
+    }
+
+    pub(crate) fn mul<'a>(self, context: &'a term::Context, values: &[&Dynamic<'a>]) -> Dynamic<'a> {
+        match self {
+            Domain::Int => Int::mul(context, &refs(&ints(values))).into(),
+            Domain::Real => Real::mul(context, &refs(&reals(values))).into(),
+            Domain::BitVec(_) => fold_bvs(values, BV::bvmul),
+        }
+    }
+
+    /// `a / b`. Integer division for `Int`, true division for `Real`, and
+    /// division rounding toward zero for `BitVec`.
+    pub(crate) fn div<'a>(self, a: &Dynamic<'a>, b: &Dynamic<'a>) -> Dynamic<'a> {
+        match self {
+            Domain::Int => a.as_int().unwrap().div(&b.as_int().unwrap()).into(),
+            Domain::Real => a.as_real().unwrap().div(&b.as_real().unwrap()).into(),
+            Domain::BitVec(_) => a.as_bv().unwrap().bvsdiv(&b.as_bv().unwrap()).into(),
+        }
+    }
+
+    /// `a / b` rounded toward negative infinity, like `tf.math.floordiv`.
+    pub(crate) fn floor_div<'a>(self, context: &'a term::Context, a: &Dynamic<'a>, b: &Dynamic<'a>) -> Dynamic<'a> {
+        match self {
+            Domain::Int => {
+                // Z3的div是欧几里得除法，余数总是非负，所以除数为负且除不尽时要减一
+                let (a, b) = (a.as_int().unwrap(), b.as_int().unwrap());
+                let zero = Int::from_i64(context, 0);
+                let is_off = Bool::and(context, &[&b.lt(&zero), &a.modulo(&b)._eq(&zero).not()]);
+                is_off.ite(&Int::sub(context, &[&a.div(&b), &Int::from_i64(context, 1)]), &a.div(&b)).into()
+            }
+            Domain::Real => Real::from_int(&a.as_real().unwrap().div(&b.as_real().unwrap()).to_int()).into(),
+            Domain::BitVec(_) => {
+                let (a, b) = (a.as_bv().unwrap(), b.as_bv().unwrap());
+                a.bvsub(&a.bvsmod(&b)).bvsdiv(&b).into()
+            }
+        }
+    }
+
+    /// What `floor_div` leaves over, which has the sign of `b`, like
+    /// `tf.math.floormod`.
+    pub(crate) fn floor_mod<'a>(self, context: &'a term::Context, a: &Dynamic<'a>, b: &Dynamic<'a>) -> Dynamic<'a> {
+        match self {
+            Domain::BitVec(_) => a.as_bv().unwrap().bvsmod(&b.as_bv().unwrap()).into(),
+            _ => self.sub(context, &[a, &self.mul(context, &[b, &self.floor_div(context, a, b)])]),
+        }
+    }
+
+    pub(crate) fn lt<'a>(self, a: &Dynamic<'a>, b: &Dynamic<'a>) -> Bool<'a> {
+        match self {
+            Domain::Int => a.as_int().unwrap().lt(&b.as_int().unwrap()),
+            Domain::Real => a.as_real().unwrap().lt(&b.as_real().unwrap()),
+            Domain::BitVec(_) => a.as_bv().unwrap().bvslt(&b.as_bv().unwrap()),
+        }
+    }
+
+    pub(crate) fn gt<'a>(self, a: &Dynamic<'a>, b: &Dynamic<'a>) -> Bool<'a> {
+        self.lt(b, a)
+    }
+
+    /// The smallest value we let a cell hold. It is what an empty unsorted
+    /// segment's max comes out as.
+    pub(crate) fn min_value(self, context: &term::Context) -> Dynamic<'_> {
+        match self {
+            Domain::BitVec(width) => self.constant(context, i64::MIN >> (64 - width.bits())),
+            _ => self.constant(context, i64::MIN),
+        }
+    }
+
+    /// The largest value we let a cell hold.
+    pub(crate) fn max_value(self, context: &term::Context) -> Dynamic<'_> {
+        match self {
+            Domain::BitVec(width) => self.constant(context, i64::MAX >> (64 - width.bits())),
+            _ => self.constant(context, i64::MAX),
+        }
+    }
+}
+
+impl Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Domain::Int => write!(f, "int"),
+            Domain::Real => write!(f, "real"),
+            Domain::BitVec(width) => write!(f, "bv{}", width.bits()),
+        }
+    }
+}
+
+impl FromStr for Domain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Domain, String> {
+        match s {
+            "int" => Ok(Domain::Int),
+            "real" => Ok(Domain::Real),
+            "bv8" => Ok(Domain::BitVec(BitWidth::W8)),
+            "bv16" => Ok(Domain::BitVec(BitWidth::W16)),
+            "bv32" => Ok(Domain::BitVec(BitWidth::W32)),
+            "bv64" => Ok(Domain::BitVec(BitWidth::W64)),
+            _ => Err(format!(
+                "unknown domain `{}`; expected `int`, `real`, `bv8`, `bv16`, `bv32` or `bv64`",
+                s
+            )),
+        }
+    }
+}
+
+fn ints<'a>(values: &[&Dynamic<'a>]) -> Vec<Int<'a>> {
+    values.iter().map(|v| v.as_int().unwrap()).collect()
+}
+
+fn reals<'a>(values: &[&Dynamic<'a>]) -> Vec<Real<'a>> {
+    values.iter().map(|v| v.as_real().unwrap()).collect()
+}
+
+fn fold_bvs<'a>(values: &[&Dynamic<'a>], op: fn(&BV<'a>, &BV<'a>) -> BV<'a>) -> Dynamic<'a> {
+    let mut bvs = values.iter().map(|v| v.as_bv().unwrap());
+    let first = bvs.next().unwrap();
+    bvs.fold(first, |acc, bv| op(&acc, &bv)).into()
+}
+
+/// The signed value of a constant bit-vector, if it is at most 64 bits wide.
+fn bv_as_i64(bv: &BV) -> Option<i64> {
+    let shift = 64u32.checked_sub(bv.get_size()).filter(|&shift| shift < 64)?;
+    Some(((bv.as_u64()? << shift) as i64) >> shift)
+}
+
+fn refs<T>(values: &[T]) -> Vec<&T> {
+    values.iter().collect()
+}
+
+/// A constant cell's value, if it is an integer that fits in an `i64`.
+pub(crate) fn cell_as_i64(cell: &Dynamic) -> Option<i64> {
+    match cell.sort_kind() {
+        SortKind::Int => cell.as_int()?.as_i64(),
+        SortKind::Real => match cell.as_real()?.as_real()? {
+            (n, 1) => Some(n),
+            _ => None,
+        },
+        SortKind::BV => bv_as_i64(&cell.as_bv()?),
+        _ => None,
+    }
+}
+
+/// A constant cell's value, as near as an `f64` gets to it.
+pub(crate) fn cell_as_f64(cell: &Dynamic) -> Option<f64> {
+    match cell.sort_kind() {
+        SortKind::Int => cell.as_int()?.as_i64().map(|n| n as f64),
+        SortKind::Real => cell.as_real()?.as_real().map(|(n, d)| n as f64 / d as f64),
+        SortKind::BV => bv_as_i64(&cell.as_bv()?).map(|n| n as f64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOMAINS: [Domain; 6] = [
+        Domain::Int,
+        Domain::Real,
+        Domain::BitVec(BitWidth::W8),
+        Domain::BitVec(BitWidth::W16),
+        Domain::BitVec(BitWidth::W32),
+        Domain::BitVec(BitWidth::W64),
+    ];
+
+    #[test]
+    fn floor_div_and_mod_round_down() {
+        let context = term::Context::new();
+        // (a, b, a // b, a % b)，和`tf.math.floordiv`、`tf.math.floormod`一致
+        let cases = [
+            (7, 2, 3, 1),
+            (-7, 2, -4, 1),
+            (7, -2, -4, -1),
+            (-7, -2, 3, -1),
+            (-6, 2, -3, 0),
+            (6, -3, -2, 0),
+        ];
+        for domain in DOMAINS {
+            for &(a, b, div, rem) in &cases {
+                let (a_cell, b_cell) = (domain.constant(&context, a), domain.constant(&context, b));
+                let value = |cell: Dynamic| cell_as_i64(&cell.simplify());
+                assert_eq!(value(domain.floor_div(&context, &a_cell, &b_cell)), Some(div), "{} // {} in {}", a, b, domain);
+                assert_eq!(value(domain.floor_mod(&context, &a_cell, &b_cell)), Some(rem), "{} % {} in {}", a, b, domain);
+            }
+        }
+    }
+
+    #[test]
+    fn bit_vectors_have_the_range_of_their_width() {
+        let context = term::Context::new();
+        let range = |domain: Domain| {
+            let value = |cell: Dynamic| cell_as_i64(&cell.simplify()).unwrap();
+            (value(domain.min_value(&context)), value(domain.max_value(&context)))
+        };
+        assert_eq!(range(Domain::BitVec(BitWidth::W8)), (-128, 127));
+        assert_eq!(range(Domain::BitVec(BitWidth::W16)), (-32768, 32767));
+        assert_eq!(range(Domain::BitVec(BitWidth::W32)), (i32::MIN as i64, i32::MAX as i64));
+        assert_eq!(range(Domain::BitVec(BitWidth::W64)), (i64::MIN, i64::MAX));
+
+        // 宽度不对的位向量读不出来，而不是溢出
+        assert_eq!(cell_as_i64(&BV::from_i64(&context, -1, 128).into()), None);
+        assert_eq!(cell_as_i64(&Domain::BitVec(BitWidth::W8).constant(&context, 200)), Some(-56));
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for domain in DOMAINS {
+            assert_eq!(domain.to_string().parse::<Domain>(), Ok(domain));
+        }
+        assert!("bv0".parse::<Domain>().is_err());
+        assert!("bv128".parse::<Domain>().is_err());
+    }
+}
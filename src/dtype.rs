@@ -0,0 +1,45 @@
+//! The element types of tensors.
+//!
+//! Every value in the encoding is still a `Vecs<Int>`: a `Bool` tensor holds
+//! 0s and 1s, and a `Float` tensor holds whatever the encoding of its component
+//! produces. The dtype is tracked alongside the value, so that we never wire a
+//! value into a component that wouldn't accept it (e.g. an `Int` tensor as
+//! `TfBooleanMask`'s mask), and so that programs print the types they compute.
+//!
+//! Only `Domain::Real` divides without rounding, so `Float`s are only ever
+//! synthesized in it; in any other domain a spec with `Float`s is rejected.
+
+use std::fmt::{self, Display};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DType {
+    #[default]
+    Int,
+    Bool,
+    Float,
+}
+
+impl DType {
+    /// Every dtype, in the order of their encodings.
+    pub const ALL: [DType; 3] = [DType::Int, DType::Bool, DType::Float];
+
+    /// Can arithmetic be done on tensors of this dtype?
+    pub fn is_numeric(self) -> bool {
+        self != DType::Bool
+    }
+
+    /// The integer that stands for this dtype in the SMT encoding.
+    pub(crate) fn encode(self) -> i64 {
+        self as i64
+    }
+}
+
+impl Display for DType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DType::Int => write!(f, "int"),
+            DType::Bool => write!(f, "bool"),
+            DType::Float => write!(f, "float"),
+        }
+    }
+}
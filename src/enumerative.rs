@@ -0,0 +1,305 @@
+//! Bottom-up enumerative synthesis, in the style of TF-Coder.
+//!
+//! Rather than encoding the whole program search as one SMT query, we grow a
+//! pool of concrete values starting from the spec's inputs. Each round applies
+//! every component to every combination of pooled operands whose weights add
+//! up to the round's weight. A value that is observationally equivalent to one
+//! we've already seen (same dtype, shape and cells) is discarded, since any
+//! program using it could use the earlier, cheaper value instead.
+//!
+//! Components are evaluated concretely by handing their usual encoding
+//! constant operands and simplifying, so the semantics are exactly the ones
+//! `Synthesizer` uses in its default `Domain::Int`. Pooled values are integers,
+//! so there is no enumerating in other domains, nor with `Float`s, which need
+//! `Domain::Real`.
+
+use crate::backend::{Backend, CheckResult, Z3Backend};
+use crate::component::Component;
+use crate::{
+    check_spec_dtypes, concrete_vecs, input_vecs, shape_of_vecs, simplify_vecs, validate_spec, DType,
+    Domain, Error, Id, Instruction, Library, Operator, Program, ProgramBuilder, Result, Specification,
+    Tensor, Vecs, DIMS,
+};
+use crate::term::{self, Ast};
+use std::collections::{HashMap, HashSet};
+use std::time;
+
+/// Where a value in the pool came from.
+#[derive(Debug)]
+enum Source {
+    /// The `i`th input of the specification.
+    Input(usize),
+    /// Applying the `i`th (deduplicated) component to these pooled values.
+    Apply(usize, Vec<usize>),
+}
+
+#[derive(Debug)]
+struct Value {
+    tensor: Vecs<i64>,
+    dtype: DType,
+    weight: u32,
+    source: Source,
+}
+
+impl Value {
+    fn shape(&self) -> Option<[usize; 2]> {
+        shape_of_vecs(&self.tensor)
+    }
+}
+
+/// A synthesizer that searches for programs by weighted bottom-up enumeration
+/// of concrete values, using the same `Library`, `Specification` and `Program`
+/// types as `Synthesizer`.
+#[derive(Debug)]
+pub struct EnumerativeSynthesizer<'a> {
+    context: &'a term::Context,
+    library: &'a Library,
+    spec: &'a dyn Specification,
+    max_weight: u32,
+    timeout: Option<time::Duration>,
+}
+
+impl<'a> EnumerativeSynthesizer<'a> {
+    pub fn new(
+        context: &'a term::Context,
+        library: &'a Library,
+        spec: &'a dyn Specification,
+    ) -> Result<Self> {
+        if library.components.is_empty() {
+            return Err(Error::NoComponents);
+        }
+        validate_spec(context, spec)?;
+
+        Ok(EnumerativeSynthesizer {
+            context,
+            library,
+            spec,
+            max_weight: (spec.arity() + library.components.len()) as u32,
+            timeout: None,
+        })
+    }
+
+    /// Configure the maximum weight of the programs we enumerate.
+    ///
+    /// Every input and every component application weighs one, so this bounds
+    /// the size of the synthesized program's expression tree. It defaults to
+    /// the longest program `Synthesizer` would consider.
+    pub fn set_max_weight(&mut self, max_weight: u32) -> &mut Self {
+        self.max_weight = max_weight;
+        self
+    }
+
+    /// Configure the timeout.
+    ///
+    /// No timeout means that we will keep going until we've enumerated every
+    /// program up to the maximum weight. Providing a number of milliseconds
+    /// means we will give up after roughly that long.
+    pub fn set_timeout(&mut self, milliseconds: Option<u32>) -> &mut Self {
+        self.timeout = milliseconds.map(|ms| time::Duration::from_millis(ms as u64));
+        self
+    }
+
+    /// The library's components, with duplicates removed. Unlike the SMT
+    /// encoding, enumeration can reuse a component any number of times, so
+    /// multiple copies would only repeat work. Components that need
+    /// immediates are left out, since we can't enumerate their constants.
+    fn distinct_components(&self) -> Vec<&'a dyn Component> {
+        let mut seen: Vec<Operator> = vec![];
+        let mut components = vec![];
+        for c in &self.library.components {
+            if c.immediate_arity() > 0 {
+                continue;
+            }
+            let operands: Vec<_> = (0..c.operand_arity() as u32).map(Id).collect();
+            let operator = c.make_operator(&vec![], &operands);
+            if !seen.contains(&operator) {
+                seen.push(operator);
+                components.push(&**c);
+            }
+        }
+        components
+    }
+
+    /// Synthesize a program!
+    pub fn synthesize(&mut self) -> Result<Program> {
+        let deadline = self.timeout.map(|d| time::Instant::now() + d);
+        let domain = Domain::Int;
+        check_spec_dtypes(self.spec, domain)?;
+
+        let inputs: Vec<Tensor<'a>> = self
+            .spec
+            .inputs()
+            .iter()
+            .map(|input| input_vecs(self.context, domain, input))
+            .collect();
+        let components = self.distinct_components();
+        let output_dtype = self.spec.output_dtype();
+
+        let mut values = vec![];
+        let mut seen = HashSet::new();
+        for (i, (input, dtype)) in inputs.iter().zip(self.spec.input_dtypes()).enumerate() {
+            let tensor = simplify_vecs(input).unwrap();
+            seen.insert((tensor.clone(), dtype));
+            values.push(Value {
+                tensor,
+                dtype,
+                weight: 1,
+                source: Source::Input(i),
+            });
+        }
+
+        for weight in 2..=self.max_weight {
+            debug!("enumerating values of weight = {}", weight);
+
+            for (c, component) in components.iter().enumerate() {
+                let arity = component.operand_arity();
+                for operand_values in operand_combinations(&values, arity, weight - 1) {
+                    if deadline.is_some_and(|d| time::Instant::now() >= d) {
+                        return Err(Error::Timeout);
+                    }
+
+                    let dtypes: Vec<_> = operand_values.iter().map(|&v| values[v].dtype).collect();
+                    let dtype = match component.result_dtype(&dtypes) {
+                        Some(DType::Float) | None => continue,
+                        Some(dtype) => dtype,
+                    };
+                    let shapes: Vec<_> = operand_values.iter().map(|&v| values[v].shape()).collect();
+                    if !component.accepts_operand_shapes(&shapes) {
+                        continue;
+                    }
+
+                    let operands: Vec<_> = operand_values
+                        .iter()
+                        .map(|&v| concrete_vecs(self.context, domain, &values[v].tensor))
+                        .collect();
+                    let constraint = component.operand_constraint(self.context, &[], &operands, domain);
+                    if constraint.simplify().as_bool() == Some(false) {
+                        continue;
+                    }
+                    let expression =
+                        component.make_expression(self.context, &[], &operands, domain);
+                    let tensor = match simplify_vecs(&expression) {
+                        // 放不进DIMS的值在编码里存不下
+                        Some(tensor) if shape_of_vecs(&tensor).is_some_and(|s| s[0] <= DIMS[0] && s[1] <= DIMS[1]) => {
+                            tensor
+                        }
+                        _ => continue,
+                    };
+                    if !seen.insert((tensor.clone(), dtype)) {
+                        continue;
+                    }
+
+                    let is_solution = output_dtype.is_none_or(|d| d == dtype)
+                        && self.satisfies_spec(&inputs, &tensor, domain);
+                    values.push(Value {
+                        tensor,
+                        dtype,
+                        weight,
+                        source: Source::Apply(c, operand_values),
+                    });
+
+                    if is_solution {
+                        let program = self.to_program(&values, values.len() - 1, &components);
+                        debug!("enumeration found:\n{}", program);
+                        return Ok(program);
+                    }
+                }
+            }
+        }
+
+        Err(Error::SynthesisUnsatisfiable(None))
+    }
+
+    fn satisfies_spec(&self, inputs: &[Tensor<'a>], tensor: &Vecs<i64>, domain: Domain) -> bool {
+        let output = concrete_vecs(self.context, domain, tensor);
+        let spec = self
+            .spec
+            .make_expression(self.context, inputs, &output, domain);
+
+        // The spec usually folds down to a constant, but if it reads cells we
+        // don't know (e.g. unconstrained array elements outside the shape) then
+        // fall back to asking the solver.
+        match spec.simplify().as_bool() {
+            Some(b) => b,
+            None => matches!(Z3Backend::new().check(&[spec], &[], None), CheckResult::Sat(_)),
+        }
+    }
+
+    fn to_program(&self, values: &[Value], root: usize, components: &[&dyn Component]) -> Program {
+        let mut b = ProgramBuilder::new();
+        for (input, dtype) in self.spec.inputs().into_iter().zip(self.spec.input_dtypes()) {
+            b.var_with_dtype(input.clone(), dtype);
+        }
+        let mut program = b.finish();
+
+        let mut ids = HashMap::new();
+        emit(values, root, components, &mut program, &mut ids);
+        program
+    }
+}
+
+/// Append the instructions needed to compute `values[v]` to `program`, in
+/// dependency order, and return the id of the one that defines it.
+fn emit(
+    values: &[Value],
+    v: usize,
+    components: &[&dyn Component],
+    program: &mut Program,
+    ids: &mut HashMap<usize, Id>,
+) -> Id {
+    if let Some(&id) = ids.get(&v) {
+        return id;
+    }
+
+    let id = match &values[v].source {
+        Source::Input(i) => Id(*i as u32),
+        Source::Apply(c, operand_values) => {
+            let operands: Vec<_> = operand_values
+                .iter()
+                .map(|&o| emit(values, o, components, program, ids))
+                .collect();
+            let result = Id(program.instructions.len() as u32);
+            program.instructions.push(Instruction {
+                result,
+                operator: components[*c].make_operator(&vec![], &operands),
+            });
+            result
+        }
+    };
+    ids.insert(v, id);
+    id
+}
+
+/// Every tuple of `arity` pooled values whose weights sum to exactly `weight`.
+fn operand_combinations(values: &[Value], arity: usize, weight: u32) -> Vec<Vec<usize>> {
+    let mut combinations = vec![];
+    let mut current = Vec::with_capacity(arity);
+    extend_combinations(values, arity, weight, &mut current, &mut combinations);
+    combinations
+}
+
+fn extend_combinations(
+    values: &[Value],
+    arity: usize,
+    weight: u32,
+    current: &mut Vec<usize>,
+    combinations: &mut Vec<Vec<usize>>,
+) {
+    if current.len() == arity {
+        if weight == 0 {
+            combinations.push(current.clone());
+        }
+        return;
+    }
+
+    // Every remaining operand weighs at least one.
+    let remaining = (arity - current.len() - 1) as u32;
+    for (v, value) in values.iter().enumerate() {
+        if value.weight + remaining > weight {
+            continue;
+        }
+        current.push(v);
+        extend_combinations(values, arity, weight - value.weight, current, combinations);
+        current.pop();
+    }
+}
@@ -0,0 +1,195 @@
+//! Explaining why synthesis is unsatisfiable.
+//!
+//! When there is no program, we re-ask the solver the same question, but with
+//! the semantics of each component, the dataflow out of each input, the shape
+//! of the spec's output and each of its cells asserted under its own named
+//! assumption. The
+//! unsat core then tells us which of those pieces together rule out every
+//! program.
+
+use crate::{and, fresh_output, input_vecs, CheckResult, Domain, Id, Synthesizer, Tensor, DIMS};
+use crate::term::{Ast, Bool, Int};
+use std::fmt::{self, Display};
+use std::time;
+
+/// A subset of the synthesis query that is already unsatisfiable by itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnsatCore {
+    /// The length of program we asked for.
+    pub program_length: u32,
+    /// The components whose semantics are part of the contradiction, as their
+    /// index among the synthesizer's components and a description.
+    pub components: Vec<(usize, String)>,
+    /// The inputs whose values are part of the contradiction.
+    pub inputs: Vec<usize>,
+    /// Whether the spec's output shape is part of the contradiction.
+    pub output_shape: bool,
+    /// The spec's output cells, as `[row, column]`, that can't all be
+    /// produced. These include padding cells outside the output's shape.
+    pub output_cells: Vec<[usize; 2]>,
+}
+
+impl Display for UnsatCore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "no program of length {} exists:", self.program_length)?;
+
+        let cells: Vec<_> = self
+            .output_cells
+            .iter()
+            .map(|[i, j]| format!("[{}, {}]", i, j))
+            .collect();
+        match (self.output_shape, cells.len()) {
+            (false, 0) => writeln!(
+                f,
+                "  no well-formed program of this length satisfies the constraints at all"
+            )?,
+            (true, 0) => writeln!(f, "  the output's shape can't be produced")?,
+            (false, 1) => writeln!(f, "  the output cell {} can't be produced", cells[0])?,
+            (false, _) => {
+                writeln!(f, "  the output cells {} can't all be produced", cells.join(", "))?
+            }
+            (true, _) => writeln!(
+                f,
+                "  the output's shape and the cells {} can't all be produced",
+                cells.join(", ")
+            )?,
+        }
+
+        if self.inputs.is_empty() {
+            writeln!(f, "  whatever the inputs are")?;
+        } else {
+            let inputs: Vec<_> = self.inputs.iter().map(|&i| Id(i as u32).to_string()).collect();
+            writeln!(f, "  from the inputs {}", inputs.join(", "))?;
+        }
+
+        if self.components.is_empty() {
+            writeln!(f, "  whatever the components compute")
+        } else {
+            let components: Vec<_> = self
+                .components
+                .iter()
+                .map(|(i, c)| format!("{} (#{})", c, i))
+                .collect();
+            writeln!(f, "  by the components {}", components.join(", "))
+        }
+    }
+}
+
+/// What a tracking literal stands for.
+#[derive(Clone, Copy)]
+enum Part {
+    Component(usize),
+    Input(usize),
+    OutputShape,
+    OutputCell(usize, usize),
+}
+
+impl<'a> Synthesizer<'a> {
+    /// Find out why there is no program of length `output_line + 1` for
+    /// `input`.
+    ///
+    /// Returns `None` if the tracked query isn't unsatisfiable after all (e.g.
+    /// the solver timed out).
+    pub(crate) fn explain_unsatisfiable(
+        &mut self,
+        input: &[&Vec<Vec<i64>>],
+        output_line: u32,
+        domain: Domain,
+    ) -> Option<UnsatCore> {
+        let then = time::Instant::now();
+        let dims = DIMS;
+        let immediates = self.fresh_immediates(domain, dims);
+        let params = self.fresh_param_vars(domain, dims);
+        let results = self.fresh_result_vars(domain, dims);
+        let inputs: Vec<Tensor<'a>> = input.iter().map(|v| input_vecs(self.context, domain, v)).collect();
+        let output = fresh_output(self.context, domain, dims);
+
+        let context = self.context;
+        let mut assertions = vec![];
+        let mut parts = vec![];
+        let mut literals = vec![];
+        let mut track = |assertions: &mut Vec<Bool<'a>>, assertion: &Bool<'a>, part: Part| {
+            let literal = Bool::fresh_const(context, "track");
+            assertions.push(literal.implies(assertion));
+            parts.push(part);
+            literals.push(literal);
+        };
+
+        // Everything about the program's structure stays a hard constraint.
+        let output_on_line = self
+            .locations
+            .output
+            ._eq(&self.locations.line_from_u32(self.context, output_line));
+        assertions.push(self.well_formed_program.clone());
+        assertions.push(self.not_invalid_assignments.clone());
+        assertions.push(output_on_line);
+        assertions.push(self.locations.representable_dtypes(context, domain));
+        for &(p, shape) in &self.hole_shapes {
+            for (d, &len) in shape.iter().enumerate() {
+                assertions.push(params[p].dims[d]._eq(&Int::from_i64(self.context, len as i64)));
+            }
+        }
+
+        for (c, expr) in self
+            .component_exprs(&immediates, &params, &results, domain)
+            .iter()
+            .enumerate()
+        {
+            track(&mut assertions, expr, Part::Component(c));
+        }
+
+        // Dataflow out of an input is tracked with that input; all other
+        // dataflow is hard.
+        let num_inputs = inputs.len() as u32;
+        let mut input_connections = vec![vec![]; inputs.len()];
+        for ((i, _), conn) in self.connections(&inputs, &output, &params, &results) {
+            if i < num_inputs {
+                input_connections[i as usize].push(conn);
+            } else {
+                assertions.push(conn);
+            }
+        }
+        for (i, conns) in input_connections.iter().enumerate() {
+            track(&mut assertions, &and(self.context, conns), Part::Input(i));
+        }
+
+        // Compute the spec's output separately, and then track the program's
+        // output agreeing with it, first in shape and then cell by cell.
+        let expected = fresh_output(self.context, domain, dims);
+        assertions.push(self.spec.make_expression(self.context, &inputs, &expected, domain));
+        let shape = Bool::and(
+            self.context,
+            &[&output.dims[0]._eq(&expected.dims[0]), &output.dims[1]._eq(&expected.dims[1])],
+        );
+        track(&mut assertions, &shape, Part::OutputShape);
+        for i in 0..DIMS[0] {
+            for j in 0..DIMS[1] {
+                let cell = output.vecs[i][j]._eq(&expected.vecs[i][j]);
+                track(&mut assertions, &cell, Part::OutputCell(i, j));
+            }
+        }
+
+        let encoding = then.elapsed();
+        let unsat_core = match self.solve("explanation", output_line + 1, encoding, &assertions, &literals) {
+            CheckResult::Unsat(core) => core,
+            _ => return None,
+        };
+
+        let mut core = UnsatCore {
+            program_length: output_line + 1,
+            ..UnsatCore::default()
+        };
+        for i in unsat_core {
+            match parts[i] {
+                Part::Component(c) => core.components.push((c, format!("{:?}", self.components[c]))),
+                Part::Input(i) => core.inputs.push(i),
+                Part::OutputShape => core.output_shape = true,
+                Part::OutputCell(i, j) => core.output_cells.push([i, j]),
+            }
+        }
+        core.components.sort();
+        core.inputs.sort();
+        core.output_cells.sort();
+        Some(core)
+    }
+}
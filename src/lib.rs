@@ -8,64 +8,83 @@ extern crate log;
 #[macro_use]
 mod fake_logging;
 
+mod backend;
 mod builder;
+mod cache;
 pub mod component;
+mod concrete;
+mod domain;
+mod dtype;
+mod enumerative;
+mod explain;
 mod operator;
+mod report;
+pub mod term;
+mod verify;
 
+pub use backend::{Backend, CheckResult, Model, SatResult, SmtLib2Backend, Z3Backend};
 pub use builder::ProgramBuilder;
+pub use cache::Cache;
 pub use component::Component;
-pub use operator::Operator;
-
+pub use concrete::Rational;
+pub use domain::{BitWidth, Domain};
+pub use dtype::DType;
+pub use enumerative::EnumerativeSynthesizer;
+pub use explain::UnsatCore;
+pub use operator::{Operator, Reduction};
+pub use report::{SolverCall, SolverStatistics, StopReason, SynthesisReport};
+pub use verify::Mismatch;
+
+use domain::{cell_as_f64, cell_as_i64};
 use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt::{self, Display};
 use std::iter::FromIterator;
 use std::ops::Range;
+use std::path::PathBuf;
 use std::time;
-use z3::ast::{Ast, Bool, Int};
+use term::{Ast, Bool, Dynamic, Int};
 
 const _FULL_BIT_WIDTH: u32 = 32;
 
 const DIMS : [usize; 2] = [4, 10];
 
-// enum Type<'a> {
-//     intVar(Int<'a>),
-//     floatVar(Float<'a>)
-// }
-//可以用match判断是哪一种type
-
-
-fn and<'a, 'b>(context: &'a z3::Context, exprs: impl IntoIterator<Item = &'b Bool<'a>>) -> Bool<'a>
+fn and<'a, 'b>(context: &'a term::Context, exprs: impl IntoIterator<Item = &'b Bool<'a>>) -> Bool<'a>
 where
     'a: 'b,
 {
     let exprs: Vec<&_> = exprs.into_iter().collect();
 
     //Bool::from_bool(context, true).and(&exprs)
-    return z3::ast::Bool::<'a>::and(&context, &[&Bool::from_bool(context, true), 
-    &(z3::ast::Bool::<'a>::and(&context, &exprs))]);
+    return Bool::and(context, &[&Bool::from_bool(context, true), 
+    &(Bool::and(context, &exprs))]);
 }
 
-fn _or<'a, 'b>(context: &'a z3::Context, exprs: impl IntoIterator<Item = &'b Bool<'a>>) -> Bool<'a>
+fn _or<'a, 'b>(context: &'a term::Context, exprs: impl IntoIterator<Item = &'b Bool<'a>>) -> Bool<'a>
 where
     'a: 'b,
 {
     let exprs: Vec<&_> = exprs.into_iter().collect();
 
     //Bool::from_bool(context, false).or(&exprs)
-    return z3::ast::Bool::<'a>::or(&context, &[&Bool::from_bool(context, false), 
-    &(z3::ast::Bool::<'a>::or(&context, &exprs))]);
+    return Bool::or(context, &[&Bool::from_bool(context, false), 
+    &(Bool::or(context, &exprs))]);
 }
 
 //对于多维数组的扩散，我们可以使用一个纬度数组，然后动态创建一个嵌套了这么多层的vecs
 //TODO : 但是目前找不到一个方法将vecs的类型泛型表示出来，对于几纬就只能固定为几个Vec<Vec<...>>
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct Vecs<T>{
-    dims : [T ; 2],
+pub struct Vecs<T, D = T>{
+    dims : [D ; 2],
     vecs : Vec<Vec<T>>,
 }
 
-impl<T> Vecs<T>{
-    pub fn new(dims: [T ; 2]) -> Self {
+/// A tensor in the encoding: `Int` dims, and cells of the synthesis's
+/// `Domain`.
+pub type Tensor<'a> = Vecs<Dynamic<'a>, Int<'a>>;
+
+impl<T, D> Vecs<T, D>{
+    pub fn new(dims: [D ; 2]) -> Self {
         let _sz = dims.len();
         let mut vecs : Vec<Vec<T>> = Vec::new();
         for _ in 0 .. DIMS[0] {
@@ -77,105 +96,194 @@ impl<T> Vecs<T>{
 }
 
 //TODO: 动态维度，不过目前只能实现二维
-fn fresh_immediate(context: &z3::Context, _bit_width: u32, dims:[usize; 2] ) ->  Vecs<Int<'_>> {
-    let mut result: Vecs<Int<'_>> = Vecs::new([Int::from_i64(&context, dims[0] as i64), Int::from_i64(&context, dims[1] as i64)]);
+fn fresh_immediate(context: &term::Context, domain: Domain, dims:[usize; 2] ) ->  Tensor<'_> {
+    let mut result: Tensor<'_> = Vecs::new([Int::from_i64(context, dims[0] as i64), Int::from_i64(context, dims[1] as i64)]);
     let x = dims[0];
     let y = dims[1];
     for i in 0 .. x {
         for _j in 0 .. y {
-            result.vecs[i].push(Int::fresh_const(context, "immediate"));
+            result.vecs[i].push(domain.fresh_const(context, "immediate"));
         }
     }
-    return result;
+    result
 }
 
 //TODO: 动态维度，不过目前只能实现二维
-fn fresh_param(context: &z3::Context, _bit_width: u32, dims: [usize ; 2]) ->  Vecs<Int<'_>> {
-    let mut result = Vecs::new([Int::from_i64(&context, dims[0] as i64), Int::from_i64(&context, dims[1] as i64)]);
+fn fresh_param(context: &term::Context, domain: Domain, dims: [usize ; 2]) ->  Tensor<'_> {
+    // 形状也是未知量，由连接关系和各部件的编码决定
+    let mut result = Vecs::new([Int::fresh_const(context, "param_dims"), Int::fresh_const(context, "param_dims")]);
     let x = dims[0];
     let y = dims[1];
     for i in 0 .. x {
         for _j in 0 .. y {
-            result.vecs[i].push(Int::fresh_const(context, "param"));
+            result.vecs[i].push(domain.fresh_const(context, "param"));
         }
     }
-    return result;
+    result
 }
 
 //TODO: 动态维度，不过目前只能实现二维
-fn fresh_result(context: &z3::Context, _bit_width: u32, dims: [usize ; 2]) ->  Vecs<Int<'_>> {
-    let mut result = Vecs::new([Int::from_i64(&context, dims[0] as i64), Int::from_i64(&context, dims[1] as i64)]);
+fn fresh_result(context: &term::Context, domain: Domain, dims: [usize ; 2]) ->  Tensor<'_> {
+    // 形状也是未知量，由连接关系和各部件的编码决定
+    let mut result = Vecs::new([Int::fresh_const(context, "result_dims"), Int::fresh_const(context, "result_dims")]);
     let x = dims[0];
     let y = dims[1];
     for i in 0 .. x {
         for _j in 0 .. y {
-            result.vecs[i].push(Int::fresh_const(context, "result"));
+            result.vecs[i].push(domain.fresh_const(context, "result"));
         }
     }
-    return result;
+    result
 }
 
 //TODO: 动态维度，不过目前只能实现二维
-fn _fresh_input(context: &z3::Context, _bit_width: u32, dims: [usize ; 2]) ->  Vecs<Int<'_>> {
-    let mut result = Vecs::new([Int::from_i64(&context, dims[0] as i64), Int::from_i64(&context, dims[1] as i64)]);
+fn _fresh_input(context: &term::Context, domain: Domain, dims: [usize ; 2]) ->  Tensor<'_> {
+    let mut result = Vecs::new([Int::from_i64(context, dims[0] as i64), Int::from_i64(context, dims[1] as i64)]);
     let x = dims[0];
     let y = dims[1];
     for i in 0 .. x {
         for _j in 0 .. y {
-            result.vecs[i].push(Int::fresh_const(context, "input"));
+            result.vecs[i].push(domain.fresh_const(context, "input"));
         }
     }
-    return result;
+    result
 }
 
 //TODO: 动态维度，不过目前只能实现二维
-fn fresh_output(context: &z3::Context, _bit_width: u32, dims: [usize ; 2]) ->  Vecs<Int<'_>> {
-    let mut result = Vecs::new([Int::from_i64(&context, dims[0] as i64), Int::from_i64(&context, dims[1] as i64)]);
+fn fresh_output(context: &term::Context, domain: Domain, dims: [usize ; 2]) ->  Tensor<'_> {
+    // 形状也是未知量，由连接关系和各部件的编码决定
+    let mut result = Vecs::new([Int::fresh_const(context, "output_dims"), Int::fresh_const(context, "output_dims")]);
     let x = dims[0];
     let y = dims[1];
     for i in 0 .. x {
         for _j in 0 .. y {
-            result.vecs[i].push(Int::fresh_const(context, "output"));
+            result.vecs[i].push(domain.fresh_const(context, "output"));
         }
     }
-    return result;
+    result
 }
 
-fn eval_bitvec(model: &z3::Model, bv: &Int) -> i64 {
-    model.eval(bv)
-    .unwrap()
-    .as_i64()
-    .unwrap()
+/// The shape of a concrete value, if its dims aren't negative.
+fn shape_of_vecs(value: &Vecs<i64>) -> Option<[usize; 2]> {
+    let [rows, cols] = value.dims;
+    if rows < 0 || cols < 0 {
+        None
+    } else {
+        Some([rows as usize, cols as usize])
+    }
 }
 
-// fn as_f64(model: &z3::Model, fval: &Float) ->f64 {
-//     let x = model.eval(fval).unwrap();
+/// Check that the spec's `i`th input is a non-empty rectangle that fits in
+/// `DIMS`, and return its shape.
+fn check_input(i: usize, input: &[Vec<i64>]) -> Result<[usize; 2]> {
+    let shape = match shape_of(input) {
+        Some(shape) if shape[1] > 0 => shape,
+        _ => return Err(Error::EmptyInput { input: i }),
+    };
+    if input.iter().any(|row| row.len() != shape[1]) {
+        return Err(Error::RaggedInput { input: i });
+    }
+    if shape[0] > DIMS[0] || shape[1] > DIMS[1] {
+        return Err(Error::InputTooLarge {
+            input: i,
+            shape,
+            max: DIMS,
+        });
+    }
+    Ok(shape)
+}
 
-   
-//     if Z3_get_numeral_double
-    
-//     (self.ctx.z3_ctx, self.z3_ast, &mut tmp) {
-//             Some(tmp)
-//         } else {
-//             None
-//         }
-// }
-
-//将bitvec转化为i64类型
-fn eval_bitvecs<'a, I>(model: &'a z3::Model, bvs:I) -> Vec<Vecs<i64>>
+/// Convert a concrete input into constant `Vecs`, padding it out with zeros to
+/// the full `DIMS`.
+fn input_vecs<'a>(context: &'a term::Context, domain: Domain, input: &[Vec<i64>]) -> Tensor<'a> {
+    let sx = input.len();
+    let sy = input[0].len();
+
+    let mut result = Vecs::new([Int::from_i64(context, sx as i64), Int::from_i64(context, sy as i64)]);
+    for (i, row) in result.vecs.iter_mut().enumerate() {
+        for j in 0..DIMS[1] {
+            let value = input.get(i).and_then(|r| r.get(j)).copied().unwrap_or(0);
+            row.push(domain.constant(context, value));
+        }
+    }
+    result
+}
+
+/// Convert a concrete (already padded) value back into constant `Vecs`.
+fn concrete_vecs<'a>(context: &'a term::Context, domain: Domain, value: &Vecs<i64>) -> Tensor<'a> {
+    let mut result = Vecs::new([
+        Int::from_i64(context, value.dims[0]),
+        Int::from_i64(context, value.dims[1]),
+    ]);
+    for i in 0..DIMS[0] {
+        for j in 0..DIMS[1] {
+            result.vecs[i].push(domain.constant(context, value.vecs[i][j]));
+        }
+    }
+    result
+}
+
+/// Evaluate `Vecs` built only from constants down to concrete values.
+///
+/// This is how we evaluate components concretely: feed their encoding constant
+/// operands and let Z3's simplifier fold it. Returns `None` if the shape or a
+/// cell inside the shape doesn't fold to an integer constant. Cells outside the
+/// shape that don't fold (e.g. reads of an unconstrained array) are treated as
+/// zero.
+fn simplify_vecs(value: &Tensor) -> Option<Vecs<i64>> {
+    let rows = value.dims[0].simplify().as_i64()?;
+    let cols = value.dims[1].simplify().as_i64()?;
+
+    let mut result = Vecs::new([rows, cols]);
+    for i in 0..DIMS[0] {
+        for j in 0..DIMS[1] {
+            let cell = cell_as_i64(&value.vecs[i][j].simplify());
+            let is_in_shape = (i as i64) < rows && (j as i64) < cols;
+            result.vecs[i].push(match cell {
+                Some(cell) => cell,
+                None if is_in_shape => return None,
+                None => 0,
+            });
+        }
+    }
+    Some(result)
+}
+
+/// Is `0 <= dims[k] <= DIMS[k]` along both axes?
+fn fits_in_dims<'a>(context: &'a term::Context, dims: &[Int<'a>; 2]) -> Bool<'a> {
+    let zero = Int::from_i64(context, 0);
+    let bounds: Vec<_> = (0..2)
+        .flat_map(|k| [zero.le(&dims[k]), dims[k].le(&Int::from_i64(context, DIMS[k] as i64))])
+        .collect();
+    Bool::and(context, &bounds.iter().collect::<Vec<_>>())
+}
+
+/// The concrete `[rows, cols]` shape of an input, if it has any rows.
+fn shape_of(input: &[Vec<i64>]) -> Option<[usize; 2]> {
+    input.first().map(|row| [input.len(), row.len()])
+}
+
+//将立即数从模型里取出来
+fn eval_bitvecs<'a, 'b, I>(model: &dyn Model<'a>, bvs:I) -> Result<Vec<Vecs<i64>>>
 where
-    I: IntoIterator<Item = &'a Vecs< Int<'a>>>,
+    'a: 'b,
+    I: IntoIterator<Item = &'b Tensor<'a>>,
 {
-    let result : Vec<Vecs<i64>> = Vec::new();
-    for v in bvs.into_iter() {
+    let mut result : Vec<Vecs<i64>> = Vec::new();
+    for (k, v) in bvs.into_iter().enumerate() {
         let size_x = v.dims[0].as_i64().unwrap();
         let size_y = v.dims[1].as_i64().unwrap();
         let mut temp : Vecs<i64> = Vecs::new([size_x, size_y]);
         for i in 0 .. size_x as usize {
             for j in 0 .. size_y as usize {
-                temp.vecs[i].push(eval_bitvec(model, &v.vecs[i][j]));
+                // 立即数都是下标和形状，和`Domain::to_index`一样向下取整
+                let cell = model.eval(&v.vecs[i][j]).as_ref().and_then(cell_as_f64).ok_or_else(|| {
+                    Error::Unknown(format!("the model has no value for cell [{}, {}] of immediate {}", i, j, k))
+                })?;
+                temp.vecs[i].push(cell.floor() as i64);
             }
         }
+        result.push(temp);
 
         // for k in 1..v.len()+1 {
         //     temp.push(eval_bitvec(model, &v[k-1]));
@@ -183,7 +291,7 @@ where
         // result.push(temp);
 
     }
-    return result;
+    Ok(result)
 }
 
 
@@ -196,13 +304,17 @@ where
         .collect()
 }*/
 
-fn eval_line(model: &z3::Model, line: &Line) -> u32 {
-    eval_bitvec(model, line) as u32
+fn eval_line<'a>(model: &dyn Model<'a>, line: &Line<'a>) -> Result<u32> {
+    model
+        .eval_int(line)
+        .and_then(|line| u32::try_from(line).ok())
+        .ok_or_else(|| Error::Unknown(format!("the model has no line for `{}`", line)))
 }
 
-fn eval_lines<'a, I>(model: &'a z3::Model, lines: I) -> Vec<u32>
+fn eval_lines<'a, 'b, I>(model: &dyn Model<'a>, lines: I) -> Result<Vec<u32>>
 where
-    I: IntoIterator<Item = &'a Line<'a>>,
+    'a: 'b,
+    I: IntoIterator<Item = &'b Line<'a>>,
 {
     lines
         .into_iter()
@@ -212,11 +324,80 @@ where
 
 #[derive(Debug)]
 pub enum Error {
+    /// The library has no components to build programs from.
     NoComponents,
-    SynthesisUnsatisfiable,
-    SynthesisUnknown,
+    /// There is no program, and if we could work out why, here is a part of
+    /// the synthesis query that rules every program out.
+    SynthesisUnsatisfiable(Option<UnsatCore>),
+    /// We ran out of time before finding a program or ruling them all out.
+    Timeout,
+    /// The solver was interrupted before it could answer.
+    Cancelled,
+    /// The solver gave up, for the reason it reported.
+    Unknown(String),
+    /// The spec's `input`th input has shape `shape`, which doesn't fit in the
+    /// `max` shape that we encode tensors with.
+    InputTooLarge {
+        input: usize,
+        shape: [usize; 2],
+        max: [usize; 2],
+    },
+    /// The spec's `input`th input has rows of different lengths.
+    RaggedInput { input: usize },
+    /// The spec's `input`th input has no cells.
+    EmptyInput { input: usize },
+    /// A program we were given (e.g. a sketch) is malformed, for the reason
+    /// described.
+    InvalidProgram(String),
+    /// A component was asked for with arguments it can't take, for the reason
+    /// described.
+    InvalidComponent(String),
+    /// The synthesized program doesn't actually satisfy the spec. Only
+    /// reported when `Synthesizer::should_verify_programs` is on.
+    ProgramMismatch(Box<Mismatch>),
 }
 
+impl Error {
+    /// Classify a solver's `reason_unknown`.
+    fn from_reason_unknown(reason: String) -> Error {
+        match reason.as_str() {
+            "timeout" => Error::Timeout,
+            // Z3 spells it this way.
+            "canceled" | "cancelled" | "interrupted" => Error::Cancelled,
+            _ => Error::Unknown(reason),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoComponents => write!(f, "the library has no components"),
+            Error::SynthesisUnsatisfiable(None) => write!(f, "there is no program"),
+            Error::SynthesisUnsatisfiable(Some(why)) => write!(f, "there is no program: {}", why),
+            Error::Timeout => write!(f, "synthesis timed out"),
+            Error::Cancelled => write!(f, "synthesis was cancelled"),
+            Error::Unknown(reason) => write!(f, "the solver gave up: {}", reason),
+            Error::InputTooLarge { input, shape, max } => write!(
+                f,
+                "input {} has shape {:?}, but inputs can be at most {:?}",
+                Id(*input as u32),
+                shape,
+                max
+            ),
+            Error::RaggedInput { input } => {
+                write!(f, "input {} has rows of different lengths", Id(*input as u32))
+            }
+            Error::EmptyInput { input } => write!(f, "input {} is empty", Id(*input as u32)),
+            Error::InvalidProgram(why) => write!(f, "invalid program: {}", why),
+            Error::InvalidComponent(why) => write!(f, "invalid component: {}", why),
+            Error::ProgramMismatch(mismatch) => write!(f, "{}", mismatch),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -262,13 +443,95 @@ pub trait Specification: fmt::Debug {
 
     fn make_expression<'a>(
         &self,
-        context: &'a z3::Context,
-        // inputs: &Vec<Vec<BitVec<'a>>>,
-        // output: &Vec<BitVec<'a>>,
-        inputs: &Vec<Vecs<Int<'a>>>,
-        output: &Vecs<Int<'a>>,
-        bit_width: u32,
+        context: &'a term::Context,
+        inputs: &[Tensor<'a>],
+        output: &Tensor<'a>,
+        domain: Domain,
     ) -> Bool<'a>;
+
+    /// The output this specification wants for `inputs`, shape and all, if it
+    /// determines a single one.
+    ///
+    /// `make_expression` only constrains the output's cells, so this is the
+    /// only way to know the shape a specification expects.
+    fn output<'a>(
+        &self,
+        _context: &'a term::Context,
+        _inputs: &[Tensor<'a>],
+        _domain: Domain,
+    ) -> Option<Tensor<'a>> {
+        None
+    }
+
+    /// The output this specification wants on its own inputs, worked out
+    /// without the SMT encodings, if it can be. Re-checking programs trusts
+    /// this over `output`.
+    fn evaluate(&self, _domain: Domain) -> Result<Option<Vecs<Rational, i64>>> {
+        Ok(None)
+    }
+
+    /// Check that this specification is something we can synthesize a program
+    /// for, beyond the shapes of its inputs (which are always checked).
+    fn validate(&self, _context: &term::Context) -> Result<()> {
+        Ok(())
+    }
+
+    /// The dtype of each input. By default, every input holds `Int`s.
+    fn input_dtypes(&self) -> Vec<DType> {
+        vec![DType::Int; self.arity()]
+    }
+
+    /// The dtype the output must have, if the specification cares.
+    fn output_dtype(&self) -> Option<DType> {
+        None
+    }
+}
+
+/// Check everything about `spec` that the encoding relies on, so that a bad
+/// spec is reported as an error rather than a panic in the middle of encoding.
+fn validate_spec(context: &term::Context, spec: &dyn Specification) -> Result<()> {
+    let inputs = spec.inputs();
+    if spec.arity() == 0 {
+        return Err(Error::InvalidProgram("the spec must have at least one input".to_string()));
+    }
+    if inputs.len() != spec.arity() {
+        return Err(Error::InvalidProgram(format!(
+            "the spec has {} inputs, but {} input values",
+            spec.arity(),
+            inputs.len()
+        )));
+    }
+    for (i, input) in inputs.iter().enumerate() {
+        check_input(i, input)?;
+    }
+    if spec.input_dtypes().len() != spec.arity() {
+        return Err(Error::InvalidProgram(format!(
+            "the spec has {} inputs, but {} input dtypes",
+            spec.arity(),
+            spec.input_dtypes().len()
+        )));
+    }
+    spec.validate(context)
+}
+
+/// Check that `spec` only has `Float`s if `domain` can represent them (see
+/// `LocationVars::representable_dtypes`), including in the values a `Program`
+/// spec computes along the way.
+fn check_spec_dtypes(spec: &dyn Specification, domain: Domain) -> Result<()> {
+    if domain == Domain::Real {
+        return Ok(());
+    }
+    let what = if spec.input_dtypes().contains(&DType::Float) {
+        "an input"
+    } else if spec.output_dtype() == Some(DType::Float) {
+        "an output"
+    } else {
+        return spec.evaluate(domain).map(drop);
+    };
+    Err(Error::InvalidProgram(format!(
+        "the spec has {} of dtype float, which needs the real domain, not {}",
+        what, domain
+    )))
 }
 
 /// A collection of components.
@@ -284,6 +547,8 @@ impl Library {
     /// Create a library of components that is roughly equivalent to the Brahma
     /// standard library.
     pub fn brahma_std() -> Self {
+        // 下面的轴都是0或者1，不会出错
+        let reduce = |reduction, axis, keepdims| component::tf_reduce(reduction, axis, keepdims).unwrap();
         Library {
             // 7.3 Choice of Multi-set of Base Components:
             //
@@ -348,10 +613,15 @@ impl Library {
                 // // 12.
                 // component::xor(),
                 component::tf_add(),
-                component::tf_argmax(),
+                component::tf_argmax(0),
+                component::tf_argmax(1),
+                component::tf_argmin(0),
+                component::tf_argmin(1),
                 component::tf_boolean_mask(),
                 component::tf_boolean_mask_(),
-                component::tf_cast(),
+                component::tf_cast(DType::Int),
+                component::tf_cast(DType::Bool),
+                component::tf_cast(DType::Float),
                 component::tf_concat0(),
                 component::tf_concat1(),
                 component::tf_constant(),
@@ -360,16 +630,24 @@ impl Library {
                 component::tf_expand_dims(),
                 component::tf_greater(),
                 component::tf_bincount(),
-                component::tf_cumsum(),
+                component::tf_cumulative(Reduction::Sum, 1, false, false),
+                component::tf_cumulative(Reduction::Sum, 1, true, false),
+                component::tf_cumulative(Reduction::Sum, 1, false, true),
+                component::tf_cumulative(Reduction::Sum, 0, false, false),
+                component::tf_cumulative(Reduction::Prod, 1, false, false),
+                component::tf_cumulative(Reduction::Max, 1, false, false),
+                component::tf_cumulative(Reduction::Min, 1, false, false),
                 component::tf_multiply(),
                 component::tf_one_hot(),
                 component::tf_range(),
-                component::tf_reduce_max(),
-                component::tf_reduce_max0(),
-                component::tf_reduce_max1(),
-                component::tf_reduce_sum(),
-                component::tf_reduce_sum0(),
-                component::tf_reduce_sum1(),
+                reduce(Reduction::Max, None, false),
+                reduce(Reduction::Max, Some(0), false),
+                reduce(Reduction::Max, Some(1), false),
+                reduce(Reduction::Max, Some(1), true),
+                reduce(Reduction::Sum, None, false),
+                reduce(Reduction::Sum, Some(0), false),
+                reduce(Reduction::Sum, Some(1), false),
+                reduce(Reduction::Sum, Some(1), true),
                 component::tf_sequence_mask(),
                 component::tf_square(),
                 component::tf_subtract(),
@@ -380,18 +658,84 @@ impl Library {
 
                 component::tf_eye(),
                 component::tf_fill(),
-                component::tf_segment_max(),
+                component::tf_segment(Reduction::Sum),
+                component::tf_segment(Reduction::Prod),
+                component::tf_segment(Reduction::Max),
+                component::tf_segment(Reduction::Min),
+                component::tf_segment(Reduction::Mean),
+                component::tf_unsorted_segment(Reduction::Sum),
+                component::tf_unsorted_segment(Reduction::Prod),
+                component::tf_unsorted_segment(Reduction::Max),
+                component::tf_unsorted_segment(Reduction::Min),
+                component::tf_unsorted_segment(Reduction::Mean),
                 component::tf_matmul(),
                 component::tf_maximum(),
                 component::tf_minimum(),
                 component::tf_not_equal(),
                 component::tf_ones(),
-                component::tf_reduce_any0(),
-                component::tf_reduce_any1(),
-                component::tf_reduce_mean(),
-                component::tf_reduce_prod(),
-                component::tf_roll(),
+                reduce(Reduction::Any, Some(0), false),
+                reduce(Reduction::Any, Some(1), false),
+                reduce(Reduction::Mean, None, false),
+                reduce(Reduction::Mean, Some(0), false),
+                reduce(Reduction::Mean, Some(1), false),
+                reduce(Reduction::Prod, Some(1), false),
+                component::tf_roll(0),
+                component::tf_roll(1),
                 component::tf_zeros(),
+                component::tf_gather(0),
+                component::tf_gather(1),
+                component::tf_gather_nd(0),
+                component::tf_gather_nd(1),
+                component::tf_take_along_axis(0),
+                component::tf_take_along_axis(1),
+                component::tf_sort(0, false),
+                component::tf_sort(0, true),
+                component::tf_sort(1, false),
+                component::tf_sort(1, true),
+                component::tf_argsort(0, false),
+                component::tf_argsort(0, true),
+                component::tf_argsort(1, false),
+                component::tf_argsort(1, true),
+                component::tf_top_k_values(),
+                component::tf_top_k_indices(),
+                component::tf_reshape(),
+                component::tf_reshape_to(),
+                component::tf_squeeze(),
+                component::tf_tile(),
+                component::tf_stack(0),
+                component::tf_stack(1),
+                component::tf_scatter_nd(),
+                component::tf_tensor_scatter_nd_update(),
+                component::tf_tensor_scatter_nd_add(),
+                component::tf_sparse_to_dense(),
+                component::tf_less(),
+                component::tf_less_equal(),
+                component::tf_greater_equal(),
+                component::tf_logical_and(),
+                component::tf_logical_or(),
+                component::tf_logical_xor(),
+                component::tf_logical_not(),
+                reduce(Reduction::All, Some(0), false),
+                reduce(Reduction::All, Some(1), false),
+                reduce(Reduction::Min, None, false),
+                reduce(Reduction::Min, Some(0), false),
+                reduce(Reduction::Min, Some(1), false),
+                reduce(Reduction::Count, None, false),
+                reduce(Reduction::Count, Some(0), false),
+                reduce(Reduction::Count, Some(1), false),
+                reduce(Reduction::Variance, None, false),
+                component::tf_abs(),
+                component::tf_negative(),
+                component::tf_sign(),
+                component::tf_floordiv(),
+                component::tf_floormod(),
+                component::tf_pow(),
+                component::tf_clip_by_value(),
+                component::tf_slice(),
+                component::tf_strided_slice(),
+                component::tf_pad(),
+                component::tf_reverse(0),
+                component::tf_reverse(1),
             ],
         }
     }
@@ -407,21 +751,42 @@ fn line_le<'a>(lhs: &Line<'a>, rhs: &Line<'a>) -> Bool<'a> {
     lhs.le(rhs)
 }
 
+/// Every assignment of dtypes to `arity` operands.
+fn dtype_combinations(arity: usize) -> Vec<Vec<DType>> {
+    let mut combinations = vec![vec![]];
+    for _ in 0..arity {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|c: Vec<DType>| {
+                DType::ALL.iter().map(move |&d| {
+                    let mut c = c.clone();
+                    c.push(d);
+                    c
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
 #[derive(Debug)]
 struct LocationVars<'a> {
     inputs: Vec<Line<'a>>,
     params: Vec<Line<'a>>,
     results: Vec<Line<'a>>,
     output: Line<'a>,
+    // Whether each component's result is used, see `liveness`.
+    live: Vec<Bool<'a>>,
+    // The dtype of each component's result, see `well_typed_program`.
+    result_dtypes: Vec<Int<'a>>,
     line_bit_width: u32,
 }
 
 impl<'a> LocationVars<'a> {
-    fn new(context: &'a z3::Context, library: &Library, num_inputs: usize) -> Self {
+    fn new(context: &'a term::Context, components: &[&dyn Component], num_inputs: usize) -> Self {
         let max_line = num_inputs as u32
-            + library.components.len() as u32
-            + library
-                .components
+            + components.len() as u32
+            + components
                 .iter()
                 .map(|c| c.operand_arity() as u32)
                 .sum::<u32>();
@@ -436,34 +801,42 @@ impl<'a> LocationVars<'a> {
         let inputs = (0..num_inputs)
             .map(|_| Self::fresh_line(context, "input_location", line_bit_width))
             .collect();
-        let params = library
-            .components
+        let params = components
             .iter()
             .flat_map(|c| {
                 (0..c.operand_arity())
                     .map(|_| Self::fresh_line(context, "param_location", line_bit_width))
             })
             .collect();
-        let results = library
-            .components
+        let results = components
             .iter()
             .map(|_| Self::fresh_line(context, "result_location", line_bit_width))
             .collect();
         let output = Self::fresh_line(context, "output_line", line_bit_width);
+        let live = components
+            .iter()
+            .map(|_| Bool::fresh_const(context, "live"))
+            .collect();
+        let result_dtypes = components
+            .iter()
+            .map(|_| Int::fresh_const(context, "result_dtype"))
+            .collect();
         LocationVars {
             inputs,
             params,
             results,
             output,
+            live,
+            result_dtypes,
             line_bit_width,
         }
     }
 
-    fn fresh_line(context: &'a z3::Context, name: &str, _line_bit_width: u32) -> Line<'a> {
+    fn fresh_line(context: &'a term::Context, name: &str, _line_bit_width: u32) -> Line<'a> {
         Int::fresh_const(context, name)
     }
 
-    fn line_from_u32(&self, context: &'a z3::Context, line: u32) -> Line<'a> {
+    fn line_from_u32(&self, context: &'a term::Context, line: u32) -> Line<'a> {
         assert!(line < (1 << self.line_bit_width));
         Int::from_i64(context, line as i64)
     }
@@ -490,7 +863,11 @@ impl<'a> LocationVars<'a> {
         start..end
     }
 
-    fn invalid_connections(&self, library: &Library) -> HashSet<(u32, u32)> {
+    fn invalid_connections(
+        &self,
+        components: &[&dyn Component],
+        input_shapes: &[Option<[usize; 2]>],
+    ) -> HashSet<(u32, u32)> {
         let mut invalid_connections = HashSet::new();
 
         // We will never assign the output directly to an input.
@@ -519,25 +896,157 @@ impl<'a> LocationVars<'a> {
         // Finally, a well-formed will never have a component with its own
         // result as a parameter.
         let params = &mut self.params_range();
-        for (r, c) in self.results_range().zip(&library.components) {
+        for (r, c) in self.results_range().zip(components) {
             for p in params.take(c.operand_arity()) {
                 invalid_connections.insert((r, p));
             }
         }
 
+        // We know the concrete shape of every input up front, so we never
+        // need to consider passing an input to a param that can't accept its
+        // shape (e.g. a matrix as `TfRange`'s scalar limit).
+        let params = &mut self.params_range();
+        for c in components {
+            let arity = c.operand_arity();
+            for (k, p) in params.take(arity).enumerate() {
+                for (a, shape) in self.inputs_range().zip(input_shapes) {
+                    let mut shapes = vec![None; arity];
+                    shapes[k] = *shape;
+                    if !c.accepts_operand_shapes(&shapes) {
+                        invalid_connections.insert((a, p));
+                    }
+                }
+            }
+        }
+
         invalid_connections
     }
 
+    /// Forbid wiring inputs into params that can't accept their shapes.
+    ///
+    /// Skipping an invalid connection only drops the dataflow constraint
+    /// between the two locations, so we must also rule out the assignment
+    /// itself, or else the param would be left unconstrained. Some shape
+    /// requirements relate multiple operands (e.g. `TfMatmul`'s inner
+    /// dimensions must agree), so we also forbid each combination of inputs
+    /// whose shapes a component can't accept together.
+    fn well_shaped_operands(
+        &self,
+        context: &'a term::Context,
+        components: &[&dyn Component],
+        input_shapes: &[Option<[usize; 2]>],
+    ) -> Bool<'a> {
+        let mut shaped = vec![];
+        let mut params = self.params.iter();
+
+        for c in components {
+            let arity = c.operand_arity();
+            let locations: Vec<_> = params.by_ref().take(arity).collect();
+            for k in 0..arity {
+                for (a, a_shape) in input_shapes.iter().enumerate() {
+                    let mut shapes = vec![None; arity];
+                    shapes[k] = *a_shape;
+                    if !c.accepts_operand_shapes(&shapes) {
+                        let a = self.line_from_u32(context, a as u32);
+                        shaped.push(locations[k]._eq(&a).not());
+                    }
+                }
+                for l in k + 1..arity {
+                    for (a, a_shape) in input_shapes.iter().enumerate() {
+                        for (b, b_shape) in input_shapes.iter().enumerate() {
+                            let mut shapes = vec![None; arity];
+                            shapes[k] = *a_shape;
+                            shapes[l] = *b_shape;
+                            if c.accepts_operand_shapes(&shapes) {
+                                continue;
+                            }
+                            let a = self.line_from_u32(context, a as u32);
+                            let b = self.line_from_u32(context, b as u32);
+                            shaped.push(
+                                Bool::and(context, &[&locations[k]._eq(&a), &locations[l]._eq(&b)])
+                                    .not(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        and(context, &shaped)
+    }
+
+    /// Forbid wirings that pass a value to a param that can't accept its
+    /// dtype, and make the output have the dtype the spec wants.
+    ///
+    /// Dtypes don't depend on the examples, so rather than tracking them in
+    /// `Vecs`, every param and result gets one variable for its dtype. The
+    /// inputs' dtypes are known, each component's table of `result_dtype`s
+    /// relates its params to its result, and connected locations agree.
+    fn well_typed_program(
+        &self,
+        context: &'a term::Context,
+        components: &[&dyn Component],
+        input_dtypes: &[DType],
+        output_dtype: Option<DType>,
+    ) -> Bool<'a> {
+        let dtype = |d: DType| Int::from_i64(context, d.encode());
+        let param_dtypes: Vec<_> = self
+            .params
+            .iter()
+            .map(|_| Int::fresh_const(context, "param_dtype"))
+            .collect();
+        let result_dtypes = &self.result_dtypes;
+
+        let mut typed = vec![];
+        let mut params = param_dtypes.iter();
+        for (c, r) in components.iter().zip(result_dtypes) {
+            let ps: Vec<_> = params.by_ref().take(c.operand_arity()).collect();
+            for dtypes in dtype_combinations(ps.len()) {
+                let operands: Vec<_> = ps.iter().zip(&dtypes).map(|(p, &d)| p._eq(&dtype(d))).collect();
+                let result = match c.result_dtype(&dtypes) {
+                    Some(d) => r._eq(&dtype(d)),
+                    None => Bool::from_bool(context, false),
+                };
+                typed.push(and(context, &operands).implies(&result));
+            }
+        }
+
+        for (l_p, p) in self.params.iter().zip(&param_dtypes) {
+            // 每个参数的类型都得是某个合法的类型，否则上面的表就约束不到它
+            let any: Vec<_> = DType::ALL.iter().map(|&d| p._eq(&dtype(d))).collect();
+            typed.push(Bool::or(context, &any.iter().collect::<Vec<_>>()));
+
+            for (i, &d) in input_dtypes.iter().enumerate() {
+                let i = self.line_from_u32(context, i as u32);
+                typed.push(l_p._eq(&i).implies(&p._eq(&dtype(d))));
+            }
+            for (l_r, r) in self.results.iter().zip(result_dtypes) {
+                typed.push(l_p._eq(l_r).implies(&p._eq(r)));
+            }
+        }
+
+        if let Some(d) = output_dtype {
+            for (l_r, r) in self.results.iter().zip(result_dtypes) {
+                typed.push(self.output._eq(l_r).implies(&r._eq(&dtype(d))));
+            }
+        }
+
+        and(context, &typed)
+    }
+
     /// 5.1 Encoding Well-formed Programs
     fn well_formed_program(
         &self,
-        context: &'a z3::Context,
-        library: &Library,
+        context: &'a term::Context,
+        components: &[&dyn Component],
+        input_shapes: &[Option<[usize; 2]>],
+        input_dtypes: &[DType],
+        output_dtype: Option<DType>,
         invalid_connections: &mut HashSet<(u32, u32)>,
     ) -> Bool<'a> {
         let mut wfp = Vec::with_capacity(
-            // Acyclic and consistent.
-            2
+            // Acyclic, consistent, well-shaped, well-typed and live.
+            5
                 // Assignment of inputs.
                 + self.inputs.len()
                 // Lower and upper bounds on params.
@@ -547,7 +1056,10 @@ impl<'a> LocationVars<'a> {
         );
 
         wfp.push(self.consistent(context, invalid_connections));
-        wfp.push(self.acyclic(context, library));
+        wfp.push(self.acyclic(context, components));
+        wfp.push(self.well_shaped_operands(context, components, input_shapes));
+        wfp.push(self.well_typed_program(context, components, input_dtypes, output_dtype));
+        wfp.push(self.liveness(context, components));
 
         let i_len = self.line_from_u32(context, self.inputs.len() as u32);
         let m = self.line_from_u32(context, (self.results.len() + self.inputs.len()) as u32);
@@ -575,9 +1087,48 @@ impl<'a> LocationVars<'a> {
         and(context, &wfp)
     }
 
+    /// A component is live if its result is the output, or if it is wired
+    /// into a param of a live component. Only live components have to be
+    /// given valid operands (see `Component::operand_constraint`), since dead
+    /// code never runs.
+    fn liveness(&self, context: &'a term::Context, components: &[&dyn Component]) -> Bool<'a> {
+        let mut live = vec![];
+        for (l_r, is_live) in self.results.iter().zip(&self.live) {
+            live.push(self.output._eq(l_r).implies(is_live));
+        }
+
+        let mut params = self.params.iter();
+        for (c, is_live) in components.iter().zip(&self.live) {
+            for l_p in params.by_ref().take(c.operand_arity()) {
+                for (l_r, is_operand_live) in self.results.iter().zip(&self.live) {
+                    live.push(Bool::and(context, &[is_live, &l_p._eq(l_r)]).implies(is_operand_live));
+                }
+            }
+        }
+
+        and(context, &live)
+    }
+
+    /// Forbid live components from producing `Float`s unless `domain` is
+    /// `Domain::Real`: every other domain rounds when it divides, so a
+    /// `Float`'s cells would hold the wrong values.
+    fn representable_dtypes(&self, context: &'a term::Context, domain: Domain) -> Bool<'a> {
+        if domain == Domain::Real {
+            return Bool::from_bool(context, true);
+        }
+        let float = Int::from_i64(context, DType::Float.encode());
+        let no_floats: Vec<_> = self
+            .live
+            .iter()
+            .zip(&self.result_dtypes)
+            .map(|(is_live, r)| is_live.implies(&r._eq(&float).not()))
+            .collect();
+        and(context, &no_floats)
+    }
+
     fn consistent(
         &self,
-        context: &'a z3::Context,
+        context: &'a term::Context,
         invalid_connections: &mut HashSet<(u32, u32)>,
     ) -> Bool<'a> {
         let mut cons = vec![];
@@ -590,12 +1141,12 @@ impl<'a> LocationVars<'a> {
         and(context, &cons)
     }
 
-    fn acyclic(&self, context: &'a z3::Context, library: &Library) -> Bool<'a> {
+    fn acyclic(&self, context: &'a term::Context, components: &[&dyn Component]) -> Bool<'a> {
         let mut acycs = vec![];
         let mut params = self.params.iter();
         let mut results = self.results.iter();
 
-        for c in &library.components {
+        for c in components {
             let result_location = results.next().unwrap();
             for _ in 0..c.operand_arity() {
                 let param_location = params.next().unwrap();
@@ -621,11 +1172,11 @@ struct Assignments {
 }
 
 impl Assignments {
-    fn to_program(&self, num_inputs: usize, library: &Library) -> Program {
+    fn to_program(&self, input_dtypes: &[DType], components: &[&dyn Component]) -> Program {
         let mut b = ProgramBuilder::new();
-        for _ in 0..num_inputs {
+        for &dtype in input_dtypes {
             //这里没必要传数组参数了，直接给值就行
-            b.var(Vec::new());
+            b.var_with_dtype(Vec::new(), dtype);
         }
         let mut program = b.finish();
 
@@ -634,7 +1185,7 @@ impl Assignments {
 
         program
             .instructions
-            .extend(self.results.iter().zip(&library.components).map(|(&n, c)| {
+            .extend(self.results.iter().zip(components).map(|(&n, c)| {
                 let imm_arity = c.immediate_arity();
                 let immediates: Vec<_> = immediates.by_ref().take(imm_arity).collect();
 
@@ -656,13 +1207,17 @@ impl Assignments {
 #[derive(Debug)]
 pub struct Program {
     instructions: Vec<Instruction>,
-    inputs: Vec<Vec<Vec<i64>>>
+    inputs: Vec<Vec<Vec<i64>>>,
+    input_dtypes: Vec<DType>,
 }
 
 impl Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for i in &self.instructions {
-            write!(f, "{}\n", i)?;
+        for (i, dtype) in self.instructions.iter().zip(self.dtypes()) {
+            match dtype {
+                Some(dtype) => writeln!(f, "{}: {} ← {}", i.result, dtype, i.operator)?,
+                None => writeln!(f, "{}", i)?,
+            }
         }
         Ok(())
     }
@@ -681,43 +1236,187 @@ enum Timeout {
 
 #[derive(Debug)]
 pub struct Synthesizer<'a> {
-    context: &'a z3::Context,
-    library: &'a Library,
+    context: &'a term::Context,
+    // The library's components, followed by the sketch's instructions (if
+    // any) in program order.
+    components: Vec<&'a dyn Component>,
     spec: &'a dyn Specification,
+    // For each typed hole in the sketch, the index of its param and the shape
+    // it requires.
+    hole_shapes: Vec<(usize, [usize; 2])>,
+    // The locations that a sketch fixes, as given to `with_components`.
+    pinned_results: Vec<(usize, u32)>,
+    pinned_params: Vec<(usize, u32)>,
+    is_sketch: bool,
     locations: LocationVars<'a>,
     well_formed_program: Bool<'a>,
     invalid_connections: HashSet<(u32, u32)>,
     not_invalid_assignments: Bool<'a>,
     should_synthesize_minimal_programs: bool,
+    should_verify_programs: bool,
+    domain: Domain,
     timeout: Option<Timeout>,
+    backend: Box<dyn Backend>,
+    cache: Option<&'a mut Cache>,
+    // This task's key in the cache, worked out when we start synthesizing.
+    key: cache::Key,
+    // Whether the caller wants a report, so we should measure our queries.
+    is_reporting: bool,
+    query_dump_dir: Option<PathBuf>,
+    queries_dumped: u32,
+    setup_time: time::Duration,
+    report: SynthesisReport,
 }
 
 impl<'a> Synthesizer<'a> {
     pub fn new(
-        context: &'a z3::Context,
+        context: &'a term::Context,
         library: &'a Library,
         spec: &'a dyn Specification,
     ) -> Result<Self> {
         if library.components.is_empty() {
             return Err(Error::NoComponents);
         }
+        validate_spec(context, spec)?;
+
+        let components = library.components.iter().map(|c| &**c).collect();
+        Ok(Self::with_components(context, components, spec, vec![], vec![]))
+    }
+
+    /// Create a synthesizer that completes `sketch`, a partial program built
+    /// with `ProgramBuilder` whose holes (see `ProgramBuilder::hole`) are
+    /// filled in using `library`.
+    ///
+    /// The sketch's `var`s are the spec's inputs, and its last instruction is
+    /// the program's output. Every instruction in the sketch that isn't a hole
+    /// keeps its operator and operands; the library's components may only be
+    /// used to compute the values that flow into holes.
+    pub fn with_sketch(
+        context: &'a term::Context,
+        library: &'a Library,
+        spec: &'a dyn Specification,
+        sketch: &'a Program,
+    ) -> Result<Self> {
+        validate_spec(context, spec)?;
+
+        let num_inputs = sketch.arity();
+        let invalid = |why: &str| Err(Error::InvalidProgram(format!("sketch: {}", why)));
+        if num_inputs != spec.arity() {
+            return invalid("it must have one `var` per spec input");
+        }
+        let instructions = &sketch.instructions[num_inputs..];
+        if instructions.is_empty() {
+            return invalid("it must have at least one instruction");
+        }
+        if instructions.iter().any(|inst| inst.operator == Operator::Var) {
+            return invalid("all `var`s must be at the start");
+        }
+        if matches!(instructions.last().unwrap().operator, Operator::Hole(..)) {
+            return invalid("its output must not be a hole");
+        }
+
+        let mut components: Vec<&'a dyn Component> =
+            library.components.iter().map(|c| &**c).collect();
+        let num_library_params: usize = components.iter().map(|c| c.operand_arity()).sum();
+
+        // The sketch's instructions go after every library component, so its
+        // `k`th instruction lives on line `num_inputs + library.len() + k`.
+        // Holes are the only instructions whose params are left to the solver.
+        let first_sketch_line = (num_inputs + components.len()) as u32;
+        let line_of = |Id(x): Id| {
+            if (x as usize) < num_inputs {
+                x
+            } else {
+                first_sketch_line + x - num_inputs as u32
+            }
+        };
+
+        let mut pinned_results = vec![];
+        let mut pinned_params = vec![];
+        let mut hole_shapes = vec![];
+        let mut param = num_library_params;
+        for (k, inst) in instructions.iter().enumerate() {
+            pinned_results.push((components.len(), first_sketch_line + k as u32));
+            match inst.operator {
+                Operator::Hole(shape, None) => {
+                    if let Some(shape) = shape {
+                        hole_shapes.push((param, shape));
+                    }
+                    param += 1;
+                }
+                ref operator => operator.operands(|x| {
+                    pinned_params.push((param, line_of(x)));
+                    param += 1;
+                }),
+            }
+            components.push(&inst.operator);
+        }
+
+        let mut synthesizer =
+            Self::with_components(context, components, spec, pinned_results, pinned_params);
+        synthesizer.hole_shapes = hole_shapes;
+        synthesizer.is_sketch = true;
+        Ok(synthesizer)
+    }
+
+    /// Build a synthesizer over `components`, where each `(i, line)` in
+    /// `pinned_results` fixes the `i`th component's location, and each
+    /// `(i, line)` in `pinned_params` fixes the `i`th param's location.
+    fn with_components(
+        context: &'a term::Context,
+        components: Vec<&'a dyn Component>,
+        spec: &'a dyn Specification,
+        pinned_results: Vec<(usize, u32)>,
+        pinned_params: Vec<(usize, u32)>,
+    ) -> Self {
+        let then = time::Instant::now();
+        let input_shapes: Vec<_> = spec.inputs().into_iter().map(|input| shape_of(input)).collect();
+        let locations = LocationVars::new(context, &components, spec.arity());
+        let mut invalid_connections = locations.invalid_connections(&components, &input_shapes);
+        let well_formed_program = locations.well_formed_program(
+            context,
+            &components,
+            &input_shapes,
+            &spec.input_dtypes(),
+            spec.output_dtype(),
+            &mut invalid_connections,
+        );
+
+        let pinned: Vec<_> = pinned_results
+            .iter()
+            .map(|&(i, line)| locations.results[i]._eq(&locations.line_from_u32(context, line)))
+            .chain(pinned_params.iter().map(|&(i, line)| {
+                locations.params[i]._eq(&locations.line_from_u32(context, line))
+            }))
+            .collect();
+        let well_formed_program = and(context, pinned.iter().chain(Some(&well_formed_program)));
 
-        let locations = LocationVars::new(context, library, spec.arity());
-        let mut invalid_connections = locations.invalid_connections(library);
-        let well_formed_program =
-            locations.well_formed_program(context, library, &mut invalid_connections);
         let not_invalid_assignments = Bool::from_bool(context, true);
-        Ok(Synthesizer {
+        Synthesizer {
             context,
-            library,
+            components,
             spec,
+            hole_shapes: vec![],
+            pinned_results,
+            pinned_params,
+            is_sketch: false,
             locations,
             well_formed_program,
             invalid_connections,
             not_invalid_assignments,
             should_synthesize_minimal_programs: false,
+            should_verify_programs: false,
+            domain: Domain::Int,
             timeout: None,
-        })
+            backend: Box::new(Z3Backend::new()),
+            cache: None,
+            key: 0,
+            is_reporting: false,
+            query_dump_dir: None,
+            queries_dumped: 0,
+            setup_time: then.elapsed(),
+            report: SynthesisReport::default(),
+        }
     }
 
     /// Configure whether we should synthesize the minimal-length program that
@@ -730,6 +1429,33 @@ impl<'a> Synthesizer<'a> {
         self
     }
 
+    /// Configure whether we should re-check the synthesized program against
+    /// the spec's examples, including its shape, before returning it.
+    ///
+    /// This guards against bugs in the encoding. A program that fails the
+    /// check isn't returned (or cached): its wiring is ruled out, and recorded
+    /// in the cache as invalid if there is one, and the search goes on. If no
+    /// program passes, the last failure is reported as
+    /// `Error::ProgramMismatch`.
+    pub fn should_verify_programs(&mut self, should: bool) -> &mut Self {
+        self.should_verify_programs = should;
+        self
+    }
+
+    /// Configure the sort that tensor cells are encoded with.
+    ///
+    /// The default, `Domain::Int`, is the fastest to solve, but division
+    /// rounds. `Domain::Real` divides exactly, and the cells of a verified
+    /// program's output are reported as `f64`s; it is the only domain that
+    /// programs with `Float`s can be synthesized in.
+    /// `Domain::BitVec(BitWidth::W32)` and `Domain::BitVec(BitWidth::W64)`
+    /// wrap around on overflow like TF's `int32` and `int64`; concrete inputs
+    /// wrap into the width too.
+    pub fn set_domain(&mut self, domain: Domain) -> &mut Self {
+        self.domain = domain;
+        self
+    }
+
     /// Configure the timeout.
     ///
     /// No timeout means that we will keep going forever if necessary. Providing
@@ -741,29 +1467,223 @@ impl<'a> Synthesizer<'a> {
         self
     }
 
-    fn solver(&mut self) -> z3::Solver<'a> {
-        let solver = z3::Solver::new(self.context);
+    /// Configure a cache of results from previous runs.
+    ///
+    /// If the cache already holds a solution for this task, and it still
+    /// satisfies the spec, `synthesize` returns it without calling the
+    /// solver. Otherwise, any invalid
+    /// assignments it has learned for this task are ruled out up front, and
+    /// whatever we solve or learn is added to it. It is up to the caller to
+    /// `Cache::save` it afterwards.
+    pub fn set_cache(&mut self, cache: Option<&'a mut Cache>) -> &mut Self {
+        self.cache = cache;
+        self
+    }
 
-        if let Some(timeout) = self.timeout.clone() {
-            let millis = match timeout {
-                Timeout::Duration(d) => {
-                    let millis = d.as_millis();
-                    self.timeout = Some(Timeout::Instant(time::Instant::now() + d));
-                    millis as u32
-                }
-                Timeout::Instant(instant) => {
-                    let dur = instant.saturating_duration_since(time::Instant::now());
-                    dur.as_millis() as u32
-                }
-            };
+    /// Configure a directory to write every query we give the solver to, as a
+    /// standalone SMT-LIB2 file.
+    ///
+    /// Each file declares everything it uses and ends with the commands to
+    /// check it, so it can be replayed with other solvers. Files are named
+    /// after the task's cache key and the order we made the queries in, so
+    /// multiple tasks can share a directory.
+    pub fn set_query_dump_dir(&mut self, dir: Option<PathBuf>) -> &mut Self {
+        self.query_dump_dir = dir;
+        self
+    }
 
-            let mut params = z3::Params::new(self.context);
-            params.set_u32("timeout", millis);
+    /// Configure which solver decides our queries.
+    ///
+    /// By default this is Z3, in-process. The queries are still built with Z3
+    /// terms either way.
+    pub fn set_backend(&mut self, backend: Box<dyn Backend>) -> &mut Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Write `assertions` out as the next SMT-LIB2 query file, if we're
+    /// configured to. If there are `assumptions`, the query checks under them
+    /// and asks for an unsat core, otherwise it asks for a model.
+    fn dump_query(
+        &mut self,
+        assertions: &[Bool<'a>],
+        kind: &str,
+        program_length: u32,
+        assumptions: &[Bool<'a>],
+    ) {
+        let dir = match &self.query_dump_dir {
+            Some(dir) => dir.clone(),
+            None => return,
+        };
+        let path = dir.join(format!(
+            "{:016x}-{:03}-{}-length-{}.smt2",
+            self.key,
+            self.queries_dumped,
+            kind,
+            program_length
+        ));
+        self.queries_dumped += 1;
+
+        let mut query = format!("; {} query for a program of length {}\n", kind, program_length);
+        if assumptions.is_empty() {
+            query.push_str("(set-option :produce-models true)\n");
+            query.push_str(&backend::to_smtlib2(assertions));
+            query.push_str("(check-sat)\n(get-model)\n");
+        } else {
+            let assumptions: Vec<_> = assumptions.iter().map(|a| a.to_string()).collect();
+            query.push_str("(set-option :produce-unsat-cores true)\n");
+            query.push_str(&backend::to_smtlib2(assertions));
+            query.push_str(&format!("(check-sat-assuming ({}))\n", assumptions.join(" ")));
+            query.push_str("(get-unsat-core)\n");
+        }
+
+        if let Err(e) = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, query)) {
+            warn!("failed to write query to {}: {}", path.display(), e);
+        } else {
+            debug!("wrote query to {}", path.display());
+        }
+    }
+
+    /// The key for this task in the cache: everything that determines which
+    /// program we synthesize, written out in the format described in
+    /// `cache`.
+    fn cache_key(&self) -> cache::Key {
+        let context = self.context;
+        let domain = self.domain;
+        let mut description = format!("{}\n", cache::KEY_FORMAT);
+
+        // 部件用它的元数和它生成的操作符来描述，操作数和立即数都用占位的
+        let mut placeholder = Vecs::new([DIMS[0] as i64, DIMS[1] as i64]);
+        for row in &mut placeholder.vecs {
+            row.resize(DIMS[1], 0);
+        }
+        for c in &self.components {
+            let immediates = vec![placeholder.clone(); c.immediate_arity()];
+            let operands: Vec<_> = (0..c.operand_arity() as u32).map(Id).collect();
+            description.push_str(&format!(
+                "component {} {} {}\n",
+                c.operand_arity(),
+                c.immediate_arity(),
+                c.make_operator(&immediates, &operands)
+            ));
+        }
+        for (i, line) in &self.pinned_results {
+            description.push_str(&format!("pinned result {} {}\n", i, line));
+        }
+        for (i, line) in &self.pinned_params {
+            description.push_str(&format!("pinned param {} {}\n", i, line));
+        }
+        for (i, shape) in &self.hole_shapes {
+            description.push_str(&format!("hole {} {} {}\n", i, shape[0], shape[1]));
+        }
 
-            solver.set_params(&params);
+        for (input, dtype) in self.spec.inputs().iter().zip(self.spec.input_dtypes()) {
+            let rows: Vec<_> = input
+                .iter()
+                .map(|row| row.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(","))
+                .collect();
+            description.push_str(&format!("input {} {}\n", dtype, rows.join(";")));
         }
+        if let Some(dtype) = self.spec.output_dtype() {
+            description.push_str(&format!("output {}\n", dtype));
+        }
+        // 规约对输出的约束化简后写成SMT-LIB2，输出用固定的名字，这样每次都一样
+        let inputs: Vec<_> = self
+            .spec
+            .inputs()
+            .iter()
+            .map(|input| input_vecs(context, domain, input))
+            .collect();
+        let mut output = Vecs::new([
+            Int::new_const(context, "output_rows"),
+            Int::new_const(context, "output_cols"),
+        ]);
+        for (i, row) in output.vecs.iter_mut().enumerate() {
+            for j in 0..DIMS[1] {
+                let name = format!("output_{}_{}", i, j);
+                row.push(domain.new_const(context, name));
+            }
+        }
+        let spec = self.spec.make_expression(context, &inputs, &output, domain);
+        description.push_str(&format!("spec {}\n", spec.simplify()));
+
+        description.push_str(&format!(
+            "minimal {}\ndomain {}\n",
+            self.should_synthesize_minimal_programs, domain
+        ));
+        cache::key(&description)
+    }
 
-        solver
+    /// Start the timeout's clock, unless it is already running.
+    fn start_clock(&mut self) {
+        if let Some(Timeout::Duration(d)) = self.timeout {
+            self.timeout = Some(Timeout::Instant(time::Instant::now() + d));
+        }
+    }
+
+    /// How long the next query may take. The clock starts with the first
+    /// query.
+    fn remaining_timeout(&mut self) -> Option<time::Duration> {
+        self.start_clock();
+        match self.timeout.clone()? {
+            Timeout::Duration(d) => Some(d),
+            Timeout::Instant(instant) => {
+                Some(instant.saturating_duration_since(time::Instant::now()))
+            }
+        }
+    }
+
+    /// Hand `assertions` to the backend.
+    fn check(
+        &mut self,
+        assertions: &[Bool<'a>],
+        assumptions: &[Bool<'a>],
+    ) -> (CheckResult<'a>, SolverStatistics) {
+        let timeout = self.remaining_timeout();
+        if timeout == Some(time::Duration::from_millis(0)) {
+            return (CheckResult::Unknown("timeout".to_string()), SolverStatistics::default());
+        }
+        self.backend
+            .check_with_statistics(assertions, assumptions, timeout)
+    }
+
+    /// Hand a query of the given `kind` to the backend, the way every query
+    /// goes: dumped if we're configured to, under what's left of the timeout,
+    /// and recorded in the report.
+    fn solve(
+        &mut self,
+        kind: &'static str,
+        program_length: u32,
+        encoding: time::Duration,
+        assertions: &[Bool<'a>],
+        assumptions: &[Bool<'a>],
+    ) -> CheckResult<'a> {
+        self.dump_query(assertions, kind, program_length, assumptions);
+
+        // 数节点要遍历整个查询，很慢：只在要报告时才数，而且这段时间也算在超时里
+        let query_size = if self.is_reporting {
+            self.start_clock();
+            report::ast_size(assertions)
+        } else {
+            0
+        };
+
+        let then = time::Instant::now();
+        let (result, statistics) = self.check(assertions, assumptions);
+        self.report.solver_calls.push(SolverCall {
+            kind,
+            program_length,
+            encoding,
+            solving: then.elapsed(),
+            query_size,
+            result: result.sat_result(),
+            reason_unknown: match &result {
+                CheckResult::Unknown(reason) => Some(reason.clone()),
+                _ => None,
+            },
+            statistics,
+        });
+        result
     }
 
     fn is_invalid_connection(&self, i: u32, j: u32) -> bool {
@@ -785,34 +1705,40 @@ impl<'a> Synthesizer<'a> {
 
     //要为每个部件都生成这样一个bitvec的数组，但是在运行的过程中数组的dims会有变化，因此该怎样
     // 目前想法，产生一个很大的bitvec二维数组，此处默认是30*30，通过dims来控制，然后新添加一个变量dims[2]，用来表示这个数组中的哪些元素是有用的
-    fn fresh_immediates(&self, bit_width: u32, dims : [usize; 2]) -> Vec<Vecs<Int<'a>>> {
-        self.library
-            .components
+    fn fresh_immediates(&self, domain: Domain, dims : [usize; 2]) -> Vec<Tensor<'a>> {
+        self.components
             .iter()
             .flat_map(|c| {
-                (0..c.immediate_arity()).map(|_| fresh_immediate(self.context, bit_width, dims))
+                (0..c.immediate_arity()).map(|_| fresh_immediate(self.context, domain, dims))
             })
             .collect()
     }
 
-    fn fresh_param_vars(&self, bit_width: u32, dims : [usize; 2]) -> Vec<Vecs<Int<'a>>> {
-        self.library
-            .components
+    fn fresh_param_vars(&self, domain: Domain, dims : [usize; 2]) -> Vec<Tensor<'a>> {
+        self.components
             .iter()
-            .flat_map(|c| (0..c.operand_arity()).map(|_| fresh_param(self.context, bit_width, dims)))
+            .flat_map(|c| (0..c.operand_arity()).map(|_| fresh_param(self.context, domain, dims)))
             .collect()
     }
 
 
-    fn fresh_result_vars(&self, bit_width: u32, dims : [usize; 2]) -> Vec<Vecs<Int<'a>>> {
-        self.library
-            .components
+    fn fresh_result_vars(&self, domain: Domain, dims : [usize; 2]) -> Vec<Tensor<'a>> {
+        self.components
             .iter()
-            .map(|_| fresh_result(self.context, bit_width, dims))
+            .map(|_| fresh_result(self.context, domain, dims))
             .collect()
     }
 
-    fn _add_invalid_assignment(&mut self, assignments: &Assignments) {
+    fn add_invalid_assignment(&mut self, assignments: &Assignments) {
+        self.block_assignment(assignments);
+
+        let key = self.key;
+        if let Some(cache) = &mut self.cache {
+            cache.insert_invalid_assignment(key, assignments.clone());
+        }
+    }
+
+    fn block_assignment(&mut self, assignments: &Assignments) {
         // TODO: like souper, we should have multiple cases here for if we're
         // trying to synthesize any constants or not. When we're synthesizing
         // constants, allow reusing the same location assignments N times with
@@ -844,7 +1770,7 @@ impl<'a> Synthesizer<'a> {
         );
 
         let not_this_assignment = and(self.context, &[results, params]).not();
-        self.not_invalid_assignments = z3::ast::Bool::<'_>::and(self.context, &[&self.not_invalid_assignments, &not_this_assignment]);
+        self.not_invalid_assignments = Bool::and(self.context, &[&self.not_invalid_assignments, &not_this_assignment]);
         //self.not_invalid_assignments = self.not_invalid_assignments.and(&[&not_this_assignment]);
     }
 
@@ -858,8 +1784,10 @@ impl<'a> Synthesizer<'a> {
         &mut self,
         input: &Vec<&Vec<Vec<i64>>>,
         output_line: u32,
-        bit_width: u32,
+        domain: Domain,
     ) -> Result<Assignments> {
+        let then = time::Instant::now();
+
         /*debug!(
             "finite synthesis at bit width {} with inputs = {:#018X?}",
             bit_width,
@@ -875,43 +1803,22 @@ impl<'a> Synthesizer<'a> {
 
         let dims = DIMS;
 
-        let immediates = self.fresh_immediates(bit_width, dims);
+        let immediates = self.fresh_immediates(domain, dims);
         
         //let mut works_for_inputs = Vec::with_capacity(inputs.len() * 4);
         //现在就一组输入直接就是4，用来存储bool们
         let mut works_for_inputs = Vec::with_capacity(3);
 
         
-        let params = self.fresh_param_vars(bit_width, dims);
-        let results = self.fresh_result_vars(bit_width, dims);
+        let params = self.fresh_param_vars(domain, dims);
+        let results = self.fresh_result_vars(domain, dims);
 
         
         //将Vec<Vec<Vec<i64>>>类型的inputs转化为Vec<Vecs<Int<'_>>>,
-        let input_iter = input.iter();
-        let mut inputs : Vec<Vecs<Int<'_>>> = Vec::new();
-        for v in input_iter {
-            //v type Vec<Vec<i64>>
-            let sx = v.len();
-            let sy = v[0].len();
-
-            let mut temp : Vecs<Int<'_>> = Vecs::new([Int::from_i64(&self.context, sx as i64), Int::from_i64(&self.context, sy as i64)]);
-            for i in 0..DIMS[0] {
-                if i < sx {
-                    for j in 0..sy {
-                        temp.vecs[i].push(Int::from_i64(self.context, v[i][j] as i64));
-                    }
-                    for _j in sy .. DIMS[1] {
-                        temp.vecs[i].push(Int::from_i64(self.context, 0));
-                    }
-                } else {
-                    for _j in 0 .. DIMS[1] {
-                        temp.vecs[i].push(Int::from_i64(self.context, 0));
-                    }
-                }
-            }
-            // println!("{:?}", temp);
-            inputs.push(temp);
-        }
+        let inputs: Vec<Tensor<'_>> = input
+            .iter()
+            .map(|v| input_vecs(self.context, domain, v))
+            .collect();
             
         /*let inputs: Vec<_> = input
             .iter()
@@ -922,29 +1829,36 @@ impl<'a> Synthesizer<'a> {
             ).collect();*/
             
         //i[0] ?
-        let output = fresh_output(self.context, bit_width, dims);
+        let output = fresh_output(self.context, domain, dims);
         ////用library中components按顺序构造出语句
-        let lib = self.library(&immediates, &params, &results, bit_width);
-        //println!("lib : {}", lib);
+        let lib = self.library(&immediates, &params, &results, domain);
         works_for_inputs.push(lib);
 
+        // 带形状的洞只能接受对应形状的值
+        let hole_shapes: Vec<_> = self
+            .hole_shapes
+            .iter()
+            .flat_map(|&(p, shape)| (0..2).map(move |d| (p, d, shape[d])))
+            .map(|(p, d, len)| params[p].dims[d]._eq(&Int::from_i64(self.context, len as i64)))
+            .collect();
+        works_for_inputs.push(and(self.context, &hole_shapes));
+        works_for_inputs.push(self.locations.representable_dtypes(self.context, domain));
+
         //建立行数和值之间的关系
         let conn = self.connectivity(&inputs, &output, &params, &results);
-        //println!("conn : {}", conn);
         works_for_inputs.push(conn);
         
 
         let spec = self
             .spec
-            .make_expression(self.context, &inputs, &output, bit_width);
-        //println!("spec : {}", spec);
+            .make_expression(self.context, &inputs, &output, domain);
         works_for_inputs.push(spec);
         
 
         let works_for_inputs: Vec<&_> = works_for_inputs.iter().collect();
 
         assert!(self.spec.arity() <= output_line as usize);
-        assert!((output_line as usize) < self.spec.arity() + self.library.components.len());
+        assert!((output_line as usize) < self.spec.arity() + self.components.len());
         let output_on_line = self
             .locations
             .output
@@ -955,36 +1869,25 @@ impl<'a> Synthesizer<'a> {
         //     .and(&works_for_inputs)
         //     .and(&[&self.not_invalid_assignments, &output_on_line]);
         
-        let query = z3::ast::Bool::<'_>::and(self.context, &[&self.well_formed_program, 
-            &(z3::ast::Bool::<'_>::and(self.context, &works_for_inputs)),
-            &(z3::ast::Bool::<'_>::and(self.context, &[&self.not_invalid_assignments, &output_on_line])),
+        let query = Bool::and(self.context, &[&self.well_formed_program, 
+            &(Bool::and(self.context, &works_for_inputs)),
+            &(Bool::and(self.context, &[&self.not_invalid_assignments, &output_on_line])),
             ]);
 
         trace!("finite synthesis query =\n{:?}", query);
 
-        let solver = self.solver();
-        solver.assert(&query);
+        let encoding = then.elapsed();
+        let result = self.solve("synthesis", output_line + 1, encoding, &[query], &[]);
 
-        match solver.check() {
-            z3::SatResult::Unknown => Err(Error::SynthesisUnknown),
-            z3::SatResult::Unsat => Err(Error::SynthesisUnsatisfiable),
-            z3::SatResult::Sat => {
-                let model = solver.get_model();
+        match result {
+            CheckResult::Unknown(reason) => Err(Error::from_reason_unknown(reason)),
+            CheckResult::Unsat(_) => Err(Error::SynthesisUnsatisfiable(None)),
+            CheckResult::Sat(model) => {
+                let immediates = eval_bitvecs(&*model, &immediates)?;
 
+                let params = eval_lines(&*model, &self.locations.params)?;
 
-                // let x : i64 = -10;
-                // let x_bv = Int::from_i64(self.context, x);
-                // println!(" bitvec :{}", bit_width);
-                // println!("bv : {}", x_bv);
-                // let y =  eval_bitvec(&Option::as_ref(&model).unwrap(), &x_bv);
-                // println!(" y :{}", y);
-                
-
-                let immediates = eval_bitvecs(&Option::as_ref(&model).unwrap(), &immediates);
-
-                let params = eval_lines(&Option::as_ref(&model).unwrap(), &self.locations.params);
-
-                let results = eval_lines(&Option::as_ref(&model).unwrap(), &self.locations.results);
+                let results = eval_lines(&*model, &self.locations.results)?;
 
                 let assignments = Assignments {
                     immediates,
@@ -995,7 +1898,7 @@ impl<'a> Synthesizer<'a> {
 
                 debug!(
                     "finite synthesis generated:\n{}",
-                    assignments.to_program(self.spec.arity(), &self.library)
+                    assignments.to_program(&self.spec.input_dtypes(), &self.components)
                 );
 
                 Ok(assignments)
@@ -1048,11 +1951,28 @@ impl<'a> Synthesizer<'a> {
     /// 5.2 Encoding Dataflow in Programs
     fn connectivity(
         &self,
-        inputs: &Vec<Vecs<Int<'a>>>,
-        output: &Vecs<Int<'a>>,
-        params: &Vec<Vecs<Int<'a>>>,
-        results: &Vec<Vecs<Int<'a>>>,
+        inputs: &[Tensor<'a>],
+        output: &Tensor<'a>,
+        params: &Vec<Tensor<'a>>,
+        results: &Vec<Tensor<'a>>,
     ) -> Bool<'a> {
+        let conn: Vec<_> = self
+            .connections(inputs, output, params, results)
+            .into_iter()
+            .map(|(_, c)| c)
+            .collect();
+        and(self.context, &conn)
+    }
+
+    /// Each `(i, j)` pair of locations that may be connected, along with the
+    /// constraint that connecting them implies their values are equal.
+    fn connections(
+        &self,
+        inputs: &[Tensor<'a>],
+        output: &Tensor<'a>,
+        params: &Vec<Tensor<'a>>,
+        results: &Vec<Tensor<'a>>,
+    ) -> Vec<((u32, u32), Bool<'a>)> {
         let locs_to_vars: Vec<_> = self
             .locations
             .inputs
@@ -1063,8 +1983,6 @@ impl<'a> Synthesizer<'a> {
             .chain(Some((&self.locations.output, output)))
             .collect();
 
-        //println!("location_len : {:?}", locs_to_vars);
-
         let mut conn =
             Vec::with_capacity(locs_to_vars.len() * locs_to_vars.len() + locs_to_vars.len());
 
@@ -1076,16 +1994,19 @@ impl<'a> Synthesizer<'a> {
 
                 //这边默认x和y的len相等
                 //判断类型为Vecs<BV<'_>>的x和y中的元素相等关系
-                let mut temp = x.vecs[0][0]._eq(&y.vecs[0][0]);
+                let mut temp = Bool::and(
+                    self.context,
+                    &[&x.dims[0]._eq(&y.dims[0]), &x.dims[1]._eq(&y.dims[1])],
+                );
                 for i in 0..DIMS[0] {
                     for j in 0..DIMS[1] {
                         let temp2 = x.vecs[i][j]._eq(&y.vecs[i][j]);
-                        temp = z3::ast::Bool::<'_>::and(self.context, &[&temp, &temp2]);
+                        temp = Bool::and(self.context, &[&temp, &temp2]);
                         //temp = temp.and(&[&temp2]);
 
                     }
                 }
-                conn.push(l_x._eq(l_y).implies(&temp));
+                conn.push(((i as u32, j as u32), l_x._eq(l_y).implies(&temp)));
 
                 // let mut temp = x[0]._eq(&y[0]);
                 // for _k in 1..x.len() + 1 {
@@ -1095,9 +2016,8 @@ impl<'a> Synthesizer<'a> {
                 // conn.push(l_x._eq(l_y).implies(&temp));
             }
         }
-        //println!("conn : {:?}", conn);
 
-        and(self.context, &conn)
+        conn
     }
 
     /* here
@@ -1133,17 +2053,30 @@ impl<'a> Synthesizer<'a> {
 
     fn library(
         &self,
-        immediates: &[Vecs<Int<'a>>],
-        params: &[Vecs<Int<'a>>],
-        results: &[Vecs<Int<'a>>],
-        bit_width: u32,
+        immediates: &[Tensor<'a>],
+        params: &[Tensor<'a>],
+        results: &[Tensor<'a>],
+        domain: Domain,
     ) -> Bool<'a> {
-        let mut exprs = Vec::with_capacity(self.library.components.len());
+        let exprs = self.component_exprs(immediates, params, results, domain);
+        and(self.context, &exprs)
+    }
+
+    /// The semantics of each component, i.e. that its params are valid if it
+    /// is live and its result is its expression over them.
+    fn component_exprs(
+        &self,
+        immediates: &[Tensor<'a>],
+        params: &[Tensor<'a>],
+        results: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Vec<Bool<'a>> {
+        let mut component_exprs = Vec::with_capacity(self.components.len());
         let mut immediates = immediates;
         let mut params = params;
         let mut results = results.iter();
 
-        for c in &self.library.components {
+        for (c, is_live) in self.components.iter().zip(&self.locations.live) {
             let (imms, rest) = immediates.split_at(c.immediate_arity());
             immediates = rest;
 
@@ -1152,7 +2085,14 @@ impl<'a> Synthesizer<'a> {
 
             let result = results.next().unwrap();
 
-            let expression = c.make_expression(self.context, imms, inputs, bit_width);
+            let expression = c.make_expression(self.context, imms, inputs, domain);
+
+            let mut exprs = Vec::with_capacity(3 + DIMS[0] * DIMS[1]);
+            exprs.push(is_live.implies(&c.operand_constraint(self.context, imms, inputs, domain)));
+            // 结果必须放得进DIMS，不然编码里存不下它所有的单元格
+            exprs.push(is_live.implies(&fits_in_dims(self.context, &expression.dims)));
+            exprs.push(expression.dims[0]._eq(&result.dims[0]));
+            exprs.push(expression.dims[1]._eq(&result.dims[1]));
 
             let sz1 = DIMS[0];
             let sz2 = DIMS[1];
@@ -1167,10 +2107,10 @@ impl<'a> Synthesizer<'a> {
             //     c.make_expression(self.context, imms, inputs, bit_width)[0]
             //         ._eq(&result[0]),
             // );
+            component_exprs.push(and(self.context, &exprs));
         }
 
-        //println!("exprs : {:?}", exprs);
-        and(self.context, &exprs)
+        component_exprs
     }
 
     /// Have the solver generate initial concrete inputs for finite synthesis by
@@ -1267,18 +2207,73 @@ impl<'a> Synthesizer<'a> {
     /// The synthesizer has been configured, and we're ready to create a
     /// program.
     pub fn synthesize(&mut self) -> Result<Program> {
+        self.is_reporting = false;
+        self.run().0
+    }
+
+    /// Synthesize a program, and report how we went about it, whether or not
+    /// we succeeded.
+    ///
+    /// Measuring each query's size takes a walk over the whole query, which
+    /// counts against the timeout, so this can be noticeably slower than
+    /// `synthesize`.
+    pub fn synthesize_with_report(&mut self) -> (Result<Program>, SynthesisReport) {
+        self.is_reporting = true;
+        self.run()
+    }
+
+    fn run(&mut self) -> (Result<Program>, SynthesisReport) {
+        let then = time::Instant::now();
+        self.report = SynthesisReport {
+            setup: self.setup_time,
+            ..SynthesisReport::default()
+        };
+
+        let program = self.search();
+
+        let mut report = std::mem::take(&mut self.report);
+        report.total = then.elapsed();
+        (program, report)
+    }
+
+    fn search(&mut self) -> Result<Program> {
         // 将输入从产生随即的输入改称我们自己的输入
         //let mut inputs = self.initial_concrete_inputs(array_dims)?;
         //assert!(!inputs.is_empty());
 
         //直接获取输入
-        let mut input = self.spec.inputs();
+        let input = self.spec.inputs();
 
         let arity = self.spec.arity();
         assert!(arity > 0);
+        check_spec_dtypes(self.spec, self.domain)?;
+
+        self.key = self.cache_key();
+        let key = self.key;
+        if let Some(solution) = self.cache.as_ref().and_then(|c| c.solution(key)) {
+            let mut program = solution.to_program(&self.spec.input_dtypes(), &self.components);
+            program.fill_holes();
+            program.dce();
+            // 缓存里的解不一定可信（比如是旧版本的编码解出来的），总是重新检查一遍
+            match self.check_program(program) {
+                Ok(program) => {
+                    debug!("using the cached solution");
+                    self.report.stop_reason = StopReason::Cached;
+                    return Ok(program);
+                }
+                Err(e) => {
+                    debug!("discarding the cached solution: {}", e);
+                    self.cache.as_mut().unwrap().remove_solution(key);
+                }
+            }
+        }
 
-        let longest = arity as u32 + self.library.components.len() as u32;
-        let shortest = if self.should_synthesize_minimal_programs {
+        let longest = arity as u32 + self.components.len() as u32;
+        // A sketch fixes the output to its last instruction, which is always on
+        // the last line.
+        let shortest = if self.is_sketch {
+            longest
+        } else if self.should_synthesize_minimal_programs {
             arity as u32 + 1
         } else {
             longest
@@ -1290,18 +2285,46 @@ impl<'a> Synthesizer<'a> {
         // shortest last. Because we have dead code elimination, we can also
         // skip ahead a bunch of iterations when we find long solutions that
         // contain dead code.
-        let mut best = Err(Error::SynthesisUnknown);
+        let mut best = Err(Error::Unknown("no program lengths to try".to_string()));
+        let mut solution = None;
+        let mut mismatch = None;
         let mut length = longest;
         while length >= shortest {
             //总共一组输入
-            match self.synthesize_with_length(length, &mut input) {
-                Ok(mut program) => {
+            match self.synthesize_with_length(length, &input) {
+                Ok((mut program, assignments)) => {
                     program.dce();
 
+                    // 编码有问题时解出来的程序未必满足规约：记下这组赋值，
+                    // 在同样的长度下换一个再试
+                    let program = match self.verify_program(program) {
+                        Err(Error::ProgramMismatch(m)) => {
+                            debug!("{}", m);
+                            self.add_invalid_assignment(&assignments);
+                            mismatch = Some(m);
+                            continue;
+                        }
+                        // 重新检查也受超时限制：超时了就和合成查询超时一样，
+                        // 交出目前最好的程序，但不缓存
+                        Err(e) if best.is_ok() => {
+                            debug!("couldn't verify a shorter program: {}", e);
+                            self.report.stop_reason = StopReason::Unknown(
+                                self.report
+                                    .solver_calls
+                                    .last()
+                                    .and_then(|c| c.reason_unknown.clone())
+                                    .unwrap_or_default(),
+                            );
+                            return best;
+                        }
+                        result => result?,
+                    };
+
                     assert!(program.instructions.len() > arity);
                     length = program.instructions.len() as u32 - 1;
 
                     best = Ok(program);
+                    solution = Some(assignments);
 
                     // Reset the invalid-assignments clause, since an assignment
                     // that was an invalid program of length `i` might be valid
@@ -1310,26 +2333,77 @@ impl<'a> Synthesizer<'a> {
 
                     continue;
                 }
-                err => return best.or_else(|_| err),
+                // There's no shorter program, so `best` is as good as it gets.
+                Err(Error::SynthesisUnsatisfiable(_)) if best.is_ok() => break,
+                // Otherwise we didn't finish searching, so don't cache `best`
+                // as the answer.
+                err => {
+                    self.report.stop_reason = match err {
+                        Err(Error::SynthesisUnsatisfiable(_)) => StopReason::Unsatisfiable,
+                        _ => StopReason::Unknown(
+                            self.report
+                                .solver_calls
+                                .last()
+                                .and_then(|c| c.reason_unknown.clone())
+                                .unwrap_or_default(),
+                        ),
+                    };
+                    // Every program the solver came up with failed the
+                    // re-check, which says more than whatever stopped us.
+                    if let (Err(_), Some(mismatch)) = (&best, mismatch) {
+                        return Err(Error::ProgramMismatch(mismatch));
+                    }
+                    // This is the first length we tried, so there's no program
+                    // at all. Find out why.
+                    if let Err(Error::SynthesisUnsatisfiable(_)) = err {
+                        let why = self.explain_unsatisfiable(&input, length - 1, self.domain);
+                        return Err(Error::SynthesisUnsatisfiable(why));
+                    }
+                    return best.or_else(|_| err.map(|(program, _)| program));
+                }
             }
         }
 
-        best
+        self.report.stop_reason = StopReason::Solved;
+        let program = best?;
+
+        if let (Some(cache), Some(solution)) = (&mut self.cache, solution) {
+            cache.insert_solution(key, solution);
+        }
+
+        Ok(program)
     }
 
     fn synthesize_with_length(
         &mut self,
         program_length: u32,
         input: &Vec<&Vec<Vec<i64>>>
-    ) -> Result<Program> {
+    ) -> Result<(Program, Assignments)> {
         debug!("synthesizing a program of length = {}", program_length);
+        self.report.lengths_tried.push(program_length);
 
-        let bit_width = 64;
+        let domain = self.domain;
+
+        // 先排除缓存中已知在这个长度下无效的赋值
+        let key = self.key;
+        let known_invalid: Vec<_> = self.cache.as_ref().map_or(vec![], |cache| {
+            cache
+                .invalid_assignments(key)
+                .iter()
+                .filter(|a| a.output == program_length - 1)
+                .cloned()
+                .collect()
+        });
+        for assignments in &known_invalid {
+            self.block_assignment(assignments);
+        }
 
         //只有一组输入，所以也没有cegis的循环了
-        let assignments = self.finite_synthesis(input, program_length - 1, bit_width)?;
+        let assignments = self.finite_synthesis(input, program_length - 1, domain)?;
 
-        return Ok(assignments.to_program(self.spec.arity(), self.library));
+        let mut program = assignments.to_program(&self.spec.input_dtypes(), &self.components);
+        program.fill_holes();
+        Ok((program, assignments))
 
 
         /*'cegis: loop {
@@ -1366,8 +2440,8 @@ impl<'a> Synthesizer<'a> {
 
 
 impl Program {
-    pub fn synthesize<'a>(
-        context: &'a z3::Context,
+    pub fn synthesize(
+        context: &term::Context,
         spec: &impl Specification,
         library: &Library,
         _arr_dims : Vec<usize>
@@ -1376,6 +2450,45 @@ impl Program {
         synthesizer.synthesize()
     }
 
+    /// The dtype of each value the program computes, in order, starting with
+    /// its inputs. A value's dtype is `None` if its operator can't be applied
+    /// to its operands' dtypes, or if it depends on an unfilled hole.
+    pub fn dtypes(&self) -> Vec<Option<DType>> {
+        let mut dtypes: Vec<Option<DType>> = vec![];
+        for (i, inst) in self.instructions.iter().enumerate() {
+            let dtype = match inst.operator {
+                Operator::Var => self.input_dtypes.get(i).copied(),
+                Operator::Hole(_, None) => None,
+                ref operator => {
+                    let mut operands = vec![];
+                    operator.operands(|Id(x)| operands.push(dtypes.get(x as usize).copied().flatten()));
+                    operands
+                        .into_iter()
+                        .collect::<Option<Vec<_>>>()
+                        .and_then(|operands| operator.result_dtype(&operands))
+                }
+            };
+            dtypes.push(dtype);
+        }
+        dtypes
+    }
+
+    /// Replace every use of a filled hole with the value it was filled with,
+    /// leaving the hole itself dead.
+    fn fill_holes(&mut self) {
+        let mut fillers = HashMap::new();
+        for inst in &mut self.instructions {
+            inst.operator.operands_mut(|x| {
+                if let Some(&filler) = fillers.get(x) {
+                    *x = filler;
+                }
+            });
+            if let Operator::Hole(_, Some(filler)) = inst.operator {
+                fillers.insert(inst.result, filler);
+            }
+        }
+    }
+
     pub fn dce(&mut self) {
         let mut used: HashSet<Id> = HashSet::from_iter(
             self.instructions
@@ -1425,23 +2538,101 @@ impl Specification for Program {
         .clone()
         .collect();
      
-        return inputs;
+        inputs
     }
 
-    fn make_expression<'a>(
+    fn input_dtypes(&self) -> Vec<DType> {
+        self.input_dtypes.clone()
+    }
+
+    fn output_dtype(&self) -> Option<DType> {
+        self.dtypes().pop().flatten()
+    }
+
+    /// Check that every instruction only uses earlier values and, evaluating
+    /// the program on its inputs, that every operator accepts the dtypes,
+    /// shapes and values of its operands and produces a value that fits in
+    /// `DIMS`.
+    ///
+    /// Shapes don't depend on the domain, so we always evaluate in `Int`.
+    fn validate(&self, context: &term::Context) -> Result<()> {
+        let arity = self.arity();
+        if self.instructions.len() <= arity {
+            return Err(Error::InvalidProgram(
+                "the spec has no instructions after its `var`s".to_string(),
+            ));
+        }
+
+        // 具体地求出每个值，以得到中间结果的形状；求不出来的当作未知
+        let mut values: Vec<Option<Vecs<i64>>> = self
+            .inputs
+            .iter()
+            .map(|input| simplify_vecs(&input_vecs(context, Domain::Int, input)))
+            .collect();
+        let dtypes = self.dtypes();
+        for inst in &self.instructions[arity..] {
+            let invalid = |why: String| Err(Error::InvalidProgram(format!("{}: {}", inst, why)));
+            match inst.operator {
+                Operator::Var => return invalid("all `var`s must be at the start".to_string()),
+                Operator::Hole(..) => return invalid("a spec can't have holes".to_string()),
+                _ => {}
+            }
+
+            let mut operands = vec![];
+            inst.operator.operands(|id| operands.push(id));
+            if let Some(id) = operands.iter().find(|id| id.0 >= inst.result.0) {
+                return invalid(format!("{} isn't defined before it is used", id));
+            }
+
+            if dtypes[inst.result.0 as usize].is_none() {
+                let operand_dtypes: Vec<_> = operands.iter().flat_map(|id| dtypes[id.0 as usize]).collect();
+                return invalid(format!("can't be applied to operands of dtypes {:?}", operand_dtypes));
+            }
+
+            let operand_values: Vec<_> = operands.iter().map(|id| values[id.0 as usize].as_ref()).collect();
+            let shapes: Vec<_> = operand_values
+                .iter()
+                .map(|v| v.and_then(shape_of_vecs))
+                .collect();
+            if !inst.operator.accepts_operand_shapes(&shapes) {
+                return invalid(format!("can't be applied to operands of shapes {:?}", shapes));
+            }
+
+            let value = if operand_values.iter().all(|v| v.is_some()) {
+                let operands: Vec<_> = operand_values
+                    .iter()
+                    .map(|v| concrete_vecs(context, Domain::Int, v.unwrap()))
+                    .collect();
+                let constraint = inst.operator.operand_constraint(context, &[], &operands, Domain::Int);
+                if constraint.simplify().as_bool() == Some(false) {
+                    return invalid("its operands are invalid, e.g. an index is out of range or a divisor is zero".to_string());
+                }
+                simplify_vecs(&inst.operator.make_expression(context, &[], &operands, Domain::Int))
+            } else {
+                None
+            };
+            if let Some(shape) = value.as_ref().and_then(shape_of_vecs) {
+                if shape[0] > DIMS[0] || shape[1] > DIMS[1] {
+                    return invalid(format!(
+                        "its result has shape {:?}, but values can be at most {:?}",
+                        shape, DIMS
+                    ));
+                }
+            }
+            values.push(value);
+        }
+        Ok(())
+    }
+
+    fn output<'a>(
         &self,
-        context: &'a z3::Context,
-        inputs: &Vec<Vecs<Int<'a>>>,
-        output: &Vecs<Int<'a>>,
-        bit_width: u32,
-    ) -> Bool<'a> {
+        context: &'a term::Context,
+        inputs: &[Tensor<'a>],
+        domain: Domain,
+    ) -> Option<Tensor<'a>> {
         assert!(self.instructions.len() > inputs.len());
 
-        let mut vars: Vec<_> = inputs.iter().cloned().collect();
-
-        //测试输入有没有被正确传达到
-        // let x : Vec<_> = vars.iter().clone().collect();
-        // println!("inputs : {:?}", x);
+        let mut vars: Vec<_> = inputs.to_vec();
 
         let mut operands = vec![];
         for instr in self.instructions.iter().skip(inputs.len()) {
@@ -1458,48 +2649,444 @@ impl Specification for Program {
             vars.push(
                 instr
                     .operator
-                    .make_expression(context, &immediates, &operands, bit_width),
+                    .make_expression(context, &immediates, &operands, domain),
             );
-            
-            // let x : Vec<_> = vars.iter().clone().collect();
-            // println!("vars : {:?}", x);
         }
 
         //最后的结果
-        let vars = vars.pop().unwrap();
-        // let x  = vars.clone();
-        // println!("vars : {:?}", x);
+        vars.pop()
+    }
 
-        // let x  = output.clone();
-        // println!("output : {:?}", output);
+    fn evaluate(&self, domain: Domain) -> Result<Option<Vecs<Rational, i64>>> {
+        concrete::evaluate(self, &self.inputs(), domain).map(Some)
+    }
+
+    fn make_expression<'a>(
+        &self,
+        context: &'a term::Context,
+        inputs: &[Tensor<'a>],
+        output: &Tensor<'a>,
+        domain: Domain,
+    ) -> Bool<'a> {
+        let vars = self.output(context, inputs, domain).unwrap();
 
         //利用vars和output中的元素相等构成逻辑表达式
-        let mut temp: Bool<'_> = vars.vecs[0][0]._eq(&output.vecs[0][0]);
+        // 形状也要一样，不然补零得到的更大的张量单元格也全都对得上
+        let mut temp: Bool<'_> = Bool::and(context, &[
+            &vars.dims[0]._eq(&output.dims[0]),
+            &vars.dims[1]._eq(&output.dims[1]),
+        ]);
 
-        // println!("temp0 : {}", temp);
         for i in 0..DIMS[0] {
             for j in 0..DIMS[1] {
                 let temp2 = vars.vecs[i][j]._eq(&output.vecs[i][j]);
-                //temp = temp.and(&[&temp2]);
-                temp = z3::ast::Bool::<'_>::and(&context, &[&temp, &temp2]);
+                temp = Bool::and(context, &[&temp, &temp2]);
             }
         }
-        
+        temp
+    }
 
-        // let mut temp = vars[0]._eq(&output[0]);
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // for i in 2..vars.len() {
-        //     //println!("{}", vars.len());
-        //     let temp2 = vars[i-1]._eq(&output[i-1]);
-        //     temp = temp.and(&[&temp2]);
-        // }
+    fn context() -> term::Context {
+        term::Context::new()
+    }
 
-        // println!("temp : {}", temp);
-        
-        return temp;    
+    #[test]
+    fn stale_cached_solutions_are_discarded() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_subtract(), component::tf_add()],
+        };
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, 2, 3]]);
+        let b = builder.var(vec![vec![10, 20, 30]]);
+        builder.tf_add(a, b);
+        let spec = builder.finish();
+
+        // 缓存里放一个错误的解：`a - b`
+        let key = Synthesizer::new(&context, &library, &spec).unwrap().cache_key();
+        let path = std::env::temp_dir().join(format!("synth-cache-{}-stale-solutions", std::process::id()));
+        let mut cache = Cache::open(&path).unwrap();
+        let stale = Assignments {
+            immediates: vec![],
+            params: vec![0, 1, 0, 1],
+            results: vec![2, 3],
+            output: 2,
+        };
+        cache.insert_solution(key, stale.clone());
+
+        let mut synthesizer = Synthesizer::new(&context, &library, &spec).unwrap();
+        synthesizer.set_cache(Some(&mut cache));
+        let (program, report) = synthesizer.synthesize_with_report();
+        assert_eq!(report.stop_reason, StopReason::Solved);
+        assert!(program.unwrap().to_string().contains("TfAdd"));
+        assert!(!report.solver_calls.is_empty());
+        assert!(report.solver_calls.iter().all(|call| call.statistics.memory.is_some()));
+        drop(synthesizer);
+        let _ = std::fs::remove_file(&path);
+        assert!(cache.solution(key).is_some_and(|solution| *solution != stale));
+    }
+
+    /// A spec that can only be checked by asking the solver: it has no
+    /// interpreter, and a constant of its own keeps simplification from
+    /// deciding its encoding.
+    #[derive(Debug)]
+    struct EncodedOnly(Program);
+
+    impl Specification for EncodedOnly {
+        fn arity(&self) -> usize {
+            self.0.arity()
+        }
+
+        fn inputs(&self) -> Vec<&Vec<Vec<i64>>> {
+            self.0.inputs()
+        }
+
+        fn make_expression<'a>(
+            &self,
+            context: &'a term::Context,
+            inputs: &[Tensor<'a>],
+            output: &Tensor<'a>,
+            domain: Domain,
+        ) -> Bool<'a> {
+            let slack = Int::fresh_const(context, "slack");
+            let slack = slack._eq(&Int::from_i64(context, 0));
+            Bool::and(context, &[&self.0.make_expression(context, inputs, output, domain), &slack])
+        }
+    }
+
+    #[test]
+    fn verification_queries_are_dumped_and_reported() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_add()],
+        };
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, 2, 3]]);
+        let b = builder.var(vec![vec![10, 20, 30]]);
+        builder.tf_add(a, b);
+        let spec = EncodedOnly(builder.finish());
+
+        let dir = std::env::temp_dir().join(format!("synth-queries-{}-verification", std::process::id()));
+        let mut synthesizer = Synthesizer::new(&context, &library, &spec).unwrap();
+        synthesizer.should_verify_programs(true).set_query_dump_dir(Some(dir.clone()));
+        let (program, report) = synthesizer.synthesize_with_report();
+        assert!(program.unwrap().to_string().contains("TfAdd"));
+        assert!(report.solver_calls.iter().any(|call| call.kind == "verification"));
+
+        let dumped: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(dumped.iter().any(|name| name.contains("-verification-")), "{:?}", dumped);
+    }
+
+    #[test]
+    fn unsat_core_names_the_output_shape() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_abs()],
+        };
+        // 单元格全是0，对得上；只有形状[3, 1]是`TfAbs`做不出来的
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![0, 0, 0]]);
+        builder.tf_transpose(a);
+        let spec = builder.finish();
+
+        let result = Synthesizer::new(&context, &library, &spec).unwrap().synthesize();
+        match result {
+            Err(Error::SynthesisUnsatisfiable(Some(core))) => {
+                assert!(core.output_shape);
+                assert!(core.output_cells.is_empty());
+                assert!(core.to_string().contains("the output's shape can't be produced"));
+            }
+            result => panic!("expected an unsat core, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn verified_programs_are_evaluated_without_the_synthesis_query() {
+        let context = context();
+        // `TfConcat0`可以直接算，`TfMatmul`只能退回到编码
+        for concat in [true, false] {
+            let library = Library {
+                components: vec![if concat { component::tf_concat0() } else { component::tf_matmul() }],
+            };
+            let mut builder = ProgramBuilder::new();
+            let a = builder.var(vec![vec![1, 2], vec![3, 4]]);
+            let b = builder.var(vec![vec![5, 6], vec![7, 8]]);
+            if concat {
+                builder.tf_concat0(a, b);
+            } else {
+                builder.tf_matmul(a, b);
+            }
+            let spec = builder.finish();
+
+            let mut synthesizer = Synthesizer::new(&context, &library, &spec).unwrap();
+            synthesizer.should_verify_programs(true);
+            let program = synthesizer.synthesize().unwrap();
+            assert_eq!(program.to_string(), spec.to_string());
+        }
+    }
+
+    #[test]
+    fn real_domain_synthesizes_immediates_and_divides() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_reshape_to(), component::tf_divide()],
+        };
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, 2, 3, 4, 5, 6]]);
+        let b = builder.var(vec![vec![2]]);
+        let c = builder.tf_reshape_to(a, [3, 2]);
+        builder.tf_divide(c, b);
+        let spec = builder.finish();
+
+        let mut synthesizer = Synthesizer::new(&context, &library, &spec).unwrap();
+        synthesizer.set_domain(Domain::Real).should_verify_programs(true);
+        let program = synthesizer.synthesize().unwrap().to_string();
+        // 先除还是先变形都行，但形状这个立即数必须取对
+        assert!(program.contains("TfDivide: "), "{}", program);
+        assert!(program.contains("shape = [3, 2]"), "{}", program);
+    }
+
+    #[test]
+    fn floats_need_the_real_domain() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_divide(), component::tf_cast(DType::Int)],
+        };
+        let rejects = |spec: &Program, domain: Domain, why: &str| {
+            let mut synthesizer = Synthesizer::new(&context, &library, spec).unwrap();
+            match synthesizer.set_domain(domain).synthesize() {
+                Err(Error::InvalidProgram(message)) => {
+                    assert!(message.contains(why), "{:?} doesn't mention {:?}", message, why)
+                }
+                result => panic!("expected an invalid program, got {:?}", result),
+            }
+        };
+
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![7, 9]]);
+        let b = builder.var(vec![vec![2, 4]]);
+        builder.tf_divide(a, b);
+        let spec = builder.finish();
+        rejects(&spec, Domain::Int, "an output of dtype float");
+        rejects(&spec, Domain::BitVec(BitWidth::W8), "not bv8");
+        assert!(matches!(
+            EnumerativeSynthesizer::new(&context, &library, &spec).unwrap().synthesize(),
+            Err(Error::InvalidProgram(_))
+        ));
+
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var_with_dtype(vec![vec![7, 9]], DType::Float);
+        builder.tf_cast(a, DType::Int);
+        rejects(&builder.finish(), Domain::Int, "an input of dtype float");
+
+        // 中间结果是浮点数也不行
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![7, 9]]);
+        let b = builder.var(vec![vec![2, 4]]);
+        let c = builder.tf_divide(a, b);
+        builder.tf_cast(c, DType::Int);
+        rejects(&builder.finish(), Domain::Int, "TfDivide");
+
+        // 整除只能先除再转成整数，而整数域里不能用浮点数
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![7, 9]]);
+        let b = builder.var(vec![vec![2, 4]]);
+        builder.tf_floordiv(a, b);
+        let spec = builder.finish();
+        let mut synthesizer = Synthesizer::new(&context, &library, &spec).unwrap();
+        assert!(matches!(synthesizer.synthesize(), Err(Error::SynthesisUnsatisfiable(_))));
+        synthesizer.set_domain(Domain::Real).should_verify_programs(true);
+        let program = synthesizer.synthesize().unwrap();
+        assert_eq!(
+            concrete::evaluate(&program, &spec.inputs(), Domain::Real).unwrap().vecs[0][..2],
+            [Rational::from(3), Rational::from(2)]
+        );
     }
 
+    #[test]
+    fn bit_vector_domain_wraps_around() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_subtract(), component::tf_add()],
+        };
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![100, 1]]);
+        builder.tf_add(a, a);
+        let spec = builder.finish();
+
+        // 在8位里100 + 100是-56
+        let mut synthesizer = Synthesizer::new(&context, &library, &spec).unwrap();
+        synthesizer.set_domain(Domain::BitVec(BitWidth::W8)).should_verify_programs(true);
+        let program = synthesizer.synthesize().unwrap();
+        assert_eq!(program.to_string(), spec.to_string());
+        assert_eq!(
+            concrete::evaluate(&program, &spec.inputs(), Domain::BitVec(BitWidth::W8)).unwrap().vecs[0][..2],
+            [Rational::from(-56), Rational::from(2)]
+        );
+    }
+
+    #[test]
+    fn enumeration_finds_the_smallest_program() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_add(), component::tf_multiply(), component::tf_square()],
+        };
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, 2, 3]]);
+        let b = builder.var(vec![vec![4, 5, 6]]);
+        let c = builder.tf_add(a, b);
+        builder.tf_square(c);
+        let spec = builder.finish();
+
+        let mut synthesizer = EnumerativeSynthesizer::new(&context, &library, &spec).unwrap();
+        synthesizer.set_max_weight(4);
+        let program = synthesizer.synthesize().unwrap();
+        assert_eq!(program.to_string(), spec.to_string());
+        let output = concrete::evaluate(&program, &spec.inputs(), Domain::Int).unwrap();
+        assert_eq!(output.vecs[0][..3], [Rational::from(25), Rational::from(49), Rational::from(81)]);
+        assert_eq!(output.dims, [1, 3]);
+
+        // 权重不够时应该找不到
+        synthesizer.set_max_weight(3);
+        assert!(synthesizer.synthesize().is_err());
+    }
+
+    #[test]
+    fn enumeration_matches_the_spec_by_value() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_add(), component::tf_square()],
+        };
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, -2], vec![3, 0]]);
+        let b = builder.var(vec![vec![2, 2], vec![-5, 1]]);
+        let c = builder.tf_add(a, b);
+        builder.tf_multiply(c, c);
+        let spec = builder.finish();
+
+        // 库里没有乘法，只能用平方算出同样的值
+        let mut synthesizer = EnumerativeSynthesizer::new(&context, &library, &spec).unwrap();
+        let program = synthesizer.synthesize().unwrap();
+        assert!(program.to_string().contains("TfSquare"), "{}", program);
+        let output = concrete::evaluate(&program, &spec.inputs(), Domain::Int).unwrap();
+        assert_eq!(output.dims, [2, 2]);
+        assert_eq!(output.vecs[0][..2], [Rational::from(9), Rational::from(0)]);
+        assert_eq!(output.vecs[1][..2], [Rational::from(4), Rational::from(1)]);
+    }
+
+    #[test]
+    fn sketches_keep_their_instructions_and_fill_holes() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_square(), component::tf_subtract()],
+        };
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, 2, 3]]);
+        let b = builder.var(vec![vec![4, 5, 6]]);
+        let c = builder.tf_square(a);
+        builder.tf_add(c, b);
+        let spec = builder.finish();
+
+        // 库里没有加法，只能由草图提供
+        let mut builder = ProgramBuilder::new();
+        builder.var(vec![vec![1, 2, 3]]);
+        let b = builder.var(vec![vec![4, 5, 6]]);
+        let hole = builder.hole(Some([1, 3]));
+        builder.tf_add(hole, b);
+        let sketch = builder.finish();
+
+        let mut synthesizer = Synthesizer::with_sketch(&context, &library, &spec, &sketch).unwrap();
+        synthesizer.should_verify_programs(true);
+        let program = synthesizer.synthesize().unwrap().to_string();
+        assert!(program.contains("TfSquare: "), "{}", program);
+        assert!(program.contains("TfAdd: "), "{}", program);
+    }
+
+    #[test]
+    fn inputs_are_never_wired_into_params_that_reject_their_shape() {
+        let context = context();
+        let library = Library {
+            components: vec![component::tf_expand_dims()],
+        };
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1], vec![2]]);
+        let b = builder.var(vec![vec![0], vec![0]]);
+        builder.tf_add(a, b);
+        let spec = builder.finish();
+
+        // `TfExpandDims`只接受行向量，所以不存在合法的程序。如果只是跳过了连接约束，
+        // 参数就成了自由变量，求解器可以让它取`[[1, 2]]`来伪造出结果。
+        // 直接查询编码本身，因为`synthesize`会复查并丢掉这样的程序
+        let mut synthesizer = Synthesizer::new(&context, &library, &spec).unwrap();
+        match synthesizer.finite_synthesis(&spec.inputs(), 2, Domain::Int) {
+            Err(Error::SynthesisUnsatisfiable(_)) => {}
+            Err(e) => panic!("expected no program, got {:?}", e),
+            Ok(assignments) => panic!("expected no program, got {:?}", assignments),
+        }
+    }
+
+    #[test]
+    fn validate_rejects_bad_specs() {
+        let context = context();
+        let library = Library::brahma_std();
+        let rejects = |spec: Program, why: &str| match Synthesizer::new(&context, &library, &spec) {
+            Err(Error::InvalidProgram(message)) => {
+                assert!(message.contains(why), "{:?} doesn't mention {:?}", message, why)
+            }
+            Err(e) => panic!("expected an invalid program, got {:?}", e),
+            Ok(_) => panic!("expected an invalid program:\n{}", spec),
+        };
+
+        // 只有输入，没有指令
+        let mut builder = ProgramBuilder::new();
+        builder.var(vec![vec![1, 2]]);
+        rejects(builder.finish(), "no instructions");
+
+        // 布尔值不能相加
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var_with_dtype(vec![vec![1, 0]], DType::Bool);
+        let b = builder.var(vec![vec![1, 2]]);
+        builder.tf_add(a, b);
+        rejects(builder.finish(), "dtypes");
+
+        // [3, 1]和[4, 2]无法广播
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1], vec![2], vec![3]]);
+        let b = builder.var(vec![vec![1, 2], vec![3, 4], vec![5, 6], vec![7, 8]]);
+        builder.tf_multiply(a, b);
+        rejects(builder.finish(), "shapes");
+
+        // 转置后是[5, 3]，超出了4*10
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, 2, 3, 4, 5]; 3]);
+        builder.tf_transpose(a);
+        rejects(builder.finish(), "shapes");
+
+        // 平铺后是[6, 2]，超出了4*10
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, 2], vec![3, 4]]);
+        let multiples = builder.var(vec![vec![3, 1]]);
+        builder.tf_tile(a, multiples);
+        rejects(builder.finish(), "operands are invalid");
+
+        // 下标越界
+        let mut builder = ProgramBuilder::new();
+        let a = builder.var(vec![vec![1, 2, 3]]);
+        let indices = builder.var(vec![vec![0, 3]]);
+        builder.tf_gather(a, indices, 1);
+        rejects(builder.finish(), "operands are invalid");
+    }
 }
+
 /* 
 #[cfg(test)]
 mod tests {
@@ -1561,10 +3148,7 @@ mod tests {
 
     #[test]
     fn synthesize() {
-        let mut config = z3::Config::new();
-        config.set_model_generation(true);
-
-        let context = z3::Context::new(&config);
+        let context = term::Context::new();
 
         let library = Library::brahma_std();
         let mut builder = ProgramBuilder::new();
@@ -1580,10 +3164,7 @@ mod tests {
 
     #[test]
     fn synthesize_select() {
-        let mut config = z3::Config::new();
-        config.set_model_generation(true);
-
-        let context = z3::Context::new(&config);
+        let context = term::Context::new();
 
         let mut library = Library::brahma_std();
         library.components.push(component::select());
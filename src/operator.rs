@@ -1,4 +1,4 @@
-use crate::Id;
+use crate::{DType, Id};
 use std::fmt::{self, Display};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -11,11 +11,13 @@ pub enum Operator {
 
     // 我自己的操作符号
     TfAdd(Id, Id),
-    TfArgmax(Id),
+    TfArgmax(Id, usize),
+    TfArgmin(Id, usize),
     // 第一个是两个输入维度相同的情况，第二个是不同的情况
     TfBooleanMask(Id, Id),
     TfBooleanMask_(Id, Id),
-    TfCast(Id),
+    // 第二个是转换的目标类型
+    TfCast(Id, DType),
     // 下标表示不同的axis
     TfConcat0(Id, Id),
     TfConcat1(Id, Id),
@@ -25,17 +27,12 @@ pub enum Operator {
     TfExpandDims(Id),
     TfGreater(Id, Id),
     TfBincount(Id),
-    TfCumsum(Id, Id),
+    TfCumulative(Id, Reduction, usize, bool, bool),
     TfMultiply(Id, Id),
     TfOneHot(Id, Id),
     TfRange(Id, Id),
     // 下标表示不同的axis，没有下标就是axis=-1
-    TfReduceMax(Id),
-    TfReduceMax0(Id),
-    TfReduceMax1(Id),
-    TfReduceSum(Id),
-    TfReduceSum0(Id),
-    TfReduceSum1(Id),
+    TfReduce(Id, Reduction, Option<usize>, bool),
     TfSequenceMask(Id),
     TfSquare(Id),
     TfSubtract(Id, Id),
@@ -47,50 +44,97 @@ pub enum Operator {
 
     TfEye(Id, Id),
     TfFill(Id, Id),
-    TfSegmentMax(Id, Id),
+    // 最后一个是段内的归约方式，不排序的版本多一个num_segments
+    TfSegment(Id, Id, Reduction),
+    TfUnsortedSegment(Id, Id, Id, Reduction),
     TfMatmul(Id, Id),
     TfMaximum(Id, Id),
     TfMinimum(Id, Id),
     TfNotEqual(Id, Id),
     TfOnes(Id),
     // 下标表示不同的axis，没有下标就是axis=-1
-    TfReduceAny0(Id),
-    TfReduceAny1(Id),
-    TfReduceMean(Id),
-    TfReduceProd(Id),
-    TfRoll(Id),
+    TfRoll(Id, i64, usize),
     TfZeros(Id),
+    // 第三个是axis，TfGatherNd则是batch_dims
+    TfGather(Id, Id, usize),
+    TfGatherNd(Id, Id, usize),
+    TfTakeAlongAxis(Id, Id, usize),
+    // 第二个是axis，第三个表示是否降序
+    TfSort(Id, usize, bool),
+    TfArgsort(Id, usize, bool),
+    // tf.math.top_k的两个结果，第二个操作数是k
+    TfTopKValues(Id, Id),
+    TfTopKIndices(Id, Id),
+    // 第二个是形状：TfReshape的形状是个操作数，TfReshapeTo的是合成出来的常量
+    TfReshape(Id, Id),
+    TfReshapeTo(Id, [i64; 2]),
+    TfSqueeze(Id),
+    TfTile(Id, Id),
+    // 第三个是axis
+    TfStack(Id, Id, usize),
+    TfScatterNd(Id, Id, Id),
+    TfTensorScatterNdUpdate(Id, Id, Id),
+    TfTensorScatterNdAdd(Id, Id, Id),
+    TfSparseToDense(Id, Id, Id),
+    TfLess(Id, Id),
+    TfLessEqual(Id, Id),
+    TfGreaterEqual(Id, Id),
+    TfLogicalAnd(Id, Id),
+    TfLogicalOr(Id, Id),
+    TfLogicalXor(Id, Id),
+    TfLogicalNot(Id),
+    TfAbs(Id),
+    TfNegative(Id),
+    TfSign(Id),
+    TfFloorDiv(Id, Id),
+    TfFloorMod(Id, Id),
+    TfPow(Id, Id),
+    TfClipByValue(Id, Id, Id),
+    // 切片的参数：begin和size，或者begin、end和strides；填充的是每个轴前后各补多少
+    TfSlice(Id, [i64; 2], [i64; 2]),
+    TfStridedSlice(Id, [i64; 2], [i64; 2], [i64; 2]),
+    TfPad(Id, [[i64; 2]; 2]),
+    TfReverse(Id, usize),
+
+    // 草图中的洞：可选的形状约束，以及合成后填进去的值（未填时为None）
+    Hole(Option<[usize; 2]>, Option<Id>),
 }
 
 impl Operator {
     pub fn arity(&self) -> usize {
         match self {
-            Operator::Var => 0,
+            Operator::Var | Operator::Hole(_, None) => 0,
             // | Operator::Const(_) => 0,
 
-            Operator::TfArgmax(_)
-            | Operator::TfCast(_)
+            Operator::TfArgmax(_, _)
+            | Operator::TfArgmin(_, _)
+            | Operator::TfCast(_, _)
             | Operator::TfConstant(_)
             | Operator::TfExpandDims(_)
             | Operator::TfBincount(_)
-            | Operator::TfReduceMax(_)
-            | Operator::TfReduceMax0(_)
-            | Operator::TfReduceMax1(_)
-            | Operator::TfReduceSum(_)
-            | Operator::TfReduceSum0(_)
-            | Operator::TfReduceSum1(_)
+            | Operator::TfCumulative(_, _, _, _, _)
+            | Operator::TfReduce(_, _, _, _)
             | Operator::TfSequenceMask(_)
             | Operator::TfSquare(_)
             | Operator::TfTranspose(_)
             | Operator::TfWhere1(_)
 
             | Operator::TfOnes(_)
-            | Operator::TfReduceAny0(_)
-            | Operator::TfReduceAny1(_)
-            | Operator::TfReduceMean(_)
-            | Operator::TfReduceProd(_)
-            | Operator::TfRoll(_)
+            | Operator::TfRoll(_, _, _)
             | Operator::TfZeros(_)
+            | Operator::TfSort(_, _, _)
+            | Operator::TfArgsort(_, _, _)
+            | Operator::TfReshapeTo(_, _)
+            | Operator::TfSqueeze(_)
+            | Operator::TfLogicalNot(_)
+            | Operator::TfAbs(_)
+            | Operator::TfNegative(_)
+            | Operator::TfSign(_)
+            | Operator::TfSlice(_, _, _)
+            | Operator::TfStridedSlice(_, _, _, _)
+            | Operator::TfPad(_, _)
+            | Operator::TfReverse(_, _)
+            | Operator::Hole(_, Some(_))
             => 1,
             Operator::TfAdd(_, _)
             | Operator::TfBooleanMask(_, _)
@@ -100,7 +144,6 @@ impl Operator {
             | Operator::TfDivide(_, _)
             | Operator::TfEqual(_, _)
             | Operator::TfGreater(_, _)
-            | Operator::TfCumsum(_, _)
             | Operator::TfMultiply(_, _)
             | Operator::TfOneHot(_, _)
             | Operator::TfRange(_, _)
@@ -109,13 +152,36 @@ impl Operator {
 
             | Operator::TfEye(_, _)
             | Operator::TfFill(_, _)
-            | Operator::TfSegmentMax(_, _)
+            | Operator::TfSegment(_, _, _)
             | Operator::TfMatmul(_, _)
             | Operator::TfMaximum(_, _)
             | Operator::TfMinimum(_, _)
             | Operator::TfNotEqual(_, _)
+            | Operator::TfGather(_, _, _)
+            | Operator::TfGatherNd(_, _, _)
+            | Operator::TfTakeAlongAxis(_, _, _)
+            | Operator::TfTopKValues(_, _)
+            | Operator::TfTopKIndices(_, _)
+            | Operator::TfReshape(_, _)
+            | Operator::TfTile(_, _)
+            | Operator::TfStack(_, _, _)
+            | Operator::TfLess(_, _)
+            | Operator::TfLessEqual(_, _)
+            | Operator::TfGreaterEqual(_, _)
+            | Operator::TfLogicalAnd(_, _)
+            | Operator::TfLogicalOr(_, _)
+            | Operator::TfLogicalXor(_, _)
+            | Operator::TfFloorDiv(_, _)
+            | Operator::TfFloorMod(_, _)
+            | Operator::TfPow(_, _)
             => 2,
             | Operator::TfWhere3(_, _, _)
+            | Operator::TfUnsortedSegment(_, _, _, _)
+            | Operator::TfScatterNd(_, _, _)
+            | Operator::TfTensorScatterNdUpdate(_, _, _)
+            | Operator::TfTensorScatterNdAdd(_, _, _)
+            | Operator::TfSparseToDense(_, _, _)
+            | Operator::TfClipByValue(_, _, _)
             => 3,
         }
     }
@@ -130,30 +196,37 @@ impl Operator {
         match *self {
             Operator::Var 
             // | Operator::Const(_) 
+            | Operator::Hole(_, None)
             => {},
-            Operator::TfArgmax(a)
-            | Operator::TfCast(a)
+            Operator::TfArgmax(a, _)
+            | Operator::TfArgmin(a, _)
+            | Operator::TfCast(a, _)
             | Operator::TfConstant(a)
             | Operator::TfExpandDims(a)
             | Operator::TfBincount(a)
-            | Operator::TfReduceMax(a)
-            | Operator::TfReduceMax0(a)
-            | Operator::TfReduceMax1(a)
-            | Operator::TfReduceSum(a)
-            | Operator::TfReduceSum0(a)
-            | Operator::TfReduceSum1(a)
+            | Operator::TfCumulative(a, _, _, _, _)
+            | Operator::TfReduce(a, _, _, _)
             | Operator::TfSequenceMask(a)
             | Operator::TfSquare(a)
             | Operator::TfTranspose(a)
             | Operator::TfWhere1(a)
 
             | Operator::TfOnes(a)
-            | Operator::TfReduceAny0(a)
-            | Operator::TfReduceAny1(a)
-            | Operator::TfReduceMean(a)
-            | Operator::TfReduceProd(a)
-            | Operator::TfRoll(a)
+            | Operator::TfRoll(a, _, _)
             | Operator::TfZeros(a)
+            | Operator::TfSort(a, _, _)
+            | Operator::TfArgsort(a, _, _)
+            | Operator::TfReshapeTo(a, _)
+            | Operator::TfSqueeze(a)
+            | Operator::TfLogicalNot(a)
+            | Operator::TfAbs(a)
+            | Operator::TfNegative(a)
+            | Operator::TfSign(a)
+            | Operator::TfSlice(a, _, _)
+            | Operator::TfStridedSlice(a, _, _, _)
+            | Operator::TfPad(a, _)
+            | Operator::TfReverse(a, _)
+            | Operator::Hole(_, Some(a))
             => {
                 f(a);
             },
@@ -165,7 +238,6 @@ impl Operator {
             | Operator::TfDivide(a, b)
             | Operator::TfEqual(a, b)
             | Operator::TfGreater(a, b)
-            | Operator::TfCumsum(a, b)
             | Operator::TfMultiply(a, b)
             | Operator::TfOneHot(a, b)
             | Operator::TfRange(a, b)
@@ -174,16 +246,39 @@ impl Operator {
 
             | Operator::TfEye(a, b)
             | Operator::TfFill(a, b)
-            | Operator::TfSegmentMax(a, b)
+            | Operator::TfSegment(a, b, _)
             | Operator::TfMatmul(a, b)
             | Operator::TfMaximum(a, b)
             | Operator::TfMinimum(a, b)
             | Operator::TfNotEqual(a, b)
+            | Operator::TfGather(a, b, _)
+            | Operator::TfGatherNd(a, b, _)
+            | Operator::TfTakeAlongAxis(a, b, _)
+            | Operator::TfTopKValues(a, b)
+            | Operator::TfTopKIndices(a, b)
+            | Operator::TfReshape(a, b)
+            | Operator::TfTile(a, b)
+            | Operator::TfStack(a, b, _)
+            | Operator::TfLess(a, b)
+            | Operator::TfLessEqual(a, b)
+            | Operator::TfGreaterEqual(a, b)
+            | Operator::TfLogicalAnd(a, b)
+            | Operator::TfLogicalOr(a, b)
+            | Operator::TfLogicalXor(a, b)
+            | Operator::TfFloorDiv(a, b)
+            | Operator::TfFloorMod(a, b)
+            | Operator::TfPow(a, b)
             => {
                 f(a);
                 f(b);
             },
             | Operator::TfWhere3(a, b, c)
+            | Operator::TfUnsortedSegment(a, b, c, _)
+            | Operator::TfScatterNd(a, b, c)
+            | Operator::TfTensorScatterNdUpdate(a, b, c)
+            | Operator::TfTensorScatterNdAdd(a, b, c)
+            | Operator::TfSparseToDense(a, b, c)
+            | Operator::TfClipByValue(a, b, c)
             => {
                 f(a);
                 f(b);
@@ -196,30 +291,37 @@ impl Operator {
         match self {
             Operator::Var 
             // | Operator::Const(_) 
+            | Operator::Hole(_, None)
             => {},
-            Operator::TfArgmax(a)
-            | Operator::TfCast(a)
+            Operator::TfArgmax(a, _)
+            | Operator::TfArgmin(a, _)
+            | Operator::TfCast(a, _)
             | Operator::TfConstant(a)
             | Operator::TfExpandDims(a)
             | Operator::TfBincount(a)
-            | Operator::TfReduceMax(a)
-            | Operator::TfReduceMax0(a)
-            | Operator::TfReduceMax1(a)
-            | Operator::TfReduceSum(a)
-            | Operator::TfReduceSum0(a)
-            | Operator::TfReduceSum1(a)
+            | Operator::TfCumulative(a, _, _, _, _)
+            | Operator::TfReduce(a, _, _, _)
             | Operator::TfSequenceMask(a)
             | Operator::TfSquare(a)
             | Operator::TfTranspose(a)
             | Operator::TfWhere1(a)
 
             | Operator::TfOnes(a)
-            | Operator::TfReduceAny0(a)
-            | Operator::TfReduceAny1(a)
-            | Operator::TfReduceMean(a)
-            | Operator::TfReduceProd(a)
-            | Operator::TfRoll(a)
+            | Operator::TfRoll(a, _, _)
             | Operator::TfZeros(a)
+            | Operator::TfSort(a, _, _)
+            | Operator::TfArgsort(a, _, _)
+            | Operator::TfReshapeTo(a, _)
+            | Operator::TfSqueeze(a)
+            | Operator::TfLogicalNot(a)
+            | Operator::TfAbs(a)
+            | Operator::TfNegative(a)
+            | Operator::TfSign(a)
+            | Operator::TfSlice(a, _, _)
+            | Operator::TfStridedSlice(a, _, _, _)
+            | Operator::TfPad(a, _)
+            | Operator::TfReverse(a, _)
+            | Operator::Hole(_, Some(a))
             => {
                 f(a);
             },
@@ -231,7 +333,6 @@ impl Operator {
             | Operator::TfDivide(a, b)
             | Operator::TfEqual(a, b)
             | Operator::TfGreater(a, b)
-            | Operator::TfCumsum(a, b)
             | Operator::TfMultiply(a, b)
             | Operator::TfOneHot(a, b)
             | Operator::TfRange(a, b)
@@ -240,16 +341,39 @@ impl Operator {
 
             | Operator::TfEye(a, b)
             | Operator::TfFill(a, b)
-            | Operator::TfSegmentMax(a, b)
+            | Operator::TfSegment(a, b, _)
             | Operator::TfMatmul(a, b)
             | Operator::TfMaximum(a, b)
             | Operator::TfMinimum(a, b)
             | Operator::TfNotEqual(a, b)
+            | Operator::TfGather(a, b, _)
+            | Operator::TfGatherNd(a, b, _)
+            | Operator::TfTakeAlongAxis(a, b, _)
+            | Operator::TfTopKValues(a, b)
+            | Operator::TfTopKIndices(a, b)
+            | Operator::TfReshape(a, b)
+            | Operator::TfTile(a, b)
+            | Operator::TfStack(a, b, _)
+            | Operator::TfLess(a, b)
+            | Operator::TfLessEqual(a, b)
+            | Operator::TfGreaterEqual(a, b)
+            | Operator::TfLogicalAnd(a, b)
+            | Operator::TfLogicalOr(a, b)
+            | Operator::TfLogicalXor(a, b)
+            | Operator::TfFloorDiv(a, b)
+            | Operator::TfFloorMod(a, b)
+            | Operator::TfPow(a, b)
              => {
                 f(a);
                 f(b);
             },
             | Operator::TfWhere3(a, b, c)
+            | Operator::TfUnsortedSegment(a, b, c, _)
+            | Operator::TfScatterNd(a, b, c)
+            | Operator::TfTensorScatterNdUpdate(a, b, c)
+            | Operator::TfTensorScatterNdAdd(a, b, c)
+            | Operator::TfSparseToDense(a, b, c)
+            | Operator::TfClipByValue(a, b, c)
             => {
                 f(a);
                 f(b);
@@ -265,10 +389,11 @@ impl Display for Operator {
             Operator::Var => write!(f, "var: vec"),
             //Operator::Const(c) => write!(f, "const: {:?}", c),
             Operator::TfAdd(a, b) => write!(f, "TfAdd: {}, {}", a, b),
-            Operator::TfArgmax(a) => write!(f, "TfArgmax: {}, axis = 1", a),
+            Operator::TfArgmax(a, axis) => write!(f, "TfArgmax: {}, axis = {}", a, axis),
+            Operator::TfArgmin(a, axis) => write!(f, "TfArgmin: {}, axis = {}", a, axis),
             Operator::TfBooleanMask(a, b) => write!(f, "TfBooleanMask: {}, {}", a, b),
             Operator::TfBooleanMask_(a, b) => write!(f, "TfBooleanMask: {}, {}", a, b),
-            Operator::TfCast(a) => write!(f, "TfCast: {}", a),
+            Operator::TfCast(a, dtype) => write!(f, "TfCast: {}, dtype = {}", a, dtype),
             Operator::TfConcat0(a, b) => write!(f, "TfConcat: {}, {}, axis = 0", a, b),
             Operator::TfConcat1(a, b) => write!(f, "TfConcat: {}, {}, axis = 1", a, b),
             Operator::TfConstant(a) => write!(f, "TfConstant: {}", a),
@@ -277,16 +402,38 @@ impl Display for Operator {
             Operator::TfExpandDims(a) => write!(f, "TfExpandDims: {}, axis = 1", a),
             Operator::TfGreater(a, b) => write!(f, "TfGreater: {}, {}", a, b),
             Operator::TfBincount(a) => write!(f, "TfBincount: {}", a),
-            Operator::TfCumsum(a, b) => write!(f, "TfCumsum: {}, {}", a, b),
+            Operator::TfCumulative(a, reduction, axis, exclusive, reverse) => {
+                let name = match reduction {
+                    Reduction::Sum => "TfCumsum",
+                    Reduction::Prod => "TfCumprod",
+                    Reduction::Max => "TfCummax",
+                    _ => "TfCummin",
+                };
+                write!(f, "{}: {}, axis = {}", name, a, axis)?;
+                if *exclusive {
+                    write!(f, ", exclusive = True")?;
+                }
+                if *reverse {
+                    write!(f, ", reverse = True")?;
+                }
+                Ok(())
+            }
             Operator::TfMultiply(a, b) => write!(f, "TfMultiply: {}, {}", a, b),
             Operator::TfOneHot(a, b) => write!(f, "TfOneHot: {}, {}", a, b),
             Operator::TfRange(a, b) => write!(f, "TfRange: {}, {}", a, b),
-            Operator::TfReduceMax(a) => write!(f, "TfReduceMax: {}", a),
-            Operator::TfReduceMax0(a) => write!(f, "TfReduceMax: {}, axis = 0", a),
-            Operator::TfReduceMax1(a) => write!(f, "TfReduceMax: {}, axis = 1", a),
-            Operator::TfReduceSum(a) => write!(f, "TfReduceSum: {}", a),
-            Operator::TfReduceSum0(a) => write!(f, "TfReduceSum: {}, axis = 0", a),
-            Operator::TfReduceSum1(a) => write!(f, "TfReduceSum: {}, axis = 1", a),
+            Operator::TfReduce(a, reduction, axis, keepdims) => {
+                match reduction {
+                    Reduction::Count => write!(f, "TfCountNonzero: {}", a)?,
+                    _ => write!(f, "TfReduce{}: {}", reduction, a)?,
+                }
+                if let Some(axis) = axis {
+                    write!(f, ", axis = {}", axis)?;
+                }
+                if *keepdims {
+                    write!(f, ", keepdims = True")?;
+                }
+                Ok(())
+            }
             Operator::TfSequenceMask(a) => write!(f, "TfSequenceMask: {}", a),
             Operator::TfSquare(a) => write!(f, "TfSquare: {}", a),
             Operator::TfSubtract(a, b) => write!(f, "TfSubtract: {}, {}", a, b),
@@ -297,18 +444,105 @@ impl Display for Operator {
 
             Operator::TfEye(a, b) => write!(f, "TfEye: {}, {}", a, b),
             Operator::TfFill(a, b) => write!(f, "TfFill: {}, {}", a, b),
-            Operator::TfSegmentMax(a, b) => write!(f, "TfSegmentMax: {}, {}", a, b),
+            Operator::TfSegment(a, b, reduction) => write!(f, "TfSegment{}: {}, {}", reduction, a, b),
+            Operator::TfUnsortedSegment(a, b, c, reduction) => {
+                write!(f, "TfUnsortedSegment{}: {}, {}, {}", reduction, a, b, c)
+            }
             Operator::TfMatmul(a, b) => write!(f, "TfMatmul: {}, {}", a, b),
             Operator::TfMaximum(a, b) => write!(f, "TfMaximum: {}, {}", a, b),
             Operator::TfMinimum(a, b) => write!(f, "TfMinimum: {}, {}", a, b),
             Operator::TfNotEqual(a, b) => write!(f, "TfNotEqual: {}, {}", a, b),
             Operator::TfOnes(a) => write!(f, "TfOnes: {}", a),
-            Operator::TfReduceAny0(a) => write!(f, "TfReduceAny: {}, axis = 0", a),
-            Operator::TfReduceAny1(a) => write!(f, "TfReduceAny: {}, axis = 1", a),
-            Operator::TfReduceMean(a) => write!(f, "TfReduceMean: {}, axis = 0", a),
-            Operator::TfReduceProd(a) => write!(f, "TfReduceProd: {}, axis = 1", a),
-            Operator::TfRoll(a) => write!(f, "TfRoll: {}", a),
+            Operator::TfRoll(a, shift, axis) => write!(f, "TfRoll: {}, shift = {}, axis = {}", a, shift, axis),
             Operator::TfZeros(a) => write!(f, "TfZeros: {}", a),
+            Operator::TfGather(a, b, axis) => write!(f, "TfGather: {}, {}, axis = {}", a, b, axis),
+            Operator::TfGatherNd(a, b, batch_dims) => {
+                write!(f, "TfGatherNd: {}, {}, batch_dims = {}", a, b, batch_dims)
+            }
+            Operator::TfTakeAlongAxis(a, b, axis) => {
+                write!(f, "TfTakeAlongAxis: {}, {}, axis = {}", a, b, axis)
+            }
+            Operator::TfSort(a, axis, descending) => {
+                write!(f, "TfSort: {}, axis = {}, direction = {}", a, axis, direction(*descending))
+            }
+            Operator::TfArgsort(a, axis, descending) => {
+                write!(f, "TfArgsort: {}, axis = {}, direction = {}", a, axis, direction(*descending))
+            }
+            Operator::TfTopKValues(a, b) => write!(f, "TfTopK: {}, {}, .values", a, b),
+            Operator::TfTopKIndices(a, b) => write!(f, "TfTopK: {}, {}, .indices", a, b),
+            Operator::TfReshape(a, b) => write!(f, "TfReshape: {}, {}", a, b),
+            Operator::TfReshapeTo(a, shape) => write!(f, "TfReshape: {}, shape = {:?}", a, shape),
+            Operator::TfSqueeze(a) => write!(f, "TfSqueeze: {}", a),
+            Operator::TfTile(a, b) => write!(f, "TfTile: {}, {}", a, b),
+            Operator::TfStack(a, b, axis) => write!(f, "TfStack: [{}, {}], axis = {}", a, b, axis),
+            Operator::TfScatterNd(a, b, c) => write!(f, "TfScatterNd: {}, {}, {}", a, b, c),
+            Operator::TfTensorScatterNdUpdate(a, b, c) => write!(f, "TfTensorScatterNdUpdate: {}, {}, {}", a, b, c),
+            Operator::TfTensorScatterNdAdd(a, b, c) => write!(f, "TfTensorScatterNdAdd: {}, {}, {}", a, b, c),
+            Operator::TfSparseToDense(a, b, c) => write!(f, "TfSparseToDense: {}, {}, {}", a, b, c),
+            Operator::TfLess(a, b) => write!(f, "TfLess: {}, {}", a, b),
+            Operator::TfLessEqual(a, b) => write!(f, "TfLessEqual: {}, {}", a, b),
+            Operator::TfGreaterEqual(a, b) => write!(f, "TfGreaterEqual: {}, {}", a, b),
+            Operator::TfLogicalAnd(a, b) => write!(f, "TfLogicalAnd: {}, {}", a, b),
+            Operator::TfLogicalOr(a, b) => write!(f, "TfLogicalOr: {}, {}", a, b),
+            Operator::TfLogicalXor(a, b) => write!(f, "TfLogicalXor: {}, {}", a, b),
+            Operator::TfLogicalNot(a) => write!(f, "TfLogicalNot: {}", a),
+            Operator::TfAbs(a) => write!(f, "TfAbs: {}", a),
+            Operator::TfNegative(a) => write!(f, "TfNegative: {}", a),
+            Operator::TfSign(a) => write!(f, "TfSign: {}", a),
+            Operator::TfFloorDiv(a, b) => write!(f, "TfFloorDiv: {}, {}", a, b),
+            Operator::TfFloorMod(a, b) => write!(f, "TfFloorMod: {}, {}", a, b),
+            Operator::TfPow(a, b) => write!(f, "TfPow: {}, {}", a, b),
+            Operator::TfClipByValue(a, b, c) => write!(f, "TfClipByValue: {}, {}, {}", a, b, c),
+            Operator::TfSlice(a, begin, size) => write!(f, "TfSlice: {}, begin = {:?}, size = {:?}", a, begin, size),
+            Operator::TfStridedSlice(a, begin, end, strides) => write!(
+                f,
+                "TfStridedSlice: {}, begin = {:?}, end = {:?}, strides = {:?}",
+                a, begin, end, strides
+            ),
+            Operator::TfPad(a, paddings) => write!(f, "TfPad: {}, paddings = {:?}", a, paddings),
+            Operator::TfReverse(a, axis) => write!(f, "TfReverse: {}, axis = [{}]", a, axis),
+            Operator::Hole(_, Some(a)) => write!(f, "Hole: {}", a),
+            Operator::Hole(Some(shape), None) => write!(f, "??: {:?}", shape),
+            Operator::Hole(None, None) => write!(f, "??"),
+        }
+    }
+}
+
+fn direction(descending: bool) -> &'static str {
+    if descending {
+        "DESCENDING"
+    } else {
+        "ASCENDING"
+    }
+}
+
+/// How a reduction combines values. `Any`, `All` and `Count` read nonzero
+/// values as true, and segments only support the first five.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Reduction {
+    Sum,
+    Prod,
+    Max,
+    Min,
+    Mean,
+    Any,
+    All,
+    Count,
+    Variance,
+}
+
+impl Display for Reduction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reduction::Sum => write!(f, "Sum"),
+            Reduction::Prod => write!(f, "Prod"),
+            Reduction::Max => write!(f, "Max"),
+            Reduction::Min => write!(f, "Min"),
+            Reduction::Mean => write!(f, "Mean"),
+            Reduction::Any => write!(f, "Any"),
+            Reduction::All => write!(f, "All"),
+            Reduction::Count => write!(f, "Count"),
+            Reduction::Variance => write!(f, "Variance"),
         }
     }
 }
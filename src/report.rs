@@ -0,0 +1,156 @@
+//! Statistics about a synthesis run, for building benchmark tables.
+
+use crate::term::{self, Ast, Wrap};
+use std::fmt::{self, Display};
+use std::time::Duration;
+use crate::backend::SatResult;
+
+/// Why `Synthesizer::synthesize` stopped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum StopReason {
+    /// We haven't run yet.
+    #[default]
+    NotStarted,
+    /// The cache already had a solution, so we never called the solver.
+    Cached,
+    /// We found a program, and searched for shorter ones as far as we were
+    /// asked to.
+    Solved,
+    /// There is no program of any length we tried.
+    Unsatisfiable,
+    /// The solver gave up, for the given reason (e.g. "timeout"). We may still
+    /// have found a (longer) program before this happened.
+    Unknown(String),
+}
+
+/// What the solver reported about a single query. Solvers that don't report
+/// some figure leave it out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SolverStatistics {
+    pub conflicts: Option<u64>,
+    pub decisions: Option<u64>,
+    /// The solver's memory use after the query, in megabytes.
+    pub memory: Option<f64>,
+}
+
+impl SolverStatistics {
+    /// Take note of the statistic `key`, as a solver named it.
+    pub(crate) fn record(&mut self, key: &str, value: f64) {
+        // Z3的SAT内核和SMT内核各有一份冲突和决策的计数，加起来才是总数；
+        // SMT-LIB2的`get-info`用`-`代替键里的空格
+        let add = |count: &mut Option<u64>| *count = Some(count.unwrap_or(0) + value as u64);
+        match key.trim_start_matches(':').replace('-', " ").as_str() {
+            "conflicts" | "sat conflicts" => add(&mut self.conflicts),
+            "decisions" | "sat decisions" => add(&mut self.decisions),
+            "memory" => self.memory = Some(value),
+            _ => {}
+        }
+    }
+}
+
+/// A single query handed to the solver.
+#[derive(Clone, Debug)]
+pub struct SolverCall {
+    /// What the query was for: "synthesis", "verification" or
+    /// "explanation".
+    pub kind: &'static str,
+    /// The length of program we asked for.
+    pub program_length: u32,
+    /// Time spent building the query.
+    pub encoding: Duration,
+    /// Time spent in the solver.
+    pub solving: Duration,
+    /// The number of distinct AST nodes in the query. Only measured by
+    /// `Synthesizer::synthesize_with_report`; it is zero otherwise.
+    pub query_size: usize,
+    pub result: SatResult,
+    /// The solver's explanation, if `result` is `Unknown`.
+    pub reason_unknown: Option<String>,
+    pub statistics: SolverStatistics,
+}
+
+/// Statistics about a run of `Synthesizer::synthesize_with_report`.
+#[derive(Clone, Debug, Default)]
+pub struct SynthesisReport {
+    /// Time spent building the well-formedness constraints when the
+    /// synthesizer was created.
+    pub setup: Duration,
+    /// Time spent in `synthesize_with_report` itself.
+    pub total: Duration,
+    /// Every program length we tried, in order.
+    pub lengths_tried: Vec<u32>,
+    /// Every query we handed to the solver, in order.
+    pub solver_calls: Vec<SolverCall>,
+    pub stop_reason: StopReason,
+}
+
+impl SynthesisReport {
+    /// Total time spent building queries.
+    pub fn encoding(&self) -> Duration {
+        self.solver_calls.iter().map(|c| c.encoding).sum()
+    }
+
+    /// Total time spent in the solver.
+    pub fn solving(&self) -> Duration {
+        self.solver_calls.iter().map(|c| c.solving).sum()
+    }
+
+    /// Total conflicts, over the queries whose solver reported them.
+    pub fn conflicts(&self) -> u64 {
+        self.solver_calls.iter().filter_map(|c| c.statistics.conflicts).sum()
+    }
+
+    /// Total decisions, over the queries whose solver reported them.
+    pub fn decisions(&self) -> u64 {
+        self.solver_calls.iter().filter_map(|c| c.statistics.decisions).sum()
+    }
+}
+
+impl Display for SynthesisReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "stopped: {:?}", self.stop_reason)?;
+        writeln!(f, "lengths tried: {:?}", self.lengths_tried)?;
+        writeln!(
+            f,
+            "time: setup {:.3?}, encoding {:.3?}, solving {:.3?}, total {:.3?}",
+            self.setup,
+            self.encoding(),
+            self.solving(),
+            self.total
+        )?;
+        writeln!(
+            f,
+            "solver calls: {} ({} conflicts, {} decisions)",
+            self.solver_calls.len(),
+            self.conflicts(),
+            self.decisions()
+        )?;
+        for call in &self.solver_calls {
+            write!(
+                f,
+                "  {} length {}: {:?} (encoding {:.3?}, solving {:.3?}, {} nodes",
+                call.kind, call.program_length, call.result, call.encoding, call.solving, call.query_size
+            )?;
+            let stats = &call.statistics;
+            if let Some(conflicts) = stats.conflicts {
+                write!(f, ", {} conflicts", conflicts)?;
+            }
+            if let Some(decisions) = stats.decisions {
+                write!(f, ", {} decisions", decisions)?;
+            }
+            if let Some(memory) = stats.memory {
+                write!(f, ", {:.2} MB", memory)?;
+            }
+            writeln!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// The number of distinct nodes in `asts`, counting shared subterms once.
+pub(crate) fn ast_size<'a>(asts: &[impl Ast<'a>]) -> usize {
+    let mut size = 0;
+    term::post_order(asts.iter().map(Wrap::term), |_| size += 1);
+    size
+}
+
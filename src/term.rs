@@ -0,0 +1,1435 @@
+//! Solver-independent terms.
+//!
+//! The encodings build their queries out of the terms here, and a `Backend`
+//! hands them to whichever solver decides them: `Z3Backend` translates them
+//! into Z3's terms, and `SmtLib2Backend` prints them as SMT-LIB2. The API
+//! follows the `z3` crate's, which the encodings were first written against.
+//!
+//! Terms aren't hash-consed, but they are shared: cloning a term is cheap, and
+//! a subterm used in several places is stored (and printed, and translated)
+//! once. Operations whose operands are all literals are folded as they're
+//! built, with SMT-LIB's semantics, so a term over literals already is its
+//! value and `simplify` has nothing left to do.
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+/// Hands out the names of fresh constants. Terms built for one query should
+/// all come from the same context, so that their fresh names don't clash.
+#[derive(Debug, Default)]
+pub struct Context {
+    fresh: Cell<u64>,
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context::default()
+    }
+
+    fn fresh_name(&self, prefix: &str) -> String {
+        let n = self.fresh.get();
+        self.fresh.set(n + 1);
+        format!("{}!{}", prefix, n)
+    }
+}
+
+/// The sort of a term.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Sort {
+    Bool,
+    Int,
+    Real,
+    /// Bit-vectors of the given width, which is at most 128.
+    BitVec(u32),
+    /// Arrays from the first sort to the second.
+    Array(Box<Sort>, Box<Sort>),
+}
+
+impl Sort {
+    pub fn bool(_context: &Context) -> Sort {
+        Sort::Bool
+    }
+
+    pub fn int(_context: &Context) -> Sort {
+        Sort::Int
+    }
+
+    pub fn real(_context: &Context) -> Sort {
+        Sort::Real
+    }
+
+    pub fn bitvector(_context: &Context, width: u32) -> Sort {
+        Sort::BitVec(check_width(width))
+    }
+
+    pub fn array(_context: &Context, domain: &Sort, range: &Sort) -> Sort {
+        Sort::Array(Box::new(domain.clone()), Box::new(range.clone()))
+    }
+
+    pub fn kind(&self) -> SortKind {
+        match self {
+            Sort::Bool => SortKind::Bool,
+            Sort::Int => SortKind::Int,
+            Sort::Real => SortKind::Real,
+            Sort::BitVec(_) => SortKind::BV,
+            Sort::Array(..) => SortKind::Array,
+        }
+    }
+
+    /// The value a constant of this sort takes when nothing says otherwise.
+    pub(crate) fn zero(&self) -> Value {
+        match self {
+            Sort::Bool => Value::Bool(false),
+            Sort::Int => Value::Int(0),
+            Sort::Real => Value::Real(0, 1),
+            Sort::BitVec(width) => Value::BV(0, *width),
+            Sort::Array(_, range) => Value::Array(Box::new(range.zero()), vec![]),
+        }
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sort::Bool => write!(f, "Bool"),
+            Sort::Int => write!(f, "Int"),
+            Sort::Real => write!(f, "Real"),
+            Sort::BitVec(width) => write!(f, "(_ BitVec {})", width),
+            Sort::Array(domain, range) => write!(f, "(Array {} {})", domain, range),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SortKind {
+    Bool,
+    Int,
+    Real,
+    BV,
+    Array,
+}
+
+/// A literal value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Value {
+    Bool(bool),
+    Int(i128),
+    /// A fraction in lowest terms, with a positive denominator.
+    Real(i128, i128),
+    /// The bits of a bit-vector of the given width, with the bits above it
+    /// all zero.
+    BV(u128, u32),
+    /// An array's value everywhere, except at the indices it stores values
+    /// at, latest last.
+    Array(Box<Value>, Vec<(Value, Value)>),
+}
+
+impl Value {
+    /// The fraction `n / d` in lowest terms.
+    pub(crate) fn real(n: i128, d: i128) -> Option<Value> {
+        if d == 0 {
+            return None;
+        }
+        let g = gcd(n, d);
+        let (n, d) = (n / g, d / g);
+        if d < 0 {
+            Some(Value::Real(n.checked_neg()?, d.checked_neg()?))
+        } else {
+            Some(Value::Real(n, d))
+        }
+    }
+
+    /// The bit-vector of the given width holding `n`, wrapped around.
+    pub(crate) fn bv(n: i128, width: u32) -> Value {
+        Value::BV(n as u128 & mask(width), width)
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn as_int(&self) -> Option<i128> {
+        match self {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn as_rational(&self) -> Option<(i128, i128)> {
+        match self {
+            Value::Int(n) => Some((*n, 1)),
+            Value::Real(n, d) => Some((*n, *d)),
+            _ => None,
+        }
+    }
+
+    fn as_bits(&self) -> Option<(u128, u32)> {
+        match self {
+            Value::BV(bits, width) => Some((*bits, *width)),
+            _ => None,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        matches!(self, Value::Int(0) | Value::Real(0, _) | Value::BV(0, _))
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    // 只有分子分母都是0的时候才是0；`Value::real`不会这样调用
+    a.max(1) as i128
+}
+
+/// Our literals hold the bits of a bit-vector in a `u128`.
+fn check_width(width: u32) -> u32 {
+    assert!(0 < width && width <= 128, "unsupported bit-vector width {}", width);
+    width
+}
+
+fn mask(width: u32) -> u128 {
+    u128::MAX >> (128 - width)
+}
+
+/// The two's complement value of `bits`.
+fn signed(bits: u128, width: u32) -> i128 {
+    let shift = 128 - width;
+    ((bits << shift) as i128) >> shift
+}
+
+/// What a term does with its arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Op {
+    /// An uninterpreted constant with the given name.
+    Const(String),
+    Literal(Value),
+    Not,
+    And,
+    Or,
+    Xor,
+    Implies,
+    Eq,
+    Distinct,
+    Ite,
+    Add,
+    Sub,
+    Neg,
+    Mul,
+    /// Integer division, rounding so that the remainder is nonnegative.
+    Div,
+    Mod,
+    /// Division of reals.
+    RealDiv,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    ToReal,
+    /// Round a real down to an integer.
+    ToInt,
+    BvAdd,
+    BvSub,
+    BvMul,
+    BvNeg,
+    BvSdiv,
+    BvSrem,
+    BvSmod,
+    BvUdiv,
+    BvUrem,
+    BvSlt,
+    BvSle,
+    BvUlt,
+    BvUle,
+    /// The unsigned value of a bit-vector.
+    Bv2Nat,
+    /// The low bits of an integer.
+    Int2Bv,
+    Select,
+    Store,
+    /// The array holding its argument everywhere.
+    ConstArray,
+}
+
+impl Op {
+    /// The SMT-LIB2 name of the function, for everything but constants,
+    /// literals and constant arrays.
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Not => "not",
+            Op::And => "and",
+            Op::Or => "or",
+            Op::Xor => "xor",
+            Op::Implies => "=>",
+            Op::Eq => "=",
+            Op::Distinct => "distinct",
+            Op::Ite => "ite",
+            Op::Add => "+",
+            Op::Sub | Op::Neg => "-",
+            Op::Mul => "*",
+            Op::Div => "div",
+            Op::Mod => "mod",
+            Op::RealDiv => "/",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::ToReal => "to_real",
+            Op::ToInt => "to_int",
+            Op::BvAdd => "bvadd",
+            Op::BvSub => "bvsub",
+            Op::BvMul => "bvmul",
+            Op::BvNeg => "bvneg",
+            Op::BvSdiv => "bvsdiv",
+            Op::BvSrem => "bvsrem",
+            Op::BvSmod => "bvsmod",
+            Op::BvUdiv => "bvudiv",
+            Op::BvUrem => "bvurem",
+            Op::BvSlt => "bvslt",
+            Op::BvSle => "bvsle",
+            Op::BvUlt => "bvult",
+            Op::BvUle => "bvule",
+            Op::Bv2Nat => "bv2nat",
+            Op::Select => "select",
+            Op::Store => "store",
+            Op::Const(_) | Op::Literal(_) | Op::Int2Bv | Op::ConstArray => unreachable!(),
+        }
+    }
+}
+
+pub(crate) struct Node {
+    op: Op,
+    sort: Sort,
+    args: Vec<Term>,
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        // 一长串只用一次的子项会递归地析构，可能爆栈，所以自己用栈来拆
+        let mut stack = std::mem::take(&mut self.args);
+        while let Some(term) = stack.pop() {
+            if let Ok(mut node) = Rc::try_unwrap(term.0) {
+                stack.append(&mut node.args);
+            }
+        }
+    }
+}
+
+mod private {
+    use super::Node;
+    use std::rc::Rc;
+
+    /// A shared node of a term.
+    #[derive(Clone)]
+    pub struct Term(pub(super) Rc<Node>);
+
+    impl Term {
+        /// Identifies the node, for as long as the term is alive.
+        pub(crate) fn id(&self) -> usize {
+            Rc::as_ptr(&self.0) as usize
+        }
+    }
+
+    pub trait Wrap {
+        fn term(&self) -> &Term;
+        fn wrap(term: Term) -> Self;
+    }
+}
+
+pub(crate) use private::{Term, Wrap};
+
+impl fmt::Debug for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_term(f, self)
+    }
+}
+
+impl Term {
+    pub(crate) fn op(&self) -> &Op {
+        &self.0.op
+    }
+
+    pub(crate) fn sort(&self) -> &Sort {
+        &self.0.sort
+    }
+
+    pub(crate) fn args(&self) -> &[Term] {
+        &self.0.args
+    }
+
+    fn literal(&self) -> Option<&Value> {
+        match &self.0.op {
+            Op::Literal(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn is(&self, other: &Term) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    fn leaf(op: Op, sort: Sort) -> Term {
+        Term(Rc::new(Node { op, sort, args: vec![] }))
+    }
+
+    fn from_value(value: Value) -> Term {
+        let sort = match &value {
+            Value::Bool(_) => Sort::Bool,
+            Value::Int(_) => Sort::Int,
+            Value::Real(..) => Sort::Real,
+            Value::BV(_, width) => Sort::BitVec(*width),
+            Value::Array(..) => unreachable!("array literals are built from `ConstArray` and `Store`"),
+        };
+        Term::leaf(Op::Literal(value), sort)
+    }
+
+    /// Apply `op`, folding what we can.
+    fn apply(op: Op, sort: Sort, args: Vec<Term>) -> Term {
+        if let Some(term) = Term::fold(&op, &sort, &args) {
+            return term;
+        }
+        Term(Rc::new(Node { op, sort, args }))
+    }
+
+    fn fold(op: &Op, sort: &Sort, args: &[Term]) -> Option<Term> {
+        let literals: Option<Vec<_>> = args.iter().map(|a| a.literal().cloned()).collect();
+        if let Some(literals) = literals {
+            if !matches!(op, Op::Select | Op::Store | Op::ConstArray) {
+                return Some(Term::from_value(apply(op, sort, &literals)?));
+            }
+        }
+        let is_true = |t: &Term| t.literal() == Some(&Value::Bool(true));
+        let is_false = |t: &Term| t.literal() == Some(&Value::Bool(false));
+        match op {
+            Op::And if args.iter().any(is_false) => Some(Term::from_value(Value::Bool(false))),
+            Op::Or if args.iter().any(is_true) => Some(Term::from_value(Value::Bool(true))),
+            Op::And | Op::Or => {
+                let neutral = if *op == Op::And { is_true } else { is_false };
+                let rest: Vec<_> = args.iter().filter(|a| !neutral(a)).cloned().collect();
+                match rest.len() {
+                    0 => Some(Term::from_value(Value::Bool(*op == Op::And))),
+                    1 => rest.into_iter().next(),
+                    n if n < args.len() => Some(Term::apply(op.clone(), sort.clone(), rest)),
+                    _ => None,
+                }
+            }
+            Op::Not => match args[0].op() {
+                Op::Not => Some(args[0].args()[0].clone()),
+                _ => None,
+            },
+            Op::Implies if is_false(&args[0]) || is_true(&args[1]) => Some(Term::from_value(Value::Bool(true))),
+            Op::Implies if is_true(&args[0]) => Some(args[1].clone()),
+            Op::Eq if args[0].is(&args[1]) => Some(Term::from_value(Value::Bool(true))),
+            Op::Ite if is_true(&args[0]) || args[1].is(&args[2]) => Some(args[1].clone()),
+            Op::Ite if is_false(&args[0]) => Some(args[2].clone()),
+            Op::Mul | Op::BvMul => args
+                .iter()
+                .find(|a| a.literal().is_some_and(Value::is_zero))
+                .cloned(),
+            Op::Select => select(&args[0], &args[1]),
+            _ => None,
+        }
+    }
+}
+
+/// Read `index` out of the `store`s and constant array that `array` is built
+/// from, as far as we can tell which one it comes from.
+fn select(array: &Term, index: &Term) -> Option<Term> {
+    let mut array = array;
+    let mut skipped = false;
+    loop {
+        match array.op() {
+            Op::ConstArray => return Some(array.args()[0].clone()),
+            Op::Store => {
+                let (stored_at, value) = (&array.args()[1], &array.args()[2]);
+                if stored_at.is(index) {
+                    return Some(value.clone());
+                }
+                match (stored_at.literal(), index.literal()) {
+                    (Some(a), Some(b)) if a == b => return Some(value.clone()),
+                    (Some(_), Some(_)) => {
+                        array = &array.args()[0];
+                        skipped = true;
+                    }
+                    _ => break,
+                }
+            }
+            _ => break,
+        }
+    }
+    let range = match array.sort() {
+        Sort::Array(_, range) => (**range).clone(),
+        _ => unreachable!(),
+    };
+    if skipped {
+        Some(Term::apply(Op::Select, range, vec![array.clone(), index.clone()]))
+    } else {
+        None
+    }
+}
+
+/// The value of `op` applied to `args`, or `None` if SMT-LIB leaves it
+/// unspecified (dividing by zero) or it doesn't fit in our literals.
+fn apply(op: &Op, sort: &Sort, args: &[Value]) -> Option<Value> {
+    let bools = || args.iter().map(Value::as_bool).collect::<Option<Vec<_>>>();
+    let ints = || args.iter().map(Value::as_int).collect::<Option<Vec<_>>>();
+    let rationals = || args.iter().map(Value::as_rational).collect::<Option<Vec<_>>>();
+    let bits = || args.iter().map(Value::as_bits).collect::<Option<Vec<_>>>();
+    let compare = |f: fn(&std::cmp::Ordering) -> bool| -> Option<Value> {
+        let rs = rationals()?;
+        let (a, b): ((i128, i128), (i128, i128)) = (rs[0], rs[1]);
+        // 分母都是正的，交叉相乘不改变大小关系
+        let order = a.0.checked_mul(b.1)?.cmp(&b.0.checked_mul(a.1)?);
+        Some(Value::Bool(f(&order)))
+    };
+    let width = match sort {
+        Sort::BitVec(width) => *width,
+        _ => args.first().and_then(Value::as_bits).map_or(0, |(_, width)| width),
+    };
+    let bv = |n: i128| Value::bv(n, width);
+    Some(match op {
+        Op::Const(_) | Op::Literal(_) => unreachable!(),
+        Op::Not => Value::Bool(!bools()?[0]),
+        Op::And => Value::Bool(bools()?.into_iter().all(|b| b)),
+        Op::Or => Value::Bool(bools()?.into_iter().any(|b| b)),
+        Op::Xor => Value::Bool(bools()?.into_iter().fold(false, |a, b| a != b)),
+        Op::Implies => {
+            let bs = bools()?;
+            Value::Bool(!bs[0] || bs[1])
+        }
+        Op::Eq | Op::Distinct => {
+            if args.iter().any(|a| matches!(a, Value::Array(..))) {
+                return None;
+            }
+            let all_equal = args.windows(2).all(|w| w[0] == w[1]);
+            let all_distinct = (0..args.len()).all(|i| !args[i + 1..].contains(&args[i]));
+            Value::Bool(if *op == Op::Eq { all_equal } else { all_distinct })
+        }
+        Op::Ite => {
+            if args[0].as_bool()? {
+                args[1].clone()
+            } else {
+                args[2].clone()
+            }
+        }
+        Op::Add | Op::Sub | Op::Neg | Op::Mul if *sort == Sort::Int => {
+            let ns = ints()?;
+            match op {
+                Op::Add => Value::Int(ns.into_iter().try_fold(0i128, i128::checked_add)?),
+                Op::Sub => Value::Int(ns[1..].iter().try_fold(ns[0], |a, &b| a.checked_sub(b))?),
+                Op::Neg => Value::Int(ns[0].checked_neg()?),
+                _ => Value::Int(ns.into_iter().try_fold(1i128, i128::checked_mul)?),
+            }
+        }
+        Op::Add | Op::Sub | Op::Neg | Op::Mul => {
+            let rs = rationals()?;
+            let add = |(an, ad): (i128, i128), (bn, bd): (i128, i128)| -> Option<(i128, i128)> {
+                Some((an.checked_mul(bd)?.checked_add(bn.checked_mul(ad)?)?, ad.checked_mul(bd)?))
+            };
+            let (n, d) = match op {
+                Op::Add => rs.into_iter().try_fold((0, 1), add)?,
+                Op::Sub => rs[1..].iter().try_fold(rs[0], |a, &(n, d)| add(a, (n.checked_neg()?, d)))?,
+                Op::Neg => (rs[0].0.checked_neg()?, rs[0].1),
+                _ => rs
+                    .into_iter()
+                    .try_fold((1i128, 1i128), |(an, ad), (bn, bd)| Some((an.checked_mul(bn)?, ad.checked_mul(bd)?)))?,
+            };
+            Value::real(n, d)?
+        }
+        Op::Div => {
+            let ns = ints()?;
+            Value::Int(ns[0].checked_div_euclid(ns[1])?)
+        }
+        Op::Mod => {
+            let ns = ints()?;
+            Value::Int(ns[0].checked_rem_euclid(ns[1])?)
+        }
+        Op::RealDiv => {
+            let rs = rationals()?;
+            Value::real(rs[0].0.checked_mul(rs[1].1)?, rs[0].1.checked_mul(rs[1].0)?)?
+        }
+        Op::Lt => compare(|o| o.is_lt())?,
+        Op::Le => compare(|o| o.is_le())?,
+        Op::Gt => compare(|o| o.is_gt())?,
+        Op::Ge => compare(|o| o.is_ge())?,
+        Op::ToReal => Value::Real(ints()?[0], 1),
+        Op::ToInt => {
+            let (n, d) = rationals()?[0];
+            Value::Int(n.div_euclid(d))
+        }
+        Op::BvAdd => bv(bits()?.into_iter().fold(0u128, |a, (b, _)| a.wrapping_add(b)) as i128),
+        Op::BvSub => {
+            let bs = bits()?;
+            bv(bs[1..].iter().fold(bs[0].0, |a, (b, _)| a.wrapping_sub(*b)) as i128)
+        }
+        Op::BvMul => bv(bits()?.into_iter().fold(1u128, |a, (b, _)| a.wrapping_mul(b)) as i128),
+        Op::BvNeg => bv((bits()?[0].0 as i128).wrapping_neg()),
+        Op::BvSdiv | Op::BvSrem | Op::BvSmod => {
+            let bs = bits()?;
+            let (a, b) = (signed(bs[0].0, width), signed(bs[1].0, width));
+            match (op, b) {
+                // SMT-LIB定义了除以0：商是全1或1（看被除数的符号），余数是被除数
+                (Op::BvSdiv, 0) => bv(if a < 0 { 1 } else { -1 }),
+                (_, 0) => bv(a),
+                (Op::BvSdiv, _) => bv(a.wrapping_div(b)),
+                (Op::BvSrem, _) => bv(a.wrapping_rem(b)),
+                _ => {
+                    let r = a.wrapping_rem(b);
+                    bv(if r != 0 && (r < 0) != (b < 0) { r.wrapping_add(b) } else { r })
+                }
+            }
+        }
+        Op::BvUdiv | Op::BvUrem => {
+            let bs = bits()?;
+            let (a, b) = (bs[0].0, bs[1].0);
+            match (op, b) {
+                (Op::BvUdiv, 0) => bv(-1),
+                (_, 0) => bv(a as i128),
+                (Op::BvUdiv, _) => bv((a / b) as i128),
+                _ => bv((a % b) as i128),
+            }
+        }
+        Op::BvSlt | Op::BvSle => {
+            let bs = bits()?;
+            let (a, b) = (signed(bs[0].0, width), signed(bs[1].0, width));
+            Value::Bool(if *op == Op::BvSlt { a < b } else { a <= b })
+        }
+        Op::BvUlt | Op::BvUle => {
+            let bs = bits()?;
+            Value::Bool(if *op == Op::BvUlt { bs[0].0 < bs[1].0 } else { bs[0].0 <= bs[1].0 })
+        }
+        Op::Bv2Nat => Value::Int(i128::try_from(bits()?[0].0).ok()?),
+        Op::Int2Bv => bv(ints()?[0]),
+        Op::Select => match &args[0] {
+            Value::Array(default, stores) => stores
+                .iter()
+                .rev()
+                .find(|(index, _)| *index == args[1])
+                .map_or_else(|| (**default).clone(), |(_, value)| value.clone()),
+            _ => return None,
+        },
+        Op::Store => match &args[0] {
+            Value::Array(default, stores) => {
+                let mut stores = stores.clone();
+                stores.push((args[1].clone(), args[2].clone()));
+                Value::Array(default.clone(), stores)
+            }
+            _ => return None,
+        },
+        Op::ConstArray => Value::Array(Box::new(args[0].clone()), vec![]),
+    })
+}
+
+/// Visit every distinct node of `roots` once, after its arguments.
+pub(crate) fn post_order<'t>(roots: impl IntoIterator<Item = &'t Term>, mut visit: impl FnMut(&'t Term)) {
+    let mut seen = HashSet::new();
+    let mut stack: Vec<_> = roots.into_iter().map(|root| (root, false)).collect();
+    stack.reverse();
+    while let Some((term, expanded)) = stack.pop() {
+        if expanded {
+            visit(term);
+        } else if seen.insert(term.id()) {
+            stack.push((term, true));
+            stack.extend(term.args().iter().rev().map(|arg| (arg, false)));
+        }
+    }
+}
+
+/// The value of `term`, given the values of the constants in it, or `None` if
+/// it depends on something unspecified, like dividing by zero.
+pub(crate) fn evaluate(term: &Term, constant: impl Fn(&str, &Sort) -> Option<Value>) -> Option<Value> {
+    let mut values: HashMap<usize, Option<Value>> = HashMap::new();
+    post_order([term], |t| {
+        let value = match t.op() {
+            Op::Const(name) => constant(name, t.sort()),
+            Op::Literal(value) => Some(value.clone()),
+            // 只看取到的那一支，另一支没有定义也没关系
+            Op::Ite => match values[&t.args()[0].id()] {
+                Some(Value::Bool(true)) => values[&t.args()[1].id()].clone(),
+                Some(Value::Bool(false)) => values[&t.args()[2].id()].clone(),
+                _ => None,
+            },
+            op => t
+                .args()
+                .iter()
+                .map(|arg| values[&arg.id()].clone())
+                .collect::<Option<Vec<_>>>()
+                .and_then(|args| apply(op, t.sort(), &args)),
+        };
+        values.insert(t.id(), value);
+    });
+    values.remove(&term.id()).flatten()
+}
+
+/// The distinct constants in `roots`, by name, in the order they appear.
+pub(crate) fn constants<'t>(roots: impl IntoIterator<Item = &'t Term>) -> Vec<(String, Sort)> {
+    let mut constants = vec![];
+    let mut names = HashSet::new();
+    post_order(roots, |t| {
+        if let Op::Const(name) = t.op() {
+            if names.insert(name.clone()) {
+                constants.push((name.clone(), t.sort().clone()));
+            }
+        }
+    });
+    constants
+}
+
+/// Write `name` as an SMT-LIB2 symbol, quoting it if it needs to be.
+pub(crate) fn symbol(name: &str) -> String {
+    let is_simple = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(c));
+    if is_simple {
+        name.to_string()
+    } else {
+        format!("|{}|", name)
+    }
+}
+
+fn write_literal(f: &mut impl fmt::Write, value: &Value) -> fmt::Result {
+    match value {
+        Value::Bool(b) => write!(f, "{}", b),
+        Value::Int(n) if *n < 0 => write!(f, "(- {})", n.unsigned_abs()),
+        Value::Int(n) => write!(f, "{}", n),
+        Value::Real(n, d) => {
+            let magnitude = if *d == 1 {
+                format!("{}.0", n.unsigned_abs())
+            } else {
+                format!("(/ {}.0 {}.0)", n.unsigned_abs(), d)
+            };
+            if *n < 0 {
+                write!(f, "(- {})", magnitude)
+            } else {
+                write!(f, "{}", magnitude)
+            }
+        }
+        Value::BV(bits, width) => write!(f, "(_ bv{} {})", bits, width),
+        Value::Array(..) => unreachable!(),
+    }
+}
+
+/// Write `root` as SMT-LIB2, binding the subterms it shares with `let`s.
+///
+/// A shared subterm is bound at one more than the deepest level of the
+/// shared subterms inside it, and each level is one `let`, so that every
+/// binding only refers to those of earlier levels.
+fn write_term(f: &mut impl fmt::Write, root: &Term) -> fmt::Result {
+    let mut uses: HashMap<usize, u32> = HashMap::new();
+    post_order([root], |t| {
+        for arg in t.args() {
+            *uses.entry(arg.id()).or_insert(0) += 1;
+        }
+    });
+    let is_shared = |t: &Term| !t.args().is_empty() && uses.get(&t.id()).is_some_and(|&n| n > 1);
+
+    // 每个节点里面最深的共享子项所在的层
+    let mut depths: HashMap<usize, usize> = HashMap::new();
+    let mut levels: Vec<Vec<&Term>> = vec![];
+    post_order([root], |t| {
+        let inner = t.args().iter().map(|arg| depths[&arg.id()]).max().unwrap_or(0);
+        let depth = if is_shared(t) {
+            if levels.len() <= inner {
+                levels.push(vec![]);
+            }
+            levels[inner].push(t);
+            inner + 1
+        } else {
+            inner
+        };
+        depths.insert(t.id(), depth);
+    });
+
+    let mut names = HashMap::new();
+    for t in levels.iter().flatten() {
+        let name = format!("?x{}", names.len());
+        names.insert(t.id(), name);
+    }
+    for level in &levels {
+        write!(f, "(let (")?;
+        for (i, t) in level.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "({} ", names[&t.id()])?;
+            write_expression(f, t, &names)?;
+            write!(f, ")")?;
+        }
+        write!(f, ") ")?;
+    }
+    write_expression(f, root, &names)?;
+    for _ in &levels {
+        write!(f, ")")?;
+    }
+    Ok(())
+}
+
+/// Write `root` itself, referring to the subterms in `names` by name.
+fn write_expression(f: &mut impl fmt::Write, root: &Term, names: &HashMap<usize, String>) -> fmt::Result {
+    enum Item<'t> {
+        Term(&'t Term),
+        Text(&'static str),
+    }
+    let mut stack = vec![Item::Term(root)];
+    while let Some(item) = stack.pop() {
+        let t = match item {
+            Item::Text(text) => {
+                write!(f, "{}", text)?;
+                continue;
+            }
+            Item::Term(t) => t,
+        };
+        if !t.is(root) {
+            if let Some(name) = names.get(&t.id()) {
+                write!(f, "{}", name)?;
+                continue;
+            }
+        }
+        match t.op() {
+            Op::Const(name) => write!(f, "{}", symbol(name))?,
+            Op::Literal(value) => write_literal(f, value)?,
+            op => {
+                match op {
+                    Op::Int2Bv => write!(f, "((_ int2bv {})", match t.sort() {
+                        Sort::BitVec(width) => width,
+                        _ => unreachable!(),
+                    })?,
+                    Op::ConstArray => write!(f, "((as const {})", t.sort())?,
+                    _ => write!(f, "({}", op.name())?,
+                }
+                stack.push(Item::Text(")"));
+                for arg in t.args().iter().rev() {
+                    stack.push(Item::Term(arg));
+                    stack.push(Item::Text(" "));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Something built out of terms of a particular sort.
+pub trait Ast<'ctx>: Wrap + Clone + fmt::Debug + fmt::Display + Into<Dynamic<'ctx>> {
+    /// `self = other`.
+    fn _eq(&self, other: &Self) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Eq, Sort::Bool, self, other))
+    }
+
+    /// Every one of `values` is different.
+    fn distinct(_context: &'ctx Context, values: &[&Self]) -> Bool<'ctx> {
+        Bool::wrap(Term::apply(Op::Distinct, Sort::Bool, terms(values)))
+    }
+
+    fn get_sort(&self) -> Sort {
+        self.term().sort().clone()
+    }
+
+    /// Terms are simplified as they're built, so this is the term itself.
+    fn simplify(&self) -> Self {
+        self.clone()
+    }
+
+    /// The arguments of the term's outermost operation.
+    fn children(&self) -> Vec<Dynamic<'ctx>> {
+        self.term().args().iter().cloned().map(Dynamic::wrap).collect()
+    }
+}
+
+fn terms<T: Wrap>(values: &[&T]) -> Vec<Term> {
+    values.iter().map(|v| v.term().clone()).collect()
+}
+
+fn unary<T: Wrap>(op: Op, sort: Sort, a: &T) -> Term {
+    Term::apply(op, sort, vec![a.term().clone()])
+}
+
+fn binary<A: Wrap, B: Wrap>(op: Op, sort: Sort, a: &A, b: &B) -> Term {
+    Term::apply(op, sort, vec![a.term().clone(), b.term().clone()])
+}
+
+fn constant(name: String, sort: Sort) -> Term {
+    Term::leaf(Op::Const(name), sort)
+}
+
+macro_rules! terms {
+    ($($(#[$attr:meta])* $name:ident),*) => {$(
+        $(#[$attr])*
+        #[derive(Clone)]
+        pub struct $name<'ctx> {
+            term: Term,
+            context: PhantomData<&'ctx Context>,
+        }
+
+        impl Wrap for $name<'_> {
+            fn term(&self) -> &Term {
+                &self.term
+            }
+
+            fn wrap(term: Term) -> Self {
+                $name { term, context: PhantomData }
+            }
+        }
+
+        impl<'ctx> Ast<'ctx> for $name<'ctx> {}
+
+        impl fmt::Display for $name<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write_term(f, &self.term)
+            }
+        }
+
+        impl fmt::Debug for $name<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write_term(f, &self.term)
+            }
+        }
+
+        /// Two terms are equal if they are the same node. Terms that merely
+        /// look alike may compare unequal.
+        impl PartialEq for $name<'_> {
+            fn eq(&self, other: &Self) -> bool {
+                self.term.is(&other.term)
+            }
+        }
+
+        impl Eq for $name<'_> {}
+
+        impl std::hash::Hash for $name<'_> {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.term.id().hash(state)
+            }
+        }
+    )*};
+}
+
+terms! {
+    Bool,
+    Int,
+    Real,
+    /// A bit-vector, whose arithmetic wraps around.
+    BV,
+    Array,
+    /// A term of any sort.
+    Dynamic
+}
+
+macro_rules! into_dynamic {
+    ($($name:ident),*) => {$(
+        impl<'ctx> From<$name<'ctx>> for Dynamic<'ctx> {
+            fn from(term: $name<'ctx>) -> Dynamic<'ctx> {
+                Dynamic::wrap(term.term)
+            }
+        }
+    )*};
+}
+
+into_dynamic!(Bool, Int, Real, BV, Array);
+
+impl<'ctx> Bool<'ctx> {
+    pub fn new_const(_context: &'ctx Context, name: impl Into<String>) -> Bool<'ctx> {
+        Bool::wrap(constant(name.into(), Sort::Bool))
+    }
+
+    pub fn fresh_const(context: &'ctx Context, prefix: &str) -> Bool<'ctx> {
+        Bool::wrap(constant(context.fresh_name(prefix), Sort::Bool))
+    }
+
+    pub fn from_bool(_context: &'ctx Context, b: bool) -> Bool<'ctx> {
+        Bool::wrap(Term::from_value(Value::Bool(b)))
+    }
+
+    pub fn and(_context: &'ctx Context, values: &[&Bool<'ctx>]) -> Bool<'ctx> {
+        Bool::wrap(Term::apply(Op::And, Sort::Bool, terms(values)))
+    }
+
+    pub fn or(_context: &'ctx Context, values: &[&Bool<'ctx>]) -> Bool<'ctx> {
+        Bool::wrap(Term::apply(Op::Or, Sort::Bool, terms(values)))
+    }
+
+    pub fn not(&self) -> Bool<'ctx> {
+        Bool::wrap(unary(Op::Not, Sort::Bool, self))
+    }
+
+    pub fn implies(&self, other: &Bool<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Implies, Sort::Bool, self, other))
+    }
+
+    pub fn xor(&self, other: &Bool<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Xor, Sort::Bool, self, other))
+    }
+
+    /// `a` if this is true, and `b` otherwise.
+    pub fn ite<T: Ast<'ctx>>(&self, a: &T, b: &T) -> T {
+        assert_eq!(a.term().sort(), b.term().sort());
+        let args = vec![self.term.clone(), a.term().clone(), b.term().clone()];
+        T::wrap(Term::apply(Op::Ite, a.term().sort().clone(), args))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        self.term.literal()?.as_bool()
+    }
+}
+
+impl<'ctx> Int<'ctx> {
+    pub fn new_const(_context: &'ctx Context, name: impl Into<String>) -> Int<'ctx> {
+        Int::wrap(constant(name.into(), Sort::Int))
+    }
+
+    pub fn fresh_const(context: &'ctx Context, prefix: &str) -> Int<'ctx> {
+        Int::wrap(constant(context.fresh_name(prefix), Sort::Int))
+    }
+
+    pub fn from_i64(_context: &'ctx Context, n: i64) -> Int<'ctx> {
+        Int::wrap(Term::from_value(Value::Int(n.into())))
+    }
+
+    pub fn from_u64(_context: &'ctx Context, n: u64) -> Int<'ctx> {
+        Int::wrap(Term::from_value(Value::Int(n.into())))
+    }
+
+    /// The integer written in decimal as `s`, if it fits in an `i128`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(_context: &'ctx Context, s: &str) -> Option<Int<'ctx>> {
+        Some(Int::wrap(Term::from_value(Value::Int(s.parse().ok()?))))
+    }
+
+    /// Round `real` down.
+    pub fn from_real(real: &Real<'ctx>) -> Int<'ctx> {
+        real.to_int()
+    }
+
+    /// The value of `bv`, as a two's complement number if `signed`.
+    pub fn from_bv(bv: &BV<'ctx>, signed: bool) -> Int<'ctx> {
+        bv.to_int(signed)
+    }
+
+    pub fn add(_context: &'ctx Context, values: &[&Int<'ctx>]) -> Int<'ctx> {
+        Int::wrap(Term::apply(Op::Add, Sort::Int, terms(values)))
+    }
+
+    pub fn sub(_context: &'ctx Context, values: &[&Int<'ctx>]) -> Int<'ctx> {
+        match values {
+            [value] => (*value).clone(),
+            _ => Int::wrap(Term::apply(Op::Sub, Sort::Int, terms(values))),
+        }
+    }
+
+    pub fn mul(_context: &'ctx Context, values: &[&Int<'ctx>]) -> Int<'ctx> {
+        Int::wrap(Term::apply(Op::Mul, Sort::Int, terms(values)))
+    }
+
+    pub fn unary_minus(&self) -> Int<'ctx> {
+        Int::wrap(unary(Op::Neg, Sort::Int, self))
+    }
+
+    /// Division rounding so that `modulo` is nonnegative.
+    pub fn div(&self, other: &Int<'ctx>) -> Int<'ctx> {
+        Int::wrap(binary(Op::Div, Sort::Int, self, other))
+    }
+
+    pub fn modulo(&self, other: &Int<'ctx>) -> Int<'ctx> {
+        Int::wrap(binary(Op::Mod, Sort::Int, self, other))
+    }
+
+    pub fn lt(&self, other: &Int<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Lt, Sort::Bool, self, other))
+    }
+
+    pub fn le(&self, other: &Int<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Le, Sort::Bool, self, other))
+    }
+
+    pub fn gt(&self, other: &Int<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Gt, Sort::Bool, self, other))
+    }
+
+    pub fn ge(&self, other: &Int<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Ge, Sort::Bool, self, other))
+    }
+
+    pub fn to_real(&self) -> Real<'ctx> {
+        Real::wrap(unary(Op::ToReal, Sort::Real, self))
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        i64::try_from(self.term.literal()?.as_int()?).ok()
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        u64::try_from(self.term.literal()?.as_int()?).ok()
+    }
+}
+
+impl<'ctx> Real<'ctx> {
+    pub fn new_const(_context: &'ctx Context, name: impl Into<String>) -> Real<'ctx> {
+        Real::wrap(constant(name.into(), Sort::Real))
+    }
+
+    pub fn fresh_const(context: &'ctx Context, prefix: &str) -> Real<'ctx> {
+        Real::wrap(constant(context.fresh_name(prefix), Sort::Real))
+    }
+
+    /// `numerator / denominator`.
+    pub fn from_real(_context: &'ctx Context, numerator: i32, denominator: i32) -> Real<'ctx> {
+        let value = Value::real(numerator.into(), denominator.into()).expect("zero denominator");
+        Real::wrap(Term::from_value(value))
+    }
+
+    /// The fraction written in decimal as `numerator` over `denominator`, if
+    /// they fit in `i128`s and the denominator isn't zero.
+    pub fn from_real_str(_context: &'ctx Context, numerator: &str, denominator: &str) -> Option<Real<'ctx>> {
+        let value = Value::real(numerator.parse().ok()?, denominator.parse().ok()?)?;
+        Some(Real::wrap(Term::from_value(value)))
+    }
+
+    pub fn from_int(int: &Int<'ctx>) -> Real<'ctx> {
+        int.to_real()
+    }
+
+    pub fn add(_context: &'ctx Context, values: &[&Real<'ctx>]) -> Real<'ctx> {
+        Real::wrap(Term::apply(Op::Add, Sort::Real, terms(values)))
+    }
+
+    pub fn sub(_context: &'ctx Context, values: &[&Real<'ctx>]) -> Real<'ctx> {
+        match values {
+            [value] => (*value).clone(),
+            _ => Real::wrap(Term::apply(Op::Sub, Sort::Real, terms(values))),
+        }
+    }
+
+    pub fn mul(_context: &'ctx Context, values: &[&Real<'ctx>]) -> Real<'ctx> {
+        Real::wrap(Term::apply(Op::Mul, Sort::Real, terms(values)))
+    }
+
+    pub fn unary_minus(&self) -> Real<'ctx> {
+        Real::wrap(unary(Op::Neg, Sort::Real, self))
+    }
+
+    pub fn div(&self, other: &Real<'ctx>) -> Real<'ctx> {
+        Real::wrap(binary(Op::RealDiv, Sort::Real, self, other))
+    }
+
+    pub fn lt(&self, other: &Real<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Lt, Sort::Bool, self, other))
+    }
+
+    pub fn le(&self, other: &Real<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Le, Sort::Bool, self, other))
+    }
+
+    pub fn gt(&self, other: &Real<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Gt, Sort::Bool, self, other))
+    }
+
+    pub fn ge(&self, other: &Real<'ctx>) -> Bool<'ctx> {
+        Bool::wrap(binary(Op::Ge, Sort::Bool, self, other))
+    }
+
+    /// Round down to an integer.
+    pub fn to_int(&self) -> Int<'ctx> {
+        Int::wrap(unary(Op::ToInt, Sort::Int, self))
+    }
+
+    /// The numerator and denominator of a literal, if they fit in `i64`s.
+    pub fn as_real(&self) -> Option<(i64, i64)> {
+        let (n, d) = self.term.literal()?.as_rational()?;
+        Some((i64::try_from(n).ok()?, i64::try_from(d).ok()?))
+    }
+}
+
+macro_rules! bv_ops {
+    ($($(#[$attr:meta])* $name:ident => $op:ident, $sort:ident;)*) => {$(
+        $(#[$attr])*
+        pub fn $name(&self, other: &BV<'ctx>) -> $sort<'ctx> {
+            let sort = match Op::$op {
+                Op::BvSlt | Op::BvSle | Op::BvUlt | Op::BvUle => Sort::Bool,
+                _ => self.term.sort().clone(),
+            };
+            $sort::wrap(binary(Op::$op, sort, self, other))
+        }
+    )*};
+}
+
+impl<'ctx> BV<'ctx> {
+    pub fn new_const(_context: &'ctx Context, name: impl Into<String>, width: u32) -> BV<'ctx> {
+        BV::wrap(constant(name.into(), Sort::BitVec(check_width(width))))
+    }
+
+    pub fn fresh_const(context: &'ctx Context, prefix: &str, width: u32) -> BV<'ctx> {
+        BV::wrap(constant(context.fresh_name(prefix), Sort::BitVec(check_width(width))))
+    }
+
+    /// `n`, wrapped around to `width` bits.
+    pub fn from_i64(_context: &'ctx Context, n: i64, width: u32) -> BV<'ctx> {
+        BV::wrap(Term::from_value(Value::bv(n.into(), check_width(width))))
+    }
+
+    /// `n`, wrapped around to `width` bits.
+    pub fn from_u64(_context: &'ctx Context, n: u64, width: u32) -> BV<'ctx> {
+        BV::wrap(Term::from_value(Value::bv(n.into(), check_width(width))))
+    }
+
+    /// The low `width` bits of `int`.
+    pub fn from_int(int: &Int<'ctx>, width: u32) -> BV<'ctx> {
+        BV::wrap(unary(Op::Int2Bv, Sort::BitVec(check_width(width)), int))
+    }
+
+    /// The value of the bits, as a two's complement number if `signed`.
+    pub fn to_int(&self, signed: bool) -> Int<'ctx> {
+        let unsigned = Int::wrap(unary(Op::Bv2Nat, Sort::Int, self));
+        if !signed {
+            return unsigned;
+        }
+        // 和Z3一样，有符号的值是用无符号的值定义的
+        let width = self.get_size();
+        let zero = Term::from_value(Value::bv(0, width));
+        let is_negative = Bool::wrap(Term::apply(Op::BvSlt, Sort::Bool, vec![self.term.clone(), zero]));
+        // 2^128放不进字面量，就写成乘积
+        let modulus = match 1i128.checked_shl(width).filter(|&m| m > 0) {
+            Some(modulus) => Term::from_value(Value::Int(modulus)),
+            None => {
+                let low = Term::from_value(Value::Int(1 << (width / 2)));
+                let high = Term::from_value(Value::Int(1 << (width - width / 2)));
+                Term::apply(Op::Mul, Sort::Int, vec![low, high])
+            }
+        };
+        let modulus = Int::wrap(modulus);
+        let wrapped = Int::wrap(binary(Op::Sub, Sort::Int, &unsigned, &modulus));
+        is_negative.ite(&wrapped, &unsigned)
+    }
+
+    pub fn get_size(&self) -> u32 {
+        match self.term.sort() {
+            Sort::BitVec(width) => *width,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The bits of a literal, if it is at most 64 bits wide.
+    pub fn as_u64(&self) -> Option<u64> {
+        u64::try_from(self.term.literal()?.as_bits()?.0).ok()
+    }
+
+    pub fn bvneg(&self) -> BV<'ctx> {
+        BV::wrap(unary(Op::BvNeg, self.term.sort().clone(), self))
+    }
+
+    bv_ops! {
+        bvadd => BvAdd, BV;
+        bvsub => BvSub, BV;
+        bvmul => BvMul, BV;
+        /// Signed division, rounding toward zero.
+        bvsdiv => BvSdiv, BV;
+        /// The remainder of `bvsdiv`, which has the sign of `self`.
+        bvsrem => BvSrem, BV;
+        /// The remainder of signed division rounding down, which has the sign
+        /// of `other`.
+        bvsmod => BvSmod, BV;
+        bvudiv => BvUdiv, BV;
+        bvurem => BvUrem, BV;
+        bvslt => BvSlt, Bool;
+        bvsle => BvSle, Bool;
+        bvult => BvUlt, Bool;
+        bvule => BvUle, Bool;
+    }
+
+    pub fn bvsgt(&self, other: &BV<'ctx>) -> Bool<'ctx> {
+        other.bvslt(self)
+    }
+
+    pub fn bvsge(&self, other: &BV<'ctx>) -> Bool<'ctx> {
+        other.bvsle(self)
+    }
+}
+
+impl<'ctx> Array<'ctx> {
+    pub fn new_const(_context: &'ctx Context, name: impl Into<String>, domain: &Sort, range: &Sort) -> Array<'ctx> {
+        Array::wrap(constant(name.into(), Sort::array(_context, domain, range)))
+    }
+
+    pub fn fresh_const(context: &'ctx Context, prefix: &str, domain: &Sort, range: &Sort) -> Array<'ctx> {
+        Array::wrap(constant(context.fresh_name(prefix), Sort::array(context, domain, range)))
+    }
+
+    /// The array holding `value` at every index of `domain`.
+    pub fn const_array<A: Ast<'ctx>>(context: &'ctx Context, domain: &Sort, value: &A) -> Array<'ctx> {
+        let sort = Sort::array(context, domain, value.term().sort());
+        Array::wrap(unary(Op::ConstArray, sort, value))
+    }
+
+    pub fn select<A: Ast<'ctx>>(&self, index: &A) -> Dynamic<'ctx> {
+        let range = match self.term.sort() {
+            Sort::Array(_, range) => (**range).clone(),
+            _ => unreachable!(),
+        };
+        Dynamic::wrap(binary(Op::Select, range, self, index))
+    }
+
+    pub fn store<A: Ast<'ctx>, B: Ast<'ctx>>(&self, index: &A, value: &B) -> Array<'ctx> {
+        let args = vec![self.term.clone(), index.term().clone(), value.term().clone()];
+        Array::wrap(Term::apply(Op::Store, self.term.sort().clone(), args))
+    }
+}
+
+impl<'ctx> Dynamic<'ctx> {
+    pub fn from_ast(ast: &impl Ast<'ctx>) -> Dynamic<'ctx> {
+        Dynamic::wrap(ast.term().clone())
+    }
+
+    pub fn new_const(_context: &'ctx Context, name: impl Into<String>, sort: &Sort) -> Dynamic<'ctx> {
+        Dynamic::wrap(constant(name.into(), sort.clone()))
+    }
+
+    pub fn fresh_const(context: &'ctx Context, prefix: &str, sort: &Sort) -> Dynamic<'ctx> {
+        Dynamic::wrap(constant(context.fresh_name(prefix), sort.clone()))
+    }
+
+    pub fn sort_kind(&self) -> SortKind {
+        self.term.sort().kind()
+    }
+
+    fn as_kind<T: Wrap>(&self, kind: SortKind) -> Option<T> {
+        Some(T::wrap(self.term.clone())).filter(|_| self.sort_kind() == kind)
+    }
+
+    pub fn as_bool(&self) -> Option<Bool<'ctx>> {
+        self.as_kind(SortKind::Bool)
+    }
+
+    pub fn as_int(&self) -> Option<Int<'ctx>> {
+        self.as_kind(SortKind::Int)
+    }
+
+    pub fn as_real(&self) -> Option<Real<'ctx>> {
+        self.as_kind(SortKind::Real)
+    }
+
+    pub fn as_bv(&self) -> Option<BV<'ctx>> {
+        self.as_kind(SortKind::BV)
+    }
+
+    pub fn as_array(&self) -> Option<Array<'ctx>> {
+        self.as_kind(SortKind::Array)
+    }
+
+    /// A literal holding `value`.
+    pub(crate) fn from_value(value: Value) -> Dynamic<'ctx> {
+        Dynamic::wrap(Term::from_value(value))
+    }
+
+    /// Is this a literal value, rather than something left to evaluate?
+    #[cfg(test)]
+    pub(crate) fn is_literal(&self) -> bool {
+        self.term.literal().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folding() {
+        let context = Context::new();
+        let int = |n| Int::from_i64(&context, n);
+        assert_eq!(Int::add(&context, &[&int(2), &int(3)]).as_i64(), Some(5));
+        // SMT-LIB 的整数除法是欧几里得除法
+        assert_eq!(int(-7).div(&int(2)).as_i64(), Some(-4));
+        assert_eq!(int(-7).modulo(&int(2)).as_i64(), Some(1));
+        assert_eq!(int(1).div(&int(0)).as_i64(), None);
+
+        let x = Int::new_const(&context, "x");
+        assert_eq!(Int::mul(&context, &[&x, &int(0)]).as_i64(), Some(0));
+        let b = Bool::new_const(&context, "b");
+        assert_eq!(b.not().not(), b);
+        assert_eq!(Bool::from_bool(&context, true).ite(&x, &int(1)), x);
+        assert_eq!(b.ite(&x, &x), x);
+
+        let a = BV::from_i64(&context, -8, 4);
+        assert_eq!(a.bvsdiv(&BV::from_i64(&context, 0, 4)).as_u64(), Some(1));
+        assert_eq!(a.to_int(true).as_i64(), Some(-8));
+        assert_eq!(a.to_int(false).as_i64(), Some(8));
+    }
+
+    #[test]
+    fn select_through_stores() {
+        let context = Context::new();
+        let (int, real) = (Sort::int(&context), Sort::real(&context));
+        let zero = Real::from_real(&context, 0, 1);
+        let x = Real::new_const(&context, "x");
+        let i = Int::new_const(&context, "i");
+        let array = Array::const_array(&context, &int, &zero)
+            .store(&Int::from_i64(&context, 1), &x)
+            .store(&Int::from_i64(&context, 2), &zero);
+        assert_eq!(array.select(&Int::from_i64(&context, 1)).as_real(), Some(x.clone()));
+        assert_eq!(array.select(&Int::from_i64(&context, 3)).as_real(), Some(zero));
+        assert_eq!(array.select(&i).to_string(), format!("(select {} i)", array));
+
+        // 跳过了能确定不相等的下标之后，剩下的还是要查
+        let b = Array::new_const(&context, "b", &int, &real).store(&Int::from_i64(&context, 1), &x);
+        let selected = b.select(&Int::from_i64(&context, 2));
+        assert_eq!(selected.to_string(), "(select b 2)");
+    }
+
+    #[test]
+    fn printing() {
+        let context = Context::new();
+        let x = Int::new_const(&context, "x");
+        let y = Int::new_const(&context, "1y");
+        let s = Int::add(&context, &[&x, &Int::from_i64(&context, -1)]);
+        let t = Int::mul(&context, &[&s, &s]);
+        assert_eq!(t.to_string(), "(let ((?x0 (+ x (- 1)))) (* ?x0 ?x0))");
+        let u = Int::add(&context, &[&t, &t, &s, &y]);
+        assert_eq!(
+            u.to_string(),
+            "(let ((?x0 (+ x (- 1)))) (let ((?x1 (* ?x0 ?x0))) (+ ?x1 ?x1 ?x0 |1y|)))"
+        );
+        assert_eq!(Real::from_real(&context, -3, 6).to_string(), "(- (/ 1.0 2.0))");
+        assert_eq!(BV::from_int(&x, 8).to_string(), "((_ int2bv 8) x)");
+        assert_eq!(
+            Array::const_array(&context, &Sort::int(&context), &BV::from_i64(&context, 5, 8)).to_string(),
+            "((as const (Array Int (_ BitVec 8))) (_ bv5 8))"
+        );
+    }
+
+    #[test]
+    fn evaluate_only_the_branch_taken() {
+        let context = Context::new();
+        let b = Bool::new_const(&context, "b");
+        let x = Int::new_const(&context, "x");
+        let t = b.ite(&x.div(&Int::from_i64(&context, 0)), &Int::from_i64(&context, 1));
+        let with = |b| {
+            move |name: &str, _: &Sort| match name {
+                "b" => Some(Value::Bool(b)),
+                _ => Some(Value::Int(7)),
+            }
+        };
+        assert_eq!(evaluate(t.term(), with(false)), Some(Value::Int(1)));
+        assert_eq!(evaluate(t.term(), with(true)), None);
+    }
+
+    #[test]
+    fn deep_terms() {
+        let context = Context::new();
+        let x = Int::new_const(&context, "x");
+        let mut t = x.clone();
+        for _ in 0..100_000 {
+            t = Int::add(&context, &[&t, &x]);
+        }
+        let value = evaluate(t.term(), |_, _| Some(Value::Int(1)));
+        assert_eq!(value, Some(Value::Int(100_001)));
+        let printed = t.to_string();
+        assert!(printed.starts_with("(+ (+ (+ ") && printed.ends_with(" x) x)"));
+        drop(t);
+    }
+}
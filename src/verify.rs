@@ -0,0 +1,237 @@
+//! Re-checking synthesized programs against the spec.
+//!
+//! The synthesis query only asks that the program's output equal the spec's
+//! output as `Vecs`, and a bug in an encoding or in turning a model back into a
+//! program can make that a weaker promise than it looks. When asked to, we
+//! re-check the final `Program` on its own: work out its output on the spec's
+//! inputs, and check that the spec accepts exactly that output. If it doesn't,
+//! we report how it differs from what the spec wants instead of returning it.
+//!
+//! The program's output is always worked out by `concrete`'s interpreter, so
+//! that a wrong encoding shows up as a mismatch instead of being agreed with.
+//! So is the spec's, when it is a `Program`; other specs fall back to their
+//! encodings, and we only accept values that those pin down completely.
+
+use crate::concrete;
+use crate::domain::cell_as_f64;
+use crate::{
+    fresh_output, input_vecs, CheckResult, Error, Model, Program, Rational, Result, Synthesizer,
+    Tensor, Vecs, DIMS,
+};
+use crate::term::{Ast, Bool, Int};
+use std::fmt::{self, Display};
+use std::time;
+
+/// How a synthesized program's output differs from the spec's.
+#[derive(Debug)]
+pub struct Mismatch {
+    /// The program that was synthesized.
+    pub program: Program,
+    /// The shape the spec wants, as `[rows, columns]`. If the spec doesn't
+    /// determine a shape, this is the program's own.
+    pub expected_shape: [i64; 2],
+    /// The shape the program produces.
+    pub actual_shape: [i64; 2],
+    /// Every cell, inside either shape, whose value differs, as `([row,
+    /// column], expected, actual)`. Cells are `f64`s so that they can hold
+    /// `Domain::Real` values.
+    pub cells: Vec<([usize; 2], f64, f64)>,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "the synthesized program doesn't satisfy the spec:")?;
+        for line in self.program.to_string().lines() {
+            writeln!(f, "  {}", line)?;
+        }
+        if self.expected_shape != self.actual_shape {
+            writeln!(
+                f,
+                "  it has shape {:?}, but the spec wants {:?}",
+                self.actual_shape, self.expected_shape
+            )?;
+        }
+        for ([i, j], expected, actual) in &self.cells {
+            writeln!(f, "  cell [{}, {}] is {}, but the spec wants {}", i, j, actual, expected)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the value that `model` gives `value`.
+fn read_value<'a>(model: &dyn Model<'a>, value: &Tensor<'a>) -> Option<Vecs<f64, i64>> {
+    let mut result = Vecs::new([model.eval_int(&value.dims[0])?, model.eval_int(&value.dims[1])?]);
+    for i in 0..DIMS[0] {
+        for j in 0..DIMS[1] {
+            result.vecs[i].push(cell_as_f64(&model.eval(&value.vecs[i][j])?)?);
+        }
+    }
+    Some(result)
+}
+
+fn as_f64s(value: &Vecs<Rational, i64>) -> Vecs<f64, i64> {
+    let mut result = Vecs::new(value.dims);
+    for (row, cells) in result.vecs.iter_mut().zip(&value.vecs) {
+        row.extend(cells.iter().map(|cell| cell.to_f64()));
+    }
+    result
+}
+
+impl<'a> Synthesizer<'a> {
+    /// Hand `constraint` to the backend, as a verification query for a
+    /// program of length `program_length`, and return a model if there is one.
+    fn verification_query(
+        &mut self,
+        program_length: u32,
+        encoding: time::Duration,
+        constraint: &Bool<'a>,
+    ) -> Result<Option<Box<dyn Model<'a> + 'a>>> {
+        let assertions = [constraint.clone()];
+        match self.solve("verification", program_length, encoding, &assertions, &[]) {
+            CheckResult::Sat(model) => Ok(Some(model)),
+            CheckResult::Unsat(_) => Ok(None),
+            CheckResult::Unknown(reason) => Err(Error::from_reason_unknown(reason)),
+        }
+    }
+
+    fn is_sat(&mut self, program_length: u32, encoding: time::Duration, constraint: &Bool<'a>) -> Result<bool> {
+        if let Some(b) = constraint.simplify().as_bool() {
+            return Ok(b);
+        }
+        Ok(self.verification_query(program_length, encoding, constraint)?.is_some())
+    }
+
+    /// Solve `constraint` and read off the value it gives `value`.
+    fn solve_for(
+        &mut self,
+        program_length: u32,
+        encoding: time::Duration,
+        constraint: &Bool<'a>,
+        value: &Tensor<'a>,
+    ) -> Result<Option<Vecs<f64, i64>>> {
+        let model = match self.verification_query(program_length, encoding, constraint)? {
+            Some(model) => model,
+            None => return Ok(None),
+        };
+        Ok(read_value(&*model, value))
+    }
+
+    /// Work out the value that an encoding gives `value`, which mustn't depend
+    /// on the model: encodings can introduce fresh constants, and if one of
+    /// them can still change the shape or a cell inside it, the encoding
+    /// doesn't determine the value at all.
+    fn evaluate(
+        &mut self,
+        program_length: u32,
+        encoding: time::Duration,
+        value: &Tensor<'a>,
+        what: &str,
+    ) -> Result<Vecs<f64, i64>> {
+        let unknown = || Error::Unknown(format!("couldn't evaluate {}", what));
+        let context = self.context;
+        let model = self
+            .verification_query(program_length, encoding, &Bool::from_bool(context, true))?
+            .ok_or_else(unknown)?;
+        let result = read_value(&*model, value).ok_or_else(unknown)?;
+
+        let mut differs = vec![];
+        for (&dim, value_dim) in result.dims.iter().zip(&value.dims) {
+            differs.push(value_dim._eq(&Int::from_i64(context, dim)).not());
+        }
+        for i in 0..(result.dims[0].clamp(0, DIMS[0] as i64) as usize) {
+            for j in 0..(result.dims[1].clamp(0, DIMS[1] as i64) as usize) {
+                let cell = model.eval(&value.vecs[i][j]).ok_or_else(unknown)?;
+                differs.push(value.vecs[i][j]._eq(&cell).not());
+            }
+        }
+        let differs = Bool::or(context, &differs.iter().collect::<Vec<_>>());
+        if self.is_sat(program_length, time::Duration::default(), &differs)? {
+            return Err(Error::Unknown(format!("the encoding doesn't determine {}", what)));
+        }
+        Ok(result)
+    }
+
+    /// If we were asked to, check that `program` really satisfies the spec on
+    /// the spec's inputs, independently of the synthesis query, and hand it
+    /// back if it does.
+    ///
+    /// Only the output's shape and the cells inside it count; padding cells
+    /// may differ.
+    pub(crate) fn verify_program(&mut self, program: Program) -> Result<Program> {
+        if !self.should_verify_programs {
+            return Ok(program);
+        }
+        self.check_program(program)
+    }
+
+    /// Check that `program` really satisfies the spec on the spec's inputs,
+    /// whether or not we were asked to verify programs, and hand it back if it
+    /// does.
+    pub(crate) fn check_program(&mut self, program: Program) -> Result<Program> {
+        let length = program.instructions.len() as u32;
+        let context = self.context;
+        let domain = self.domain;
+        let inputs: Vec<_> = self
+            .spec
+            .inputs()
+            .iter()
+            .map(|input| input_vecs(context, domain, input))
+            .collect();
+
+        // 程序的输出（包括形状）只由程序本身决定，直接用解释器算，不经过编码
+        let concrete = concrete::evaluate(&program, &self.spec.inputs(), domain)?;
+        let output = concrete::tensor(context, domain, &concrete);
+        let actual = as_f64s(&concrete);
+
+        let then = time::Instant::now();
+        let expected = match self.spec.evaluate(domain)? {
+            Some(expected) => as_f64s(&expected),
+            None => match self.spec.output(context, &inputs, domain) {
+                Some(expected) => {
+                    self.evaluate(length, then.elapsed(), &expected, "the spec on its inputs")?
+                }
+                None => {
+                    // All we can do is ask whether the spec accepts the
+                    // program's cells, and if not, which cells it wants
+                    // instead.
+                    let accepted = self.spec.make_expression(context, &inputs, &output, domain);
+                    if self.is_sat(length, then.elapsed(), &accepted)? {
+                        return Ok(program);
+                    }
+                    let then = time::Instant::now();
+                    let expected = fresh_output(context, domain, DIMS);
+                    let wants = self.spec.make_expression(context, &inputs, &expected, domain);
+                    let mut expected = self
+                        .solve_for(length, then.elapsed(), &wants, &expected)?
+                        .ok_or_else(|| {
+                            Error::Unknown("couldn't evaluate the spec on its inputs".to_string())
+                        })?;
+                    expected.dims = actual.dims;
+                    expected
+                }
+            },
+        };
+
+        let mut cells = vec![];
+        let rows = expected.dims[0].max(actual.dims[0]).clamp(0, DIMS[0] as i64) as usize;
+        let cols = expected.dims[1].max(actual.dims[1]).clamp(0, DIMS[1] as i64) as usize;
+        for i in 0..rows {
+            for j in 0..cols {
+                if expected.vecs[i][j] != actual.vecs[i][j] {
+                    cells.push(([i, j], expected.vecs[i][j], actual.vecs[i][j]));
+                }
+            }
+        }
+
+        if cells.is_empty() && expected.dims == actual.dims {
+            return Ok(program);
+        }
+
+        Err(Error::ProgramMismatch(Box::new(Mismatch {
+            program,
+            expected_shape: expected.dims,
+            actual_shape: actual.dims,
+            cells,
+        })))
+    }
+}